@@ -0,0 +1,6 @@
+UPDATE avatars
+SET avatar_300x300_key = '/uploads/' || avatar_300x300_key,
+    avatar_40x40_key = '/uploads/' || avatar_40x40_key;
+
+ALTER TABLE avatars RENAME COLUMN avatar_300x300_key TO avatar_300x300_url;
+ALTER TABLE avatars RENAME COLUMN avatar_40x40_key TO avatar_40x40_url;
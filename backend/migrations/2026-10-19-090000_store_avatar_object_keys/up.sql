@@ -0,0 +1,8 @@
+-- Avatars reference media storage keys; public URLs are resolved by the storage backend
+ALTER TABLE avatars RENAME COLUMN avatar_300x300_url TO avatar_300x300_key;
+ALTER TABLE avatars RENAME COLUMN avatar_40x40_url TO avatar_40x40_key;
+
+-- Files written before this migration live under the local upload directory
+UPDATE avatars
+SET avatar_300x300_key = regexp_replace(avatar_300x300_key, '^/uploads/', ''),
+    avatar_40x40_key = regexp_replace(avatar_40x40_key, '^/uploads/', '');
@@ -0,0 +1,16 @@
+ALTER TABLE avatars
+    ADD COLUMN avatar_300x300_key VARCHAR,
+    ADD COLUMN avatar_40x40_key VARCHAR;
+
+-- Keep the closest WebP renditions; everything else is dropped with the table
+UPDATE avatars
+SET avatar_300x300_key = (
+        SELECT storage_key FROM media_variants
+        WHERE avatar_id = avatars.id AND size = 300 AND density = 1 AND format = 'webp'
+    ),
+    avatar_40x40_key = (
+        SELECT storage_key FROM media_variants
+        WHERE avatar_id = avatars.id AND size = 40 AND density = 1 AND format = 'webp'
+    );
+
+DROP TABLE media_variants;
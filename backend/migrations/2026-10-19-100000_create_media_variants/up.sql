@@ -0,0 +1,32 @@
+-- One row per stored rendition of an avatar: a logical size at a pixel density in one format.
+-- Renditions with equal pixel dimensions (e.g. 40 @ 2x and 80 @ 1x) share a storage key.
+CREATE TABLE media_variants (
+    id SERIAL PRIMARY KEY,
+    avatar_id INTEGER NOT NULL REFERENCES avatars(id) ON DELETE CASCADE,
+    size INTEGER NOT NULL CHECK (size > 0),
+    density INTEGER NOT NULL CHECK (density > 0),
+    format VARCHAR(8) NOT NULL CHECK (format IN ('avif', 'webp', 'jpeg')),
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    storage_key VARCHAR NOT NULL,
+    byte_size BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (avatar_id, size, density, format)
+);
+
+CREATE INDEX index_media_variants_on_storage_key ON media_variants (storage_key);
+
+-- Existing avatars only have their two WebP files; their exact dimensions and sizes are unknown
+INSERT INTO media_variants (avatar_id, size, density, format, width, height, storage_key, created_at)
+SELECT id, 300, 1, 'webp', 300, 300, avatar_300x300_key, created_at
+FROM avatars
+WHERE avatar_300x300_key IS NOT NULL;
+
+INSERT INTO media_variants (avatar_id, size, density, format, width, height, storage_key, created_at)
+SELECT id, 40, 1, 'webp', 40, 40, avatar_40x40_key, created_at
+FROM avatars
+WHERE avatar_40x40_key IS NOT NULL;
+
+ALTER TABLE avatars
+    DROP COLUMN avatar_300x300_key,
+    DROP COLUMN avatar_40x40_key;
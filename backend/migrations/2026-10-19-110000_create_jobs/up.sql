@@ -0,0 +1,22 @@
+-- Background work of any kind. Workers claim due rows with `FOR UPDATE SKIP LOCKED`, so any
+-- number of them can poll the same table without handing out a job twice.
+CREATE TABLE jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR(64) NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    status VARCHAR(16) NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'running', 'succeeded', 'failed')),
+    -- Who may see the job's status; NULL for system jobs
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5 CHECK (max_attempts > 0),
+    run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_at TIMESTAMP,
+    last_error TEXT,
+    result JSONB,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX index_jobs_on_run_at_when_queued ON jobs (run_at, id) WHERE status = 'queued';
+CREATE INDEX index_jobs_on_locked_at_when_running ON jobs (locked_at) WHERE status = 'running';
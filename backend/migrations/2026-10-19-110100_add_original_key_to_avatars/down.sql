@@ -0,0 +1 @@
+ALTER TABLE avatars DROP COLUMN original_key;
@@ -0,0 +1,3 @@
+-- The uploaded file, kept so variants can be rendered again. NULL for avatars uploaded
+-- before originals were stored.
+ALTER TABLE avatars ADD COLUMN original_key VARCHAR;
@@ -0,0 +1,3 @@
+ALTER TABLE avatars
+    DROP COLUMN dominant_color,
+    DROP COLUMN blurhash;
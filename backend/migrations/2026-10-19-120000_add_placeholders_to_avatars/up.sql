@@ -0,0 +1,4 @@
+-- What clients paint while the avatar loads; NULL for avatars processed before this
+ALTER TABLE avatars
+    ADD COLUMN blurhash VARCHAR,
+    ADD COLUMN dominant_color VARCHAR(7);
@@ -0,0 +1,3 @@
+DROP TABLE uploads;
+ALTER TABLE messages DROP COLUMN attachment_id;
+DROP TABLE attachments;
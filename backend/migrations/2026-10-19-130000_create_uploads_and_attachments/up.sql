@@ -0,0 +1,37 @@
+-- Files shared in chat. Messages point at them; the bytes live in media storage.
+CREATE TABLE attachments (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    storage_key VARCHAR NOT NULL,
+    filename VARCHAR(255),
+    content_type VARCHAR(255) NOT NULL,
+    byte_size BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX index_attachments_on_user_id ON attachments (user_id);
+
+ALTER TABLE messages ADD COLUMN attachment_id INTEGER REFERENCES attachments(id) ON DELETE SET NULL;
+
+CREATE INDEX index_messages_on_attachment_id ON messages (attachment_id) WHERE attachment_id IS NOT NULL;
+
+-- Resumable (tus) uploads in progress. Received bytes are stored as parts in media storage
+-- and assembled once `upload_offset` reaches `upload_length`.
+CREATE TABLE uploads (
+    id UUID PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR(16) NOT NULL CHECK (purpose IN ('avatar', 'attachment')),
+    upload_length BIGINT NOT NULL CHECK (upload_length > 0),
+    upload_offset BIGINT NOT NULL DEFAULT 0 CHECK (upload_offset BETWEEN 0 AND upload_length),
+    metadata JSONB NOT NULL DEFAULT '{}',
+    -- Set while a request is appending, so two requests never write the same part
+    locked_at TIMESTAMP,
+    job_id BIGINT REFERENCES jobs(id) ON DELETE SET NULL,
+    attachment_id INTEGER REFERENCES attachments(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP NOT NULL,
+    completed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX index_uploads_on_expires_at ON uploads (expires_at);
@@ -0,0 +1,8 @@
+DROP TRIGGER jobs_count_references ON jobs;
+DROP TRIGGER attachments_count_references ON attachments;
+DROP TRIGGER avatars_count_references ON avatars;
+DROP TRIGGER media_variants_count_references ON media_variants;
+DROP FUNCTION count_job_media_references();
+DROP FUNCTION count_media_references();
+DROP FUNCTION move_media_reference(VARCHAR, VARCHAR);
+DROP TABLE media_objects;
@@ -0,0 +1,86 @@
+-- Stored objects are content-addressed, so identical uploads share one original and one set
+-- of variants. A file may only be deleted once nothing refers to it any more; the count is
+-- kept here by triggers so cascading deletes are accounted for too.
+CREATE TABLE media_objects (
+    storage_key VARCHAR PRIMARY KEY,
+    reference_count INTEGER NOT NULL DEFAULT 0 CHECK (reference_count >= 0),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO media_objects (storage_key, reference_count)
+SELECT storage_key, COUNT(*)
+FROM (
+    SELECT storage_key FROM media_variants
+    UNION ALL
+    SELECT original_key FROM avatars WHERE original_key IS NOT NULL
+    UNION ALL
+    SELECT storage_key FROM attachments
+    UNION ALL
+    SELECT payload ->> 'original_key' FROM jobs
+    WHERE kind = 'avatar.process' AND status IN ('queued', 'running') AND payload ? 'original_key'
+) AS refs (storage_key)
+GROUP BY storage_key;
+
+-- Moves one reference from `old_key` to `new_key`; either may be NULL
+CREATE FUNCTION move_media_reference(old_key VARCHAR, new_key VARCHAR) RETURNS VOID AS $$
+BEGIN
+    IF old_key IS NOT DISTINCT FROM new_key THEN
+        RETURN;
+    END IF;
+
+    IF new_key IS NOT NULL THEN
+        INSERT INTO media_objects (storage_key, reference_count)
+        VALUES (new_key, 1)
+        ON CONFLICT (storage_key) DO UPDATE
+        SET reference_count = media_objects.reference_count + 1,
+            updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    IF old_key IS NOT NULL THEN
+        UPDATE media_objects
+        SET reference_count = reference_count - 1,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE storage_key = old_key;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+-- One reference per row; the trigger argument names the column holding the key
+CREATE FUNCTION count_media_references() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM move_media_reference(
+        CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) ->> TG_ARGV[0] END,
+        CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) ->> TG_ARGV[0] END
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- An avatar job that has yet to run needs its original, though no avatar row exists yet
+CREATE FUNCTION count_job_media_references() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM move_media_reference(
+        CASE WHEN TG_OP <> 'INSERT' AND OLD.kind = 'avatar.process' AND OLD.status IN ('queued', 'running')
+            THEN OLD.payload ->> 'original_key' END,
+        CASE WHEN TG_OP <> 'DELETE' AND NEW.kind = 'avatar.process' AND NEW.status IN ('queued', 'running')
+            THEN NEW.payload ->> 'original_key' END
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER media_variants_count_references
+AFTER INSERT OR UPDATE OF storage_key OR DELETE ON media_variants
+FOR EACH ROW EXECUTE FUNCTION count_media_references('storage_key');
+
+CREATE TRIGGER avatars_count_references
+AFTER INSERT OR UPDATE OF original_key OR DELETE ON avatars
+FOR EACH ROW EXECUTE FUNCTION count_media_references('original_key');
+
+CREATE TRIGGER attachments_count_references
+AFTER INSERT OR UPDATE OF storage_key OR DELETE ON attachments
+FOR EACH ROW EXECUTE FUNCTION count_media_references('storage_key');
+
+CREATE TRIGGER jobs_count_references
+AFTER INSERT OR UPDATE OF kind, status, payload OR DELETE ON jobs
+FOR EACH ROW EXECUTE FUNCTION count_job_media_references();
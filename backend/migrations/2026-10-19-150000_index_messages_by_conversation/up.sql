@@ -0,0 +1,9 @@
+-- Conversation history is read a page at a time in (created_at, id) order. Keying the index
+-- on the unordered pair of participants lets both directions of a conversation come from a
+-- single index range scan, instead of merging two and sorting.
+CREATE INDEX idx_message_conversation ON messages (
+    LEAST(sender_id, receiver_id),
+    GREATEST(sender_id, receiver_id),
+    created_at,
+    id
+);
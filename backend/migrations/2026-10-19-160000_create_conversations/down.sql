@@ -0,0 +1,4 @@
+DROP TRIGGER messages_track_conversations ON messages;
+DROP FUNCTION track_conversations();
+DROP FUNCTION touch_conversation(INTEGER, INTEGER, INTEGER, TIMESTAMP, INTEGER);
+DROP TABLE conversations;
@@ -0,0 +1,93 @@
+-- Each user's side of each conversation, for the inbox: the latest message either way and how
+-- many received messages are still unread. Kept up to date by triggers on messages, so
+-- cascading deletes are accounted for too.
+CREATE TABLE conversations (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    counterpart_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    last_message_id INTEGER NOT NULL,
+    last_message_at TIMESTAMP NOT NULL,
+    unread_count INTEGER NOT NULL DEFAULT 0 CHECK (unread_count >= 0),
+    PRIMARY KEY (user_id, counterpart_id)
+);
+
+CREATE INDEX idx_conversation_recency ON conversations (user_id, last_message_at, last_message_id);
+
+INSERT INTO conversations (user_id, counterpart_id, last_message_id, last_message_at, unread_count)
+SELECT user_id,
+       counterpart_id,
+       (ARRAY_AGG(id ORDER BY created_at DESC, id DESC))[1],
+       MAX(created_at),
+       SUM(unread)
+FROM (
+    SELECT sender_id, receiver_id, id, created_at, 0 FROM messages
+    UNION ALL
+    SELECT receiver_id, sender_id, id, created_at, CASE WHEN is_read THEN 0 ELSE 1 END FROM messages
+) AS sides (user_id, counterpart_id, id, created_at, unread)
+GROUP BY user_id, counterpart_id;
+
+-- Records a message on `owner`'s side of the conversation with `other`
+CREATE FUNCTION touch_conversation(owner INTEGER, other INTEGER, message_id INTEGER, sent_at TIMESTAMP, unread INTEGER) RETURNS VOID AS $$
+    INSERT INTO conversations (user_id, counterpart_id, last_message_id, last_message_at, unread_count)
+    VALUES (owner, other, message_id, sent_at, unread)
+    ON CONFLICT (user_id, counterpart_id) DO UPDATE
+    SET last_message_id = CASE
+            WHEN (EXCLUDED.last_message_at, EXCLUDED.last_message_id) > (conversations.last_message_at, conversations.last_message_id)
+            THEN EXCLUDED.last_message_id
+            ELSE conversations.last_message_id
+        END,
+        last_message_at = GREATEST(conversations.last_message_at, EXCLUDED.last_message_at),
+        unread_count = conversations.unread_count + EXCLUDED.unread_count;
+$$ LANGUAGE sql;
+
+CREATE FUNCTION track_conversations() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        -- A note to self is one conversation, not two
+        IF NEW.sender_id <> NEW.receiver_id THEN
+            PERFORM touch_conversation(NEW.sender_id, NEW.receiver_id, NEW.id, NEW.created_at, 0);
+        END IF;
+        PERFORM touch_conversation(NEW.receiver_id, NEW.sender_id, NEW.id, NEW.created_at,
+                                   CASE WHEN NEW.is_read THEN 0 ELSE 1 END);
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'UPDATE' THEN
+        IF OLD.is_read <> NEW.is_read THEN
+            UPDATE conversations
+            SET unread_count = unread_count + CASE WHEN NEW.is_read THEN -1 ELSE 1 END
+            WHERE user_id = NEW.receiver_id AND counterpart_id = NEW.sender_id;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF NOT OLD.is_read THEN
+        UPDATE conversations
+        SET unread_count = unread_count - 1
+        WHERE user_id = OLD.receiver_id AND counterpart_id = OLD.sender_id;
+    END IF;
+
+    -- Fall back to the message before the deleted one, or drop the conversation with its last message
+    DELETE FROM conversations
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND NOT EXISTS (
+          SELECT 1 FROM messages
+          WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+            AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+      );
+    UPDATE conversations
+    SET (last_message_id, last_message_at) = (
+        SELECT id, created_at FROM messages
+        WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+          AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+    )
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND last_message_id = OLD.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER messages_track_conversations
+AFTER INSERT OR UPDATE OF is_read OR DELETE ON messages
+FOR EACH ROW EXECUTE FUNCTION track_conversations();
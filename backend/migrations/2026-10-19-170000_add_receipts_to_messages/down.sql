@@ -0,0 +1,5 @@
+ALTER TABLE messages
+    DROP CONSTRAINT messages_read_after_delivery,
+    DROP CONSTRAINT messages_read_at_matches_is_read,
+    DROP COLUMN read_at,
+    DROP COLUMN delivered_at;
@@ -0,0 +1,12 @@
+-- When a message reached the recipient's device and when they read it. `is_read` stays, as
+-- the flag the conversation unread counts follow, and always agrees with `read_at`.
+ALTER TABLE messages
+    ADD COLUMN delivered_at TIMESTAMP,
+    ADD COLUMN read_at TIMESTAMP;
+
+-- Messages read before receipts were recorded get the best time known
+UPDATE messages SET read_at = created_at, delivered_at = created_at WHERE is_read;
+
+ALTER TABLE messages
+    ADD CONSTRAINT messages_read_at_matches_is_read CHECK (is_read = (read_at IS NOT NULL)),
+    ADD CONSTRAINT messages_read_after_delivery CHECK (read_at IS NULL OR delivered_at IS NOT NULL);
@@ -0,0 +1,2 @@
+DROP TABLE delivery_cursors;
+DROP TABLE user_events;
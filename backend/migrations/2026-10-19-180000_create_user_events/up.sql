@@ -0,0 +1,32 @@
+-- Server events a user must not miss, such as new messages and receipts, kept until their
+-- socket has been sent them. Events are sent in id order.
+CREATE TABLE user_events (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_user_event_order ON user_events (user_id, id);
+
+-- The last event each user's socket was sent
+CREATE TABLE delivery_cursors (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    event_id BIGINT NOT NULL
+);
+
+-- Messages nobody has received yet are waiting for their receivers
+INSERT INTO user_events (user_id, payload, created_at)
+SELECT receiver_id,
+       jsonb_build_object('NewMessage', jsonb_strip_nulls(jsonb_build_object(
+           'message_id', id,
+           'from_user_id', sender_id,
+           'to_user_id', receiver_id,
+           'content', content,
+           'attachment_id', attachment_id,
+           'created_at', to_char(created_at, 'YYYY-MM-DD"T"HH24:MI:SS.US')
+       ))),
+       created_at
+FROM messages
+WHERE delivered_at IS NULL
+ORDER BY created_at, id;
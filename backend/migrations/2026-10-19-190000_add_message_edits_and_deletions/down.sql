@@ -0,0 +1,61 @@
+-- Messages are back to counting as unread until read
+DROP TRIGGER messages_track_conversations ON messages;
+CREATE OR REPLACE FUNCTION track_conversations() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        -- A note to self is one conversation, not two
+        IF NEW.sender_id <> NEW.receiver_id THEN
+            PERFORM touch_conversation(NEW.sender_id, NEW.receiver_id, NEW.id, NEW.created_at, 0);
+        END IF;
+        PERFORM touch_conversation(NEW.receiver_id, NEW.sender_id, NEW.id, NEW.created_at,
+                                   CASE WHEN NEW.is_read THEN 0 ELSE 1 END);
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'UPDATE' THEN
+        IF OLD.is_read <> NEW.is_read THEN
+            UPDATE conversations
+            SET unread_count = unread_count + CASE WHEN NEW.is_read THEN -1 ELSE 1 END
+            WHERE user_id = NEW.receiver_id AND counterpart_id = NEW.sender_id;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF NOT OLD.is_read THEN
+        UPDATE conversations
+        SET unread_count = unread_count - 1
+        WHERE user_id = OLD.receiver_id AND counterpart_id = OLD.sender_id;
+    END IF;
+
+    -- Fall back to the message before the deleted one, or drop the conversation with its last message
+    DELETE FROM conversations
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND NOT EXISTS (
+          SELECT 1 FROM messages
+          WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+            AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+      );
+    UPDATE conversations
+    SET (last_message_id, last_message_at) = (
+        SELECT id, created_at FROM messages
+        WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+          AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+    )
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND last_message_id = OLD.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER messages_track_conversations
+AFTER INSERT OR UPDATE OF is_read OR DELETE ON messages
+FOR EACH ROW EXECUTE FUNCTION track_conversations();
+
+DROP TABLE message_deletions;
+DROP TABLE message_edits;
+
+ALTER TABLE messages
+    DROP COLUMN deleted_at,
+    DROP COLUMN edited_at;
@@ -0,0 +1,83 @@
+-- A message deleted for everyone stays behind as a tombstone: `deleted_at` is set and its
+-- content, attachment and edit history are cleared.
+ALTER TABLE messages
+    ADD COLUMN edited_at TIMESTAMP,
+    ADD COLUMN deleted_at TIMESTAMP;
+
+-- What edited messages said before each edit
+CREATE TABLE message_edits (
+    id SERIAL PRIMARY KEY,
+    message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    -- When this content was replaced
+    edited_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_message_edit_message ON message_edits (message_id, id);
+
+-- Messages a participant deleted for themselves only
+CREATE TABLE message_deletions (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    deleted_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, message_id)
+);
+
+-- Messages deleted for everyone no longer count as unread
+CREATE OR REPLACE FUNCTION track_conversations() RETURNS TRIGGER AS $$
+DECLARE
+    unread_change INTEGER;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        -- A note to self is one conversation, not two
+        IF NEW.sender_id <> NEW.receiver_id THEN
+            PERFORM touch_conversation(NEW.sender_id, NEW.receiver_id, NEW.id, NEW.created_at, 0);
+        END IF;
+        PERFORM touch_conversation(NEW.receiver_id, NEW.sender_id, NEW.id, NEW.created_at,
+                                   CASE WHEN NEW.is_read OR NEW.deleted_at IS NOT NULL THEN 0 ELSE 1 END);
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'UPDATE' THEN
+        unread_change := (NOT NEW.is_read AND NEW.deleted_at IS NULL)::INTEGER
+                       - (NOT OLD.is_read AND OLD.deleted_at IS NULL)::INTEGER;
+        IF unread_change <> 0 THEN
+            UPDATE conversations
+            SET unread_count = unread_count + unread_change
+            WHERE user_id = NEW.receiver_id AND counterpart_id = NEW.sender_id;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF NOT OLD.is_read AND OLD.deleted_at IS NULL THEN
+        UPDATE conversations
+        SET unread_count = unread_count - 1
+        WHERE user_id = OLD.receiver_id AND counterpart_id = OLD.sender_id;
+    END IF;
+
+    -- Fall back to the message before the deleted one, or drop the conversation with its last message
+    DELETE FROM conversations
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND NOT EXISTS (
+          SELECT 1 FROM messages
+          WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+            AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+      );
+    UPDATE conversations
+    SET (last_message_id, last_message_at) = (
+        SELECT id, created_at FROM messages
+        WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+          AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+    )
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND last_message_id = OLD.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER messages_track_conversations ON messages;
+CREATE TRIGGER messages_track_conversations
+AFTER INSERT OR UPDATE OF is_read, deleted_at OR DELETE ON messages
+FOR EACH ROW EXECUTE FUNCTION track_conversations();
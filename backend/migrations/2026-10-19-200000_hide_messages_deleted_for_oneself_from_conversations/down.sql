@@ -0,0 +1,73 @@
+DROP TRIGGER message_deletions_track_conversations ON message_deletions;
+DROP FUNCTION track_message_deletions();
+
+-- Messages deleted for oneself are back in the inbox
+CREATE OR REPLACE FUNCTION track_conversations() RETURNS TRIGGER AS $$
+DECLARE
+    unread_change INTEGER;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        -- A note to self is one conversation, not two
+        IF NEW.sender_id <> NEW.receiver_id THEN
+            PERFORM touch_conversation(NEW.sender_id, NEW.receiver_id, NEW.id, NEW.created_at, 0);
+        END IF;
+        PERFORM touch_conversation(NEW.receiver_id, NEW.sender_id, NEW.id, NEW.created_at,
+                                   CASE WHEN NEW.is_read OR NEW.deleted_at IS NOT NULL THEN 0 ELSE 1 END);
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'UPDATE' THEN
+        unread_change := (NOT NEW.is_read AND NEW.deleted_at IS NULL)::INTEGER
+                       - (NOT OLD.is_read AND OLD.deleted_at IS NULL)::INTEGER;
+        IF unread_change <> 0 THEN
+            UPDATE conversations
+            SET unread_count = unread_count + unread_change
+            WHERE user_id = NEW.receiver_id AND counterpart_id = NEW.sender_id;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF NOT OLD.is_read AND OLD.deleted_at IS NULL THEN
+        UPDATE conversations
+        SET unread_count = unread_count - 1
+        WHERE user_id = OLD.receiver_id AND counterpart_id = OLD.sender_id;
+    END IF;
+
+    -- Fall back to the message before the deleted one, or drop the conversation with its last message
+    DELETE FROM conversations
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND NOT EXISTS (
+          SELECT 1 FROM messages
+          WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+            AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+      );
+    UPDATE conversations
+    SET (last_message_id, last_message_at) = (
+        SELECT id, created_at FROM messages
+        WHERE LEAST(sender_id, receiver_id) = LEAST(OLD.sender_id, OLD.receiver_id)
+          AND GREATEST(sender_id, receiver_id) = GREATEST(OLD.sender_id, OLD.receiver_id)
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+    )
+    WHERE (user_id, counterpart_id) IN ((OLD.sender_id, OLD.receiver_id), (OLD.receiver_id, OLD.sender_id))
+      AND last_message_id = OLD.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION refresh_conversation(INTEGER, INTEGER);
+
+-- Messages deleted for oneself meanwhile are back on both sides
+DELETE FROM conversations;
+INSERT INTO conversations (user_id, counterpart_id, last_message_id, last_message_at, unread_count)
+SELECT user_id,
+       counterpart_id,
+       (ARRAY_AGG(id ORDER BY created_at DESC, id DESC))[1],
+       MAX(created_at),
+       SUM(unread)
+FROM (
+    SELECT sender_id, receiver_id, id, created_at, 0 FROM messages WHERE sender_id <> receiver_id
+    UNION ALL
+    SELECT receiver_id, sender_id, id, created_at, CASE WHEN is_read OR deleted_at IS NOT NULL THEN 0 ELSE 1 END FROM messages
+) AS sides (user_id, counterpart_id, id, created_at, unread)
+GROUP BY user_id, counterpart_id;
@@ -0,0 +1,91 @@
+-- Points one user's side of a conversation at the latest message they haven't deleted for
+-- themselves and recounts what is unread, or drops it when nothing is left for them
+CREATE FUNCTION refresh_conversation(owner INTEGER, other INTEGER) RETURNS VOID AS $$
+DECLARE
+    latest RECORD;
+BEGIN
+    SELECT id, created_at INTO latest FROM messages
+    WHERE LEAST(sender_id, receiver_id) = LEAST(owner, other)
+      AND GREATEST(sender_id, receiver_id) = GREATEST(owner, other)
+      AND NOT EXISTS (SELECT 1 FROM message_deletions WHERE user_id = owner AND message_id = messages.id)
+    ORDER BY created_at DESC, id DESC
+    LIMIT 1;
+
+    IF NOT FOUND THEN
+        DELETE FROM conversations WHERE user_id = owner AND counterpart_id = other;
+        RETURN;
+    END IF;
+
+    UPDATE conversations
+    SET last_message_id = latest.id,
+        last_message_at = latest.created_at,
+        unread_count = (
+            SELECT COUNT(*) FROM messages
+            WHERE sender_id = other AND receiver_id = owner
+              AND NOT is_read AND deleted_at IS NULL
+              AND NOT EXISTS (SELECT 1 FROM message_deletions WHERE user_id = owner AND message_id = messages.id)
+        )
+    WHERE user_id = owner AND counterpart_id = other;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Messages a receiver deleted for themselves were already taken off their count
+CREATE OR REPLACE FUNCTION track_conversations() RETURNS TRIGGER AS $$
+DECLARE
+    unread_change INTEGER;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        -- A note to self is one conversation, not two
+        IF NEW.sender_id <> NEW.receiver_id THEN
+            PERFORM touch_conversation(NEW.sender_id, NEW.receiver_id, NEW.id, NEW.created_at, 0);
+        END IF;
+        PERFORM touch_conversation(NEW.receiver_id, NEW.sender_id, NEW.id, NEW.created_at,
+                                   CASE WHEN NEW.is_read OR NEW.deleted_at IS NOT NULL THEN 0 ELSE 1 END);
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'UPDATE' THEN
+        unread_change := (NOT NEW.is_read AND NEW.deleted_at IS NULL)::INTEGER
+                       - (NOT OLD.is_read AND OLD.deleted_at IS NULL)::INTEGER;
+        IF unread_change <> 0 AND NOT EXISTS (
+            SELECT 1 FROM message_deletions WHERE user_id = NEW.receiver_id AND message_id = NEW.id
+        ) THEN
+            UPDATE conversations
+            SET unread_count = unread_count + unread_change
+            WHERE user_id = NEW.receiver_id AND counterpart_id = NEW.sender_id;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    -- Recounted rather than adjusted, as the message's deletions may already be gone with it
+    PERFORM refresh_conversation(OLD.receiver_id, OLD.sender_id);
+    IF OLD.sender_id <> OLD.receiver_id THEN
+        PERFORM refresh_conversation(OLD.sender_id, OLD.receiver_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION track_message_deletions() RETURNS TRIGGER AS $$
+DECLARE
+    deleted RECORD;
+BEGIN
+    SELECT sender_id, receiver_id INTO deleted FROM messages WHERE id = NEW.message_id;
+    PERFORM refresh_conversation(NEW.user_id,
+                                 CASE WHEN deleted.sender_id = NEW.user_id THEN deleted.receiver_id ELSE deleted.sender_id END);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER message_deletions_track_conversations
+AFTER INSERT ON message_deletions
+FOR EACH ROW EXECUTE FUNCTION track_message_deletions();
+
+SELECT refresh_conversation(user_id, counterpart_id)
+FROM (SELECT DISTINCT user_id, counterpart_id FROM conversations) AS sides
+WHERE EXISTS (
+    SELECT 1 FROM message_deletions
+    JOIN messages ON messages.id = message_deletions.message_id
+    WHERE message_deletions.user_id = sides.user_id
+      AND (messages.sender_id = sides.counterpart_id OR messages.receiver_id = sides.counterpart_id)
+);
@@ -0,0 +1,23 @@
+CREATE OR REPLACE FUNCTION move_media_reference(old_key VARCHAR, new_key VARCHAR) RETURNS VOID AS $$
+BEGIN
+    IF old_key IS NOT DISTINCT FROM new_key THEN
+        RETURN;
+    END IF;
+
+    IF new_key IS NOT NULL THEN
+        INSERT INTO media_objects (storage_key, reference_count)
+        VALUES (new_key, 1)
+        ON CONFLICT (storage_key) DO UPDATE
+        SET reference_count = media_objects.reference_count + 1,
+            updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    IF old_key IS NOT NULL THEN
+        UPDATE media_objects
+        SET reference_count = reference_count - 1,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE storage_key = old_key;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE media_objects DROP COLUMN claimed_at;
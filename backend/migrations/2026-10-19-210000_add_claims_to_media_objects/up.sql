@@ -0,0 +1,34 @@
+-- Set while the file of an unreferenced object is being deleted. A claim that is never
+-- released, say by a crashed server, lapses after a few minutes.
+ALTER TABLE media_objects ADD COLUMN claimed_at TIMESTAMP;
+
+-- A new reference to a claimed object fails rather than point at a file about to be deleted
+CREATE OR REPLACE FUNCTION move_media_reference(old_key VARCHAR, new_key VARCHAR) RETURNS VOID AS $$
+BEGIN
+    IF old_key IS NOT DISTINCT FROM new_key THEN
+        RETURN;
+    END IF;
+
+    IF new_key IS NOT NULL THEN
+        IF EXISTS (
+            SELECT 1 FROM media_objects
+            WHERE storage_key = new_key AND claimed_at > CURRENT_TIMESTAMP - INTERVAL '5 minutes'
+            FOR UPDATE
+        ) THEN
+            RAISE EXCEPTION 'media object % is being deleted', new_key USING ERRCODE = 'object_in_use';
+        END IF;
+        INSERT INTO media_objects (storage_key, reference_count)
+        VALUES (new_key, 1)
+        ON CONFLICT (storage_key) DO UPDATE
+        SET reference_count = media_objects.reference_count + 1,
+            claimed_at = NULL,
+            updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    IF old_key IS NOT NULL THEN
+        UPDATE media_objects
+        SET reference_count = reference_count - 1,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE storage_key = old_key;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,489 @@
+use std::sync::Arc;
+use actix_cors::Cors;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderName};
+use actix_web::{middleware, web, App};
+use actix_web_httpauth::middleware::HttpAuthentication;
+
+use crate::application::use_cases::{
+    account_use_cases::{GetAccountUseCase, UpdateAccountUseCase},
+    attachment_use_cases::GetAttachmentUseCase,
+    auth_use_cases::{LoginUseCase, RegisterUseCase},
+    avatar_use_cases::{
+        DeleteAvatarUseCase, ListAvatarsUseCase, ProcessAvatarUseCase, SetDefaultAvatarUseCase, UploadAvatarUseCase,
+    },
+    conversation_use_cases::ListConversationsUseCase,
+    job_use_cases::GetJobUseCase,
+    media_use_cases::{AuthorizeMediaUseCase, ReconcileMediaUseCase},
+    message_use_cases::{
+        CheckConversationUseCase, DeleteMessageUseCase, EditMessageUseCase, GetMessageEditsUseCase, GetMessagesUseCase,
+        MarkDeliveredUseCase, MarkReadUseCase, SendMessageUseCase,
+    },
+    upload_use_cases::{
+        AppendUploadUseCase, CreateUploadUseCase, ExpireUploadsUseCase, GetUploadUseCase, TerminateUploadUseCase,
+    },
+    user_use_cases::{CreateUserUseCase, DeleteUserUseCase, GetUserByIdUseCase, ListUsersUseCase, UpdateUserUseCase},
+};
+use crate::domain::repositories::{
+    account_repository::AccountRepository,
+    attachment_repository::AttachmentRepository,
+    auth_repository::AuthRepository,
+    avatar_repository::AvatarRepository,
+    conversation_repository::ConversationRepository,
+    job_repository::JobRepository,
+    media_object_repository::MediaObjectRepository,
+    media_storage::MediaStorage,
+    message_repository::MessageRepository,
+    upload_repository::UploadRepository,
+    user_event_repository::UserEventRepository,
+    user_repository::UserRepository,
+};
+use crate::infrastructure::{
+    config::{database::DbPool, settings::Settings},
+    jobs::JobWorker,
+    repositories::{
+        account_repository::AccountRepositoryImpl,
+        attachment_repository::AttachmentRepositoryImpl,
+        auth_repository::AuthRepositoryImpl,
+        avatar_repository::AvatarRepositoryImpl,
+        conversation_repository::ConversationRepositoryImpl,
+        job_repository::JobRepositoryImpl,
+        media_object_repository::MediaObjectRepositoryImpl,
+        message_repository::MessageRepositoryImpl,
+        upload_repository::UploadRepositoryImpl,
+        user_event_repository::UserEventRepositoryImpl,
+        user_repository::UserRepositoryImpl,
+    },
+    storage,
+    websocket::{realtime_message_manager::RealtimeMessageManager, user_status_manager::UserStatusManager},
+};
+use crate::presentation::{
+    handlers::{
+        account_handlers::{self, AccountHandlers},
+        attachment_handlers::{self, AttachmentHandlers},
+        auth_handlers::{self, AuthHandlers},
+        avatar_handlers::{self, AvatarHandlers},
+        conversation_handlers::{self, ConversationHandlers},
+        health_handlers::{self, HealthHandlers},
+        job_handlers::{self, JobHandlers},
+        media_handlers::{self, MediaHandlers},
+        message_handlers::{self, MessageHandlers},
+        metrics_handlers::{self, MetricsHandlers},
+        upload_handlers::{self, UploadHandlers},
+        user_handlers::{self, UserHandlers},
+        ws_handlers,
+    },
+    jobs::{avatar_jobs::AvatarJobHandler, media_jobs::MediaReconciliationJobHandler, upload_jobs::UploadExpiryJobHandler},
+    middleware::{
+        auth::validator,
+        metrics::track_http_metrics,
+        request_id::{request_id, REQUEST_ID_HEADER},
+    },
+    openapi,
+};
+
+pub type SharedUserRepository = Arc<dyn UserRepository + Send + Sync>;
+pub type SharedAuthRepository = Arc<dyn AuthRepository + Send + Sync>;
+pub type SharedAccountRepository = Arc<dyn AccountRepository + Send + Sync>;
+pub type SharedAvatarRepository = Arc<dyn AvatarRepository + Send + Sync>;
+pub type SharedMessageRepository = Arc<dyn MessageRepository + Send + Sync>;
+pub type SharedConversationRepository = Arc<dyn ConversationRepository + Send + Sync>;
+pub type SharedMediaStorage = Arc<dyn MediaStorage + Send + Sync>;
+pub type SharedJobRepository = Arc<dyn JobRepository + Send + Sync>;
+pub type SharedUploadRepository = Arc<dyn UploadRepository + Send + Sync>;
+pub type SharedAttachmentRepository = Arc<dyn AttachmentRepository + Send + Sync>;
+pub type SharedMediaObjectRepository = Arc<dyn MediaObjectRepository + Send + Sync>;
+pub type SharedUserEventRepository = Arc<dyn UserEventRepository + Send + Sync>;
+
+/// The storage backend behind every use case, chosen once at startup.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: SharedUserRepository,
+    pub auth: SharedAuthRepository,
+    pub accounts: SharedAccountRepository,
+    pub avatars: SharedAvatarRepository,
+    pub messages: SharedMessageRepository,
+    pub conversations: SharedConversationRepository,
+    pub jobs: SharedJobRepository,
+    pub uploads: SharedUploadRepository,
+    pub attachments: SharedAttachmentRepository,
+    pub media_objects: SharedMediaObjectRepository,
+    pub user_events: SharedUserEventRepository,
+}
+
+impl Repositories {
+    pub fn postgres(pool: &DbPool, secret_key: &str) -> Self {
+        Self {
+            users: Arc::new(UserRepositoryImpl::new(pool.clone())),
+            auth: Arc::new(AuthRepositoryImpl::new(pool.clone(), secret_key.to_string())),
+            accounts: Arc::new(AccountRepositoryImpl::new(pool.clone())),
+            avatars: Arc::new(AvatarRepositoryImpl::new(pool.clone())),
+            messages: Arc::new(MessageRepositoryImpl::new(pool.clone())),
+            conversations: Arc::new(ConversationRepositoryImpl::new(pool.clone())),
+            jobs: Arc::new(JobRepositoryImpl::new(pool.clone())),
+            uploads: Arc::new(UploadRepositoryImpl::new(pool.clone())),
+            attachments: Arc::new(AttachmentRepositoryImpl::new(pool.clone())),
+            media_objects: Arc::new(MediaObjectRepositoryImpl::new(pool.clone())),
+            user_events: Arc::new(UserEventRepositoryImpl::new(pool.clone())),
+        }
+    }
+
+    /// Repositories sharing one fresh in-memory store; nothing survives a restart.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn in_memory(secret_key: &str) -> Self {
+        use crate::infrastructure::repositories::in_memory::{
+            InMemoryAccountRepository, InMemoryAttachmentRepository, InMemoryAuthRepository, InMemoryAvatarRepository,
+            InMemoryConversationRepository, InMemoryJobRepository, InMemoryMediaObjectRepository, InMemoryMessageRepository, InMemoryStore,
+            InMemoryUploadRepository, InMemoryUserEventRepository, InMemoryUserRepository,
+        };
+
+        let store = InMemoryStore::new();
+        Self {
+            users: Arc::new(InMemoryUserRepository::new(store.clone())),
+            auth: Arc::new(InMemoryAuthRepository::new(store.clone(), secret_key.to_string())),
+            accounts: Arc::new(InMemoryAccountRepository::new(store.clone())),
+            avatars: Arc::new(InMemoryAvatarRepository::new(store.clone())),
+            messages: Arc::new(InMemoryMessageRepository::new(store.clone())),
+            conversations: Arc::new(InMemoryConversationRepository::new(store.clone())),
+            jobs: Arc::new(InMemoryJobRepository::new(store.clone())),
+            uploads: Arc::new(InMemoryUploadRepository::new(store.clone())),
+            attachments: Arc::new(InMemoryAttachmentRepository::new(store.clone())),
+            media_objects: Arc::new(InMemoryMediaObjectRepository::new(store.clone())),
+            user_events: Arc::new(InMemoryUserEventRepository::new(store)),
+        }
+    }
+}
+
+/// Handlers and shared services for one server. Built once and cloned into every worker,
+/// so WebSocket connection state is shared across workers.
+#[derive(Clone)]
+pub struct AppState {
+    pub settings: web::Data<Settings>,
+    pub user_handlers: web::Data<UserHandlers<SharedUserRepository>>,
+    pub auth_handlers: web::Data<AuthHandlers<SharedAuthRepository>>,
+    pub account_handlers: web::Data<AccountHandlers<SharedAccountRepository, SharedMediaStorage>>,
+    pub avatar_handlers: web::Data<AvatarHandlers<SharedAvatarRepository, SharedAccountRepository, SharedMediaStorage, SharedJobRepository, SharedMediaObjectRepository>>,
+    pub job_handlers: web::Data<JobHandlers<SharedJobRepository>>,
+    pub media_handlers: web::Data<MediaHandlers<SharedMediaStorage, SharedAttachmentRepository, SharedAvatarRepository>>,
+    pub upload_handlers: web::Data<UploadHandlers<SharedUploadRepository, SharedMediaStorage, SharedAccountRepository, SharedJobRepository, SharedAttachmentRepository>>,
+    pub attachment_handlers: web::Data<AttachmentHandlers<SharedAttachmentRepository, SharedMediaStorage>>,
+    pub message_handlers: web::Data<MessageHandlers<SharedMessageRepository, SharedAttachmentRepository>>,
+    pub conversation_handlers: web::Data<ConversationHandlers<SharedConversationRepository, SharedAccountRepository, SharedMediaStorage>>,
+    pub health_handlers: web::Data<HealthHandlers>,
+    pub metrics_handlers: web::Data<MetricsHandlers>,
+    pub user_status_manager: web::Data<Arc<UserStatusManager>>,
+    pub realtime_message_manager: web::Data<RealtimeMessageManager>,
+    /// Not started here: `main` spawns its polling loops, tests drive it directly.
+    pub job_worker: JobWorker<SharedJobRepository>,
+    /// Also registered with `job_worker`; kept to queue the first run at startup.
+    pub media_reconciliation: Arc<MediaReconciliationJobHandler<
+        SharedAvatarRepository,
+        SharedAttachmentRepository,
+        SharedUploadRepository,
+        SharedJobRepository,
+        SharedMediaObjectRepository,
+        SharedMediaStorage,
+    >>,
+    /// Also registered with `job_worker`; kept to queue the first run at startup.
+    pub upload_expiry: Arc<UploadExpiryJobHandler<SharedUploadRepository, SharedMediaStorage, SharedJobRepository>>,
+}
+
+impl AppState {
+    pub fn new(settings: Settings, pool: DbPool) -> Self {
+        let repositories = Repositories::postgres(&pool, &settings.secret_key);
+        Self::with_repositories(settings, repositories, Some(pool))
+    }
+
+    /// Runs the whole server against in-memory storage, without Postgres.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn in_memory(settings: Settings) -> Self {
+        let repositories = Repositories::in_memory(&settings.secret_key);
+        Self::with_repositories(settings, repositories, None)
+    }
+
+    /// `pool` is only used by the readiness probe and pool metrics, and is `None` when the
+    /// repositories do not talk to Postgres.
+    pub fn with_repositories(settings: Settings, repositories: Repositories, pool: Option<DbPool>) -> Self {
+        let media_url_signer = storage::signer_from_settings(&settings);
+        let media_storage = storage::from_settings(&settings, media_url_signer.clone())
+            .expect("Failed to configure media storage");
+
+        let Repositories {
+            users: user_repository,
+            auth: auth_repository,
+            accounts: account_repository,
+            avatars: avatar_repository,
+            messages: message_repository,
+            conversations: conversation_repository,
+            jobs: job_repository,
+            uploads: upload_repository,
+            attachments: attachment_repository,
+            media_objects: media_object_repository,
+            user_events: user_event_repository,
+        } = repositories;
+
+        // Initialize WebSocket managers
+        let user_status_manager = Arc::new(UserStatusManager::new());
+        let realtime_message_manager = RealtimeMessageManager::new(user_status_manager.clone(), user_event_repository);
+
+        // Initialize handlers
+        let user_handlers = UserHandlers::new(
+            GetUserByIdUseCase::new(user_repository.clone()),
+            CreateUserUseCase::new(user_repository.clone()),
+            ListUsersUseCase::new(user_repository.clone()),
+            UpdateUserUseCase::new(user_repository.clone()),
+            DeleteUserUseCase::new(user_repository.clone()),
+        );
+
+        let auth_handlers = AuthHandlers::new(
+            LoginUseCase::new(auth_repository.clone(), settings.secret_key.clone()),
+            RegisterUseCase::new(auth_repository),
+        );
+
+        let account_handlers = AccountHandlers::new(
+            GetAccountUseCase::new(account_repository.clone(), media_storage.clone()),
+            UpdateAccountUseCase::new(account_repository.clone(), media_storage.clone()),
+        );
+
+        let conversation_handlers = ConversationHandlers::new(
+            ListConversationsUseCase::new(conversation_repository, account_repository.clone(), media_storage.clone()),
+        );
+
+        let avatar_handlers = AvatarHandlers::new(
+            UploadAvatarUseCase::new(
+                account_repository.clone(),
+                media_storage.clone(),
+                job_repository.clone(),
+                settings.media.upload_limits,
+            ),
+            ListAvatarsUseCase::new(avatar_repository.clone(), account_repository.clone(), media_storage.clone()),
+            SetDefaultAvatarUseCase::new(avatar_repository.clone(), account_repository.clone(), media_storage.clone()),
+            DeleteAvatarUseCase::new(
+                avatar_repository.clone(),
+                account_repository.clone(),
+                media_object_repository.clone(),
+                media_storage.clone(),
+            ),
+        );
+
+        let job_handlers = JobHandlers::new(GetJobUseCase::new(job_repository.clone()));
+
+        let upload_handlers = UploadHandlers::new(
+            CreateUploadUseCase::new(
+                upload_repository.clone(),
+                settings.media.resumable.clone(),
+                settings.media.upload_limits,
+            ),
+            GetUploadUseCase::new(upload_repository.clone()),
+            AppendUploadUseCase::new(
+                upload_repository.clone(),
+                media_storage.clone(),
+                UploadAvatarUseCase::new(
+                    account_repository.clone(),
+                    media_storage.clone(),
+                    job_repository.clone(),
+                    settings.media.upload_limits,
+                ),
+                attachment_repository.clone(),
+                settings.media.resumable.part_bytes,
+            ),
+            TerminateUploadUseCase::new(upload_repository.clone(), media_storage.clone()),
+        );
+
+        let attachment_handlers = AttachmentHandlers::new(
+            GetAttachmentUseCase::new(attachment_repository.clone(), media_storage.clone()),
+        );
+
+        let avatar_job_handler = AvatarJobHandler::new(
+            ProcessAvatarUseCase::new(
+                avatar_repository.clone(),
+                account_repository,
+                media_storage.clone(),
+                settings.media.avatar_variants.clone(),
+                settings.media.upload_limits,
+            ),
+            realtime_message_manager.clone(),
+        );
+        let media_reconciliation = Arc::new(MediaReconciliationJobHandler::new(
+            ReconcileMediaUseCase::new(
+                avatar_repository.clone(),
+                attachment_repository.clone(),
+                upload_repository.clone(),
+                job_repository.clone(),
+                media_object_repository,
+                media_storage.clone(),
+                settings.media.gc.grace_period,
+            ),
+            job_repository.clone(),
+            settings.media.gc.clone(),
+        ));
+        let upload_expiry = Arc::new(UploadExpiryJobHandler::new(
+            ExpireUploadsUseCase::new(upload_repository, media_storage.clone()),
+            job_repository.clone(),
+            settings.media.resumable.cleanup_interval,
+        ));
+        let job_worker = JobWorker::new(
+            job_repository,
+            vec![Arc::new(avatar_job_handler), media_reconciliation.clone(), upload_expiry.clone()],
+            settings.jobs.clone(),
+        );
+
+        let media_handlers = MediaHandlers::new(
+            media_storage.clone(),
+            media_url_signer,
+            settings.secret_key.clone(),
+            AuthorizeMediaUseCase::new(attachment_repository.clone(), avatar_repository),
+        );
+
+        let message_handlers = MessageHandlers::new(
+            SendMessageUseCase::new(message_repository.clone(), attachment_repository),
+            GetMessagesUseCase::new(message_repository.clone()),
+            MarkDeliveredUseCase::new(message_repository.clone()),
+            MarkReadUseCase::new(message_repository.clone()),
+            EditMessageUseCase::new(message_repository.clone(), settings.chat.edit_window),
+            DeleteMessageUseCase::new(message_repository.clone()),
+            GetMessageEditsUseCase::new(message_repository.clone()),
+            CheckConversationUseCase::new(message_repository),
+            realtime_message_manager.clone(),
+        );
+
+        let health_handlers = HealthHandlers::new(
+            pool.clone(),
+            media_storage,
+            user_status_manager.clone(),
+        );
+
+        let metrics_handlers = MetricsHandlers::new(pool, user_status_manager.clone());
+
+        Self {
+            settings: web::Data::new(settings),
+            user_handlers: web::Data::new(user_handlers),
+            auth_handlers: web::Data::new(auth_handlers),
+            account_handlers: web::Data::new(account_handlers),
+            avatar_handlers: web::Data::new(avatar_handlers),
+            job_handlers: web::Data::new(job_handlers),
+            upload_handlers: web::Data::new(upload_handlers),
+            attachment_handlers: web::Data::new(attachment_handlers),
+            media_handlers: web::Data::new(media_handlers),
+            message_handlers: web::Data::new(message_handlers),
+            conversation_handlers: web::Data::new(conversation_handlers),
+            health_handlers: web::Data::new(health_handlers),
+            metrics_handlers: web::Data::new(metrics_handlers),
+            user_status_manager: web::Data::new(user_status_manager),
+            realtime_message_manager: web::Data::new(realtime_message_manager),
+            job_worker,
+            media_reconciliation,
+            upload_expiry,
+        }
+    }
+}
+
+/// Builds the application with fresh state; convenient for tests that need a single app.
+pub fn build_app(
+    settings: Settings,
+    pool: DbPool,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    build_app_with_state(&AppState::new(settings, pool))
+}
+
+pub fn build_app_with_state(
+    state: &AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let state = state.clone();
+    let auth = HttpAuthentication::bearer(validator);
+
+    let allowed_origins = state.settings.allowed_origins.clone();
+    let cors = Cors::default()
+        .allowed_origin_fn(move |origin, _req_head| {
+            allowed_origins.iter().any(|allowed| allowed.as_bytes() == origin.as_bytes())
+        })
+        .allowed_methods(vec!["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"])
+        .allowed_headers(vec![
+            header::AUTHORIZATION,
+            header::ACCEPT,
+            header::CONTENT_TYPE,
+            REQUEST_ID_HEADER,
+            HeaderName::from_static("traceparent"),
+            HeaderName::from_static("tracestate"),
+            upload_handlers::TUS_RESUMABLE,
+            upload_handlers::UPLOAD_LENGTH,
+            upload_handlers::UPLOAD_OFFSET,
+            upload_handlers::UPLOAD_METADATA,
+        ])
+        .expose_headers(vec![
+            REQUEST_ID_HEADER,
+            header::LOCATION,
+            upload_handlers::TUS_RESUMABLE,
+            upload_handlers::TUS_VERSION_HEADER,
+            upload_handlers::TUS_EXTENSION,
+            upload_handlers::TUS_MAX_SIZE,
+            upload_handlers::UPLOAD_OFFSET,
+            upload_handlers::UPLOAD_LENGTH,
+            upload_handlers::UPLOAD_METADATA,
+            upload_handlers::UPLOAD_EXPIRES,
+        ])
+        .supports_credentials()
+        .max_age(3600);
+
+    App::new()
+        .wrap(middleware::from_fn(track_http_metrics))
+        .wrap(middleware::from_fn(request_id))
+        .app_data(state.settings.clone())
+        .app_data(state.user_handlers.clone())
+        .app_data(state.auth_handlers.clone())
+        .app_data(state.account_handlers.clone())
+        .app_data(state.avatar_handlers.clone())
+        .app_data(state.job_handlers.clone())
+        .app_data(state.upload_handlers.clone())
+        .app_data(state.attachment_handlers.clone())
+        .app_data(state.media_handlers.clone())
+        .app_data(state.message_handlers.clone())
+        .app_data(state.conversation_handlers.clone())
+        .app_data(state.health_handlers.clone())
+        .app_data(state.metrics_handlers.clone())
+        .app_data(state.user_status_manager.clone())
+        .app_data(state.realtime_message_manager.clone())
+        // Probes and metrics are registered ahead of the CORS scope so they bypass both CORS and auth
+        .configure(|cfg| health_handlers::configure(cfg, state.health_handlers.clone()))
+        .configure(|cfg| metrics_handlers::configure(cfg, state.metrics_handlers.clone()))
+        .service(
+            web::scope("")
+                .wrap(cors)
+                .configure(ws_handlers::configure)
+                .configure(|cfg| media_handlers::configure(cfg, state.media_handlers.clone()))
+                .service(
+                    web::scope("/api/v1")
+                        .configure(|cfg| auth_handlers::configure(cfg, state.auth_handlers.clone()))
+                        .configure(openapi::configure)
+                        .service(
+                            web::scope("")
+                                .wrap(auth)
+                                .configure(|cfg| user_handlers::configure(cfg, state.user_handlers.clone()))
+                                .configure(|cfg| account_handlers::configure(cfg, state.account_handlers.clone()))
+                                .configure(|cfg| avatar_handlers::configure(cfg, state.avatar_handlers.clone()))
+                                .configure(|cfg| job_handlers::configure(cfg, state.job_handlers.clone()))
+                                .configure(|cfg| upload_handlers::configure(cfg, state.upload_handlers.clone()))
+                                .configure(|cfg| attachment_handlers::configure(cfg, state.attachment_handlers.clone()))
+                                .configure(|cfg| message_handlers::configure(cfg, state.message_handlers.clone()))
+                                .configure(|cfg| conversation_handlers::configure(cfg, state.conversation_handlers.clone()))
+                        )
+                )
+        )
+}
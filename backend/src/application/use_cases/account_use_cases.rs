@@ -1,30 +1,43 @@
 use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
 use crate::domain::entities::account::{Account, UpdateAccountDto};
 
-pub struct GetAccountUseCase<T: AccountRepository> {
+pub struct GetAccountUseCase<T: AccountRepository, S: MediaStorage> {
     account_repository: T,
+    media_storage: S,
 }
 
-impl<T: AccountRepository> GetAccountUseCase<T> {
-    pub fn new(account_repository: T) -> Self {
-        Self { account_repository }
+impl<T: AccountRepository, S: MediaStorage> GetAccountUseCase<T, S> {
+    pub fn new(account_repository: T, media_storage: S) -> Self {
+        Self { account_repository, media_storage }
     }
 
     pub async fn execute(&self, user_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
-        self.account_repository.find_by_user_id(user_id).await
+        let mut account = self.account_repository.find_by_user_id(user_id).await?;
+        resolve_avatar_urls(&mut account, &self.media_storage);
+        Ok(account)
     }
 }
 
-pub struct UpdateAccountUseCase<T: AccountRepository> {
+pub struct UpdateAccountUseCase<T: AccountRepository, S: MediaStorage> {
     account_repository: T,
+    media_storage: S,
 }
 
-impl<T: AccountRepository> UpdateAccountUseCase<T> {
-    pub fn new(account_repository: T) -> Self {
-        Self { account_repository }
+impl<T: AccountRepository, S: MediaStorage> UpdateAccountUseCase<T, S> {
+    pub fn new(account_repository: T, media_storage: S) -> Self {
+        Self { account_repository, media_storage }
     }
 
     pub async fn execute(&self, user_id: i32, account_dto: UpdateAccountDto) -> Result<Account, Box<dyn std::error::Error>> {
-        self.account_repository.update(user_id, account_dto).await
+        let mut account = self.account_repository.update(user_id, account_dto).await?;
+        resolve_avatar_urls(&mut account, &self.media_storage);
+        Ok(account)
     }
-}
\ No newline at end of file
+}
+
+pub(crate) fn resolve_avatar_urls<S: MediaStorage>(account: &mut Account, media_storage: &S) {
+    if let Some(avatar) = account.default_avatar.as_mut() {
+        avatar.resolve_urls(media_storage);
+    }
+}
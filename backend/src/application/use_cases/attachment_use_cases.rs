@@ -0,0 +1,25 @@
+use crate::domain::entities::attachment::{Attachment, AttachmentNotFound};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+
+pub struct GetAttachmentUseCase<A: AttachmentRepository, S: MediaStorage> {
+    attachment_repository: A,
+    media_storage: S,
+}
+
+impl<A: AttachmentRepository, S: MediaStorage> GetAttachmentUseCase<A, S> {
+    pub fn new(attachment_repository: A, media_storage: S) -> Self {
+        Self {
+            attachment_repository,
+            media_storage,
+        }
+    }
+
+    /// Attachments the user neither uploaded nor exchanged in a message are reported as missing.
+    pub async fn execute(&self, user_id: i32, attachment_id: i32) -> Result<Attachment, Box<dyn std::error::Error>> {
+        let mut attachment = self.attachment_repository.find_visible_to(attachment_id, user_id).await?
+            .ok_or(AttachmentNotFound(attachment_id))?;
+        attachment.resolve_url(&self.media_storage);
+        Ok(attachment)
+    }
+}
@@ -1,80 +1,389 @@
-use std::path::{Path, PathBuf};
-use uuid::Uuid;
-use image::{ImageOutputFormat};
-use webp::Encoder;
-use crate::domain::entities::avatar::AvatarUploadResponse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::application::use_cases::account_use_cases::resolve_avatar_urls;
+use crate::domain::entities::account::Account;
+use crate::domain::entities::avatar::{Avatar, AvatarCrop, AvatarNotFound, AvatarGallery, AvatarProcessingJob, AvatarUploadAccepted, NewAvatar};
+use crate::domain::entities::job::NewJob;
+use crate::domain::entities::media::{content_digest, ImageFormat, ImagePlaceholder, MediaVariant, UploadLimits, VariantPlan};
 use crate::domain::repositories::avatar_repository::AvatarRepository;
 use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::infrastructure::imaging;
+use crate::infrastructure::metrics::metrics;
 
-const LARGE_SIZE: u32 = 300;
-const SMALL_SIZE: u32 = 40;
+pub struct UploadAvatarUseCase<U: AccountRepository, S: MediaStorage, J: JobRepository> {
+    account_repository: U,
+    media_storage: S,
+    job_repository: J,
+    upload_limits: UploadLimits,
+}
+
+impl<U: AccountRepository, S: MediaStorage, J: JobRepository> UploadAvatarUseCase<U, S, J> {
+    pub fn new(account_repository: U, media_storage: S, job_repository: J, upload_limits: UploadLimits) -> Self {
+        Self {
+            account_repository,
+            media_storage,
+            job_repository,
+            upload_limits,
+        }
+    }
+
+    pub fn upload_limits(&self) -> &UploadLimits {
+        &self.upload_limits
+    }
+
+    /// Stores the original and queues it for processing. Everything that can be checked
+    /// from the image header, including the crop, is checked here so the caller hears
+    /// about it right away.
+    pub async fn execute(&self, account_id: i32, image_data: Vec<u8>, crop: AvatarCrop) -> Result<AvatarUploadAccepted, Box<dyn std::error::Error>> {
+        // Account routes are keyed by user id; resolve the real account row first
+        let account = self.account_repository.find_by_user_id(account_id).await?;
+
+        let upload = imaging::inspect_upload(&image_data, &self.upload_limits)?;
+        crop.square_region(upload.width, upload.height)?;
+
+        // Content-addressed: uploading the same file again, from any account, lands on the
+        // same key. Variants are stored next to the original, under the same prefix
+        let original_key = format!("avatars/{}/original.{}", content_digest(&image_data), upload.extension());
+
+        // The queued job is what references the original, so it goes in before the file does:
+        // unreferenced files may be deleted at any time. A worker that claims the job before
+        // the file is there fails the attempt and tries again later
+        let payload = AvatarProcessingJob {
+            user_id: account.user_id,
+            account_id: account.id,
+            original_key: original_key.clone(),
+            crop,
+        };
+        let job = self.job_repository.enqueue(NewJob {
+            user_id: Some(account.user_id),
+            ..NewJob::new(AvatarProcessingJob::KIND, serde_json::to_value(&payload)?)
+        }).await?;
+
+        if let Err(e) = self.media_storage.put(&original_key, image_data, upload.mime_type()).await {
+            self.job_repository.fail(job.id, &e.to_string(), None).await?;
+            return Err(Box::new(e));
+        }
+
+        Ok(AvatarUploadAccepted {
+            job_id: job.id,
+            message: "Avatar uploaded and queued for processing".to_string(),
+        })
+    }
+}
+
+pub struct ProcessAvatarUseCase<T: AvatarRepository, U: AccountRepository, S: MediaStorage> {
+    avatar_repository: T,
+    account_repository: U,
+    media_storage: S,
+    variant_plan: VariantPlan,
+    upload_limits: UploadLimits,
+}
+
+impl<T: AvatarRepository, U: AccountRepository, S: MediaStorage> ProcessAvatarUseCase<T, U, S> {
+    pub fn new(
+        avatar_repository: T,
+        account_repository: U,
+        media_storage: S,
+        variant_plan: VariantPlan,
+        upload_limits: UploadLimits,
+    ) -> Self {
+        Self {
+            avatar_repository,
+            account_repository,
+            media_storage,
+            variant_plan,
+            upload_limits,
+        }
+    }
+
+    /// Renders the original into every variant of the plan, stores them and makes the new
+    /// avatar the account's default. Storage keys depend only on the original's content and
+    /// the crop, so running it again after a failure overwrites rather than duplicates files,
+    /// and an upload identical to an earlier one reuses that one's variants unrendered.
+    pub async fn execute(&self, job: &AvatarProcessingJob) -> Result<Avatar, Box<dyn std::error::Error>> {
+        let prefix = job.variant_prefix();
+        let planned: BTreeMap<(u32, ImageFormat), String> = self.variant_plan.pixel_sizes().into_iter()
+            .flat_map(|pixels| self.variant_plan.formats.iter().map(move |format| (pixels, *format)))
+            .map(|(pixels, format)| ((pixels, format), format!("{}/{}.{}", prefix, pixels, format.extension())))
+            .collect();
+
+        // The avatar found may be deleted before the new one refers to its files; they are
+        // rendered afresh then
+        let reused = match self.find_rendered(&planned).await? {
+            Some((stored, placeholder)) => {
+                self.avatar_repository.create_reusing_variants(self.new_avatar(job, &planned, &stored, placeholder)).await?
+            }
+            None => None,
+        };
+        let mut avatar = match reused {
+            Some(avatar) => avatar,
+            None => {
+                let (stored, placeholder) = self.render(job, &planned).await?;
+                self.avatar_repository.create(self.new_avatar(job, &planned, &stored, placeholder)).await?
+            }
+        };
+        self.account_repository.set_default_avatar(job.user_id, avatar.id).await?;
+
+        avatar.resolve_urls(&self.media_storage);
+        Ok(avatar)
+    }
+
+    fn new_avatar(
+        &self,
+        job: &AvatarProcessingJob,
+        planned: &BTreeMap<(u32, ImageFormat), String>,
+        stored: &StoredFiles,
+        placeholder: ImagePlaceholder,
+    ) -> NewAvatar {
+        let variants = self.variant_plan.renditions()
+            .flat_map(|(size, density)| self.variant_plan.formats.iter().map(move |format| (size, density, *format)))
+            .map(|(size, density, format)| {
+                let pixels = size * density;
+                MediaVariant {
+                    size,
+                    density,
+                    format,
+                    width: pixels,
+                    height: pixels,
+                    storage_key: planned[&(pixels, format)].clone(),
+                    byte_size: stored[&(pixels, format)],
+                }
+            })
+            .collect();
+
+        NewAvatar {
+            account_id: job.account_id,
+            original_key: Some(job.original_key.clone()),
+            variants,
+            placeholder: Some(placeholder),
+        }
+    }
+
+    /// Sizes of the planned files and the placeholder of an avatar that already has every
+    /// one of them.
+    async fn find_rendered(
+        &self,
+        planned: &BTreeMap<(u32, ImageFormat), String>,
+    ) -> Result<Option<(StoredFiles, ImagePlaceholder)>, Box<dyn std::error::Error>> {
+        let Some(first_key) = planned.values().next() else {
+            return Ok(None);
+        };
+        let Some(existing) = self.avatar_repository.find_by_variant_key(first_key).await? else {
+            return Ok(None);
+        };
+        let (Some(blurhash), Some(dominant_color)) = (existing.blurhash, existing.dominant_color) else {
+            return Ok(None);
+        };
+
+        let sizes: HashMap<&str, Option<i64>> = existing.variants.iter()
+            .map(|variant| (variant.storage_key.as_str(), variant.byte_size))
+            .collect();
+        // A plan that has grown since is rendered afresh rather than patched up
+        let stored = planned.iter()
+            .map(|(file, key)| sizes.get(key.as_str()).map(|byte_size| (*file, *byte_size)))
+            .collect::<Option<HashMap<_, _>>>();
+
+        Ok(stored.map(|stored| (stored, ImagePlaceholder { blurhash, dominant_color })))
+    }
+
+    async fn render(
+        &self,
+        job: &AvatarProcessingJob,
+        planned: &BTreeMap<(u32, ImageFormat), String>,
+    ) -> Result<(StoredFiles, ImagePlaceholder), Box<dyn std::error::Error>> {
+        let original = self.media_storage.get(&job.original_key).await?;
+
+        // Decoding, scaling and encoding are CPU-bound; keep them off the async runtime
+        let timer = metrics().avatar_processing_duration_seconds.start_timer();
+        let (limits, crop, plan) = (self.upload_limits, job.crop, self.variant_plan.clone());
+        let rendered = tokio::task::spawn_blocking(move || render_variants(&original, &limits, &crop, &plan))
+            .await?
+            .map_err(|e| -> Box<dyn std::error::Error> { e })?;
+        timer.observe_duration();
+
+        let mut stored = HashMap::new();
+        for ((pixels, format), bytes) in rendered.files {
+            let byte_size = bytes.len() as i64;
+            self.media_storage.put(&planned[&(pixels, format)], bytes, format.mime_type()).await?;
+            stored.insert((pixels, format), Some(byte_size));
+        }
+
+        Ok((stored, rendered.placeholder))
+    }
+}
+
+/// Byte sizes of the stored files, keyed by edge length in pixels and format. Unknown for
+/// files reused from avatars stored before sizes were tracked.
+type StoredFiles = HashMap<(u32, ImageFormat), Option<i64>>;
 
-pub struct UploadAvatarUseCase<T: AvatarRepository, U: AccountRepository> {
+struct RenderedAvatar {
+    /// Encoded file contents keyed by edge length in pixels and format.
+    files: Vec<((u32, ImageFormat), Vec<u8>)>,
+    placeholder: ImagePlaceholder,
+}
+
+/// One encoded file per distinct edge length and format, shared by the renditions that need
+/// it, and a placeholder for the whole square.
+fn render_variants(
+    original: &[u8],
+    limits: &UploadLimits,
+    crop: &AvatarCrop,
+    plan: &VariantPlan,
+) -> Result<RenderedAvatar, Box<dyn std::error::Error + Send + Sync>> {
+    // Sniffed, bounded and turned upright before anything else touches the pixels
+    let img = imaging::decode_upload(original, limits)?;
+
+    // Every variant is scaled from the same square, so all of them are exactly square
+    let region = crop.square_region(img.width(), img.height())?;
+    let square = img.crop_imm(region.x, region.y, region.side, region.side);
+
+    let mut files = Vec::new();
+    for pixels in plan.pixel_sizes() {
+        let resized = square.resize_exact(pixels, pixels, image::imageops::FilterType::Lanczos3);
+        for format in &plan.formats {
+            files.push(((pixels, *format), imaging::encode(&resized, *format)?));
+        }
+    }
+    Ok(RenderedAvatar { files, placeholder: imaging::placeholder(&square)? })
+}
+
+pub struct ListAvatarsUseCase<T: AvatarRepository, U: AccountRepository, S: MediaStorage> {
     avatar_repository: T,
     account_repository: U,
-    upload_dir: PathBuf,
+    media_storage: S,
 }
 
-impl<T: AvatarRepository, U: AccountRepository> UploadAvatarUseCase<T, U> {
-    pub fn new(avatar_repository: T, account_repository: U, upload_dir: PathBuf) -> Self {
+impl<T: AvatarRepository, U: AccountRepository, S: MediaStorage> ListAvatarsUseCase<T, U, S> {
+    pub fn new(avatar_repository: T, account_repository: U, media_storage: S) -> Self {
         Self {
             avatar_repository,
             account_repository,
-            upload_dir,
+            media_storage,
         }
     }
 
-    pub async fn execute(&self, account_id: i32, image_data: Vec<u8>) -> Result<AvatarUploadResponse, Box<dyn std::error::Error>> {
-        // Create account-specific directory
-        let account_dir = self.upload_dir.join(account_id.to_string());
-        std::fs::create_dir_all(&account_dir)?;
-
-        // Process images
-        let img = image::load_from_memory(&image_data)?;
-
-        // Generate UUIDs for filenames
-        let large_uuid = Uuid::new_v4();
-        let small_uuid = Uuid::new_v4();
-
-        // Process large image (300x300)
-        let large_image = img.resize(LARGE_SIZE, LARGE_SIZE, image::imageops::FilterType::Lanczos3);
-        let large_filename = format!("300_{}.webp", large_uuid);
-        let large_path = account_dir.join(&large_filename);
-        let large_webp = self.create_webp(&large_image)?;
-        std::fs::write(&large_path, large_webp)?;
-
-        // Process small image (40x40)
-        let small_image = img.resize(SMALL_SIZE, SMALL_SIZE, image::imageops::FilterType::Lanczos3);
-        let small_filename = format!("40_{}.webp", small_uuid);
-        let small_path = account_dir.join(&small_filename);
-        let small_webp = self.create_webp(&small_image)?;
-        std::fs::write(&small_path, small_webp)?;
-
-        // Create URLs (relative to upload directory)
-        let large_url = format!("/uploads/{}/{}", account_id, large_filename);
-        let small_url = format!("/uploads/{}/{}", account_id, small_filename);
-
-        // Save to database and set as default avatar
-        let avatar = self.avatar_repository.create(
-            account_id,
-            large_url.clone(),
-            small_url.clone(),
-        ).await?;
-
-        // Set as default avatar
-        self.account_repository.set_default_avatar(account_id, avatar.id).await?;
-
-        Ok(AvatarUploadResponse {
-            avatar_300x300_url: large_url,
-            avatar_40x40_url: small_url,
-            message: "Avatar uploaded successfully".to_string(),
+    pub async fn execute(&self, user_id: i32) -> Result<AvatarGallery, Box<dyn std::error::Error>> {
+        let account = self.account_repository.find_by_user_id(user_id).await?;
+        let mut avatars = self.avatar_repository.find_by_account_id(account.id).await?;
+        for avatar in avatars.iter_mut() {
+            avatar.resolve_urls(&self.media_storage);
+        }
+
+        Ok(AvatarGallery {
+            default_avatar_id: account.default_avatar_id,
+            avatars,
         })
     }
+}
 
-    fn create_webp(&self, img: &image::DynamicImage) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let rgba = img.to_rgba8();
-        let encoder = Encoder::from_rgba(&rgba, img.width(), img.height());
-        let encoded = encoder.encode(75f32); // Quality factor of 75
-        Ok(encoded.to_vec())
+pub struct SetDefaultAvatarUseCase<T: AvatarRepository, U: AccountRepository, S: MediaStorage> {
+    avatar_repository: T,
+    account_repository: U,
+    media_storage: S,
+}
+
+impl<T: AvatarRepository, U: AccountRepository, S: MediaStorage> SetDefaultAvatarUseCase<T, U, S> {
+    pub fn new(avatar_repository: T, account_repository: U, media_storage: S) -> Self {
+        Self {
+            avatar_repository,
+            account_repository,
+            media_storage,
+        }
     }
-}
\ No newline at end of file
+
+    pub async fn execute(&self, user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+        let account = self.account_repository.find_by_user_id(user_id).await?;
+        let avatar = find_owned_avatar(&self.avatar_repository, account.id, avatar_id).await?;
+
+        let mut account = self.account_repository.set_default_avatar(user_id, avatar.id).await?;
+        resolve_avatar_urls(&mut account, &self.media_storage);
+        Ok(account)
+    }
+}
+
+pub struct DeleteAvatarUseCase<T: AvatarRepository, U: AccountRepository, M: MediaObjectRepository, S: MediaStorage> {
+    avatar_repository: T,
+    account_repository: U,
+    media_object_repository: M,
+    media_storage: S,
+}
+
+impl<T: AvatarRepository, U: AccountRepository, M: MediaObjectRepository, S: MediaStorage> DeleteAvatarUseCase<T, U, M, S> {
+    pub fn new(avatar_repository: T, account_repository: U, media_object_repository: M, media_storage: S) -> Self {
+        Self {
+            avatar_repository,
+            account_repository,
+            media_object_repository,
+            media_storage,
+        }
+    }
+
+    /// Deletes the avatar and those of its stored files no other avatar shares. Deleting the
+    /// default avatar makes the most recent remaining one the default, if there is one.
+    pub async fn execute(&self, user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+        let account = self.account_repository.find_by_user_id(user_id).await?;
+        let avatar = find_owned_avatar(&self.avatar_repository, account.id, avatar_id).await?;
+
+        self.avatar_repository.delete(avatar.id).await?;
+
+        if account.default_avatar_id == Some(avatar.id) {
+            if let Some(previous) = self.avatar_repository.find_latest_by_account_id(account.id).await? {
+                self.account_repository.set_default_avatar(user_id, previous.id).await?;
+            }
+        }
+
+        // The row goes first so a failed delete never leaves an avatar pointing at missing files
+        delete_unreferenced_files(&self.media_object_repository, &self.media_storage, &avatar).await?;
+
+        let mut account = self.account_repository.find_by_user_id(user_id).await?;
+        resolve_avatar_urls(&mut account, &self.media_storage);
+        Ok(account)
+    }
+}
+
+/// Deletes the files of a deleted avatar that nothing refers to any more. Identical uploads
+/// share their files, so the others stay.
+pub(crate) async fn delete_unreferenced_files<M: MediaObjectRepository, S: MediaStorage>(
+    media_object_repository: &M,
+    media_storage: &S,
+    avatar: &Avatar,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let keys: Vec<String> = avatar.variants.iter()
+        .map(|variant| variant.storage_key.clone())
+        .chain(avatar.original_key.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    delete_unreferenced_objects(media_object_repository, media_storage, &keys).await?;
+    Ok(())
+}
+
+/// Deletes those of `keys` nothing refers to any more and returns them. The keys are claimed
+/// first, and only released once their objects are gone, so no transaction stays open while
+/// the storage is busy.
+pub(crate) async fn delete_unreferenced_objects<M: MediaObjectRepository, S: MediaStorage>(
+    media_object_repository: &M,
+    media_storage: &S,
+    keys: &[String],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let claimed = media_object_repository.claim_unreferenced(keys).await?;
+    for key in &claimed {
+        media_storage.delete(key).await?;
+    }
+    media_object_repository.release_claimed(&claimed).await?;
+    Ok(claimed)
+}
+
+/// Avatars of other accounts are reported as missing rather than forbidden, so ids cannot be probed.
+async fn find_owned_avatar<T: AvatarRepository>(
+    avatar_repository: &T,
+    account_id: i32,
+    avatar_id: i32,
+) -> Result<Avatar, Box<dyn std::error::Error>> {
+    match avatar_repository.find_by_id(avatar_id).await? {
+        Some(avatar) if avatar.account_id == account_id => Ok(avatar),
+        _ => Err(Box::new(AvatarNotFound(avatar_id))),
+    }
+}
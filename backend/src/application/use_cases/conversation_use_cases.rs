@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use crate::domain::entities::conversation::{ConversationPage, ConversationSummary, Counterpart, MessagePreview};
+use crate::domain::entities::message::MessageCursor;
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::conversation_repository::ConversationRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use super::account_use_cases::resolve_avatar_urls;
+
+/// Conversations per page when the client does not ask for a size.
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+/// Largest page a client can ask for; bigger requests are cut down to it.
+pub const MAX_PAGE_SIZE: i64 = 50;
+
+pub struct ListConversationsUseCase<C: ConversationRepository, A: AccountRepository, S: MediaStorage> {
+    conversation_repository: C,
+    account_repository: A,
+    media_storage: S,
+}
+
+impl<C: ConversationRepository, A: AccountRepository, S: MediaStorage> ListConversationsUseCase<C, A, S> {
+    pub fn new(conversation_repository: C, account_repository: A, media_storage: S) -> Self {
+        Self { conversation_repository, account_repository, media_storage }
+    }
+
+    /// The user's inbox, most recently active conversation first. A conversation that gets a
+    /// new message while the client pages moves to the front, so it can show up twice.
+    pub async fn execute(&self, user_id: i32, before: Option<MessageCursor>, limit: Option<i64>) -> Result<ConversationPage, Box<dyn std::error::Error>> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        // One extra conversation tells whether there is another page
+        let mut conversations = self.conversation_repository.find_for_user(user_id, before, limit + 1).await?;
+        let has_more = conversations.len() as i64 > limit;
+        conversations.truncate(limit as usize);
+
+        let counterpart_ids: Vec<i32> = conversations.iter().map(|conversation| conversation.counterpart_id).collect();
+        let mut accounts: HashMap<i32, _> = self.account_repository.find_by_user_ids(&counterpart_ids).await?
+            .into_iter()
+            .map(|mut account| {
+                resolve_avatar_urls(&mut account, &self.media_storage);
+                (account.user_id, account)
+            })
+            .collect();
+
+        let older = conversations.last()
+            .filter(|_| has_more)
+            .map(|conversation| conversation.last_message.cursor().to_string());
+        let conversations = conversations.into_iter()
+            .map(|conversation| ConversationSummary {
+                last_message: MessagePreview::from(&conversation.last_message),
+                counterpart: Counterpart::new(
+                    conversation.counterpart_id,
+                    conversation.counterpart_username,
+                    accounts.remove(&conversation.counterpart_id),
+                ),
+                unread_count: conversation.unread_count,
+            })
+            .collect();
+
+        Ok(ConversationPage { conversations, older })
+    }
+}
+
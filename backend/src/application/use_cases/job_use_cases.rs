@@ -0,0 +1,20 @@
+use crate::domain::entities::job::{Job, JobNotFound};
+use crate::domain::repositories::job_repository::JobRepository;
+
+pub struct GetJobUseCase<J: JobRepository> {
+    job_repository: J,
+}
+
+impl<J: JobRepository> GetJobUseCase<J> {
+    pub fn new(job_repository: J) -> Self {
+        Self { job_repository }
+    }
+
+    /// Jobs of other users, and system jobs, are reported as missing.
+    pub async fn execute(&self, user_id: i32, job_id: i64) -> Result<Job, Box<dyn std::error::Error>> {
+        match self.job_repository.find_by_id(job_id).await? {
+            Some(job) if job.user_id == Some(user_id) => Ok(job),
+            _ => Err(Box::new(JobNotFound(job_id))),
+        }
+    }
+}
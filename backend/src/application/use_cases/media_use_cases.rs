@@ -0,0 +1,163 @@
+use std::collections::{BTreeSet, HashSet};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+use crate::domain::entities::avatar::AvatarProcessingJob;
+use crate::domain::entities::media::{MediaOwner, MediaReconciliation, MediaReference};
+use crate::domain::entities::upload::Upload;
+use crate::application::use_cases::avatar_use_cases::{delete_unreferenced_files, delete_unreferenced_objects};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::avatar_repository::AvatarRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::domain::repositories::upload_repository::UploadRepository;
+
+/// Key prefixes under which every object belongs to a database row: avatar files, attachments
+/// and the parts of resumable uploads. Anything else in the bucket, such as health probes, is
+/// left alone.
+const RECONCILED_PREFIXES: [&str; 3] = ["avatars/", "attachments/", "uploads/"];
+
+pub struct ReconcileMediaUseCase<T: AvatarRepository, A: AttachmentRepository, U: UploadRepository, J: JobRepository, M: MediaObjectRepository, S: MediaStorage> {
+    avatar_repository: T,
+    attachment_repository: A,
+    upload_repository: U,
+    job_repository: J,
+    media_object_repository: M,
+    media_storage: S,
+    grace_period: Duration,
+}
+
+impl<T: AvatarRepository, A: AttachmentRepository, U: UploadRepository, J: JobRepository, M: MediaObjectRepository, S: MediaStorage>
+    ReconcileMediaUseCase<T, A, U, J, M, S>
+{
+    pub fn new(
+        avatar_repository: T,
+        attachment_repository: A,
+        upload_repository: U,
+        job_repository: J,
+        media_object_repository: M,
+        media_storage: S,
+        grace_period: Duration,
+    ) -> Self {
+        Self {
+            avatar_repository,
+            attachment_repository,
+            upload_repository,
+            job_repository,
+            media_object_repository,
+            media_storage,
+            grace_period,
+        }
+    }
+
+    /// Finds stored objects nothing refers to, and avatars, attachments and unfinished
+    /// uploads whose files are gone. Objects younger than the grace period are never
+    /// reported, as they may belong to an upload that has not reached the database yet.
+    ///
+    /// With `delete`, orphaned objects are removed, and so are avatars with missing files
+    /// together with the files they still have, and uploads that lost their parts. An
+    /// account using such an avatar as its default is left without one. Attachments are
+    /// only reported, as messages still carry them.
+    pub async fn execute(&self, delete: bool) -> Result<MediaReconciliation, Box<dyn std::error::Error>> {
+        let mut references = self.avatar_repository.media_references().await?;
+        references.extend(self.attachment_repository.media_references().await?);
+        // Parts are stored under the upload's prefix as they arrive, so only the first one is
+        // known to exist
+        let uploads = self.upload_repository.find_all().await?;
+        references.extend(uploads.iter()
+            .filter(|upload| upload.completed_at.is_none() && upload.offset > 0)
+            .map(|upload| MediaReference { owner: MediaOwner::Upload(upload.id), key: upload.part_key(0) }));
+        let upload_prefixes: HashSet<String> = uploads.iter().map(Upload::parts_prefix).collect();
+        let mut referenced: HashSet<&str> = references.iter().map(|reference| reference.key.as_str()).collect();
+
+        // Originals waiting to be processed have no avatar row yet
+        let pending = self.job_repository.find_unfinished(AvatarProcessingJob::KIND).await?;
+        let pending_originals: Vec<String> = pending.into_iter()
+            .filter_map(|job| serde_json::from_value::<AvatarProcessingJob>(job.payload).ok())
+            .map(|payload| payload.original_key)
+            .collect();
+        referenced.extend(pending_originals.iter().map(String::as_str));
+
+        let settled_before = SystemTime::now().checked_sub(self.grace_period).unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut stored = HashSet::new();
+        let mut orphaned_objects = Vec::new();
+        for prefix in RECONCILED_PREFIXES {
+            for object in self.media_storage.list(prefix).await? {
+                // Objects of unknown age are treated as fresh
+                let settled = object.last_modified.is_some_and(|modified| modified <= settled_before);
+                let in_upload = object.key.rfind('/').is_some_and(|end| upload_prefixes.contains(&object.key[..=end]));
+                if settled && !referenced.contains(object.key.as_str()) && !in_upload {
+                    orphaned_objects.push(object.key.clone());
+                }
+                stored.insert(object.key);
+            }
+        }
+        orphaned_objects.sort();
+
+        let mut missing_objects: Vec<MediaReference> = references.iter()
+            .filter(|reference| !stored.contains(&reference.key))
+            .cloned()
+            .collect();
+        missing_objects.sort();
+
+        if delete {
+            // Checked against the counts once more, as an upload may have referenced one since
+            delete_unreferenced_objects(&self.media_object_repository, &self.media_storage, &orphaned_objects).await?;
+            let broken: BTreeSet<MediaOwner> = missing_objects.iter().map(|reference| reference.owner).collect();
+            for owner in broken {
+                match owner {
+                    MediaOwner::Avatar(avatar_id) => self.delete_avatar(avatar_id).await?,
+                    MediaOwner::Upload(upload_id) => self.discard_upload(upload_id).await?,
+                    MediaOwner::Attachment(_) => {}
+                }
+            }
+        }
+
+        Ok(MediaReconciliation {
+            orphaned_objects,
+            missing_objects,
+            deleted: delete,
+        })
+    }
+
+    async fn delete_avatar(&self, avatar_id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        // Deleted by a user since the references were read
+        let Some(avatar) = self.avatar_repository.find_by_id(avatar_id).await? else {
+            return Ok(());
+        };
+
+        self.avatar_repository.delete(avatar.id).await?;
+        delete_unreferenced_files(&self.media_object_repository, &self.media_storage, &avatar).await
+    }
+
+    async fn discard_upload(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        // Finished since the uploads were read, which deletes the parts too
+        match self.upload_repository.find_by_id(upload_id).await? {
+            Some(upload) if upload.completed_at.is_none() => self.upload_repository.delete(upload_id).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct AuthorizeMediaUseCase<A: AttachmentRepository, T: AvatarRepository> {
+    attachment_repository: A,
+    avatar_repository: T,
+}
+
+impl<A: AttachmentRepository, T: AvatarRepository> AuthorizeMediaUseCase<A, T> {
+    pub fn new(attachment_repository: A, avatar_repository: T) -> Self {
+        Self { attachment_repository, avatar_repository }
+    }
+
+    /// Whether `user_id` may read the object under `key` without a signed URL: attachments
+    /// they can see and files of their own avatars. Nothing else is served by bearer token.
+    pub async fn execute(&self, user_id: i32, key: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        if key.starts_with("attachments/") {
+            self.attachment_repository.is_key_visible_to(key, user_id).await
+        } else if key.starts_with("avatars/") {
+            self.avatar_repository.is_key_owned_by(key, user_id).await
+        } else {
+            Ok(false)
+        }
+    }
+}
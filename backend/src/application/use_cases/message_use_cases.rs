@@ -1,16 +1,40 @@
-use crate::domain::entities::message::DatabaseMessage;
+use std::time::Duration;
+use chrono::SubsecRound;
+use crate::domain::entities::message::{
+    DatabaseMessage, MarkReadError, MessageChangeError, MessageCursor, MessageDeletion, MessageEdit, MessagePage, MessageRange, ReadReceipt,
+    SendMessageError,
+};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
 use crate::domain::repositories::message_repository::MessageRepository;
 
-pub struct SendMessageUseCase<T: MessageRepository> {
+pub struct SendMessageUseCase<T: MessageRepository, A: AttachmentRepository> {
     message_repository: T,
+    attachment_repository: A,
 }
 
-impl<T: MessageRepository> SendMessageUseCase<T> {
-    pub fn new(message_repository: T) -> Self {
-        Self { message_repository }
+impl<T: MessageRepository, A: AttachmentRepository> SendMessageUseCase<T, A> {
+    pub fn new(message_repository: T, attachment_repository: A) -> Self {
+        Self {
+            message_repository,
+            attachment_repository,
+        }
     }
 
-    pub async fn execute(&self, sender_id: i32, receiver_id: i32, content: String) -> Result<DatabaseMessage, String> {
+    /// Senders can only attach files they uploaded themselves. A recipient that does not
+    /// exist is reported by the repository when the message cannot be stored.
+    pub async fn execute(&self, sender_id: i32, receiver_id: i32, content: String, attachment_id: Option<i32>) -> Result<DatabaseMessage, SendMessageError> {
+        if content.trim().is_empty() && attachment_id.is_none() {
+            return Err(SendMessageError::Empty);
+        }
+
+        if let Some(attachment_id) = attachment_id {
+            let attachment = self.attachment_repository.find_by_id(attachment_id).await
+                .map_err(|e| SendMessageError::Database(format!("Database error: {}", e)))?;
+            if attachment.is_none_or(|attachment| attachment.user_id != sender_id) {
+                return Err(SendMessageError::AttachmentNotFound(attachment_id));
+            }
+        }
+
         let message = DatabaseMessage {
             id: 0, // Will be set by the database
             sender_id,
@@ -18,11 +42,21 @@ impl<T: MessageRepository> SendMessageUseCase<T> {
             content,
             is_read: false,
             created_at: chrono::Utc::now().naive_utc(),
+            attachment_id,
+            delivered_at: None,
+            read_at: None,
+            edited_at: None,
+            deleted_at: None,
         };
         self.message_repository.save_message(message).await
     }
 }
 
+/// Messages per page when the client does not ask for a size.
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+/// Largest page a client can ask for; bigger requests are cut down to it.
+pub const MAX_PAGE_SIZE: i64 = 100;
+
 pub struct GetMessagesUseCase<T: MessageRepository> {
     message_repository: T,
 }
@@ -32,7 +66,218 @@ impl<T: MessageRepository> GetMessagesUseCase<T> {
         Self { message_repository }
     }
 
-    pub async fn execute(&self, user1_id: i32, user2_id: i32) -> Result<Vec<DatabaseMessage>, String> {
-        self.message_repository.get_messages(user1_id, user2_id).await
+    /// Pages through `viewer_id`'s conversation with `counterpart_id`, newest message first, without
+    /// the messages they deleted for themselves. Without cursors this is the latest page; with
+    /// `before` it continues backwards into the history. With only `after` it pages forwards
+    /// from there instead, returning the messages right after the cursor, so a client
+    /// catching up never skips any.
+    pub async fn execute(
+        &self,
+        viewer_id: i32,
+        counterpart_id: i32,
+        before: Option<MessageCursor>,
+        after: Option<MessageCursor>,
+        limit: Option<i64>,
+    ) -> Result<MessagePage, String> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let forwards = after.is_some() && before.is_none();
+
+        // One extra message tells whether there is another page
+        let range = MessageRange { before, after, oldest_first: forwards, limit: limit + 1, hidden_for: Some(viewer_id) };
+        let mut messages = self.message_repository.get_messages(viewer_id, counterpart_id, range).await?;
+        let has_more = messages.len() as i64 > limit;
+        messages.truncate(limit as usize);
+        if forwards {
+            messages.reverse();
+        }
+
+        let newest = messages.first().map(DatabaseMessage::cursor);
+        let oldest = messages.last().map(DatabaseMessage::cursor);
+        // The client already holds the messages at the cursors it sent, so the next page in
+        // either direction starts from them unless this page stopped short of them.
+        let older = if has_more && !forwards { oldest } else { after };
+        let newer = if has_more && forwards { newest } else { before.or(newest).or(after) };
+
+        Ok(MessagePage {
+            messages,
+            older: older.map(|cursor| cursor.to_string()),
+            newer: newer.map(|cursor| cursor.to_string()),
+            has_more,
+        })
+    }
+}
+
+pub struct MarkDeliveredUseCase<T: MessageRepository> {
+    message_repository: T,
+}
+
+impl<T: MessageRepository> MarkDeliveredUseCase<T> {
+    pub fn new(message_repository: T) -> Self {
+        Self { message_repository }
+    }
+
+    /// Records that the messages reached their receiver now. Returns only the ones that had
+    /// not been delivered before, oldest first.
+    pub async fn execute(&self, message_ids: &[i32]) -> Result<Vec<DatabaseMessage>, String> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Postgres keeps microseconds, so the time reported is the time stored
+        let delivered_at = chrono::Utc::now().naive_utc().round_subsecs(6);
+        self.message_repository.mark_delivered(message_ids, delivered_at).await
+    }
+}
+
+pub struct MarkReadUseCase<T: MessageRepository> {
+    message_repository: T,
+}
+
+impl<T: MessageRepository> MarkReadUseCase<T> {
+    pub fn new(message_repository: T) -> Self {
+        Self { message_repository }
+    }
+
+    /// Marks what `reader_id` received from `counterpart_id` read, up to and including
+    /// `up_to_message_id`. That message can be from either of them, so a reader who replied
+    /// last can pass their own reply.
+    pub async fn execute(&self, reader_id: i32, counterpart_id: i32, up_to_message_id: i32) -> Result<ReadReceipt, MarkReadError> {
+        let up_to = self.message_repository.find_message(up_to_message_id).await
+            .map_err(MarkReadError::Database)?
+            .filter(|message| {
+                (message.sender_id == reader_id && message.receiver_id == counterpart_id)
+                    || (message.sender_id == counterpart_id && message.receiver_id == reader_id)
+            })
+            .ok_or(MarkReadError::MessageNotFound(up_to_message_id))?;
+
+        let read_at = chrono::Utc::now().naive_utc().round_subsecs(6);
+        let read = self.message_repository
+            .mark_read(reader_id, counterpart_id, up_to.cursor(), read_at)
+            .await
+            .map_err(MarkReadError::Database)?;
+
+        Ok(ReadReceipt {
+            message_ids: read.iter().map(|message| message.id).collect(),
+            read_at,
+        })
+    }
+}
+
+/// Looks up a message in one of `user_id`'s conversations. Messages of other conversations
+/// are reported missing, like ones that do not exist.
+async fn find_participating<T: MessageRepository>(message_repository: &T, user_id: i32, message_id: i32) -> Result<DatabaseMessage, MessageChangeError> {
+    message_repository.find_message(message_id).await
+        .map_err(MessageChangeError::Database)?
+        .filter(|message| message.sender_id == user_id || message.receiver_id == user_id)
+        .ok_or(MessageChangeError::NotFound(message_id))
+}
+
+pub struct EditMessageUseCase<T: MessageRepository> {
+    message_repository: T,
+    edit_window: Duration,
+}
+
+impl<T: MessageRepository> EditMessageUseCase<T> {
+    pub fn new(message_repository: T, edit_window: Duration) -> Self {
+        Self { message_repository, edit_window }
+    }
+
+    /// Replaces the content of a message the editor sent within the edit window. The earlier
+    /// content is kept in the message's edits; an edit that changes nothing is not recorded.
+    pub async fn execute(&self, editor_id: i32, message_id: i32, content: String) -> Result<DatabaseMessage, MessageChangeError> {
+        let message = find_participating(&self.message_repository, editor_id, message_id).await?;
+        if message.deleted_at.is_some() {
+            return Err(MessageChangeError::NotFound(message_id));
+        }
+        if message.sender_id != editor_id {
+            return Err(MessageChangeError::NotSender(message_id));
+        }
+
+        let now = chrono::Utc::now().naive_utc().round_subsecs(6);
+        let age = (now - message.created_at).to_std().unwrap_or_default();
+        if age > self.edit_window {
+            return Err(MessageChangeError::EditWindowClosed(message_id));
+        }
+        if content.trim().is_empty() && message.attachment_id.is_none() {
+            return Err(MessageChangeError::Empty);
+        }
+        if content == message.content {
+            return Ok(message);
+        }
+
+        self.message_repository.edit_message(message_id, content, now).await
+            .map_err(MessageChangeError::Database)?
+            .ok_or(MessageChangeError::NotFound(message_id))
+    }
+}
+
+pub struct DeleteMessageUseCase<T: MessageRepository> {
+    message_repository: T,
+}
+
+impl<T: MessageRepository> DeleteMessageUseCase<T> {
+    pub fn new(message_repository: T) -> Self {
+        Self { message_repository }
+    }
+
+    /// Deletes a message from the user's own history, or for both participants when the
+    /// sender asks for `for_everyone`. Deleting again reports the earlier deletion.
+    pub async fn execute(&self, user_id: i32, message_id: i32, for_everyone: bool) -> Result<MessageDeletion, MessageChangeError> {
+        let message = find_participating(&self.message_repository, user_id, message_id).await?;
+        let now = chrono::Utc::now().naive_utc().round_subsecs(6);
+
+        if !for_everyone {
+            self.message_repository.delete_for_user(message_id, user_id, now).await
+                .map_err(MessageChangeError::Database)?;
+            return Ok(MessageDeletion { message, for_everyone, deleted_at: now });
+        }
+
+        if message.sender_id != user_id {
+            return Err(MessageChangeError::NotSender(message_id));
+        }
+        let tombstone = match message.deleted_at {
+            Some(_) => message,
+            None => self.message_repository.delete_for_everyone(message_id, now).await
+                .map_err(MessageChangeError::Database)?
+                .ok_or(MessageChangeError::NotFound(message_id))?,
+        };
+        let deleted_at = tombstone.deleted_at.unwrap_or(now);
+
+        Ok(MessageDeletion { message: tombstone, for_everyone, deleted_at })
     }
-}
\ No newline at end of file
+}
+
+pub struct GetMessageEditsUseCase<T: MessageRepository> {
+    message_repository: T,
+}
+
+impl<T: MessageRepository> GetMessageEditsUseCase<T> {
+    pub fn new(message_repository: T) -> Self {
+        Self { message_repository }
+    }
+
+    /// What a message in one of the user's conversations said before each edit, oldest
+    /// first. Empty once it is deleted for everyone.
+    pub async fn execute(&self, user_id: i32, message_id: i32) -> Result<Vec<MessageEdit>, MessageChangeError> {
+        find_participating(&self.message_repository, user_id, message_id).await?;
+        self.message_repository.find_edits(message_id).await.map_err(MessageChangeError::Database)
+    }
+}
+
+pub struct CheckConversationUseCase<T: MessageRepository> {
+    message_repository: T,
+}
+
+impl<T: MessageRepository> CheckConversationUseCase<T> {
+    pub fn new(message_repository: T) -> Self {
+        Self { message_repository }
+    }
+
+    /// Whether either user has ever sent the other a message, including messages since
+    /// deleted.
+    pub async fn execute(&self, user_id: i32, counterpart_id: i32) -> Result<bool, String> {
+        let range = MessageRange { before: None, after: None, oldest_first: false, limit: 1, hidden_for: None };
+        let messages = self.message_repository.get_messages(user_id, counterpart_id, range).await?;
+        Ok(!messages.is_empty())
+    }
+}
@@ -2,4 +2,9 @@ pub mod user_use_cases;
 pub mod auth_use_cases;
 pub mod account_use_cases;
 pub mod message_use_cases;
-pub mod avatar_use_cases;
\ No newline at end of file
+pub mod avatar_use_cases;
+pub mod job_use_cases;
+pub mod media_use_cases;
+pub mod upload_use_cases;
+pub mod attachment_use_cases;
+pub mod conversation_use_cases;
\ No newline at end of file
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use uuid::Uuid;
+use crate::application::use_cases::avatar_use_cases::UploadAvatarUseCase;
+use crate::domain::entities::attachment::NewAttachment;
+use crate::domain::entities::avatar::{AvatarCrop, InvalidCrop};
+use crate::domain::entities::media::{content_digest, UploadLimits, UploadRejection};
+use crate::domain::entities::upload::{NewUpload, Upload, UploadError, UploadOutcome, UploadPurpose};
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_storage::{MediaStorage, MediaStorageError};
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::infrastructure::config::settings::ResumableUploadSettings;
+use crate::infrastructure::imaging;
+
+/// A request that has not stored a part for this long is assumed to have died, and another
+/// request may take over its upload.
+const STALE_LOCK: Duration = Duration::from_secs(5 * 60);
+
+/// Longest attachment file name kept, in characters.
+const MAX_FILENAME_CHARS: usize = 255;
+
+pub struct CreateUploadUseCase<R: UploadRepository> {
+    upload_repository: R,
+    settings: ResumableUploadSettings,
+    avatar_limits: UploadLimits,
+}
+
+impl<R: UploadRepository> CreateUploadUseCase<R> {
+    pub fn new(upload_repository: R, settings: ResumableUploadSettings, avatar_limits: UploadLimits) -> Self {
+        Self {
+            upload_repository,
+            settings,
+            avatar_limits,
+        }
+    }
+
+    /// Largest upload accepted for any purpose.
+    pub fn max_bytes(&self) -> u64 {
+        self.settings.max_bytes
+    }
+
+    /// Everything that can be checked before the first byte arrives is checked here, so a
+    /// client does not send a whole file only to have it refused.
+    pub async fn execute(&self, user_id: i32, length: i64, metadata: BTreeMap<String, String>) -> Result<Upload, Box<dyn std::error::Error>> {
+        let purpose: UploadPurpose = metadata.get("purpose")
+            .ok_or_else(|| UploadError::Invalid("the `purpose` metadata key is required".to_string()))?
+            .parse()?;
+
+        let max_bytes = match purpose {
+            UploadPurpose::Avatar => {
+                avatar_crop(&metadata)?;
+                self.settings.max_bytes.min(self.avatar_limits.max_bytes as u64)
+            }
+            UploadPurpose::Attachment => self.settings.max_bytes,
+        };
+        if length <= 0 {
+            return Err(Box::new(UploadError::Invalid("the length must be positive".to_string())));
+        }
+        if length as u64 > max_bytes {
+            return Err(Box::new(UploadError::TooLarge { max_bytes }));
+        }
+
+        self.upload_repository.create(NewUpload {
+            user_id,
+            purpose,
+            length,
+            metadata,
+            expires_at: Utc::now().naive_utc() + chrono::Duration::from_std(self.settings.expiry)?,
+        }).await
+    }
+}
+
+pub struct GetUploadUseCase<R: UploadRepository> {
+    upload_repository: R,
+}
+
+impl<R: UploadRepository> GetUploadUseCase<R> {
+    pub fn new(upload_repository: R) -> Self {
+        Self { upload_repository }
+    }
+
+    pub async fn execute(&self, user_id: i32, upload_id: Uuid) -> Result<Upload, Box<dyn std::error::Error>> {
+        let upload = find_upload(&self.upload_repository, user_id, upload_id).await?;
+        if upload.is_expired(Utc::now().naive_utc()) {
+            return Err(Box::new(UploadError::Expired));
+        }
+        Ok(upload)
+    }
+}
+
+pub struct AppendUploadUseCase<R: UploadRepository, S: MediaStorage, U: AccountRepository, J: JobRepository, A: AttachmentRepository> {
+    upload_repository: R,
+    media_storage: S,
+    upload_avatar_use_case: UploadAvatarUseCase<U, S, J>,
+    attachment_repository: A,
+    part_bytes: usize,
+}
+
+impl<R: UploadRepository, S: MediaStorage, U: AccountRepository, J: JobRepository, A: AttachmentRepository> AppendUploadUseCase<R, S, U, J, A> {
+    pub fn new(
+        upload_repository: R,
+        media_storage: S,
+        upload_avatar_use_case: UploadAvatarUseCase<U, S, J>,
+        attachment_repository: A,
+        part_bytes: usize,
+    ) -> Self {
+        Self {
+            upload_repository,
+            media_storage,
+            upload_avatar_use_case,
+            attachment_repository,
+            part_bytes,
+        }
+    }
+
+    /// Appends `body` to the upload, which must have reached exactly `offset`. Bytes are
+    /// stored part by part as they arrive, so when the body breaks off, what came before is
+    /// kept and the client resumes from there.
+    ///
+    /// The request that supplies the last byte also hands the file on: avatars are queued for
+    /// processing and attachments stored. A file refused at that point is discarded with its
+    /// upload. On any other failure the upload stays where it is, and appending nothing at the
+    /// final offset tries again.
+    pub async fn execute<B, D, E>(&self, user_id: i32, upload_id: Uuid, offset: i64, body: B) -> Result<Upload, Box<dyn std::error::Error>>
+    where
+        B: Stream<Item = Result<D, E>> + Unpin,
+        D: AsRef<[u8]>,
+        E: std::error::Error + 'static,
+    {
+        let upload = find_upload(&self.upload_repository, user_id, upload_id).await?;
+        let now = Utc::now().naive_utc();
+        if upload.is_expired(now) {
+            return Err(Box::new(UploadError::Expired));
+        }
+        if upload.offset != offset {
+            return Err(Box::new(UploadError::OffsetMismatch { offset: upload.offset }));
+        }
+        if upload.completed_at.is_some() {
+            return Ok(upload);
+        }
+
+        let stale_before = now - chrono::Duration::from_std(STALE_LOCK)?;
+        if !self.upload_repository.lock(upload.id, offset, stale_before).await? {
+            // Lost a race with another request; tell the client which one it lost
+            let current = find_upload(&self.upload_repository, user_id, upload_id).await?;
+            return Err(Box::new(match current.offset {
+                current_offset if current_offset != offset => UploadError::OffsetMismatch { offset: current_offset },
+                _ => UploadError::Locked,
+            }));
+        }
+
+        let received = match self.receive(&upload, body).await {
+            Ok(received) => received,
+            Err(e) => {
+                self.upload_repository.unlock(upload.id).await?;
+                return Err(e);
+            }
+        };
+        if received < upload.length {
+            self.upload_repository.unlock(upload.id).await?;
+            return find_upload(&self.upload_repository, user_id, upload_id).await;
+        }
+
+        match self.finish(&upload).await {
+            Ok(completed) => Ok(completed),
+            Err(e) if e.is::<UploadRejection>() || e.is::<InvalidCrop>() => {
+                discard(&self.upload_repository, &self.media_storage, &upload).await?;
+                Err(e)
+            }
+            Err(e) => {
+                self.upload_repository.unlock(upload.id).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Stores the body in parts and returns the offset reached.
+    async fn receive<B, D, E>(&self, upload: &Upload, mut body: B) -> Result<i64, Box<dyn std::error::Error>>
+    where
+        B: Stream<Item = Result<D, E>> + Unpin,
+        D: AsRef<[u8]>,
+        E: std::error::Error + 'static,
+    {
+        let mut offset = upload.offset;
+        let mut part = Vec::new();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    self.store_part(upload, offset, part).await?;
+                    return Err(Box::new(e));
+                }
+            };
+
+            let chunk = chunk.as_ref();
+            if offset + (part.len() + chunk.len()) as i64 > upload.length {
+                return Err(Box::new(UploadError::ExceedsLength { length: upload.length }));
+            }
+            part.extend_from_slice(chunk);
+
+            while part.len() >= self.part_bytes {
+                let rest = part.split_off(self.part_bytes);
+                offset = self.store_part(upload, offset, std::mem::replace(&mut part, rest)).await?;
+            }
+        }
+
+        self.store_part(upload, offset, part).await
+    }
+
+    async fn store_part(&self, upload: &Upload, offset: i64, bytes: Vec<u8>) -> Result<i64, Box<dyn std::error::Error>> {
+        if bytes.is_empty() {
+            return Ok(offset);
+        }
+
+        let end = offset + bytes.len() as i64;
+        self.media_storage.put(&upload.part_key(offset), bytes, "application/offset+octet-stream").await?;
+        self.upload_repository.advance(upload.id, end).await?;
+        Ok(end)
+    }
+
+    async fn finish(&self, upload: &Upload) -> Result<Upload, Box<dyn std::error::Error>> {
+        let data = self.assemble(upload).await?;
+
+        let outcome = match upload.purpose {
+            UploadPurpose::Avatar => {
+                let accepted = self.upload_avatar_use_case.execute(upload.user_id, data, avatar_crop(&upload.metadata)?).await?;
+                UploadOutcome::Avatar { job_id: accepted.job_id }
+            }
+            UploadPurpose::Attachment => {
+                // Only images are served as what they are; anything else is a download
+                let content_type = imaging::sniff(&data).map_or("application/octet-stream", |format| format.to_mime_type());
+                // Content-addressed; the same file attached twice is stored once
+                let storage_key = format!("attachments/{}", content_digest(&data));
+                let byte_size = data.len() as i64;
+                self.media_storage.put(&storage_key, data, content_type).await?;
+
+                let attachment = self.attachment_repository.create(NewAttachment {
+                    user_id: upload.user_id,
+                    storage_key,
+                    filename: upload.metadata.get("filename").and_then(|name| attachment_filename(name)),
+                    content_type: content_type.to_string(),
+                    byte_size,
+                }).await?;
+                UploadOutcome::Attachment { attachment_id: attachment.id }
+            }
+        };
+
+        let completed = self.upload_repository.complete(upload.id, outcome).await?;
+        delete_parts(&self.media_storage, upload).await?;
+        Ok(completed)
+    }
+
+    /// Reads the parts back in order. Each part names the offset it starts at, so a part left
+    /// behind by a request that died before recording it is simply never reached.
+    async fn assemble(&self, upload: &Upload) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut data = Vec::with_capacity(upload.length as usize);
+        while (data.len() as i64) < upload.length {
+            let part = self.media_storage.get(&upload.part_key(data.len() as i64)).await?;
+            if part.is_empty() {
+                return Err(Box::new(MediaStorageError::NotFound(upload.part_key(data.len() as i64))));
+            }
+            data.extend(part);
+        }
+        if data.len() as i64 != upload.length {
+            return Err(format!("Upload {} has {} bytes stored, expected {}", upload.id, data.len(), upload.length).into());
+        }
+        Ok(data)
+    }
+}
+
+pub struct TerminateUploadUseCase<R: UploadRepository, S: MediaStorage> {
+    upload_repository: R,
+    media_storage: S,
+}
+
+impl<R: UploadRepository, S: MediaStorage> TerminateUploadUseCase<R, S> {
+    pub fn new(upload_repository: R, media_storage: S) -> Self {
+        Self {
+            upload_repository,
+            media_storage,
+        }
+    }
+
+    /// Discards the upload and what was received for it. A finished upload's avatar or
+    /// attachment is not affected.
+    pub async fn execute(&self, user_id: i32, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        let upload = find_upload(&self.upload_repository, user_id, upload_id).await?;
+        discard(&self.upload_repository, &self.media_storage, &upload).await
+    }
+}
+
+pub struct ExpireUploadsUseCase<R: UploadRepository, S: MediaStorage> {
+    upload_repository: R,
+    media_storage: S,
+}
+
+impl<R: UploadRepository, S: MediaStorage> ExpireUploadsUseCase<R, S> {
+    pub fn new(upload_repository: R, media_storage: S) -> Self {
+        Self {
+            upload_repository,
+            media_storage,
+        }
+    }
+
+    /// Discards every expired upload and returns how many there were.
+    pub async fn execute(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let expired = self.upload_repository.find_expired(Utc::now().naive_utc()).await?;
+        for upload in &expired {
+            discard(&self.upload_repository, &self.media_storage, upload).await?;
+        }
+        Ok(expired.len())
+    }
+}
+
+/// Uploads of other users are reported as missing.
+async fn find_upload<R: UploadRepository>(upload_repository: &R, user_id: i32, upload_id: Uuid) -> Result<Upload, Box<dyn std::error::Error>> {
+    match upload_repository.find_by_id(upload_id).await? {
+        Some(upload) if upload.user_id == user_id => Ok(upload),
+        _ => Err(Box::new(UploadError::NotFound)),
+    }
+}
+
+async fn discard<R: UploadRepository, S: MediaStorage>(upload_repository: &R, media_storage: &S, upload: &Upload) -> Result<(), Box<dyn std::error::Error>> {
+    delete_parts(media_storage, upload).await?;
+    upload_repository.delete(upload.id).await
+}
+
+async fn delete_parts<S: MediaStorage>(media_storage: &S, upload: &Upload) -> Result<(), Box<dyn std::error::Error>> {
+    for part in media_storage.list(&upload.parts_prefix()).await? {
+        media_storage.delete(&part.key).await?;
+    }
+    Ok(())
+}
+
+fn avatar_crop(metadata: &BTreeMap<String, String>) -> Result<AvatarCrop, InvalidCrop> {
+    AvatarCrop::from_fields(|name| metadata.get(name).map(String::as_str))
+}
+
+/// The last path segment of a client-supplied file name, without control characters.
+fn attachment_filename(name: &str) -> Option<String> {
+    let name: String = name.rsplit(['/', '\\']).next()?
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FILENAME_CHARS)
+        .collect();
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use chrono::NaiveDateTime;
 use crate::domain::entities::avatar::Avatar;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Account {
     pub id: i32,
     pub user_id: i32,
@@ -15,7 +16,7 @@ pub struct Account {
     pub updated_at: NaiveDateTime,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateAccountDto {
     pub first_name: Option<String>,
     pub middle_name: Option<String>,
@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::domain::repositories::media_storage::MediaStorage;
+
+/// A file shared in chat. Messages refer to it by id.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Attachment {
+    pub id: i32,
+    /// The user who uploaded it.
+    pub user_id: i32,
+    #[serde(skip)]
+    pub storage_key: String,
+    /// Name of the file on the sender's device, without any directories.
+    pub filename: Option<String>,
+    /// Detected from the content for images; anything else is `application/octet-stream`.
+    pub content_type: String,
+    pub byte_size: i64,
+    /// Where to download the file; filled in for the caller.
+    pub url: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Attachment {
+    pub fn resolve_url<S: MediaStorage + ?Sized>(&mut self, media_storage: &S) {
+        self.url = Some(media_storage.public_url(&self.storage_key));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAttachment {
+    pub user_id: i32,
+    pub storage_key: String,
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub byte_size: i64,
+}
+
+/// The attachment does not exist, or the user neither uploaded it nor exchanged it in a message.
+#[derive(Debug, PartialEq)]
+pub struct AttachmentNotFound(pub i32);
+
+impl std::fmt::Display for AttachmentNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Attachment {} not found", self.0)
+    }
+}
+
+impl std::error::Error for AttachmentNotFound {}
@@ -1,19 +1,262 @@
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use chrono::NaiveDateTime;
+use crate::domain::entities::media::{content_digest, responsive_sources, ImagePlaceholder, ImageSources, MediaVariant};
+use crate::domain::repositories::media_storage::MediaStorage;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Avatar {
     pub id: i32,
     pub account_id: i32,
+    /// The uploaded file the variants were rendered from, if it was kept.
+    #[serde(skip)]
+    pub original_key: Option<String>,
+    /// Stored renditions; clients only ever see the resolved URLs.
+    #[serde(skip)]
+    pub variants: Vec<MediaVariant>,
+    /// 300px rendition, for clients that predate `images`.
     pub avatar_300x300_url: Option<String>,
+    /// 40px rendition, for clients that predate `images`.
     pub avatar_40x40_url: Option<String>,
+    /// Sources keyed by logical size in CSS pixels.
+    pub images: BTreeMap<u32, ImageSources>,
+    /// BlurHash to paint until an image has loaded. Absent for avatars processed before
+    /// placeholders were computed.
+    #[schema(example = "UKO2?U%2Tw=w]~RBVZRi};RPxuwH-;~qD%M{")]
+    pub blurhash: Option<String>,
+    /// Most common color of the avatar as `#rrggbb`, for a flat placeholder.
+    #[schema(example = "#6b8fa3")]
+    pub dominant_color: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
-#[derive(Debug, Serialize)]
-pub struct AvatarUploadResponse {
-    pub avatar_300x300_url: String,
-    pub avatar_40x40_url: String,
+impl Avatar {
+    /// Fills the URL fields from the stored variants.
+    pub fn resolve_urls<S: MediaStorage + ?Sized>(&mut self, media_storage: &S) {
+        self.images = responsive_sources(&self.variants, media_storage);
+        self.avatar_300x300_url = self.images.get(&300).map(|sources| sources.src.clone());
+        self.avatar_40x40_url = self.images.get(&40).map(|sources| sources.src.clone());
+    }
+}
+
+/// An avatar as processed, before it has an id.
+#[derive(Debug, Clone)]
+pub struct NewAvatar {
+    pub account_id: i32,
+    /// The uploaded file the variants were rendered from.
+    pub original_key: Option<String>,
+    pub variants: Vec<MediaVariant>,
+    pub placeholder: Option<ImagePlaceholder>,
+}
+
+/// An upload stored and queued for processing. The variants follow with an `AvatarReady`
+/// WebSocket event, or can be polled for at `/jobs/{job_id}`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AvatarUploadAccepted {
+    pub job_id: i64,
     pub message: String,
-}
\ No newline at end of file
+}
+
+/// Payload of the job that renders an uploaded original into variants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvatarProcessingJob {
+    pub user_id: i32,
+    pub account_id: i32,
+    pub original_key: String,
+    pub crop: AvatarCrop,
+}
+
+impl AvatarProcessingJob {
+    pub const KIND: &'static str = "avatar.process";
+
+    /// Where the variants are stored: next to the original, under a name derived from the
+    /// crop. Identical originals cropped alike render identically, so they share the prefix.
+    pub fn variant_prefix(&self) -> String {
+        let directory = self.original_key.rsplit_once('/').map_or("", |(directory, _)| directory);
+        let crop = serde_json::to_vec(&self.crop).unwrap_or_default();
+        format!("{}/{}", directory, &content_digest(&crop)[..16])
+    }
+}
+
+/// Every avatar an account has uploaded, newest first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AvatarGallery {
+    pub default_avatar_id: Option<i32>,
+    pub avatars: Vec<Avatar>,
+}
+
+/// What crop rectangle coordinates are measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CropUnits {
+    /// Source image pixels.
+    #[default]
+    Pixels,
+    /// Fractions of the source image's width and height, from 0 to 1.
+    Normalized,
+}
+
+impl std::str::FromStr for CropUnits {
+    type Err = InvalidCrop;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pixels" | "px" => Ok(Self::Pixels),
+            "normalized" => Ok(Self::Normalized),
+            other => Err(InvalidCrop(format!("unknown units `{}`, expected `pixels` or `normalized`", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub units: CropUnits,
+}
+
+/// Point of interest as fractions of the source image's width and height, from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FocalPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Client instructions for cutting the square avatar out of an uploaded image. Without a
+/// rectangle the whole image is used; without a focal point the square is centered.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct AvatarCrop {
+    pub rect: Option<CropRect>,
+    pub focal_point: Option<FocalPoint>,
+}
+
+/// Square area of the source image, in pixels, that every variant is scaled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SquareRegion {
+    pub x: u32,
+    pub y: u32,
+    pub side: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidCrop(pub String);
+
+impl std::fmt::Display for InvalidCrop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid crop: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCrop {}
+
+/// The avatar does not exist, or belongs to another account.
+#[derive(Debug, PartialEq)]
+pub struct AvatarNotFound(pub i32);
+
+impl std::fmt::Display for AvatarNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Avatar {} not found", self.0)
+    }
+}
+
+impl std::error::Error for AvatarNotFound {}
+
+impl AvatarCrop {
+    /// Names of the text fields a crop is described with, in upload forms and upload metadata.
+    pub const FIELDS: [&'static str; 7] = ["crop_x", "crop_y", "crop_width", "crop_height", "crop_units", "focal_x", "focal_y"];
+
+    /// Reads a crop from named text fields. The crop rectangle needs all four of its fields
+    /// and the focal point both of its own; leaving a group out entirely selects the default.
+    pub fn from_fields<'a>(field: impl Fn(&str) -> Option<&'a str>) -> Result<Self, InvalidCrop> {
+        let number = |name: &str| -> Result<Option<f64>, InvalidCrop> {
+            field(name)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f64>().map_err(|_| InvalidCrop(format!("`{}` must be a number", name))))
+                .transpose()
+        };
+
+        let rect = match (number("crop_x")?, number("crop_y")?, number("crop_width")?, number("crop_height")?) {
+            (Some(x), Some(y), Some(width), Some(height)) => Some(CropRect {
+                x,
+                y,
+                width,
+                height,
+                units: field("crop_units").map_or(Ok(CropUnits::default()), |units| units.parse())?,
+            }),
+            (None, None, None, None) => None,
+            _ => return Err(InvalidCrop("crop_x, crop_y, crop_width and crop_height go together".to_string())),
+        };
+
+        let focal_point = match (number("focal_x")?, number("focal_y")?) {
+            (Some(x), Some(y)) => Some(FocalPoint { x, y }),
+            (None, None) => None,
+            _ => return Err(InvalidCrop("focal_x and focal_y go together".to_string())),
+        };
+
+        Ok(Self { rect, focal_point })
+    }
+
+    /// The largest square inside the crop rectangle, positioned as close to centered on the
+    /// focal point as the rectangle allows.
+    pub fn square_region(&self, image_width: u32, image_height: u32) -> Result<SquareRegion, InvalidCrop> {
+        let (width, height) = (image_width as f64, image_height as f64);
+        if image_width == 0 || image_height == 0 {
+            return Err(InvalidCrop("image is empty".to_string()));
+        }
+
+        let (left, top, right, bottom) = match self.rect {
+            None => (0, 0, image_width, image_height),
+            Some(rect) => {
+                let values = [rect.x, rect.y, rect.width, rect.height];
+                if values.iter().any(|value| !value.is_finite() || *value < 0.0) {
+                    return Err(InvalidCrop("coordinates must be non-negative numbers".to_string()));
+                }
+                let (scale_x, scale_y) = match rect.units {
+                    CropUnits::Pixels => (1.0, 1.0),
+                    CropUnits::Normalized if values.iter().all(|value| *value <= 1.0) => (width, height),
+                    CropUnits::Normalized => {
+                        return Err(InvalidCrop("normalized coordinates must be between 0 and 1".to_string()));
+                    }
+                };
+
+                // Edges are snapped to whole pixels and clipped to the image, so rounding
+                // in the client never causes a rejection
+                let left = (rect.x * scale_x).round().min(width) as u32;
+                let top = (rect.y * scale_y).round().min(height) as u32;
+                let right = ((rect.x + rect.width) * scale_x).round().min(width) as u32;
+                let bottom = ((rect.y + rect.height) * scale_y).round().min(height) as u32;
+                if right <= left || bottom <= top {
+                    return Err(InvalidCrop(format!(
+                        "rectangle lies outside the {}x{} image or is empty", image_width, image_height
+                    )));
+                }
+                (left, top, right, bottom)
+            }
+        };
+
+        let side = (right - left).min(bottom - top);
+        let (focus_x, focus_y) = match self.focal_point {
+            None => ((left + right) as f64 / 2.0, (top + bottom) as f64 / 2.0),
+            Some(point) if (0.0..=1.0).contains(&point.x) && (0.0..=1.0).contains(&point.y) => {
+                (point.x * width, point.y * height)
+            }
+            Some(_) => return Err(InvalidCrop("focal point must be between 0 and 1".to_string())),
+        };
+
+        Ok(SquareRegion {
+            x: centered_start(focus_x, side, left, right),
+            y: centered_start(focus_y, side, top, bottom),
+            side,
+        })
+    }
+}
+
+/// Start of a `side`-long span centered on `focus`, shifted to stay within `min..max`.
+fn centered_start(focus: f64, side: u32, min: u32, max: u32) -> u32 {
+    let start = (focus - side as f64 / 2.0).round().max(0.0) as u32;
+    start.clamp(min, max - side)
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use chrono::NaiveDateTime;
+use crate::domain::entities::account::Account;
+use crate::domain::entities::avatar::Avatar;
+use crate::domain::entities::message::DatabaseMessage;
+
+/// Characters of the last message shown in the inbox.
+pub const PREVIEW_LENGTH: usize = 100;
+
+/// One user's side of a conversation with another.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub user_id: i32,
+    pub counterpart_id: i32,
+    pub counterpart_username: String,
+    /// Latest message in either direction that the user hasn't deleted for themselves.
+    pub last_message: DatabaseMessage,
+    /// Messages received from the counterpart that are still unread.
+    pub unread_count: i32,
+}
+
+/// Who a conversation is with.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct Counterpart {
+    pub user_id: i32,
+    pub username: String,
+    pub first_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub last_name: Option<String>,
+    pub avatar: Option<Avatar>,
+}
+
+impl Counterpart {
+    /// `account` is absent for users who never got one; they are listed by username alone.
+    pub fn new(user_id: i32, username: String, account: Option<Account>) -> Self {
+        let account = account.map(|account| (account.first_name, account.middle_name, account.last_name, account.default_avatar));
+        let (first_name, middle_name, last_name, avatar) = account.unwrap_or_default();
+        Self { user_id, username, first_name, middle_name, last_name, avatar }
+    }
+}
+
+/// Enough of a message to show it in a list.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct MessagePreview {
+    pub id: i32,
+    pub sender_id: i32,
+    /// The first characters of the message, ending in `…` when cut short.
+    pub content: String,
+    pub has_attachment: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<&DatabaseMessage> for MessagePreview {
+    fn from(message: &DatabaseMessage) -> Self {
+        let mut content: String = message.content.chars().take(PREVIEW_LENGTH).collect();
+        if content.len() < message.content.len() {
+            content.push('…');
+        }
+
+        Self {
+            id: message.id,
+            sender_id: message.sender_id,
+            content,
+            has_attachment: message.attachment_id.is_some(),
+            created_at: message.created_at,
+        }
+    }
+}
+
+/// An entry of the inbox.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct ConversationSummary {
+    pub counterpart: Counterpart,
+    pub last_message: MessagePreview,
+    /// Messages received from the counterpart that are still unread.
+    pub unread_count: i32,
+}
+
+/// One page of the inbox, most recently active conversation first.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct ConversationPage {
+    pub conversations: Vec<ConversationSummary>,
+    /// Pass as `before` to load the conversations that were last active earlier. Absent on
+    /// the last page.
+    pub older: Option<String>,
+}
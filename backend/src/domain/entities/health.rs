@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+impl HealthReport {
+    pub fn from_components(components: BTreeMap<String, ComponentHealth>) -> Self {
+        let status = if components.values().all(|c| c.status == HealthStatus::Up) {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+
+        Self { status, components }
+    }
+}
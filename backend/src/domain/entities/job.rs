@@ -0,0 +1,103 @@
+use std::str::FromStr;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Attempts a job gets unless it asks for something else.
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for `run_at`, or for a free worker.
+    Queued,
+    Running,
+    Succeeded,
+    /// Out of attempts, or failed in a way retrying cannot fix.
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "queued" => Ok(Self::Queued),
+            "running" => Ok(Self::Running),
+            "succeeded" => Ok(Self::Succeeded),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("Unknown job status '{}'", other)),
+        }
+    }
+}
+
+/// A unit of background work. `kind` selects the handler that runs it and `payload` is that
+/// handler's input; neither means anything to the queue itself.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    #[serde(skip)]
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    /// The user allowed to see the job; `None` for system jobs.
+    #[serde(skip)]
+    pub user_id: Option<i32>,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    /// When the job is next due, while it is queued.
+    pub run_at: NaiveDateTime,
+    #[serde(skip)]
+    pub locked_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    /// What the handler reported on success.
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub user_id: Option<i32>,
+    pub max_attempts: i32,
+    /// `None` runs the job as soon as a worker is free.
+    pub run_at: Option<NaiveDateTime>,
+}
+
+impl NewJob {
+    pub fn new(kind: &str, payload: serde_json::Value) -> Self {
+        Self {
+            kind: kind.to_string(),
+            payload,
+            user_id: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            run_at: None,
+        }
+    }
+}
+
+/// The job does not exist, belongs to another user or is a system job.
+#[derive(Debug, PartialEq)]
+pub struct JobNotFound(pub i64);
+
+impl std::fmt::Display for JobNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Job {} not found", self.0)
+    }
+}
+
+impl std::error::Error for JobNotFound {}
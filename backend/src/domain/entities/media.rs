@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::domain::repositories::media_storage::MediaStorage;
+
+/// Encoding of a stored image rendition. Declared in order of client preference, which is
+/// also the order `srcset` maps list them in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Avif,
+    Webp,
+    /// Fallback every browser can decode.
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Avif => "avif",
+            Self::Webp => "webp",
+            Self::Jpeg => "jpeg",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Avif => "image/avif",
+            Self::Webp => "image/webp",
+            Self::Jpeg => "image/jpeg",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Avif => "avif",
+            Self::Webp => "webp",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "avif" => Ok(Self::Avif),
+            "webp" => Ok(Self::Webp),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            other => Err(format!("Unknown image format '{}', expected 'avif', 'webp' or 'jpeg'", other)),
+        }
+    }
+}
+
+/// One stored rendition of an image: a logical size in CSS pixels, rendered at a pixel
+/// density, in one format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaVariant {
+    pub size: u32,
+    pub density: u32,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub storage_key: String,
+    /// Unknown for renditions stored before variants were tracked.
+    pub byte_size: Option<i64>,
+}
+
+/// What clients can paint while an image loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePlaceholder {
+    pub blurhash: String,
+    /// Most common color, as `#rrggbb`.
+    pub dominant_color: String,
+}
+
+/// Declarative list of renditions produced for every uploaded image: each size at each
+/// density, in each format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantPlan {
+    pub sizes: Vec<u32>,
+    pub densities: Vec<u32>,
+    pub formats: Vec<ImageFormat>,
+}
+
+impl Default for VariantPlan {
+    fn default() -> Self {
+        Self {
+            sizes: vec![40, 80, 160, 300, 600],
+            densities: vec![1, 2],
+            formats: vec![ImageFormat::Avif, ImageFormat::Webp, ImageFormat::Jpeg],
+        }
+    }
+}
+
+impl VariantPlan {
+    /// Every `(size, density)` pair of the plan.
+    pub fn renditions(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.sizes.iter().flat_map(move |size| self.densities.iter().map(move |density| (*size, *density)))
+    }
+
+    /// Distinct square edge lengths to render; `40 @ 2x` and `80 @ 1x` need only one image.
+    pub fn pixel_sizes(&self) -> BTreeSet<u32> {
+        self.renditions().map(|(size, density)| size * density).collect()
+    }
+}
+
+/// Sources for one logical size, ready for `<img src srcset>` and `<picture><source>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ImageSources {
+    /// 1x rendition in the most compatible format, for `src`.
+    pub src: String,
+    /// `srcset` attribute value per format, e.g. `"…/40.avif 1x, …/80.avif 2x"`.
+    pub srcset: BTreeMap<ImageFormat, String>,
+}
+
+/// Groups variants by logical size and resolves their URLs.
+pub fn responsive_sources<S: MediaStorage + ?Sized>(variants: &[MediaVariant], media_storage: &S) -> BTreeMap<u32, ImageSources> {
+    let mut by_size: BTreeMap<u32, Vec<&MediaVariant>> = BTreeMap::new();
+    for variant in variants {
+        by_size.entry(variant.size).or_default().push(variant);
+    }
+
+    by_size.into_iter()
+        .map(|(size, mut variants)| {
+            variants.sort_by_key(|variant| (variant.format, variant.density));
+
+            let mut srcset: BTreeMap<ImageFormat, String> = BTreeMap::new();
+            for variant in &variants {
+                let candidate = format!("{} {}x", media_storage.public_url(&variant.storage_key), variant.density);
+                srcset.entry(variant.format)
+                    .and_modify(|list| {
+                        list.push_str(", ");
+                        list.push_str(&candidate);
+                    })
+                    .or_insert(candidate);
+            }
+
+            // Lowest density of the last (most compatible) format
+            let fallback = variants.iter()
+                .min_by_key(|variant| (std::cmp::Reverse(variant.format), variant.density))
+                .map(|variant| media_storage.public_url(&variant.storage_key))
+                .unwrap_or_default();
+
+            (size, ImageSources { src: fallback, srcset })
+        })
+        .collect()
+}
+
+/// Bounds on uploaded images, enforced before the bytes are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadLimits {
+    pub max_bytes: usize,
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            max_width: 8192,
+            max_height: 8192,
+        }
+    }
+}
+
+/// Why an uploaded image was refused.
+#[derive(Debug, PartialEq)]
+pub enum UploadRejection {
+    /// The bytes are not a JPEG, PNG, WebP or GIF image, whatever the file name says.
+    UnsupportedType,
+    TooManyBytes { max_bytes: usize },
+    TooManyPixels { width: u32, height: u32, max_width: u32, max_height: u32 },
+    /// Recognised, but the data is corrupt or truncated.
+    Unreadable(String),
+}
+
+impl std::fmt::Display for UploadRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedType => write!(f, "Only JPEG, PNG, WebP and GIF images are accepted"),
+            Self::TooManyBytes { max_bytes } => write!(f, "Uploads are limited to {} bytes", max_bytes),
+            Self::TooManyPixels { width, height, max_width, max_height } => write!(
+                f, "Image is {}x{} pixels; the limit is {}x{}", width, height, max_width, max_height
+            ),
+            Self::Unreadable(reason) => write!(f, "Image could not be decoded: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for UploadRejection {}
+
+/// Hex-encoded SHA-256 of `bytes`. Uploads are stored under the digest of their content, so
+/// the same file uploaded twice ends up in one place.
+pub fn content_digest(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// How many rows refer to a stored object. Identical content is stored once, so one object
+/// can outlive several of the avatars and attachments using it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaObject {
+    pub storage_key: String,
+    pub reference_count: i32,
+}
+
+/// The row a stored object belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum MediaOwner {
+    /// One of the avatar's variants or its original.
+    Avatar(i32),
+    Attachment(i32),
+    /// A part of an upload still under way.
+    Upload(Uuid),
+}
+
+/// A stored object a row points at.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MediaReference {
+    pub owner: MediaOwner,
+    pub key: String,
+}
+
+/// What a media reconciliation pass found out of step between storage and the database.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaReconciliation {
+    /// Stored objects nothing refers to.
+    pub orphaned_objects: Vec<String>,
+    /// References to objects that are not stored.
+    pub missing_objects: Vec<MediaReference>,
+    /// Whether the findings were removed, or only reported.
+    pub deleted: bool,
+}
+
+impl MediaReconciliation {
+    /// Kind of the scheduled job that runs a reconciliation pass.
+    pub const JOB_KIND: &'static str = "media.reconcile";
+}
@@ -1,9 +1,13 @@
+use std::str::FromStr;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDateTime;
+use utoipa::ToSchema;
+use chrono::{DateTime, NaiveDateTime};
 use diesel::prelude::*;
 use crate::schema::messages;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable)]
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, ToSchema)]
 #[diesel(table_name = messages)]
 pub struct DatabaseMessage {
     pub id: i32,
@@ -12,13 +16,291 @@ pub struct DatabaseMessage {
     pub content: String,
     pub is_read: bool,
     pub created_at: NaiveDateTime,
+    /// File shared with the message, sent by the sender beforehand as a resumable upload.
+    pub attachment_id: Option<i32>,
+    /// When the message reached the receiver over their socket, or when they read it if that
+    /// came first.
+    pub delivered_at: Option<NaiveDateTime>,
+    /// When the receiver read the message; set exactly when `is_read` is.
+    pub read_at: Option<NaiveDateTime>,
+    /// When the sender last changed the content.
+    pub edited_at: Option<NaiveDateTime>,
+    /// When the sender deleted the message for everyone. The message is then a tombstone,
+    /// with no content or attachment.
+    pub deleted_at: Option<NaiveDateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl DatabaseMessage {
+    pub fn cursor(&self) -> MessageCursor {
+        MessageCursor { created_at: self.created_at, id: self.id }
+    }
+}
+
+/// Body of a message sent through the REST API.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SendMessageDto {
+    #[serde(default)]
+    pub content: String,
+    /// File uploaded beforehand by the sender.
+    pub attachment_id: Option<i32>,
+}
+
+/// Why a message was not sent.
+#[derive(Debug, PartialEq)]
+pub enum SendMessageError {
+    /// Neither text nor an attachment.
+    Empty,
+    RecipientNotFound(i32),
+    /// The attachment does not exist or was uploaded by someone else.
+    AttachmentNotFound(i32),
+    Database(String),
+}
+
+impl std::fmt::Display for SendMessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "A message needs content or an attachment"),
+            Self::RecipientNotFound(user_id) => write!(f, "User {} not found", user_id),
+            Self::AttachmentNotFound(attachment_id) => write!(f, "Attachment {} not found", attachment_id),
+            Self::Database(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for SendMessageError {}
+
+/// Body of a request marking a conversation read.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MarkReadDto {
+    /// Last message the reader has seen. It and every earlier message they received in the
+    /// conversation are marked read.
+    pub up_to_message_id: i32,
+}
+
+/// Messages a reader has just read. Messages that were already read are left out.
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+pub struct ReadReceipt {
+    pub message_ids: Vec<i32>,
+    pub read_at: NaiveDateTime,
+}
+
+/// Why a conversation was not marked read.
+#[derive(Debug, PartialEq)]
+pub enum MarkReadError {
+    /// The message does not exist or belongs to another conversation.
+    MessageNotFound(i32),
+    Database(String),
+}
+
+impl std::fmt::Display for MarkReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MessageNotFound(message_id) => write!(f, "Message {} not found", message_id),
+            Self::Database(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for MarkReadError {}
+
+/// Body of a request editing a message.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditMessageDto {
+    pub content: String,
+}
+
+/// Earlier content of an edited message.
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+pub struct MessageEdit {
+    pub content: String,
+    /// When this content was replaced.
+    pub edited_at: NaiveDateTime,
+}
+
+/// Whom a message was just deleted for.
+#[derive(Debug, Clone)]
+pub struct MessageDeletion {
+    /// The tombstone when deleted for everyone, otherwise the message as it still is for the
+    /// other participant.
+    pub message: DatabaseMessage,
+    pub for_everyone: bool,
+    pub deleted_at: NaiveDateTime,
+}
+
+/// Why a message was not edited or deleted.
+#[derive(Debug, PartialEq)]
+pub enum MessageChangeError {
+    /// The message does not exist or is in someone else's conversation. Deleted messages
+    /// cannot be edited either.
+    NotFound(i32),
+    /// Only the sender may edit a message or delete it for everyone.
+    NotSender(i32),
+    /// The message is too old to edit.
+    EditWindowClosed(i32),
+    /// Neither text nor an attachment would be left.
+    Empty,
+    Database(String),
+}
+
+impl std::fmt::Display for MessageChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(message_id) => write!(f, "Message {} not found", message_id),
+            Self::NotSender(message_id) => write!(f, "Only the sender can change message {}", message_id),
+            Self::EditWindowClosed(message_id) => write!(f, "Message {} can no longer be edited", message_id),
+            Self::Empty => write!(f, "A message needs content or an attachment"),
+            Self::Database(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for MessageChangeError {}
+
+/// Position of a message within a conversation. Messages are ordered by `(created_at, id)`, so
+/// two sent within the same microsecond still have a fixed order.
+///
+/// Clients get cursors as opaque strings and hand them back unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MessageCursor {
+    pub created_at: NaiveDateTime,
+    pub id: i32,
+}
+
+impl std::fmt::Display for MessageCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let raw = format!("{}:{}", self.created_at.and_utc().timestamp_micros(), self.id);
+        f.write_str(&BASE64.encode(raw))
+    }
+}
+
+impl FromStr for MessageCursor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid cursor '{}'", value);
+        let raw = BASE64.decode(value).ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+        let (micros, id) = raw.split_once(':').ok_or_else(invalid)?;
+        let created_at = micros.parse().ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?
+            .naive_utc();
+        let id = id.parse().map_err(|_| invalid())?;
+        Ok(Self { created_at, id })
+    }
+}
+
+/// A slice of a conversation to load: the messages strictly between the cursors, read from the
+/// newest end, or from the oldest end with `oldest_first`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageRange {
+    pub before: Option<MessageCursor>,
+    pub after: Option<MessageCursor>,
+    pub oldest_first: bool,
+    pub limit: i64,
+    /// Leaves out the messages this user deleted for themselves.
+    pub hidden_for: Option<i32>,
+}
+
+/// One page of a conversation, newest message first.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct MessagePage {
+    pub messages: Vec<DatabaseMessage>,
+    /// Pass as `before` to load the messages older than this page. Absent once the start of
+    /// the conversation has been reached.
+    pub older: Option<String>,
+    /// Pass as `after` to load the messages newer than this page, including ones sent since.
+    /// Absent only while the conversation is empty.
+    pub newer: Option<String>,
+    /// Whether more messages lie past this page in the direction being paged: older ones,
+    /// or newer ones when paging with only `after`.
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub enum WebSocketMessage {
+    /// Client request to send a message. It is stored, then answered with `ChatAck` or
+    /// `ChatFailed` carrying the same `client_msg_id`.
     Chat {
         to_user_id: i32,
         content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attachment_id: Option<i32>,
+        /// Chosen by the client to match the answer to its request.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_msg_id: Option<String>,
+    },
+    /// Server event: a message was sent to this user.
+    NewMessage {
+        message_id: i32,
+        from_user_id: i32,
+        to_user_id: i32,
+        content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        attachment_id: Option<i32>,
+        created_at: NaiveDateTime,
+        /// The sender's `client_msg_id`, when it sent one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_msg_id: Option<String>,
+    },
+    /// Server event: a `Chat` request was stored.
+    ChatAck {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_msg_id: Option<String>,
+        message_id: i32,
+        created_at: NaiveDateTime,
+    },
+    /// Server event: a `Chat` request could not be stored, so nothing was sent.
+    ChatFailed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_msg_id: Option<String>,
+        message: String,
+    },
+    /// Server event: a message this user sent or received was edited by its sender.
+    MessageEdited {
+        message_id: i32,
+        from_user_id: i32,
+        to_user_id: i32,
+        content: String,
+        edited_at: NaiveDateTime,
+    },
+    /// Server event: a message was deleted. For everyone, it is now a tombstone; otherwise
+    /// this user deleted it from their own history, from another session.
+    MessageDeleted {
+        message_id: i32,
+        for_everyone: bool,
+        deleted_at: NaiveDateTime,
+    },
+    /// Client request to mark its conversation with `user_id` read up to and including
+    /// `up_to_message_id`. The other user is sent a `Read` event.
+    MarkRead {
+        user_id: i32,
+        up_to_message_id: i32,
+    },
+    /// Server event: messages this user sent reached the receiver's socket.
+    Delivered {
+        by_user_id: i32,
+        message_ids: Vec<i32>,
+        delivered_at: NaiveDateTime,
+    },
+    /// Server event: messages this user sent were read by the receiver.
+    Read {
+        by_user_id: i32,
+        message_ids: Vec<i32>,
+        read_at: NaiveDateTime,
+    },
+    /// The sender started typing in their conversation with `user_id`. Sent by clients, and
+    /// relayed to that user only, with `user_id` then naming who is typing, once either of the
+    /// two has messaged the other. Nothing is stored, and users who are offline never hear of it. Clients typing for a while repeat it every
+    /// few seconds, as the server sends `TypingStopped` for them when it stops hearing it.
+    TypingStarted {
+        user_id: i32,
+    },
+    /// The sender stopped typing in their conversation with `user_id`; relayed like
+    /// `TypingStarted`.
+    TypingStopped {
+        user_id: i32,
     },
     Status {
         user_id: i32,
@@ -41,7 +323,17 @@ pub enum WebSocketMessage {
     },
     Error {
         message: String,
-    }
+    },
+    /// Server event: an uploaded avatar has been processed and is now the default.
+    AvatarReady {
+        job_id: i64,
+        avatar_id: i32,
+    },
+    /// Server event: an uploaded avatar could not be processed.
+    AvatarFailed {
+        job_id: i64,
+        message: String,
+    },
 }
 
 impl actix::Message for WebSocketMessage {
@@ -2,4 +2,11 @@ pub mod user;
 pub mod auth;
 pub mod account;
 pub mod message;
-pub mod avatar;
\ No newline at end of file
+pub mod avatar;
+pub mod health;
+pub mod media;
+pub mod job;
+pub mod upload;
+pub mod attachment;
+pub mod conversation;
+pub mod user_event;
\ No newline at end of file
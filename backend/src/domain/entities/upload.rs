@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// What a finished upload is handed to, chosen with the `purpose` metadata key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadPurpose {
+    /// Queued for processing like a multipart avatar upload, and subject to the same limits.
+    Avatar,
+    /// Stored as a chat attachment that messages can refer to.
+    Attachment,
+}
+
+impl UploadPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Avatar => "avatar",
+            Self::Attachment => "attachment",
+        }
+    }
+}
+
+impl FromStr for UploadPurpose {
+    type Err = UploadError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "avatar" => Ok(Self::Avatar),
+            "attachment" => Ok(Self::Attachment),
+            other => Err(UploadError::Invalid(format!(
+                "unknown purpose '{}', expected 'avatar' or 'attachment'", other
+            ))),
+        }
+    }
+}
+
+/// A resumable upload. It is created with its final length and filled in by any number of
+/// requests, each continuing where the last one stopped.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Upload {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub user_id: i32,
+    pub purpose: UploadPurpose,
+    /// Total size in bytes, declared when the upload was created.
+    pub length: i64,
+    /// Bytes received so far.
+    pub offset: i64,
+    /// Decoded `Upload-Metadata` sent when the upload was created.
+    pub metadata: BTreeMap<String, String>,
+    /// Set while a request is appending.
+    #[serde(skip)]
+    pub locked_at: Option<NaiveDateTime>,
+    /// Avatar processing job started by the finished upload.
+    pub job_id: Option<i64>,
+    /// Attachment created from the finished upload.
+    pub attachment_id: Option<i32>,
+    /// When the upload and everything received for it are discarded, finished or not.
+    pub expires_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Upload {
+    /// Kind of the scheduled job that discards expired uploads.
+    pub const EXPIRY_JOB_KIND: &'static str = "uploads.expire";
+
+    /// Prefix of every part stored for the upload.
+    pub fn parts_prefix(&self) -> String {
+        format!("uploads/{}/", self.id)
+    }
+
+    /// Key of the part starting at `offset`. Offsets are zero-padded so keys sort in order.
+    pub fn part_key(&self, offset: i64) -> String {
+        format!("{}{:020}", self.parts_prefix(), offset)
+    }
+
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUpload {
+    pub user_id: i32,
+    pub purpose: UploadPurpose,
+    pub length: i64,
+    pub metadata: BTreeMap<String, String>,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Where the bytes of a finished upload went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadOutcome {
+    Avatar { job_id: i64 },
+    Attachment { attachment_id: i32 },
+}
+
+/// Why a resumable upload request was refused.
+#[derive(Debug, PartialEq)]
+pub enum UploadError {
+    /// The length or metadata is missing, malformed or not allowed for the purpose.
+    Invalid(String),
+    /// The declared length is over the limit for the upload's purpose.
+    TooLarge { max_bytes: u64 },
+    /// The request carries more bytes than the declared length leaves room for.
+    ExceedsLength { length: i64 },
+    /// The request does not continue where the upload stopped.
+    OffsetMismatch { offset: i64 },
+    /// Another request is appending to the upload.
+    Locked,
+    Expired,
+    /// The upload does not exist, or belongs to another user.
+    NotFound,
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(reason) => write!(f, "Invalid upload: {}", reason),
+            Self::TooLarge { max_bytes } => write!(f, "Uploads of this kind are limited to {} bytes", max_bytes),
+            Self::ExceedsLength { length } => write!(f, "The upload is only {} bytes long", length),
+            Self::OffsetMismatch { offset } => write!(f, "The upload continues at offset {}", offset),
+            Self::Locked => write!(f, "Another request is writing to the upload"),
+            Self::Expired => write!(f, "The upload has expired"),
+            Self::NotFound => write!(f, "The upload does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
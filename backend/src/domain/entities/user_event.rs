@@ -0,0 +1,12 @@
+use chrono::NaiveDateTime;
+use crate::domain::entities::message::WebSocketMessage;
+
+/// A server event waiting in a user's queue until their socket has been sent it. Ids grow in
+/// the order the user's events were queued.
+#[derive(Debug, Clone)]
+pub struct UserEvent {
+    pub id: i64,
+    pub user_id: i32,
+    pub event: WebSocketMessage,
+    pub created_at: NaiveDateTime,
+}
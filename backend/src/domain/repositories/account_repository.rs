@@ -1,10 +1,36 @@
+use std::sync::Arc;
 use async_trait::async_trait;
 use crate::domain::entities::account::{Account, UpdateAccountDto};
 
 #[async_trait]
 pub trait AccountRepository {
     async fn find_by_user_id(&self, user_id: i32) -> Result<Account, Box<dyn std::error::Error>>;
+    /// Accounts of those users that have one, with their default avatars, in no particular order.
+    async fn find_by_user_ids(&self, user_ids: &[i32]) -> Result<Vec<Account>, Box<dyn std::error::Error>>;
     async fn update(&self, user_id: i32, account: UpdateAccountDto) -> Result<Account, Box<dyn std::error::Error>>;
     async fn set_default_avatar(&self, user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>>;
     async fn load_default_avatar(&self, account: &mut Account) -> Result<(), Box<dyn std::error::Error>>;
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl<T: AccountRepository + Send + Sync + ?Sized> AccountRepository for Arc<T> {
+    async fn find_by_user_id(&self, user_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+        (**self).find_by_user_id(user_id).await
+    }
+
+    async fn find_by_user_ids(&self, user_ids: &[i32]) -> Result<Vec<Account>, Box<dyn std::error::Error>> {
+        (**self).find_by_user_ids(user_ids).await
+    }
+
+    async fn update(&self, user_id: i32, account: UpdateAccountDto) -> Result<Account, Box<dyn std::error::Error>> {
+        (**self).update(user_id, account).await
+    }
+
+    async fn set_default_avatar(&self, user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+        (**self).set_default_avatar(user_id, avatar_id).await
+    }
+
+    async fn load_default_avatar(&self, account: &mut Account) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).load_default_avatar(account).await
+    }
+}
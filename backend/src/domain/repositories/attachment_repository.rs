@@ -0,0 +1,40 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::entities::attachment::{Attachment, NewAttachment};
+use crate::domain::entities::media::MediaReference;
+
+#[async_trait]
+pub trait AttachmentRepository {
+    async fn create(&self, attachment: NewAttachment) -> Result<Attachment, Box<dyn std::error::Error>>;
+    async fn find_by_id(&self, attachment_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>>;
+    /// The attachment, if `user_id` uploaded it or sent or received a message carrying it.
+    async fn find_visible_to(&self, attachment_id: i32, user_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>>;
+    /// Whether any attachment stored under `storage_key` is visible to `user_id`, as in
+    /// `find_visible_to`. Identical files share a key, so several attachments may.
+    async fn is_key_visible_to(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>>;
+    /// The stored object of every attachment.
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<T: AttachmentRepository + Send + Sync + ?Sized> AttachmentRepository for Arc<T> {
+    async fn create(&self, attachment: NewAttachment) -> Result<Attachment, Box<dyn std::error::Error>> {
+        (**self).create(attachment).await
+    }
+
+    async fn find_by_id(&self, attachment_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>> {
+        (**self).find_by_id(attachment_id).await
+    }
+
+    async fn find_visible_to(&self, attachment_id: i32, user_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>> {
+        (**self).find_visible_to(attachment_id, user_id).await
+    }
+
+    async fn is_key_visible_to(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        (**self).is_key_visible_to(storage_key, user_id).await
+    }
+
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>> {
+        (**self).media_references().await
+    }
+}
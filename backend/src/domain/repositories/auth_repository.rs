@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use async_trait::async_trait;
 use crate::domain::entities::{
     auth::{AuthUser, RegisterUserDto},
@@ -8,4 +9,15 @@ use crate::domain::entities::{
 pub trait AuthRepository {
     async fn authenticate(&self, auth: AuthUser) -> Result<User, Box<dyn std::error::Error + Send + Sync>>;
     async fn register(&self, register_dto: RegisterUserDto) -> Result<User, Box<dyn std::error::Error + Send + Sync>>;
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl<T: AuthRepository + Send + Sync + ?Sized> AuthRepository for Arc<T> {
+    async fn authenticate(&self, auth: AuthUser) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
+        (**self).authenticate(auth).await
+    }
+
+    async fn register(&self, register_dto: RegisterUserDto) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
+        (**self).register(register_dto).await
+    }
+}
@@ -1,9 +1,66 @@
+use std::sync::Arc;
 use async_trait::async_trait;
-use crate::domain::entities::avatar::Avatar;
+use crate::domain::entities::avatar::{Avatar, NewAvatar};
+use crate::domain::entities::media::MediaReference;
 
 #[async_trait]
 pub trait AvatarRepository {
-    async fn create(&self, account_id: i32, avatar_300x300_url: String, avatar_40x40_url: String) -> Result<Avatar, Box<dyn std::error::Error>>;
+    /// Stores the avatar together with its variants.
+    async fn create(&self, avatar: NewAvatar) -> Result<Avatar, Box<dyn std::error::Error>>;
+    /// Stores the avatar like `create`, for variants reused from another avatar, but only if
+    /// every variant's file is still referenced. The new rows take their references at once,
+    /// so a concurrent delete cannot release the files in between. `None` when one is no
+    /// longer referenced, as its file may be gone.
+    async fn create_reusing_variants(&self, avatar: NewAvatar) -> Result<Option<Avatar>, Box<dyn std::error::Error>>;
     async fn find_by_account_id(&self, account_id: i32) -> Result<Vec<Avatar>, Box<dyn std::error::Error>>;
     async fn find_latest_by_account_id(&self, account_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>>;
+    async fn find_by_id(&self, avatar_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>>;
+    /// The newest avatar, of any account, with a variant stored under `storage_key`.
+    async fn find_by_variant_key(&self, storage_key: &str) -> Result<Option<Avatar>, Box<dyn std::error::Error>>;
+    /// Whether an avatar of `user_id`'s account has its original or a variant stored under
+    /// `storage_key`.
+    async fn is_key_owned_by(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>>;
+    /// Removes the row; an account using it as default is left without one (`ON DELETE SET NULL`).
+    async fn delete(&self, avatar_id: i32) -> Result<(), Box<dyn std::error::Error>>;
+    /// Every stored object any avatar points at: all variants and originals.
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<T: AvatarRepository + Send + Sync + ?Sized> AvatarRepository for Arc<T> {
+    async fn create(&self, avatar: NewAvatar) -> Result<Avatar, Box<dyn std::error::Error>> {
+        (**self).create(avatar).await
+    }
+
+    async fn create_reusing_variants(&self, avatar: NewAvatar) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        (**self).create_reusing_variants(avatar).await
+    }
+
+    async fn find_by_account_id(&self, account_id: i32) -> Result<Vec<Avatar>, Box<dyn std::error::Error>> {
+        (**self).find_by_account_id(account_id).await
+    }
+
+    async fn find_latest_by_account_id(&self, account_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        (**self).find_latest_by_account_id(account_id).await
+    }
+
+    async fn find_by_id(&self, avatar_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        (**self).find_by_id(avatar_id).await
+    }
+
+    async fn find_by_variant_key(&self, storage_key: &str) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        (**self).find_by_variant_key(storage_key).await
+    }
+
+    async fn is_key_owned_by(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        (**self).is_key_owned_by(storage_key, user_id).await
+    }
+
+    async fn delete(&self, avatar_id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).delete(avatar_id).await
+    }
+
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>> {
+        (**self).media_references().await
+    }
 }
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::entities::conversation::Conversation;
+use crate::domain::entities::message::MessageCursor;
+
+/// The inbox. Conversations follow from the messages saved, so this is read-only.
+#[async_trait]
+pub trait ConversationRepository {
+    /// Up to `limit` of the user's conversations whose last message comes before `before`,
+    /// most recent last message first.
+    async fn find_for_user(&self, user_id: i32, before: Option<MessageCursor>, limit: i64) -> Result<Vec<Conversation>, Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<T: ConversationRepository + Send + Sync + ?Sized> ConversationRepository for Arc<T> {
+    async fn find_for_user(&self, user_id: i32, before: Option<MessageCursor>, limit: i64) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
+        (**self).find_for_user(user_id, before, limit).await
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use crate::domain::entities::job::{Job, NewJob};
+
+/// A persistent queue of background jobs, shared by every worker.
+#[async_trait]
+pub trait JobRepository {
+    async fn enqueue(&self, job: NewJob) -> Result<Job, Box<dyn std::error::Error>>;
+    /// Hands out the longest-due queued job of one of `kinds`, marked running with the attempt
+    /// counted, or `None` when nothing is due. A job still running `lease` after it was
+    /// claimed is assumed abandoned by a crashed worker and handed out again. No job is ever
+    /// held by two claims at once, however many workers poll concurrently.
+    async fn claim(&self, kinds: &[&str], lease: Duration) -> Result<Option<Job>, Box<dyn std::error::Error>>;
+    async fn complete(&self, job_id: i64, result: Option<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>>;
+    /// Records a failed attempt. The job is queued again for `retry_at`, or failed for good
+    /// when there is none.
+    async fn fail(&self, job_id: i64, error: &str, retry_at: Option<NaiveDateTime>) -> Result<(), Box<dyn std::error::Error>>;
+    async fn find_by_id(&self, job_id: i64) -> Result<Option<Job>, Box<dyn std::error::Error>>;
+    /// Jobs of `kind` that are queued or running, oldest first.
+    async fn find_unfinished(&self, kind: &str) -> Result<Vec<Job>, Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<T: JobRepository + Send + Sync + ?Sized> JobRepository for Arc<T> {
+    async fn enqueue(&self, job: NewJob) -> Result<Job, Box<dyn std::error::Error>> {
+        (**self).enqueue(job).await
+    }
+
+    async fn claim(&self, kinds: &[&str], lease: Duration) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+        (**self).claim(kinds, lease).await
+    }
+
+    async fn complete(&self, job_id: i64, result: Option<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).complete(job_id, result).await
+    }
+
+    async fn fail(&self, job_id: i64, error: &str, retry_at: Option<NaiveDateTime>) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).fail(job_id, error, retry_at).await
+    }
+
+    async fn find_by_id(&self, job_id: i64) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+        (**self).find_by_id(job_id).await
+    }
+
+    async fn find_unfinished(&self, kind: &str) -> Result<Vec<Job>, Box<dyn std::error::Error>> {
+        (**self).find_unfinished(kind).await
+    }
+}
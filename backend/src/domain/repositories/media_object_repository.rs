@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::domain::entities::media::MediaObject;
+
+/// Reference counts of stored objects. Rows referring to an object are counted as they are
+/// written, including rows removed by cascading deletes, so this never has to be told.
+#[async_trait]
+pub trait MediaObjectRepository {
+    /// Counts for those of `keys` that are tracked.
+    async fn find_by_keys(&self, keys: &[String]) -> Result<Vec<MediaObject>, Box<dyn std::error::Error>>;
+    /// Claims those of `keys` nothing refers to any more, untracked ones included, so their
+    /// objects can be deleted, and returns them. Until released, counting a new reference to
+    /// a claimed key fails, so an upload of the same content never has its file deleted from
+    /// under it.
+    async fn claim_unreferenced(&self, keys: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+    /// Stops tracking claimed keys once their objects are gone. Keys referenced again since,
+    /// after their claim lapsed, are kept.
+    async fn release_claimed(&self, keys: &[String]) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<T: MediaObjectRepository + Send + Sync + ?Sized> MediaObjectRepository for Arc<T> {
+    async fn find_by_keys(&self, keys: &[String]) -> Result<Vec<MediaObject>, Box<dyn std::error::Error>> {
+        (**self).find_by_keys(keys).await
+    }
+
+    async fn claim_unreferenced(&self, keys: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        (**self).claim_unreferenced(keys).await
+    }
+
+    async fn release_claimed(&self, keys: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).release_claimed(keys).await
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+use async_trait::async_trait;
+
+#[derive(Debug)]
+pub enum MediaStorageError {
+    /// No object is stored under the key.
+    NotFound(String),
+    /// The key is empty, absolute or tries to escape the storage root.
+    InvalidKey(String),
+    /// The backend could not be reached or refused the operation.
+    Backend(String),
+}
+
+impl fmt::Display for MediaStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaStorageError::NotFound(key) => write!(f, "Media object not found: {}", key),
+            MediaStorageError::InvalidKey(key) => write!(f, "Invalid media key: {}", key),
+            MediaStorageError::Backend(message) => write!(f, "Media storage error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for MediaStorageError {}
+
+/// What `MediaStorage::stat` knows about a stored object.
+#[derive(Debug, Clone)]
+pub struct MediaObjectInfo {
+    pub size: u64,
+    pub content_type: String,
+    pub last_modified: Option<SystemTime>,
+    /// Quoted entity tag, ready for an `ETag` header.
+    pub etag: String,
+}
+
+/// One entry of `MediaStorage::list`.
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Object storage for uploaded media. Objects are addressed by relative keys such as
+/// `avatars/<sha256>/<crop>/300.webp`; the database stores keys and clients get URLs from
+/// `public_url`, so the backend can change without rewriting rows.
+#[async_trait]
+pub trait MediaStorage: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), MediaStorageError>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaStorageError>;
+    /// Reads bytes `start..=end`; callers clamp `end` to the object size first.
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, MediaStorageError>;
+    async fn stat(&self, key: &str) -> Result<MediaObjectInfo, MediaStorageError>;
+    /// Deleting a missing object is not an error.
+    async fn delete(&self, key: &str) -> Result<(), MediaStorageError>;
+    async fn exists(&self, key: &str) -> Result<bool, MediaStorageError>;
+    /// Every object whose key starts with `prefix`, in no particular order.
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, MediaStorageError>;
+    fn public_url(&self, key: &str) -> String;
+}
+
+#[async_trait]
+impl<T: MediaStorage + Send + Sync + ?Sized> MediaStorage for Arc<T> {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), MediaStorageError> {
+        (**self).put(key, bytes, content_type).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaStorageError> {
+        (**self).get(key).await
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, MediaStorageError> {
+        (**self).get_range(key, start, end).await
+    }
+
+    async fn stat(&self, key: &str) -> Result<MediaObjectInfo, MediaStorageError> {
+        (**self).stat(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaStorageError> {
+        (**self).delete(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, MediaStorageError> {
+        (**self).exists(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, MediaStorageError> {
+        (**self).list(prefix).await
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        (**self).public_url(key)
+    }
+}
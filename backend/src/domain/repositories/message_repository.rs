@@ -1,9 +1,70 @@
+use std::sync::Arc;
 use async_trait::async_trait;
-use crate::domain::entities::message::DatabaseMessage;
+use chrono::NaiveDateTime;
+use crate::domain::entities::message::{DatabaseMessage, MessageCursor, MessageEdit, MessageRange, SendMessageError};
 
 #[async_trait]
 pub trait MessageRepository {
-    async fn save_message(&self, message: DatabaseMessage) -> Result<DatabaseMessage, String>;
-    async fn get_messages(&self, user1_id: i32, user2_id: i32) -> Result<Vec<DatabaseMessage>, String>;
-    async fn mark_as_read(&self, message_id: i32) -> Result<(), String>;
-}
\ No newline at end of file
+    /// Stores a new message. A recipient or attachment that does not exist (any more) is
+    /// reported as such rather than as a database error.
+    async fn save_message(&self, message: DatabaseMessage) -> Result<DatabaseMessage, SendMessageError>;
+    /// Up to `range.limit` messages between the two users, in either direction, inside the
+    /// range. Newest first, or oldest first with `range.oldest_first`.
+    async fn get_messages(&self, user1_id: i32, user2_id: i32, range: MessageRange) -> Result<Vec<DatabaseMessage>, String>;
+    async fn find_message(&self, message_id: i32) -> Result<Option<DatabaseMessage>, String>;
+    /// Records the messages as delivered at `at`. Returns those that were not delivered yet.
+    async fn mark_delivered(&self, message_ids: &[i32], at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String>;
+    /// Records every unread message `reader_id` received from `sender_id` up to and including
+    /// `up_to` as read (and delivered, if it was not yet) at `at`. Returns those messages,
+    /// oldest first.
+    async fn mark_read(&self, reader_id: i32, sender_id: i32, up_to: MessageCursor, at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String>;
+    /// Replaces the content of a message, adding the old content to its edits. Returns `None`
+    /// when the message is missing or deleted for everyone.
+    async fn edit_message(&self, message_id: i32, content: String, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String>;
+    /// Earlier contents of the message, oldest first.
+    async fn find_edits(&self, message_id: i32) -> Result<Vec<MessageEdit>, String>;
+    /// Turns the message into a tombstone and forgets its edits. Returns `None` when the
+    /// message is missing or already a tombstone.
+    async fn delete_for_everyone(&self, message_id: i32, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String>;
+    /// Hides the message from `user_id`'s history; deleting it again changes nothing.
+    async fn delete_for_user(&self, message_id: i32, user_id: i32, at: NaiveDateTime) -> Result<(), String>;
+}
+
+#[async_trait]
+impl<T: MessageRepository + Send + Sync + ?Sized> MessageRepository for Arc<T> {
+    async fn save_message(&self, message: DatabaseMessage) -> Result<DatabaseMessage, SendMessageError> {
+        (**self).save_message(message).await
+    }
+
+    async fn get_messages(&self, user1_id: i32, user2_id: i32, range: MessageRange) -> Result<Vec<DatabaseMessage>, String> {
+        (**self).get_messages(user1_id, user2_id, range).await
+    }
+
+    async fn find_message(&self, message_id: i32) -> Result<Option<DatabaseMessage>, String> {
+        (**self).find_message(message_id).await
+    }
+
+    async fn mark_delivered(&self, message_ids: &[i32], at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String> {
+        (**self).mark_delivered(message_ids, at).await
+    }
+
+    async fn mark_read(&self, reader_id: i32, sender_id: i32, up_to: MessageCursor, at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String> {
+        (**self).mark_read(reader_id, sender_id, up_to, at).await
+    }
+
+    async fn edit_message(&self, message_id: i32, content: String, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String> {
+        (**self).edit_message(message_id, content, at).await
+    }
+
+    async fn find_edits(&self, message_id: i32) -> Result<Vec<MessageEdit>, String> {
+        (**self).find_edits(message_id).await
+    }
+
+    async fn delete_for_everyone(&self, message_id: i32, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String> {
+        (**self).delete_for_everyone(message_id, at).await
+    }
+
+    async fn delete_for_user(&self, message_id: i32, user_id: i32, at: NaiveDateTime) -> Result<(), String> {
+        (**self).delete_for_user(message_id, user_id, at).await
+    }
+}
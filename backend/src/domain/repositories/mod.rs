@@ -2,4 +2,11 @@ pub mod user_repository;
 pub mod auth_repository;
 pub mod account_repository;
 pub mod message_repository;
-pub mod avatar_repository;
\ No newline at end of file
+pub mod avatar_repository;
+pub mod media_storage;
+pub mod job_repository;
+pub mod upload_repository;
+pub mod attachment_repository;
+pub mod media_object_repository;
+pub mod conversation_repository;
+pub mod user_event_repository;
\ No newline at end of file
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use crate::domain::entities::upload::{NewUpload, Upload, UploadOutcome};
+
+/// Resumable uploads and how far each has got. The bytes themselves are in media storage.
+#[async_trait]
+pub trait UploadRepository {
+    async fn create(&self, upload: NewUpload) -> Result<Upload, Box<dyn std::error::Error>>;
+    async fn find_by_id(&self, upload_id: Uuid) -> Result<Option<Upload>, Box<dyn std::error::Error>>;
+    /// Reserves an unfinished upload for one appending request, provided it is still at
+    /// `offset`. A lock taken before `stale_before` is assumed to belong to a request that
+    /// died and is taken over. Returns whether the lock was taken.
+    async fn lock(&self, upload_id: Uuid, offset: i64, stale_before: NaiveDateTime) -> Result<bool, Box<dyn std::error::Error>>;
+    /// Records bytes stored up to `offset` and renews the lock.
+    async fn advance(&self, upload_id: Uuid, offset: i64) -> Result<(), Box<dyn std::error::Error>>;
+    async fn unlock(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>>;
+    /// Marks the upload finished and releases its lock.
+    async fn complete(&self, upload_id: Uuid, outcome: UploadOutcome) -> Result<Upload, Box<dyn std::error::Error>>;
+    async fn delete(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>>;
+    /// Uploads whose `expires_at` is at or before `now`, oldest first.
+    async fn find_expired(&self, now: NaiveDateTime) -> Result<Vec<Upload>, Box<dyn std::error::Error>>;
+    /// Every upload not discarded yet, finished or not, oldest first.
+    async fn find_all(&self) -> Result<Vec<Upload>, Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<T: UploadRepository + Send + Sync + ?Sized> UploadRepository for Arc<T> {
+    async fn create(&self, upload: NewUpload) -> Result<Upload, Box<dyn std::error::Error>> {
+        (**self).create(upload).await
+    }
+
+    async fn find_by_id(&self, upload_id: Uuid) -> Result<Option<Upload>, Box<dyn std::error::Error>> {
+        (**self).find_by_id(upload_id).await
+    }
+
+    async fn lock(&self, upload_id: Uuid, offset: i64, stale_before: NaiveDateTime) -> Result<bool, Box<dyn std::error::Error>> {
+        (**self).lock(upload_id, offset, stale_before).await
+    }
+
+    async fn advance(&self, upload_id: Uuid, offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).advance(upload_id, offset).await
+    }
+
+    async fn unlock(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).unlock(upload_id).await
+    }
+
+    async fn complete(&self, upload_id: Uuid, outcome: UploadOutcome) -> Result<Upload, Box<dyn std::error::Error>> {
+        (**self).complete(upload_id, outcome).await
+    }
+
+    async fn delete(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).delete(upload_id).await
+    }
+
+    async fn find_expired(&self, now: NaiveDateTime) -> Result<Vec<Upload>, Box<dyn std::error::Error>> {
+        (**self).find_expired(now).await
+    }
+
+    async fn find_all(&self) -> Result<Vec<Upload>, Box<dyn std::error::Error>> {
+        (**self).find_all().await
+    }
+}
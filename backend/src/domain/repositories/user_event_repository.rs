@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use crate::domain::entities::message::WebSocketMessage;
+use crate::domain::entities::user_event::UserEvent;
+
+/// How long events stay queued once the delivery cursor has passed them. Each of a user's
+/// sockets keeps its own place in the queue, so one may still be sending events another has
+/// already acknowledged.
+pub const ACKNOWLEDGED_EVENT_RETENTION: Duration = Duration::from_secs(60);
+
+/// Per-user queues of server events, and how far each user's sockets have got through theirs.
+#[async_trait]
+pub trait UserEventRepository {
+    /// Queues the event behind the user's earlier ones and returns it with its id.
+    async fn append(&self, user_id: i32, event: WebSocketMessage) -> Result<UserEvent, String>;
+    /// Up to `limit` events past `after`, or past the user's delivery cursor without it,
+    /// oldest first.
+    async fn pending(&self, user_id: i32, after: Option<i64>, limit: i64) -> Result<Vec<UserEvent>, String>;
+    /// Moves the user's delivery cursor to `event_id`, never backwards, and drops the events
+    /// it has passed that are older than `ACKNOWLEDGED_EVENT_RETENTION`.
+    async fn acknowledge(&self, user_id: i32, event_id: i64) -> Result<(), String>;
+    /// Drops every queued event of the user's that carries the message's content: its
+    /// `NewMessage` and any `MessageEdited`.
+    async fn discard_message_events(&self, user_id: i32, message_id: i32) -> Result<(), String>;
+}
+
+#[async_trait]
+impl<T: UserEventRepository + Send + Sync + ?Sized> UserEventRepository for Arc<T> {
+    async fn append(&self, user_id: i32, event: WebSocketMessage) -> Result<UserEvent, String> {
+        (**self).append(user_id, event).await
+    }
+
+    async fn pending(&self, user_id: i32, after: Option<i64>, limit: i64) -> Result<Vec<UserEvent>, String> {
+        (**self).pending(user_id, after, limit).await
+    }
+
+    async fn acknowledge(&self, user_id: i32, event_id: i64) -> Result<(), String> {
+        (**self).acknowledge(user_id, event_id).await
+    }
+
+    async fn discard_message_events(&self, user_id: i32, message_id: i32) -> Result<(), String> {
+        (**self).discard_message_events(user_id, message_id).await
+    }
+}
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use async_trait::async_trait;
 use crate::domain::entities::user::{User, CreateUserDto, UpdateUserDto};
 
@@ -10,4 +11,29 @@ pub trait UserRepository {
 
     async fn update(&self, id: i32, user: UpdateUserDto) -> Result<User, Box<dyn std::error::Error>>;
     async fn delete(&self, user_id: i32) -> Result<(), Box<dyn std::error::Error>>;
-}
\ No newline at end of file
+}
+
+/// Lets use cases and handlers hold a shared `Arc<dyn UserRepository + Send + Sync>`, so the
+/// storage backend can be chosen at startup.
+#[async_trait]
+impl<T: UserRepository + Send + Sync + ?Sized> UserRepository for Arc<T> {
+    async fn find_by_id(&self, user_id: i32) -> Result<User, Box<dyn std::error::Error>> {
+        (**self).find_by_id(user_id).await
+    }
+
+    async fn create(&self, user: CreateUserDto) -> Result<User, Box<dyn std::error::Error>> {
+        (**self).create(user).await
+    }
+
+    async fn find_all(&self) -> Result<Vec<User>, Box<dyn std::error::Error>> {
+        (**self).find_all().await
+    }
+
+    async fn update(&self, id: i32, user: UpdateUserDto) -> Result<User, Box<dyn std::error::Error>> {
+        (**self).update(id, user).await
+    }
+
+    async fn delete(&self, user_id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).delete(user_id).await
+    }
+}
@@ -0,0 +1,382 @@
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::domain::entities::media::{ImageFormat, UploadLimits, VariantPlan};
+
+const DEFAULT_ALLOWED_ORIGINS: &[&str] = &[
+    "http://localhost:3000",
+    "http://192.168.100.7:3000",
+    "http://0.0.0.0:3000",
+];
+
+const DEFAULT_MEDIA_URL_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Where repositories keep their data, picked with `--storage <postgres|memory>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    #[default]
+    Postgres,
+    /// Process memory only; needs the `test-support` feature. Meant for demos.
+    Memory,
+}
+
+impl StorageBackend {
+    /// Reads `--storage <value>` or `--storage=<value>` from command-line arguments.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut storage = Self::default();
+
+        while let Some(arg) = args.next() {
+            let value = match arg.strip_prefix("--storage") {
+                Some("") => args.next().ok_or("--storage requires a value")?,
+                Some(value) if value.starts_with('=') => value[1..].to_string(),
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            };
+            storage = value.parse()?;
+        }
+
+        Ok(storage)
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "postgres" => Ok(Self::Postgres),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!("Unknown storage backend '{}', expected 'postgres' or 'memory'", other)),
+        }
+    }
+}
+
+/// Connection details for an S3-compatible bucket.
+#[derive(Debug, Clone)]
+pub struct S3Settings {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Address the bucket as `{endpoint}/{bucket}` instead of `{bucket}.{endpoint}`.
+    pub path_style: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaBackend {
+    /// Files under `Settings::upload_dir`.
+    Local,
+    S3(S3Settings),
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaSettings {
+    pub backend: MediaBackend,
+    /// Prefix for public media URLs, e.g. a CDN. When unset, media is served by the backend
+    /// through signed, expiring `/media` URLs.
+    pub public_base_url: Option<String>,
+    /// Key for signing media URLs; falls back to `Settings::secret_key`.
+    pub signing_key: Option<String>,
+    /// How long a signed media URL stays valid, at minimum.
+    pub url_ttl: Duration,
+    /// Renditions stored for every uploaded avatar.
+    pub avatar_variants: VariantPlan,
+    /// Largest upload accepted, in bytes and in pixels.
+    pub upload_limits: UploadLimits,
+    pub gc: MediaGcSettings,
+    pub resumable: ResumableUploadSettings,
+}
+
+impl MediaSettings {
+    pub fn local() -> Self {
+        Self {
+            backend: MediaBackend::Local,
+            public_base_url: None,
+            signing_key: None,
+            url_ttl: DEFAULT_MEDIA_URL_TTL,
+            avatar_variants: VariantPlan::default(),
+            upload_limits: UploadLimits::default(),
+            gc: MediaGcSettings::default(),
+            resumable: ResumableUploadSettings::default(),
+        }
+    }
+
+    /// Reads `MEDIA_STORAGE` (`local` or `s3`), `MEDIA_PUBLIC_BASE_URL`, `MEDIA_SIGNING_KEY`,
+    /// `MEDIA_URL_TTL_SECS`, the comma-separated `AVATAR_VARIANT_SIZES`, `AVATAR_VARIANT_DENSITIES`
+    /// and `AVATAR_VARIANT_FORMATS`, `MEDIA_MAX_UPLOAD_BYTES`, `MEDIA_MAX_IMAGE_WIDTH`,
+    /// `MEDIA_MAX_IMAGE_HEIGHT`, the `MEDIA_GC_*` and `MEDIA_RESUMABLE_*` variables and, for S3, `S3_ENDPOINT`, `S3_REGION`, `S3_BUCKET`,
+    /// `S3_ACCESS_KEY_ID`, `S3_SECRET_ACCESS_KEY` and `S3_PATH_STYLE`.
+    pub fn from_env() -> Self {
+        let backend = match env::var("MEDIA_STORAGE").as_deref() {
+            Ok("s3") => MediaBackend::S3(S3Settings {
+                endpoint: env::var("S3_ENDPOINT").expect("S3_ENDPOINT must be set"),
+                region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                bucket: env::var("S3_BUCKET").expect("S3_BUCKET must be set"),
+                access_key_id: env::var("S3_ACCESS_KEY_ID").expect("S3_ACCESS_KEY_ID must be set"),
+                secret_access_key: env::var("S3_SECRET_ACCESS_KEY").expect("S3_SECRET_ACCESS_KEY must be set"),
+                path_style: env::var("S3_PATH_STYLE").map(|v| v != "false").unwrap_or(true),
+            }),
+            Ok("local") | Err(_) => MediaBackend::Local,
+            Ok(other) => panic!("Unknown MEDIA_STORAGE '{}', expected 'local' or 's3'", other),
+        };
+
+        Self {
+            backend,
+            public_base_url: env::var("MEDIA_PUBLIC_BASE_URL").ok().filter(|url| !url.is_empty()),
+            signing_key: env::var("MEDIA_SIGNING_KEY").ok().filter(|key| !key.is_empty()),
+            url_ttl: env::var("MEDIA_URL_TTL_SECS")
+                .map(|secs| Duration::from_secs(secs.parse().expect("MEDIA_URL_TTL_SECS must be a number of seconds")))
+                .unwrap_or(DEFAULT_MEDIA_URL_TTL),
+            avatar_variants: variant_plan_from_env(),
+            upload_limits: upload_limits_from_env(),
+            gc: MediaGcSettings::from_env(),
+            resumable: ResumableUploadSettings::from_env(),
+        }
+    }
+}
+
+/// Reconciliation of stored media against the database.
+#[derive(Debug, Clone)]
+pub struct MediaGcSettings {
+    /// How often the reconciliation job runs; `None` leaves it to `admin gc-media`.
+    pub interval: Option<Duration>,
+    /// Objects younger than this are never reported, as their upload may still be in flight.
+    pub grace_period: Duration,
+    /// Whether scheduled runs delete what they find, or only report it.
+    pub delete: bool,
+}
+
+impl Default for MediaGcSettings {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            grace_period: Duration::from_secs(60 * 60),
+            delete: false,
+        }
+    }
+}
+
+impl MediaGcSettings {
+    /// Reads `MEDIA_GC_INTERVAL_SECS` (unset or 0 disables the schedule), `MEDIA_GC_GRACE_SECS`
+    /// and `MEDIA_GC_DELETE`.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let seconds = |name: &str| env::var(name)
+            .ok()
+            .map(|value| Duration::from_secs(value.parse().unwrap_or_else(|_| panic!("{} must be a number of seconds", name))));
+
+        Self {
+            interval: seconds("MEDIA_GC_INTERVAL_SECS").filter(|interval| !interval.is_zero()),
+            grace_period: seconds("MEDIA_GC_GRACE_SECS").unwrap_or(default.grace_period),
+            delete: env::var("MEDIA_GC_DELETE").is_ok_and(|value| value == "true"),
+        }
+    }
+}
+
+/// Resumable (tus) uploads. Avatar uploads are additionally held to `MediaSettings::upload_limits`.
+#[derive(Debug, Clone)]
+pub struct ResumableUploadSettings {
+    /// Largest upload accepted. Finished uploads are assembled in memory, so keep this modest.
+    pub max_bytes: u64,
+    /// Received bytes are stored in parts of up to this size, so an interrupted request
+    /// loses at most one part.
+    pub part_bytes: usize,
+    /// How long an upload may take from creation to its last byte.
+    pub expiry: Duration,
+    /// How often expired uploads are discarded; `None` leaves them in place.
+    pub cleanup_interval: Option<Duration>,
+}
+
+impl Default for ResumableUploadSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes: 100 * 1024 * 1024,
+            part_bytes: 5 * 1024 * 1024,
+            expiry: Duration::from_secs(24 * 60 * 60),
+            cleanup_interval: Some(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+impl ResumableUploadSettings {
+    /// Reads `MEDIA_RESUMABLE_MAX_BYTES`, `MEDIA_RESUMABLE_PART_BYTES`, `MEDIA_RESUMABLE_EXPIRY_SECS`
+    /// and `MEDIA_RESUMABLE_CLEANUP_INTERVAL_SECS` (0 disables the cleanup).
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let number = |name: &str| env::var(name)
+            .ok()
+            .map(|value| value.parse::<u64>().unwrap_or_else(|_| panic!("{} must be a whole number", name)));
+
+        let settings = Self {
+            max_bytes: number("MEDIA_RESUMABLE_MAX_BYTES").unwrap_or(default.max_bytes),
+            part_bytes: number("MEDIA_RESUMABLE_PART_BYTES").map_or(default.part_bytes, |bytes| bytes as usize),
+            expiry: number("MEDIA_RESUMABLE_EXPIRY_SECS").map_or(default.expiry, Duration::from_secs),
+            cleanup_interval: number("MEDIA_RESUMABLE_CLEANUP_INTERVAL_SECS")
+                .map_or(default.cleanup_interval, |secs| Some(Duration::from_secs(secs)).filter(|interval| !interval.is_zero())),
+        };
+        assert!(
+            settings.max_bytes > 0 && settings.part_bytes > 0 && !settings.expiry.is_zero(),
+            "MEDIA_RESUMABLE_MAX_BYTES, MEDIA_RESUMABLE_PART_BYTES and MEDIA_RESUMABLE_EXPIRY_SECS must be positive"
+        );
+        settings
+    }
+}
+
+fn upload_limits_from_env() -> UploadLimits {
+    fn limit<T: FromStr>(name: &str, default: T) -> T {
+        env::var(name)
+            .map(|value| value.parse().unwrap_or_else(|_| panic!("{} must be a positive number", name)))
+            .unwrap_or(default)
+    }
+
+    let default = UploadLimits::default();
+    let limits = UploadLimits {
+        max_bytes: limit("MEDIA_MAX_UPLOAD_BYTES", default.max_bytes),
+        max_width: limit("MEDIA_MAX_IMAGE_WIDTH", default.max_width),
+        max_height: limit("MEDIA_MAX_IMAGE_HEIGHT", default.max_height),
+    };
+    assert!(
+        limits.max_bytes > 0 && limits.max_width > 0 && limits.max_height > 0,
+        "MEDIA_MAX_UPLOAD_BYTES, MEDIA_MAX_IMAGE_WIDTH and MEDIA_MAX_IMAGE_HEIGHT must be positive"
+    );
+    limits
+}
+
+/// Any list left unset keeps its default; an empty or malformed one is a configuration error.
+fn variant_plan_from_env() -> VariantPlan {
+    fn list<T: FromStr>(name: &str, default: Vec<T>) -> Vec<T> {
+        let Ok(value) = env::var(name) else {
+            return default;
+        };
+        let items: Vec<T> = value.split(',')
+            .map(|item| item.trim().parse().unwrap_or_else(|_| panic!("{} has an invalid entry '{}'", name, item.trim())))
+            .collect();
+        assert!(!items.is_empty(), "{} must not be empty", name);
+        items
+    }
+
+    let default = VariantPlan::default();
+    let sizes: Vec<u32> = list("AVATAR_VARIANT_SIZES", default.sizes);
+    let densities: Vec<u32> = list("AVATAR_VARIANT_DENSITIES", default.densities);
+    let formats: Vec<ImageFormat> = list("AVATAR_VARIANT_FORMATS", default.formats);
+    assert!(
+        sizes.iter().chain(&densities).all(|value| *value > 0),
+        "AVATAR_VARIANT_SIZES and AVATAR_VARIANT_DENSITIES must be positive"
+    );
+
+    VariantPlan { sizes, densities, formats }
+}
+
+/// How this process works through the background job queue.
+#[derive(Debug, Clone)]
+pub struct JobSettings {
+    /// Polling loops started with the server; 0 leaves the queue to other processes.
+    pub workers: usize,
+    /// Pause between polls while the queue is empty.
+    pub poll_interval: Duration,
+    /// A job still running this long after it was claimed is handed to another worker.
+    pub lease: Duration,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            poll_interval: Duration::from_secs(1),
+            lease: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl JobSettings {
+    /// Reads `JOB_WORKERS`, `JOB_POLL_INTERVAL_MS` and `JOB_LEASE_SECS`.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let number = |name: &str| env::var(name)
+            .ok()
+            .map(|value| value.parse::<u64>().unwrap_or_else(|_| panic!("{} must be a whole number", name)));
+
+        Self {
+            workers: number("JOB_WORKERS").map_or(default.workers, |workers| workers as usize),
+            poll_interval: number("JOB_POLL_INTERVAL_MS").map_or(default.poll_interval, Duration::from_millis),
+            lease: number("JOB_LEASE_SECS").map_or(default.lease, Duration::from_secs),
+        }
+    }
+}
+
+/// Real-time chat over the WebSocket.
+#[derive(Debug, Clone)]
+pub struct ChatSettings {
+    /// A typing indicator is cleared when no `TypingStarted` renews it for this long.
+    /// Clients typing for longer repeat `TypingStarted` more often than this.
+    pub typing_timeout: Duration,
+    /// Repeated `TypingStarted` to the same user are relayed at most this often.
+    pub typing_interval: Duration,
+    /// How long after sending a message its sender may still edit it.
+    pub edit_window: Duration,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            typing_timeout: Duration::from_secs(6),
+            typing_interval: Duration::from_secs(2),
+            edit_window: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+impl ChatSettings {
+    /// Reads `CHAT_TYPING_TIMEOUT_MS`, `CHAT_TYPING_INTERVAL_MS` and `CHAT_EDIT_WINDOW_SECS`.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let number = |name: &str| env::var(name)
+            .ok()
+            .map(|value| value.parse::<u64>().unwrap_or_else(|_| panic!("{} must be a whole number", name)));
+
+        Self {
+            typing_timeout: number("CHAT_TYPING_TIMEOUT_MS").map_or(default.typing_timeout, Duration::from_millis),
+            typing_interval: number("CHAT_TYPING_INTERVAL_MS").map_or(default.typing_interval, Duration::from_millis),
+            edit_window: number("CHAT_EDIT_WINDOW_SECS").map_or(default.edit_window, Duration::from_secs),
+        }
+    }
+}
+
+/// Runtime configuration shared by `main` and the integration tests.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub secret_key: String,
+    pub upload_dir: PathBuf,
+    pub bind_address: String,
+    pub allowed_origins: Vec<String>,
+    pub media: MediaSettings,
+    pub jobs: JobSettings,
+    pub chat: ChatSettings,
+}
+
+impl Settings {
+    /// Reads `SECRET_KEY`, `UPLOAD_DIR`, `BIND_ADDRESS`, the comma-separated
+    /// `CORS_ALLOWED_ORIGINS`, the media storage, job queue and chat variables.
+    pub fn from_env() -> Self {
+        let allowed_origins = env::var("CORS_ALLOWED_ORIGINS")
+            .map(|origins| {
+                origins.split(',')
+                    .map(|origin| origin.trim().to_string())
+                    .filter(|origin| !origin.is_empty())
+                    .collect()
+            })
+            .unwrap_or_else(|_| DEFAULT_ALLOWED_ORIGINS.iter().map(|o| o.to_string()).collect());
+
+        Self {
+            secret_key: env::var("SECRET_KEY").expect("SECRET_KEY must be set"),
+            upload_dir: PathBuf::from(env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string())),
+            bind_address: env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8080".to_string()),
+            allowed_origins,
+            media: MediaSettings::from_env(),
+            jobs: JobSettings::from_env(),
+            chat: ChatSettings::from_env(),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Decoding of uploaded images and encoders for the formats variants are stored in.
+//!
+//! Stored files never carry metadata: encoders are only ever handed raw pixels, so EXIF
+//! (including GPS positions), XMP and ICC data in an upload cannot reach them.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use exif::{In, Tag};
+use image::codecs::jpeg::JpegEncoder;
+use image::io::{Limits, Reader};
+use image::{DynamicImage, ImageFormat as SourceFormat, RgbaImage};
+use ravif::{Img, RGB8};
+use crate::domain::entities::media::{ImageFormat, ImagePlaceholder, UploadLimits, UploadRejection};
+
+/// Formats accepted for upload.
+const ACCEPTED_FORMATS: [SourceFormat; 4] = [SourceFormat::Jpeg, SourceFormat::Png, SourceFormat::WebP, SourceFormat::Gif];
+
+/// Leading bytes that are enough to tell the accepted formats apart.
+pub const SNIFF_LEN: usize = 16;
+
+const WEBP_QUALITY: f32 = 75.0;
+const JPEG_QUALITY: u8 = 82;
+/// ravif's quality scale runs lower than JPEG's for the same visual result.
+const AVIF_QUALITY: f32 = 60.0;
+/// 1 is slowest and smallest, 10 fastest. AVIF encoding dominates variant rendering, so
+/// favour speed; the size gain of slower presets is marginal at avatar dimensions.
+const AVIF_SPEED: u8 = 10;
+
+/// BlurHash components along each axis; 4x4 keeps the hash at 36 characters.
+const BLURHASH_COMPONENTS: u32 = 4;
+/// Placeholders are computed from a thumbnail no larger than this, as they are blurry anyway.
+const PLACEHOLDER_SAMPLE_SIZE: u32 = 32;
+
+/// Encodes an opaque image. Transparency is flattened onto black, as JPEG cannot carry it.
+pub fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let rgb = image.to_rgb8();
+
+    match format {
+        ImageFormat::Webp => {
+            let encoded = webp::Encoder::from_rgb(&rgb, rgb.width(), rgb.height()).encode(WEBP_QUALITY);
+            Ok(encoded.to_vec())
+        }
+        ImageFormat::Jpeg => {
+            let mut bytes = Vec::new();
+            JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).encode_image(&rgb)?;
+            Ok(bytes)
+        }
+        ImageFormat::Avif => {
+            let pixels: Vec<RGB8> = rgb.pixels().map(|pixel| RGB8::new(pixel[0], pixel[1], pixel[2])).collect();
+            let encoded = ravif::Encoder::new()
+                .with_quality(AVIF_QUALITY)
+                .with_speed(AVIF_SPEED)
+                .encode_rgb(Img::new(pixels.as_slice(), rgb.width() as usize, rgb.height() as usize))?;
+            Ok(encoded.avif_file)
+        }
+    }
+}
+
+/// A BlurHash and the dominant color of an image. Like [`encode`], ignores transparency.
+pub fn placeholder(image: &DynamicImage) -> Result<ImagePlaceholder, Box<dyn std::error::Error + Send + Sync>> {
+    let sample = image.thumbnail(PLACEHOLDER_SAMPLE_SIZE, PLACEHOLDER_SAMPLE_SIZE).to_rgba8();
+    let blurhash = blurhash::encode(
+        BLURHASH_COMPONENTS,
+        BLURHASH_COMPONENTS,
+        sample.width(),
+        sample.height(),
+        sample.as_raw(),
+    )?;
+
+    Ok(ImagePlaceholder { blurhash, dominant_color: dominant_color(&sample) })
+}
+
+/// The average of the most populated color bucket. Channels are bucketed to their top four
+/// bits so near-identical shades count as one color.
+fn dominant_color(sample: &RgbaImage) -> String {
+    let mut buckets: HashMap<u16, (u32, [u32; 3])> = HashMap::new();
+    for pixel in sample.pixels() {
+        let [r, g, b, _] = pixel.0;
+        let bucket = (u16::from(r >> 4) << 8) | (u16::from(g >> 4) << 4) | u16::from(b >> 4);
+        let (count, sums) = buckets.entry(bucket).or_default();
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip([r, g, b]) {
+            *sum += u32::from(channel);
+        }
+    }
+
+    // Ties go to the darker bucket, so the result does not depend on hash order
+    let (count, [r, g, b]) = buckets.into_iter()
+        .max_by_key(|(bucket, (count, _))| (*count, std::cmp::Reverse(*bucket)))
+        .map_or((1, [0; 3]), |(_, totals)| totals);
+    format!("#{:02x}{:02x}{:02x}", r / count, g / count, b / count)
+}
+
+/// Identifies an accepted format from the file's leading bytes; the name and declared
+/// content type of an upload are never trusted.
+pub fn sniff(bytes: &[u8]) -> Option<SourceFormat> {
+    image::guess_format(bytes).ok().filter(|format| ACCEPTED_FORMATS.contains(format))
+}
+
+/// What an accepted upload's header says about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadInfo {
+    pub format: SourceFormat,
+    /// Dimensions once turned upright.
+    pub width: u32,
+    pub height: u32,
+    orientation: u32,
+}
+
+impl UploadInfo {
+    pub fn mime_type(&self) -> &'static str {
+        self.format.to_mime_type()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        self.format.extensions_str()[0]
+    }
+}
+
+/// Checks an upload's size, type and dimensions from its header alone, without decoding
+/// any pixels.
+pub fn inspect_upload(bytes: &[u8], limits: &UploadLimits) -> Result<UploadInfo, UploadRejection> {
+    if bytes.len() > limits.max_bytes {
+        return Err(UploadRejection::TooManyBytes { max_bytes: limits.max_bytes });
+    }
+    let format = sniff(bytes).ok_or(UploadRejection::UnsupportedType)?;
+
+    let (width, height) = Reader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|e| UploadRejection::Unreadable(e.to_string()))?;
+    if width > limits.max_width || height > limits.max_height {
+        return Err(UploadRejection::TooManyPixels {
+            width,
+            height,
+            max_width: limits.max_width,
+            max_height: limits.max_height,
+        });
+    }
+
+    // Orientations 5 to 8 turn the image by a quarter
+    let orientation = exif_orientation(bytes);
+    let (width, height) = if (5..=8).contains(&orientation) { (height, width) } else { (width, height) };
+    Ok(UploadInfo { format, width, height, orientation })
+}
+
+/// Decodes an upload once [`inspect_upload`] has accepted it, then turns it upright according
+/// to its EXIF orientation.
+pub fn decode_upload(bytes: &[u8], limits: &UploadLimits) -> Result<DynamicImage, UploadRejection> {
+    // Oversized images are refused from their header, before any pixel buffer is allocated
+    let info = inspect_upload(bytes, limits)?;
+
+    let mut decode_limits = Limits::default();
+    decode_limits.max_image_width = Some(limits.max_width);
+    decode_limits.max_image_height = Some(limits.max_height);
+    let mut reader = Reader::with_format(Cursor::new(bytes), info.format);
+    reader.limits(decode_limits);
+    let image = reader.decode().map_err(|e| UploadRejection::Unreadable(e.to_string()))?;
+
+    Ok(apply_orientation(image, info.orientation))
+}
+
+/// The EXIF orientation tag, 1 (upright) when absent or unreadable.
+fn exif_orientation(bytes: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| exif.get_field(Tag::Orientation, In::PRIMARY).and_then(|field| field.value.get_uint(0)))
+        .unwrap_or(1)
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        // Transpose
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        // Transverse
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
@@ -0,0 +1,146 @@
+//! Workers for the background job queue.
+//!
+//! Each job kind has one [`JobHandler`]. A [`JobWorker`] claims due jobs of the kinds it
+//! has handlers for, runs them and records the outcome; failed attempts are retried with
+//! exponential backoff until the job runs out of attempts.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::{error, warn};
+use crate::domain::entities::job::Job;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::infrastructure::config::settings::JobSettings;
+use crate::infrastructure::metrics::metrics;
+
+/// Upper bound on the wait between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+
+/// Why an attempt failed, and whether another attempt could succeed.
+#[derive(Debug)]
+pub struct JobError {
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl JobError {
+    /// A failure retrying cannot fix, such as invalid input.
+    pub fn permanent(error: impl std::fmt::Display) -> Self {
+        Self { message: error.to_string(), retryable: false }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for JobError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        Self { message: error.to_string(), retryable: true }
+    }
+}
+
+/// Runs the jobs of one kind. Handlers run on the worker's async runtime, so CPU-heavy work
+/// belongs on a blocking thread.
+#[async_trait(?Send)]
+pub trait JobHandler {
+    fn kind(&self) -> &'static str;
+    /// Does the work; the returned value is stored as the job's result.
+    async fn run(&self, job: &Job) -> Result<Option<serde_json::Value>, JobError>;
+    /// Called once after the job has failed for good.
+    async fn failed(&self, _job: &Job, _error: &str) {}
+}
+
+pub struct JobWorker<J: JobRepository> {
+    jobs: J,
+    handlers: Arc<HashMap<&'static str, Arc<dyn JobHandler + Send + Sync>>>,
+    settings: JobSettings,
+}
+
+impl<J: JobRepository + Clone> Clone for JobWorker<J> {
+    fn clone(&self) -> Self {
+        Self {
+            jobs: self.jobs.clone(),
+            handlers: self.handlers.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+}
+
+impl<J: JobRepository + Clone + 'static> JobWorker<J> {
+    pub fn new(jobs: J, handlers: Vec<Arc<dyn JobHandler + Send + Sync>>, settings: JobSettings) -> Self {
+        Self {
+            jobs,
+            handlers: Arc::new(handlers.into_iter().map(|handler| (handler.kind(), handler)).collect()),
+            settings,
+        }
+    }
+
+    /// Starts `JobSettings::workers` polling loops on the current actix system.
+    pub fn spawn(&self) {
+        for _ in 0..self.settings.workers {
+            actix_rt::spawn(self.clone().run());
+        }
+    }
+
+    /// Polls forever, resting whenever the queue is empty or unreachable.
+    pub async fn run(self) {
+        loop {
+            match self.run_next().await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => error!("Job queue unavailable: {}", e),
+            }
+            actix_rt::time::sleep(self.settings.poll_interval).await;
+        }
+    }
+
+    /// Runs due jobs until none is left and returns how many ran.
+    pub async fn run_pending(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut count = 0;
+        while self.run_next().await? {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Claims and runs one due job; `false` when nothing was due.
+    pub async fn run_next(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let kinds: Vec<&str> = self.handlers.keys().copied().collect();
+        let Some(job) = self.jobs.claim(&kinds, self.settings.lease).await? else {
+            return Ok(false);
+        };
+        let handler = &self.handlers[job.kind.as_str()];
+
+        // A job reclaimed after its lease ran out may already have used its last attempt
+        let outcome = if job.attempts > job.max_attempts {
+            Err(JobError::permanent("Abandoned by its worker too many times"))
+        } else {
+            handler.run(&job).await
+        };
+
+        match outcome {
+            Ok(result) => {
+                self.jobs.complete(job.id, result).await?;
+                metrics().jobs_total.with_label_values(&[&job.kind, "succeeded"]).inc();
+            }
+            Err(e) if e.retryable && job.attempts < job.max_attempts => {
+                warn!(job_id = job.id, kind = %job.kind, attempt = job.attempts, "Job failed, will retry: {}", e.message);
+                let retry_at = Utc::now().naive_utc() + chrono::Duration::from_std(backoff(job.attempts))?;
+                self.jobs.fail(job.id, &e.message, Some(retry_at)).await?;
+                metrics().jobs_total.with_label_values(&[&job.kind, "retried"]).inc();
+            }
+            Err(e) => {
+                error!(job_id = job.id, kind = %job.kind, attempt = job.attempts, "Job failed: {}", e.message);
+                self.jobs.fail(job.id, &e.message, None).await?;
+                metrics().jobs_total.with_label_values(&[&job.kind, "failed"]).inc();
+                handler.failed(&job, &e.message).await;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// 2, 4, 8... seconds after the first, second, third... attempt, capped at `MAX_BACKOFF`.
+fn backoff(attempts: i32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempts.max(1) as u32)).min(MAX_BACKOFF)
+}
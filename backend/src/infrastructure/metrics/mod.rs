@@ -0,0 +1,100 @@
+use std::sync::LazyLock;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Process-wide Prometheus collectors, exposed through `/metrics`.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub db_pool_connections: IntGaugeVec,
+    pub websocket_connections: IntGauge,
+    pub websocket_messages_total: IntCounterVec,
+    pub avatar_processing_duration_seconds: Histogram,
+    pub login_attempts_total: IntCounterVec,
+    pub jobs_total: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total number of HTTP requests"),
+            &["method", "route", "status"],
+        ).expect("valid http_requests_total metric");
+
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency in seconds"),
+            &["method", "route", "status"],
+        ).expect("valid http_request_duration_seconds metric");
+
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections by state"),
+            &["state"],
+        ).expect("valid db_pool_connections metric");
+
+        let websocket_connections = IntGauge::new(
+            "websocket_connections",
+            "Number of live WebSocket connections",
+        ).expect("valid websocket_connections metric");
+
+        let websocket_messages_total = IntCounterVec::new(
+            Opts::new("websocket_messages_total", "Real-time chat messages by outcome"),
+            &["outcome"],
+        ).expect("valid websocket_messages_total metric");
+
+        let avatar_processing_duration_seconds = Histogram::with_opts(
+            HistogramOpts::new("avatar_processing_duration_seconds", "Time spent resizing and encoding avatars")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+        ).expect("valid avatar_processing_duration_seconds metric");
+
+        let login_attempts_total = IntCounterVec::new(
+            Opts::new("login_attempts_total", "Login attempts by outcome"),
+            &["outcome"],
+        ).expect("valid login_attempts_total metric");
+
+        let jobs_total = IntCounterVec::new(
+            Opts::new("jobs_total", "Background job attempts by kind and outcome"),
+            &["kind", "outcome"],
+        ).expect("valid jobs_total metric");
+
+        registry.register(Box::new(http_requests_total.clone())).expect("register http_requests_total");
+        registry.register(Box::new(http_request_duration_seconds.clone())).expect("register http_request_duration_seconds");
+        registry.register(Box::new(db_pool_connections.clone())).expect("register db_pool_connections");
+        registry.register(Box::new(websocket_connections.clone())).expect("register websocket_connections");
+        registry.register(Box::new(websocket_messages_total.clone())).expect("register websocket_messages_total");
+        registry.register(Box::new(avatar_processing_duration_seconds.clone())).expect("register avatar_processing_duration_seconds");
+        registry.register(Box::new(login_attempts_total.clone())).expect("register login_attempts_total");
+        registry.register(Box::new(jobs_total.clone())).expect("register jobs_total");
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_pool_connections,
+            websocket_connections,
+            websocket_messages_total,
+            avatar_processing_duration_seconds,
+            login_attempts_total,
+            jobs_total,
+        }
+    }
+
+    /// Renders every registered collector in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| format!("Failed to encode metrics: {}", e))?;
+        String::from_utf8(buffer).map_err(|e| format!("Invalid metrics encoding: {}", e))
+    }
+}
@@ -1,3 +1,8 @@
 pub mod config;
+pub mod imaging;
+pub mod jobs;
+pub mod metrics;
 pub mod repositories;
+pub mod storage;
+pub mod telemetry;
 pub mod websocket;
\ No newline at end of file
@@ -3,9 +3,8 @@ use diesel::{PgConnection, QueryDsl, RunQueryDsl};
 use crate::schema::accounts;
 use crate::domain::entities::account::{Account, UpdateAccountDto};
 use async_trait::async_trait;
-use crate::domain::entities::avatar::Avatar;
 use crate::domain::repositories::account_repository::AccountRepository;
-use super::avatar_repository::AvatarRecord;
+use super::avatar_repository::{with_variants, AvatarRecord};
 
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = accounts)]
@@ -79,13 +78,13 @@ impl AccountRepositoryImpl {
 
 #[async_trait]
 impl AccountRepository for AccountRepositoryImpl {
-    async fn find_by_user_id(&self, user_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+    async fn find_by_user_id(&self, target_user_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
         use crate::schema::accounts::dsl::*;
 
         let mut conn = self.pool.get()?;
 
         let record = accounts
-            .filter(user_id.eq(user_id))
+            .filter(user_id.eq(target_user_id))
             .select(AccountRecord::as_select())
             .first(&mut conn)?;
 
@@ -95,14 +94,42 @@ impl AccountRepository for AccountRepositoryImpl {
         Ok(account)
     }
 
-    async fn update(&self, user_id: i32, dto: UpdateAccountDto) -> Result<Account, Box<dyn std::error::Error>> {
+    async fn find_by_user_ids(&self, user_ids: &[i32]) -> Result<Vec<Account>, Box<dyn std::error::Error>> {
+        use crate::schema::{accounts, avatars};
+
+        let mut conn = self.pool.get()?;
+
+        let records = accounts::table
+            .filter(accounts::user_id.eq_any(user_ids))
+            .select(AccountRecord::as_select())
+            .load(&mut conn)?;
+
+        // One query for all default avatars rather than one per account
+        let avatar_ids: Vec<i32> = records.iter().filter_map(|record| record.default_avatar_id).collect();
+        let avatar_records = avatars::table
+            .filter(avatars::id.eq_any(&avatar_ids))
+            .load::<AvatarRecord>(&mut conn)?;
+        let mut default_avatars = with_variants(&mut conn, avatar_records)?;
+
+        Ok(records.into_iter()
+            .map(|record| {
+                let mut account = Account::from(record);
+                account.default_avatar = default_avatars.iter()
+                    .position(|avatar| Some(avatar.id) == account.default_avatar_id)
+                    .map(|index| default_avatars.swap_remove(index));
+                account
+            })
+            .collect())
+    }
+
+    async fn update(&self, target_user_id: i32, dto: UpdateAccountDto) -> Result<Account, Box<dyn std::error::Error>> {
         use crate::schema::accounts::dsl::*;
 
         let mut conn = self.pool.get()?;
 
         let changeset = AccountChangeset::from(dto);
 
-        let record = diesel::update(accounts.filter(user_id.eq(user_id)))
+        let record = diesel::update(accounts.filter(user_id.eq(target_user_id)))
             .set(changeset)
             .returning(AccountRecord::as_select())
             .get_result(&mut conn)?;
@@ -113,13 +140,13 @@ impl AccountRepository for AccountRepositoryImpl {
         Ok(account)
     }
 
-    async fn set_default_avatar(&self, user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+    async fn set_default_avatar(&self, target_user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
         use crate::schema::accounts::dsl::*;
 
         let mut conn = self.pool.get()?;
 
         let record = diesel::update(accounts)
-            .filter(user_id.eq(user_id))
+            .filter(user_id.eq(target_user_id))
             .set((
                 default_avatar_id.eq(Some(avatar_id)),
                 updated_at.eq(chrono::Local::now().naive_utc()),
@@ -139,21 +166,11 @@ impl AccountRepository for AccountRepositoryImpl {
         if let Some(avatar_id) = account.default_avatar_id {
             let mut conn = self.pool.get()?;
 
-            let avatar_record = avatars
-                .find(avatar_id)
-                .first::<AvatarRecord>(&mut conn)
-                .optional()?;
-
-            if let Some(record) = avatar_record {
-                account.default_avatar = Some(Avatar {
-                    id: record.id,
-                    account_id: record.account_id,
-                    avatar_300x300_url: record.avatar_300x300_url,
-                    avatar_40x40_url: record.avatar_40x40_url,
-                    created_at: record.created_at,
-                    updated_at: record.updated_at,
-                });
-            }
+            let records = avatars
+                .filter(id.eq(avatar_id))
+                .load::<AvatarRecord>(&mut conn)?;
+
+            account.default_avatar = with_variants(&mut conn, records)?.pop();
         }
 
         Ok(())
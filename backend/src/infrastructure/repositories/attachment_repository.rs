@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use crate::domain::entities::attachment::{Attachment, NewAttachment};
+use crate::domain::entities::media::{MediaOwner, MediaReference};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::schema::{attachments, messages};
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = attachments)]
+struct AttachmentRecord {
+    id: i32,
+    user_id: i32,
+    storage_key: String,
+    filename: Option<String>,
+    content_type: String,
+    byte_size: i64,
+    created_at: chrono::NaiveDateTime,
+}
+
+impl From<AttachmentRecord> for Attachment {
+    fn from(record: AttachmentRecord) -> Self {
+        Attachment {
+            id: record.id,
+            user_id: record.user_id,
+            storage_key: record.storage_key,
+            filename: record.filename,
+            content_type: record.content_type,
+            byte_size: record.byte_size,
+            url: None,
+            created_at: record.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AttachmentRepositoryImpl {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl AttachmentRepositoryImpl {
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for AttachmentRepositoryImpl {
+    async fn create(&self, attachment: NewAttachment) -> Result<Attachment, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let record = diesel::insert_into(attachments::table)
+            .values((
+                attachments::user_id.eq(attachment.user_id),
+                attachments::storage_key.eq(&attachment.storage_key),
+                attachments::filename.eq(&attachment.filename),
+                attachments::content_type.eq(&attachment.content_type),
+                attachments::byte_size.eq(attachment.byte_size),
+                attachments::created_at.eq(Utc::now().naive_utc()),
+            ))
+            .returning(AttachmentRecord::as_returning())
+            .get_result(conn)?;
+
+        Ok(record.into())
+    }
+
+    async fn find_by_id(&self, attachment_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let record = attachments::table
+            .find(attachment_id)
+            .select(AttachmentRecord::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(record.map(Attachment::from))
+    }
+
+    async fn find_visible_to(&self, attachment_id: i32, user_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let shared_with_user = exists(
+            messages::table
+                .filter(messages::attachment_id.eq(attachments::id.nullable()))
+                .filter(messages::sender_id.eq(user_id).or(messages::receiver_id.eq(user_id)))
+        );
+        let record = attachments::table
+            .find(attachment_id)
+            .filter(attachments::user_id.eq(user_id).or(shared_with_user))
+            .select(AttachmentRecord::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(record.map(Attachment::from))
+    }
+
+    async fn is_key_visible_to(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let shared_with_user = exists(
+            messages::table
+                .filter(messages::attachment_id.eq(attachments::id.nullable()))
+                .filter(messages::sender_id.eq(user_id).or(messages::receiver_id.eq(user_id)))
+        );
+        let visible = diesel::select(exists(
+            attachments::table
+                .filter(attachments::storage_key.eq(storage_key))
+                .filter(attachments::user_id.eq(user_id).or(shared_with_user))
+        )).get_result(conn)?;
+
+        Ok(visible)
+    }
+
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let keys = attachments::table
+            .select((attachments::id, attachments::storage_key))
+            .load::<(i32, String)>(conn)?;
+
+        Ok(keys.into_iter()
+            .map(|(attachment_id, key)| MediaReference { owner: MediaOwner::Attachment(attachment_id), key })
+            .collect())
+    }
+}
@@ -23,7 +23,6 @@ impl AuthRepositoryImpl {
             .try_into()
             .expect("SECRET_KEY must be at least 16 bytes");
 
-        debug!("Initializing AuthRepositoryImpl with salt: {:?}", salt);
         Self { pool, salt }
     }
 }
@@ -33,7 +32,7 @@ impl AuthRepository for AuthRepositoryImpl {
     async fn authenticate(&self, auth: AuthUser) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
         use self::users::dsl::*;
 
-        debug!("Starting authentication for user: {}", auth.username);
+        debug!(username = %auth.username, "Starting authentication");
 
         let conn = &mut self.pool.get().map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
@@ -44,8 +43,6 @@ impl AuthRepository for AuthRepositoryImpl {
             .first::<(i32, String, String, String)>(conn)
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
-        debug!("Found stored hash for user {}: {}", auth.username, user_result.3);
-
         // Hash the provided password with the same salt for comparison
         let hashed_input = hash_with_salt(
             auth.password.as_bytes(),
@@ -54,14 +51,8 @@ impl AuthRepository for AuthRepositoryImpl {
         ).map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?
             .to_string();
 
-        debug!("Generated hash for login attempt: {}", hashed_input);
-        debug!("Hash comparison for {}", auth.username);
-        debug!("  Stored hash  : {}", user_result.3);
-        debug!("  Login hash   : {}", hashed_input);
-        debug!("  Match result : {}", user_result.3 == hashed_input);
-
         if user_result.3 == hashed_input {
-            debug!("Password verification successful for user: {}", auth.username);
+            debug!(username = %auth.username, "Password verification successful");
             Ok(User {
                 id: user_result.0,
                 username: user_result.1,
@@ -69,7 +60,7 @@ impl AuthRepository for AuthRepositoryImpl {
                 password: user_result.3,
             })
         } else {
-            debug!("Password verification failed for user: {}", auth.username);
+            debug!(username = %auth.username, "Password verification failed");
             Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Invalid password"
@@ -79,7 +70,7 @@ impl AuthRepository for AuthRepositoryImpl {
 
     async fn register(&self, register_dto: RegisterUserDto) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
         use self::users::dsl::*;
-        debug!("Starting registration for user: {}", register_dto.username);
+        debug!(username = %register_dto.username, "Starting registration");
 
         let conn = &mut self.pool.get().map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
@@ -92,7 +83,7 @@ impl AuthRepository for AuthRepositoryImpl {
             .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
 
         if existing_user.is_some() {
-            debug!("Registration failed: Username already exists: {}", register_dto.username);
+            debug!(username = %register_dto.username, "Registration failed: username already exists");
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists,
                 "Username already exists"
@@ -107,8 +98,6 @@ impl AuthRepository for AuthRepositoryImpl {
         ).map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?
             .to_string();
 
-        debug!("Generated hash for registration: {}", hashed_password);
-
         // Start transaction
         conn.transaction(|conn| {
             // Create user with all fields selected
@@ -135,8 +124,7 @@ impl AuthRepository for AuthRepositoryImpl {
                         ))
                         .execute(conn)?;
 
-                    debug!("User and account created successfully for: {}", register_dto.username);
-                    debug!("Stored hash in database: {}", user.3);
+                    debug!(username = %register_dto.username, user_id = user.0, "User and account created");
 
                     Ok(User {
                         id: user.0,
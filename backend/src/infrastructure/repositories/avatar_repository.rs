@@ -1,19 +1,131 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
 use async_trait::async_trait;
 use chrono::Utc;
-use crate::schema::avatars;
-use crate::domain::entities::avatar::Avatar;
+use crate::schema::{accounts, avatars, media_objects, media_variants};
+use crate::domain::entities::avatar::{Avatar, NewAvatar};
+use crate::domain::entities::media::{MediaOwner, MediaReference, MediaVariant};
 use crate::domain::repositories::avatar_repository::AvatarRepository;
 
 #[derive(Queryable, Debug)]
 pub(crate) struct AvatarRecord {
     pub id: i32,
     pub account_id: i32,
-    pub avatar_300x300_url: Option<String>,
-    pub avatar_40x40_url: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub original_key: Option<String>,
+    pub blurhash: Option<String>,
+    pub dominant_color: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = media_variants)]
+pub(crate) struct MediaVariantRecord {
+    pub avatar_id: i32,
+    pub size: i32,
+    pub density: i32,
+    pub format: String,
+    pub width: i32,
+    pub height: i32,
+    pub storage_key: String,
+    pub byte_size: Option<i64>,
+}
+
+impl MediaVariantRecord {
+    /// `None` for a format this build no longer knows about; such rows are skipped.
+    fn into_variant(self) -> Option<MediaVariant> {
+        Some(MediaVariant {
+            size: self.size as u32,
+            density: self.density as u32,
+            format: self.format.parse().ok()?,
+            width: self.width as u32,
+            height: self.height as u32,
+            storage_key: self.storage_key,
+            byte_size: self.byte_size,
+        })
+    }
+}
+
+impl From<AvatarRecord> for Avatar {
+    fn from(record: AvatarRecord) -> Self {
+        Avatar {
+            id: record.id,
+            account_id: record.account_id,
+            original_key: record.original_key,
+            variants: Vec::new(),
+            avatar_300x300_url: None,
+            avatar_40x40_url: None,
+            images: BTreeMap::new(),
+            blurhash: record.blurhash,
+            dominant_color: record.dominant_color,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// Inserts the avatar row and its variants, within the caller's transaction.
+fn insert_avatar(conn: &mut PgConnection, new_avatar: NewAvatar) -> QueryResult<Avatar> {
+    let NewAvatar { account_id, original_key, variants, placeholder } = new_avatar;
+    let (blurhash, dominant_color) = placeholder
+        .map(|placeholder| (placeholder.blurhash, placeholder.dominant_color))
+        .unzip();
+
+    let record = diesel::insert_into(avatars::table)
+        .values((
+            avatars::account_id.eq(account_id),
+            avatars::original_key.eq(original_key),
+            avatars::blurhash.eq(blurhash),
+            avatars::dominant_color.eq(dominant_color),
+            avatars::created_at.eq(Utc::now().naive_utc()),
+            avatars::updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .get_result::<AvatarRecord>(conn)?;
+
+    let rows: Vec<_> = variants.iter()
+        .map(|variant| (
+            media_variants::avatar_id.eq(record.id),
+            media_variants::size.eq(variant.size as i32),
+            media_variants::density.eq(variant.density as i32),
+            media_variants::format.eq(variant.format.as_str()),
+            media_variants::width.eq(variant.width as i32),
+            media_variants::height.eq(variant.height as i32),
+            media_variants::storage_key.eq(&variant.storage_key),
+            media_variants::byte_size.eq(variant.byte_size),
+        ))
+        .collect();
+    diesel::insert_into(media_variants::table).values(&rows).execute(conn)?;
+
+    let mut avatar = Avatar::from(record);
+    avatar.variants = variants;
+    Ok(avatar)
+}
+
+/// Turns avatar rows into entities, loading the variants of all of them in one query.
+pub(crate) fn with_variants(conn: &mut PgConnection, records: Vec<AvatarRecord>) -> QueryResult<Vec<Avatar>> {
+    let ids: Vec<i32> = records.iter().map(|record| record.id).collect();
+    let mut variants: HashMap<i32, Vec<MediaVariant>> = HashMap::new();
+    for record in media_variants::table
+        .filter(media_variants::avatar_id.eq_any(&ids))
+        .order_by(media_variants::id)
+        .select(MediaVariantRecord::as_select())
+        .load(conn)?
+    {
+        let avatar_id = record.avatar_id;
+        if let Some(variant) = record.into_variant() {
+            variants.entry(avatar_id).or_default().push(variant);
+        }
+    }
+
+    Ok(records.into_iter()
+        .map(|record| {
+            let id = record.id;
+            let mut avatar = Avatar::from(record);
+            avatar.variants = variants.remove(&id).unwrap_or_default();
+            avatar
+        })
+        .collect())
 }
 
 #[derive(Clone)]
@@ -29,27 +141,32 @@ impl AvatarRepositoryImpl {
 
 #[async_trait]
 impl AvatarRepository for AvatarRepositoryImpl {
-    async fn create(&self, account_id: i32, avatar_300x300_url: String, avatar_40x40_url: String) -> Result<Avatar, Box<dyn std::error::Error>> {
+    async fn create(&self, new_avatar: NewAvatar) -> Result<Avatar, Box<dyn std::error::Error>> {
         let conn = &mut self.pool.get()?;
 
-        let record = diesel::insert_into(avatars::table)
-            .values((
-                avatars::account_id.eq(account_id),
-                avatars::avatar_300x300_url.eq(avatar_300x300_url),
-                avatars::avatar_40x40_url.eq(avatar_40x40_url),
-                avatars::created_at.eq(Utc::now().naive_utc()),
-                avatars::updated_at.eq(Utc::now().naive_utc()),
-            ))
-            .get_result::<AvatarRecord>(conn)?;
+        Ok(conn.transaction(|conn| insert_avatar(conn, new_avatar))?)
+    }
 
-        Ok(Avatar {
-            id: record.id,
-            account_id: record.account_id,
-            avatar_300x300_url: record.avatar_300x300_url,
-            avatar_40x40_url: record.avatar_40x40_url,
-            created_at: record.created_at,
-            updated_at: record.updated_at,
-        })
+    async fn create_reusing_variants(&self, new_avatar: NewAvatar) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let keys: BTreeSet<String> = new_avatar.variants.iter().map(|variant| variant.storage_key.clone()).collect();
+
+        let avatar = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Locked until the new rows are counted: releasing the last other reference waits
+            let referenced = media_objects::table
+                .filter(media_objects::storage_key.eq_any(&keys))
+                .filter(media_objects::reference_count.gt(0))
+                .order_by(media_objects::storage_key)
+                .select(media_objects::storage_key)
+                .for_update()
+                .load::<String>(conn)?;
+            if referenced.len() < keys.len() {
+                return Ok(None);
+            }
+            insert_avatar(conn, new_avatar).map(Some)
+        })?;
+
+        Ok(avatar)
     }
 
     async fn find_by_account_id(&self, account_id: i32) -> Result<Vec<Avatar>, Box<dyn std::error::Error>> {
@@ -57,35 +174,94 @@ impl AvatarRepository for AvatarRepositoryImpl {
 
         let records = avatars::table
             .filter(avatars::account_id.eq(account_id))
-            .order_by(avatars::created_at.desc())
+            .order_by((avatars::created_at.desc(), avatars::id.desc()))
             .load::<AvatarRecord>(conn)?;
 
-        Ok(records.into_iter().map(|record| Avatar {
-            id: record.id,
-            account_id: record.account_id,
-            avatar_300x300_url: record.avatar_300x300_url,
-            avatar_40x40_url: record.avatar_40x40_url,
-            created_at: record.created_at,
-            updated_at: record.updated_at,
-        }).collect())
+        Ok(with_variants(conn, records)?)
     }
 
     async fn find_latest_by_account_id(&self, account_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
         let conn = &mut self.pool.get()?;
 
-        let record = avatars::table
+        let records = avatars::table
             .filter(avatars::account_id.eq(account_id))
-            .order_by(avatars::created_at.desc())
-            .first::<AvatarRecord>(conn)
-            .optional()?;
+            .order_by((avatars::created_at.desc(), avatars::id.desc()))
+            .limit(1)
+            .load::<AvatarRecord>(conn)?;
 
-        Ok(record.map(|record| Avatar {
-            id: record.id,
-            account_id: record.account_id,
-            avatar_300x300_url: record.avatar_300x300_url,
-            avatar_40x40_url: record.avatar_40x40_url,
-            created_at: record.created_at,
-            updated_at: record.updated_at,
-        }))
+        Ok(with_variants(conn, records)?.pop())
     }
-}
\ No newline at end of file
+
+    async fn is_key_owned_by(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let has_variant = diesel::dsl::exists(
+            media_variants::table
+                .filter(media_variants::avatar_id.eq(avatars::id))
+                .filter(media_variants::storage_key.eq(storage_key))
+        );
+        let owned = diesel::select(diesel::dsl::exists(
+            avatars::table
+                .inner_join(accounts::table.on(accounts::id.eq(avatars::account_id)))
+                .filter(accounts::user_id.eq(user_id))
+                .filter(avatars::original_key.eq(storage_key).or(has_variant))
+        )).get_result(conn)?;
+
+        Ok(owned)
+    }
+
+    async fn find_by_id(&self, avatar_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let records = avatars::table
+            .filter(avatars::id.eq(avatar_id))
+            .load::<AvatarRecord>(conn)?;
+
+        Ok(with_variants(conn, records)?.pop())
+    }
+
+    async fn find_by_variant_key(&self, storage_key: &str) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let records = avatars::table
+            .filter(avatars::id.eq_any(
+                media_variants::table
+                    .filter(media_variants::storage_key.eq(storage_key))
+                    .select(media_variants::avatar_id),
+            ))
+            .order_by(avatars::id.desc())
+            .limit(1)
+            .load::<AvatarRecord>(conn)?;
+
+        Ok(with_variants(conn, records)?.pop())
+    }
+
+    async fn delete(&self, avatar_id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        // Variant rows go with it through `ON DELETE CASCADE`
+        let deleted = diesel::delete(avatars::table.find(avatar_id)).execute(conn)?;
+        if deleted == 0 {
+            return Err(Box::new(diesel::result::Error::NotFound));
+        }
+
+        Ok(())
+    }
+
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let variants = media_variants::table
+            .select((media_variants::avatar_id, media_variants::storage_key))
+            .load::<(i32, String)>(conn)?;
+        let originals = avatars::table
+            .filter(avatars::original_key.is_not_null())
+            .select((avatars::id, avatars::original_key.assume_not_null()))
+            .load::<(i32, String)>(conn)?;
+
+        Ok(variants.into_iter()
+            .chain(originals)
+            .map(|(avatar_id, key)| MediaReference { owner: MediaOwner::Avatar(avatar_id), key })
+            .collect())
+    }
+}
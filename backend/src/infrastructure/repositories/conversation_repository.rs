@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{Bool, Integer, Timestamp};
+use crate::domain::entities::conversation::Conversation;
+use crate::domain::entities::message::{DatabaseMessage, MessageCursor};
+use crate::domain::repositories::conversation_repository::ConversationRepository;
+use crate::schema::{conversations, messages, users};
+
+#[derive(Clone)]
+pub struct ConversationRepositoryImpl {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl ConversationRepositoryImpl {
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ConversationRepository for ConversationRepositoryImpl {
+    async fn find_for_user(&self, user_id: i32, before: Option<MessageCursor>, limit: i64) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        // The conversations table is maintained by triggers on messages
+        let mut query = conversations::table
+            .inner_join(messages::table.on(messages::id.eq(conversations::last_message_id)))
+            .inner_join(users::table.on(users::id.eq(conversations::counterpart_id)))
+            .filter(conversations::user_id.eq(user_id))
+            .into_boxed();
+        if let Some(before) = before {
+            query = query.filter(
+                sql::<Bool>("(conversations.last_message_at, conversations.last_message_id) < (")
+                    .bind::<Timestamp, _>(before.created_at)
+                    .sql(", ")
+                    .bind::<Integer, _>(before.id)
+                    .sql(")"),
+            );
+        }
+
+        let rows = query
+            .order((conversations::last_message_at.desc(), conversations::last_message_id.desc()))
+            .limit(limit)
+            .select((
+                conversations::counterpart_id,
+                users::username,
+                messages::all_columns,
+                conversations::unread_count,
+            ))
+            .load::<(i32, String, DatabaseMessage, i32)>(conn)?;
+
+        Ok(rows.into_iter()
+            .map(|(counterpart_id, counterpart_username, last_message, unread_count)| Conversation {
+                user_id,
+                counterpart_id,
+                counterpart_username,
+                last_message,
+                unread_count,
+            })
+            .collect())
+    }
+}
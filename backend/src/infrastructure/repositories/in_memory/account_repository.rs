@@ -0,0 +1,102 @@
+use async_trait::async_trait;
+use diesel::result::Error as DieselError;
+
+use crate::domain::entities::account::{Account, UpdateAccountDto};
+use crate::domain::repositories::account_repository::AccountRepository;
+use super::{now, AccountRow, InMemoryStore};
+
+impl From<AccountRow> for Account {
+    fn from(row: AccountRow) -> Self {
+        Account {
+            id: row.id,
+            user_id: row.user_id,
+            first_name: row.first_name,
+            middle_name: row.middle_name,
+            last_name: row.last_name,
+            default_avatar_id: row.default_avatar_id,
+            default_avatar: None,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InMemoryAccountRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryAccountRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+
+    fn modify(&self, user_id: i32, change: impl FnOnce(&mut AccountRow)) -> Result<Account, DieselError> {
+        let mut tables = self.store.tables();
+        let row = tables.accounts.values_mut()
+            .find(|account| account.user_id == user_id)
+            .ok_or(DieselError::NotFound)?;
+
+        change(row);
+        row.updated_at = now();
+
+        Ok(Account::from(row.clone()))
+    }
+}
+
+#[async_trait]
+impl AccountRepository for InMemoryAccountRepository {
+    async fn find_by_user_id(&self, user_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+        let row = self.store.tables().accounts.values()
+            .find(|account| account.user_id == user_id)
+            .cloned()
+            .ok_or(DieselError::NotFound)?;
+
+        let mut account = Account::from(row);
+        self.load_default_avatar(&mut account).await?;
+
+        Ok(account)
+    }
+
+    async fn find_by_user_ids(&self, user_ids: &[i32]) -> Result<Vec<Account>, Box<dyn std::error::Error>> {
+        let rows: Vec<AccountRow> = self.store.tables().accounts.values()
+            .filter(|account| user_ids.contains(&account.user_id))
+            .cloned()
+            .collect();
+
+        let mut accounts = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut account = Account::from(row);
+            self.load_default_avatar(&mut account).await?;
+            accounts.push(account);
+        }
+
+        Ok(accounts)
+    }
+
+    async fn update(&self, user_id: i32, dto: UpdateAccountDto) -> Result<Account, Box<dyn std::error::Error>> {
+        let mut account = self.modify(user_id, |row| {
+            row.first_name = dto.first_name;
+            row.middle_name = dto.middle_name;
+            row.last_name = dto.last_name;
+        })?;
+        self.load_default_avatar(&mut account).await?;
+
+        Ok(account)
+    }
+
+    async fn set_default_avatar(&self, user_id: i32, avatar_id: i32) -> Result<Account, Box<dyn std::error::Error>> {
+        let mut account = self.modify(user_id, |row| row.default_avatar_id = Some(avatar_id))?;
+        self.load_default_avatar(&mut account).await?;
+
+        Ok(account)
+    }
+
+    async fn load_default_avatar(&self, account: &mut Account) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(avatar_id) = account.default_avatar_id {
+            account.default_avatar = self.store.tables().avatars.get(&avatar_id).cloned();
+        }
+
+        Ok(())
+    }
+}
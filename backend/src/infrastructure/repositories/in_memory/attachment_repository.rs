@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+
+use crate::domain::entities::attachment::{Attachment, NewAttachment};
+use crate::domain::entities::media::{MediaOwner, MediaReference};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use super::{now, InMemoryStore};
+
+#[derive(Clone)]
+pub struct InMemoryAttachmentRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryAttachmentRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for InMemoryAttachmentRepository {
+    async fn create(&self, attachment: NewAttachment) -> Result<Attachment, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+
+        let attachment = Attachment {
+            id: tables.next_id(),
+            user_id: attachment.user_id,
+            storage_key: attachment.storage_key,
+            filename: attachment.filename,
+            content_type: attachment.content_type,
+            byte_size: attachment.byte_size,
+            url: None,
+            created_at: now(),
+        };
+        tables.attachments.insert(attachment.id, attachment.clone());
+
+        Ok(attachment)
+    }
+
+    async fn find_by_id(&self, attachment_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().attachments.get(&attachment_id).cloned())
+    }
+
+    async fn find_visible_to(&self, attachment_id: i32, user_id: i32) -> Result<Option<Attachment>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        let shared_with_user = tables.messages.values().any(|message| {
+            message.attachment_id == Some(attachment_id) && (message.sender_id == user_id || message.receiver_id == user_id)
+        });
+        Ok(tables.attachments.get(&attachment_id)
+            .filter(|attachment| attachment.user_id == user_id || shared_with_user)
+            .cloned())
+    }
+
+    async fn is_key_visible_to(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        Ok(tables.attachments.values()
+            .filter(|attachment| attachment.storage_key == storage_key)
+            .any(|attachment| {
+                attachment.user_id == user_id || tables.messages.values().any(|message| {
+                    message.attachment_id == Some(attachment.id) && (message.sender_id == user_id || message.receiver_id == user_id)
+                })
+            }))
+    }
+
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().attachments.values()
+            .map(|attachment| MediaReference { owner: MediaOwner::Attachment(attachment.id), key: attachment.storage_key.clone() })
+            .collect())
+    }
+}
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use bcrypt::{hash_with_salt, DEFAULT_COST};
+use tracing::debug;
+
+use crate::domain::entities::auth::{AuthUser, RegisterUserDto};
+use crate::domain::entities::user::User;
+use crate::domain::repositories::auth_repository::AuthRepository;
+use super::{now, AccountRow, InMemoryStore};
+
+/// Hashes passwords exactly like `AuthRepositoryImpl`, so tokens and credentials behave the
+/// same in both storage modes.
+#[derive(Clone)]
+pub struct InMemoryAuthRepository {
+    store: InMemoryStore,
+    salt: [u8; 16],
+}
+
+impl InMemoryAuthRepository {
+    pub fn new(store: InMemoryStore, secret_key: String) -> Self {
+        let salt = secret_key.as_bytes()[..16]
+            .try_into()
+            .expect("SECRET_KEY must be at least 16 bytes");
+
+        Self { store, salt }
+    }
+
+    fn hash(&self, password: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(hash_with_salt(password.as_bytes(), DEFAULT_COST, self.salt)?.to_string())
+    }
+}
+
+#[async_trait]
+impl AuthRepository for InMemoryAuthRepository {
+    async fn authenticate(&self, auth: AuthUser) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
+        debug!(username = %auth.username, "Starting authentication");
+
+        let user = self.store.tables().users.values()
+            .find(|user| user.username == auth.username)
+            .cloned()
+            .ok_or(diesel::result::Error::NotFound)?;
+
+        if user.password == self.hash(&auth.password)? {
+            debug!(username = %auth.username, "Password verification successful");
+            Ok(user)
+        } else {
+            debug!(username = %auth.username, "Password verification failed");
+            Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invalid password"
+            )))
+        }
+    }
+
+    async fn register(&self, register_dto: RegisterUserDto) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
+        debug!(username = %register_dto.username, "Starting registration");
+
+        // Hash before taking the lock; bcrypt is deliberately slow
+        let hashed_password = self.hash(&register_dto.password)?;
+
+        let mut tables = self.store.tables();
+        if tables.users.values().any(|user| user.username == register_dto.username) {
+            debug!(username = %register_dto.username, "Registration failed: username already exists");
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "Username already exists"
+            )));
+        }
+
+        let user = tables.insert_user(&register_dto.username, &register_dto.email, &hashed_password)?;
+
+        let account = AccountRow {
+            id: tables.next_id(),
+            user_id: user.id,
+            first_name: register_dto.first_name,
+            middle_name: register_dto.middle_name,
+            last_name: register_dto.last_name,
+            default_avatar_id: None,
+            created_at: now(),
+            updated_at: now(),
+        };
+        tables.accounts.insert(account.id, account);
+
+        debug!(username = %register_dto.username, user_id = user.id, "User and account created");
+
+        Ok(user)
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use async_trait::async_trait;
+use diesel::result::Error as DieselError;
+
+use crate::domain::entities::avatar::{Avatar, NewAvatar};
+use crate::domain::entities::media::{MediaOwner, MediaReference};
+use crate::domain::repositories::avatar_repository::AvatarRepository;
+use super::media_object_repository::reference_counts;
+use super::{now, InMemoryStore, Tables};
+
+#[derive(Clone)]
+pub struct InMemoryAvatarRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryAvatarRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AvatarRepository for InMemoryAvatarRepository {
+    async fn create(&self, new_avatar: NewAvatar) -> Result<Avatar, Box<dyn std::error::Error>> {
+        insert_avatar(&mut self.store.tables(), new_avatar)
+    }
+
+    async fn create_reusing_variants(&self, new_avatar: NewAvatar) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let counts = reference_counts(&tables);
+        if !new_avatar.variants.iter().all(|variant| counts.contains_key(variant.storage_key.as_str())) {
+            return Ok(None);
+        }
+        insert_avatar(&mut tables, new_avatar).map(Some)
+    }
+
+    async fn find_by_account_id(&self, account_id: i32) -> Result<Vec<Avatar>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        // Newest first; ids break ties between avatars created within the same tick
+        Ok(tables.avatars.values()
+            .rev()
+            .filter(|avatar| avatar.account_id == account_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_latest_by_account_id(&self, account_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        Ok(self.find_by_account_id(account_id).await?.into_iter().next())
+    }
+
+    async fn find_by_id(&self, avatar_id: i32) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().avatars.get(&avatar_id).cloned())
+    }
+
+    async fn find_by_variant_key(&self, storage_key: &str) -> Result<Option<Avatar>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().avatars.values()
+            .rev()
+            .find(|avatar| avatar.variants.iter().any(|variant| variant.storage_key == storage_key))
+            .cloned())
+    }
+
+    async fn is_key_owned_by(&self, storage_key: &str, user_id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        Ok(tables.avatars.values()
+            .filter(|avatar| tables.accounts.get(&avatar.account_id).is_some_and(|account| account.user_id == user_id))
+            .any(|avatar| {
+                avatar.original_key.as_deref() == Some(storage_key)
+                    || avatar.variants.iter().any(|variant| variant.storage_key == storage_key)
+            }))
+    }
+
+    async fn delete(&self, avatar_id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        if tables.avatars.remove(&avatar_id).is_none() {
+            return Err(Box::new(DieselError::NotFound));
+        }
+
+        // Mirror `ON DELETE SET NULL` on accounts.default_avatar_id
+        for account in tables.accounts.values_mut() {
+            if account.default_avatar_id == Some(avatar_id) {
+                account.default_avatar_id = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn media_references(&self) -> Result<Vec<MediaReference>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().avatars.values()
+            .flat_map(|avatar| {
+                avatar.variants.iter()
+                    .map(|variant| variant.storage_key.clone())
+                    .chain(avatar.original_key.clone())
+                    .map(|key| MediaReference { owner: MediaOwner::Avatar(avatar.id), key })
+            })
+            .collect())
+    }
+}
+
+fn insert_avatar(tables: &mut Tables, new_avatar: NewAvatar) -> Result<Avatar, Box<dyn std::error::Error>> {
+    // Enforce the accounts foreign key
+    if !tables.accounts.contains_key(&new_avatar.account_id) {
+        return Err(Box::new(DieselError::NotFound));
+    }
+
+    let (blurhash, dominant_color) = new_avatar.placeholder
+        .map(|placeholder| (placeholder.blurhash, placeholder.dominant_color))
+        .unzip();
+    let avatar = Avatar {
+        id: tables.next_id(),
+        account_id: new_avatar.account_id,
+        original_key: new_avatar.original_key,
+        variants: new_avatar.variants,
+        avatar_300x300_url: None,
+        avatar_40x40_url: None,
+        images: BTreeMap::new(),
+        blurhash,
+        dominant_color,
+        created_at: now(),
+        updated_at: now(),
+    };
+    tables.avatars.insert(avatar.id, avatar.clone());
+
+    Ok(avatar)
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+use async_trait::async_trait;
+
+use crate::domain::entities::conversation::Conversation;
+use crate::domain::entities::message::{DatabaseMessage, MessageCursor};
+use crate::domain::repositories::conversation_repository::ConversationRepository;
+use super::InMemoryStore;
+
+/// Derives conversations from the messages table on every read, where Postgres keeps them in
+/// a table of their own.
+#[derive(Clone)]
+pub struct InMemoryConversationRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryConversationRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl ConversationRepository for InMemoryConversationRepository {
+    async fn find_for_user(&self, user_id: i32, before: Option<MessageCursor>, limit: i64) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        let mut by_counterpart: BTreeMap<i32, (&DatabaseMessage, i32)> = BTreeMap::new();
+        for message in tables.messages.values() {
+            let counterpart_id = match (message.sender_id == user_id, message.receiver_id == user_id) {
+                (true, _) => message.receiver_id,
+                (false, true) => message.sender_id,
+                (false, false) => continue,
+            };
+            if tables.message_deletions.contains_key(&(user_id, message.id)) {
+                continue;
+            }
+            let unread = i32::from(message.receiver_id == user_id && !message.is_read && message.deleted_at.is_none());
+
+            let entry = by_counterpart.entry(counterpart_id).or_insert((message, 0));
+            if message.cursor() > entry.0.cursor() {
+                entry.0 = message;
+            }
+            entry.1 += unread;
+        }
+
+        let mut conversations: Vec<Conversation> = by_counterpart.into_iter()
+            .filter(|(_, (last_message, _))| before.is_none_or(|before| last_message.cursor() < before))
+            .map(|(counterpart_id, (last_message, unread_count))| Conversation {
+                user_id,
+                counterpart_id,
+                counterpart_username: tables.users.get(&counterpart_id).map(|user| user.username.clone()).unwrap_or_default(),
+                last_message: last_message.clone(),
+                unread_count,
+            })
+            .collect();
+        conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.last_message.cursor()));
+        conversations.truncate(limit.max(0) as usize);
+
+        Ok(conversations)
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use diesel::result::Error as DieselError;
+
+use crate::domain::entities::job::{Job, JobStatus, NewJob};
+use crate::domain::repositories::job_repository::JobRepository;
+use super::{now, InMemoryStore};
+
+#[derive(Clone)]
+pub struct InMemoryJobRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryJobRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl JobRepository for InMemoryJobRepository {
+    async fn enqueue(&self, job: NewJob) -> Result<Job, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let now = now();
+
+        let job = Job {
+            id: tables.next_id() as i64,
+            kind: job.kind,
+            payload: job.payload,
+            status: JobStatus::Queued,
+            user_id: job.user_id,
+            attempts: 0,
+            max_attempts: job.max_attempts,
+            run_at: job.run_at.unwrap_or(now),
+            locked_at: None,
+            last_error: None,
+            result: None,
+            created_at: now,
+            updated_at: now,
+        };
+        tables.jobs.insert(job.id, job.clone());
+
+        Ok(job)
+    }
+
+    async fn claim(&self, kinds: &[&str], lease: Duration) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+        // The store lock makes the claim atomic, as `SKIP LOCKED` does in Postgres
+        let mut tables = self.store.tables();
+        let now = now();
+        let abandoned_before = now - chrono::Duration::from_std(lease)?;
+
+        let due = tables.jobs.values_mut()
+            .filter(|job| kinds.contains(&job.kind.as_str()))
+            .filter(|job| match job.status {
+                JobStatus::Queued => job.run_at <= now,
+                JobStatus::Running => job.locked_at.is_some_and(|locked_at| locked_at < abandoned_before),
+                _ => false,
+            })
+            .min_by_key(|job| (job.run_at, job.id));
+
+        Ok(due.map(|job| {
+            job.status = JobStatus::Running;
+            job.attempts += 1;
+            job.locked_at = Some(now);
+            job.updated_at = now;
+            job.clone()
+        }))
+    }
+
+    async fn complete(&self, job_id: i64, result: Option<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let job = tables.jobs.get_mut(&job_id).ok_or(DieselError::NotFound)?;
+
+        job.status = JobStatus::Succeeded;
+        job.result = result;
+        job.locked_at = None;
+        job.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn fail(&self, job_id: i64, error: &str, retry_at: Option<NaiveDateTime>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let job = tables.jobs.get_mut(&job_id).ok_or(DieselError::NotFound)?;
+        let now = now();
+
+        job.status = if retry_at.is_some() { JobStatus::Queued } else { JobStatus::Failed };
+        job.last_error = Some(error.to_string());
+        job.run_at = retry_at.unwrap_or(now);
+        job.locked_at = None;
+        job.updated_at = now;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, job_id: i64) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().jobs.get(&job_id).cloned())
+    }
+
+    async fn find_unfinished(&self, kind: &str) -> Result<Vec<Job>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        let mut jobs: Vec<Job> = tables.jobs.values()
+            .filter(|job| job.kind == kind && matches!(job.status, JobStatus::Queued | JobStatus::Running))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| (job.created_at, job.id));
+        Ok(jobs)
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+use async_trait::async_trait;
+use crate::domain::entities::avatar::AvatarProcessingJob;
+use crate::domain::entities::job::JobStatus;
+use crate::domain::entities::media::MediaObject;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use super::{InMemoryStore, Tables};
+
+#[derive(Clone)]
+pub struct InMemoryMediaObjectRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryMediaObjectRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+/// Counts references the way the Postgres triggers do: one per variant row, avatar original,
+/// attachment and avatar job yet to run.
+pub(super) fn reference_counts(tables: &Tables) -> BTreeMap<&str, i32> {
+    let mut counts = BTreeMap::new();
+    let keys = tables.avatars.values()
+        .flat_map(|avatar| {
+            avatar.variants.iter()
+                .map(|variant| variant.storage_key.as_str())
+                .chain(avatar.original_key.as_deref())
+        })
+        .chain(tables.attachments.values().map(|attachment| attachment.storage_key.as_str()))
+        .chain(tables.jobs.values()
+            .filter(|job| job.kind == AvatarProcessingJob::KIND && matches!(job.status, JobStatus::Queued | JobStatus::Running))
+            .filter_map(|job| job.payload.get("original_key")?.as_str()));
+    for key in keys {
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[async_trait]
+impl MediaObjectRepository for InMemoryMediaObjectRepository {
+    async fn find_by_keys(&self, keys: &[String]) -> Result<Vec<MediaObject>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+        let counts = reference_counts(&tables);
+
+        let keys: BTreeSet<&String> = keys.iter().collect();
+        Ok(keys.into_iter()
+            .filter_map(|key| counts.get(key.as_str()).map(|count| MediaObject {
+                storage_key: key.clone(),
+                reference_count: *count,
+            }))
+            .collect())
+    }
+
+    /// Counts are derived from the rows on every read, so there is no claim to record.
+    async fn claim_unreferenced(&self, keys: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+        let counts = reference_counts(&tables);
+
+        Ok(keys.iter()
+            .filter(|key| !counts.contains_key(key.as_str()))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect())
+    }
+
+    async fn release_claimed(&self, _keys: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
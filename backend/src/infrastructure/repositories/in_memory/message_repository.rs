@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, SubsecRound};
+
+use crate::domain::entities::message::{DatabaseMessage, MessageCursor, MessageEdit, MessageRange, SendMessageError};
+use crate::domain::repositories::message_repository::MessageRepository;
+use super::InMemoryStore;
+
+#[derive(Clone)]
+pub struct InMemoryMessageRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryMessageRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl MessageRepository for InMemoryMessageRepository {
+    async fn save_message(&self, message: DatabaseMessage) -> Result<DatabaseMessage, SendMessageError> {
+        let mut tables = self.store.tables();
+        // Enforce the foreign keys
+        if !tables.users.contains_key(&message.receiver_id) {
+            return Err(SendMessageError::RecipientNotFound(message.receiver_id));
+        }
+        if !tables.users.contains_key(&message.sender_id) {
+            return Err(SendMessageError::Database("Database error: insert or update on table \"messages\" violates foreign key constraint".to_string()));
+        }
+        if let Some(attachment_id) = message.attachment_id.filter(|attachment_id| !tables.attachments.contains_key(attachment_id)) {
+            return Err(SendMessageError::AttachmentNotFound(attachment_id));
+        }
+
+        let message = DatabaseMessage {
+            id: tables.next_id(),
+            // Postgres keeps timestamps to the microsecond, and so do cursors
+            created_at: message.created_at.round_subsecs(6),
+            ..message
+        };
+        tables.messages.insert(message.id, message.clone());
+
+        Ok(message)
+    }
+
+    async fn get_messages(&self, user1_id: i32, user2_id: i32, range: MessageRange) -> Result<Vec<DatabaseMessage>, String> {
+        let tables = self.store.tables();
+
+        let mut messages: Vec<DatabaseMessage> = tables.messages.values()
+            .filter(|message| {
+                (message.sender_id == user1_id && message.receiver_id == user2_id)
+                    || (message.sender_id == user2_id && message.receiver_id == user1_id)
+            })
+            .filter(|message| range.before.is_none_or(|before| message.cursor() < before))
+            .filter(|message| range.after.is_none_or(|after| message.cursor() > after))
+            .filter(|message| range.hidden_for.is_none_or(|user_id| !tables.message_deletions.contains_key(&(user_id, message.id))))
+            .cloned()
+            .collect();
+        messages.sort_by_key(DatabaseMessage::cursor);
+        if !range.oldest_first {
+            messages.reverse();
+        }
+        messages.truncate(range.limit.max(0) as usize);
+
+        Ok(messages)
+    }
+
+    async fn find_message(&self, message_id: i32) -> Result<Option<DatabaseMessage>, String> {
+        Ok(self.store.tables().messages.get(&message_id).cloned())
+    }
+
+    async fn mark_delivered(&self, message_ids: &[i32], at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String> {
+        let mut tables = self.store.tables();
+        let at = at.round_subsecs(6);
+
+        let mut delivered: Vec<DatabaseMessage> = tables.messages.values_mut()
+            .filter(|message| message_ids.contains(&message.id) && message.delivered_at.is_none())
+            .map(|message| {
+                message.delivered_at = Some(at);
+                message.clone()
+            })
+            .collect();
+        delivered.sort_by_key(DatabaseMessage::cursor);
+
+        Ok(delivered)
+    }
+
+    async fn mark_read(&self, reader_id: i32, sender_id: i32, up_to: MessageCursor, at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String> {
+        let mut tables = self.store.tables();
+        let at = at.round_subsecs(6);
+
+        let mut read: Vec<DatabaseMessage> = tables.messages.values_mut()
+            .filter(|message| message.receiver_id == reader_id && message.sender_id == sender_id)
+            .filter(|message| !message.is_read && message.cursor() <= up_to)
+            .map(|message| {
+                message.is_read = true;
+                message.read_at = Some(at);
+                message.delivered_at.get_or_insert(at);
+                message.clone()
+            })
+            .collect();
+        read.sort_by_key(DatabaseMessage::cursor);
+
+        Ok(read)
+    }
+
+    async fn edit_message(&self, message_id: i32, content: String, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String> {
+        let mut tables = self.store.tables();
+        let at = at.round_subsecs(6);
+        let edit_id = tables.next_id();
+
+        let Some(message) = tables.messages.get_mut(&message_id).filter(|message| message.deleted_at.is_none()) else {
+            return Ok(None);
+        };
+        let previous = std::mem::replace(&mut message.content, content);
+        message.edited_at = Some(at);
+        let message = message.clone();
+        tables.message_edits.insert(edit_id, (message_id, MessageEdit { content: previous, edited_at: at }));
+
+        Ok(Some(message))
+    }
+
+    async fn find_edits(&self, message_id: i32) -> Result<Vec<MessageEdit>, String> {
+        Ok(self.store.tables().message_edits.values()
+            .filter(|(edited, _)| *edited == message_id)
+            .map(|(_, edit)| edit.clone())
+            .collect())
+    }
+
+    async fn delete_for_everyone(&self, message_id: i32, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String> {
+        let mut tables = self.store.tables();
+
+        let Some(message) = tables.messages.get_mut(&message_id).filter(|message| message.deleted_at.is_none()) else {
+            return Ok(None);
+        };
+        message.deleted_at = Some(at.round_subsecs(6));
+        message.content.clear();
+        message.attachment_id = None;
+        let tombstone = message.clone();
+        tables.message_edits.retain(|_, (edited, _)| *edited != message_id);
+
+        Ok(Some(tombstone))
+    }
+
+    async fn delete_for_user(&self, message_id: i32, user_id: i32, at: NaiveDateTime) -> Result<(), String> {
+        let mut tables = self.store.tables();
+        if !tables.messages.contains_key(&message_id) || !tables.users.contains_key(&user_id) {
+            return Err("Database error: insert or update on table \"message_deletions\" violates foreign key constraint".to_string());
+        }
+        tables.message_deletions.entry((user_id, message_id)).or_insert(at.round_subsecs(6));
+
+        Ok(())
+    }
+}
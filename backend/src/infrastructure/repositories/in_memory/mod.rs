@@ -0,0 +1,163 @@
+//! Repositories backed by process memory instead of Postgres.
+//!
+//! Used by the use case unit tests and by `--storage memory`, which runs the whole server
+//! without a database for demos. All repositories built from one [`InMemoryStore`] share
+//! the same tables, so a user registered through the auth repository is visible to the
+//! account and user repositories. Missing rows and unique violations surface as the same
+//! `diesel::result::Error` values the Postgres implementations return, so handlers map
+//! them identically.
+
+pub mod account_repository;
+pub mod attachment_repository;
+pub mod auth_repository;
+pub mod avatar_repository;
+pub mod conversation_repository;
+pub mod job_repository;
+pub mod media_object_repository;
+pub mod message_repository;
+pub mod upload_repository;
+pub mod user_event_repository;
+pub mod user_repository;
+
+pub use account_repository::InMemoryAccountRepository;
+pub use attachment_repository::InMemoryAttachmentRepository;
+pub use auth_repository::InMemoryAuthRepository;
+pub use avatar_repository::InMemoryAvatarRepository;
+pub use conversation_repository::InMemoryConversationRepository;
+pub use job_repository::InMemoryJobRepository;
+pub use media_object_repository::InMemoryMediaObjectRepository;
+pub use message_repository::InMemoryMessageRepository;
+pub use upload_repository::InMemoryUploadRepository;
+pub use user_event_repository::InMemoryUserEventRepository;
+pub use user_repository::InMemoryUserRepository;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use chrono::NaiveDateTime;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use uuid::Uuid;
+use crate::domain::entities::attachment::Attachment;
+use crate::domain::entities::avatar::Avatar;
+use crate::domain::entities::job::Job;
+use crate::domain::entities::message::{DatabaseMessage, MessageEdit};
+use crate::domain::entities::upload::Upload;
+use crate::domain::entities::user::User;
+use crate::domain::entities::user_event::UserEvent;
+
+#[derive(Debug, Clone)]
+pub(crate) struct AccountRow {
+    pub id: i32,
+    pub user_id: i32,
+    pub first_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub last_name: Option<String>,
+    pub default_avatar_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Rows keyed by id. `BTreeMap` keeps iteration in insertion order, like a serial primary key.
+#[derive(Default)]
+pub(crate) struct Tables {
+    pub users: BTreeMap<i32, User>,
+    pub accounts: BTreeMap<i32, AccountRow>,
+    pub avatars: BTreeMap<i32, Avatar>,
+    pub messages: BTreeMap<i32, DatabaseMessage>,
+    /// Keyed by edit id, with the edited message's id.
+    pub message_edits: BTreeMap<i32, (i32, MessageEdit)>,
+    /// Keyed by `(user_id, message_id)`.
+    pub message_deletions: BTreeMap<(i32, i32), NaiveDateTime>,
+    pub jobs: BTreeMap<i64, Job>,
+    pub attachments: BTreeMap<i32, Attachment>,
+    pub uploads: BTreeMap<Uuid, Upload>,
+    pub user_events: BTreeMap<i64, UserEvent>,
+    /// Last event sent to each user's socket.
+    pub delivery_cursors: BTreeMap<i32, i64>,
+    last_id: i32,
+}
+
+impl Tables {
+    /// Ids come from one sequence so ids from different tables never collide in tests.
+    pub fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    pub fn insert_user(&mut self, username: &str, email: &str, password: &str) -> Result<User, DieselError> {
+        if self.users.values().any(|user| user.username == username) {
+            return Err(unique_violation("users_username_key"));
+        }
+        if self.users.values().any(|user| user.email == email) {
+            return Err(unique_violation("users_email_key"));
+        }
+
+        let user = User {
+            id: self.next_id(),
+            username: username.to_string(),
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        self.users.insert(user.id, user.clone());
+        Ok(user)
+    }
+
+    /// Removes a user and everything that references it, mirroring the `ON DELETE CASCADE`
+    /// foreign keys.
+    pub fn delete_user(&mut self, user_id: i32) {
+        self.users.remove(&user_id);
+
+        let account_ids: Vec<i32> = self.accounts.values()
+            .filter(|account| account.user_id == user_id)
+            .map(|account| account.id)
+            .collect();
+        self.accounts.retain(|_, account| account.user_id != user_id);
+        self.avatars.retain(|_, avatar| !account_ids.contains(&avatar.account_id));
+        self.messages.retain(|_, message| message.sender_id != user_id && message.receiver_id != user_id);
+        let messages = &self.messages;
+        self.message_edits.retain(|_, (message_id, _)| messages.contains_key(message_id));
+        self.message_deletions.retain(|(deleted_by, message_id), _| *deleted_by != user_id && messages.contains_key(message_id));
+        self.jobs.retain(|_, job| job.user_id != Some(user_id));
+        self.uploads.retain(|_, upload| upload.user_id != user_id);
+        self.user_events.retain(|_, event| event.user_id != user_id);
+        self.delivery_cursors.remove(&user_id);
+
+        let attachment_ids: Vec<i32> = self.attachments.values()
+            .filter(|attachment| attachment.user_id == user_id)
+            .map(|attachment| attachment.id)
+            .collect();
+        self.attachments.retain(|_, attachment| attachment.user_id != user_id);
+        for message in self.messages.values_mut() {
+            if message.attachment_id.is_some_and(|id| attachment_ids.contains(&id)) {
+                message.attachment_id = None;
+            }
+        }
+    }
+}
+
+/// Shared handle to the in-memory tables. Cloning is cheap and every clone sees the same data.
+#[derive(Clone, Default)]
+pub struct InMemoryStore {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn tables(&self) -> MutexGuard<'_, Tables> {
+        // A panic while holding the lock cannot leave a table half-written, so keep going
+        self.tables.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub(crate) fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+pub(crate) fn unique_violation(constraint: &str) -> DieselError {
+    DieselError::DatabaseError(
+        DatabaseErrorKind::UniqueViolation,
+        Box::new(format!("duplicate key value violates unique constraint \"{}\"", constraint)),
+    )
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use diesel::result::Error as DieselError;
+use uuid::Uuid;
+
+use crate::domain::entities::upload::{NewUpload, Upload, UploadOutcome};
+use crate::domain::repositories::upload_repository::UploadRepository;
+use super::{now, InMemoryStore};
+
+#[derive(Clone)]
+pub struct InMemoryUploadRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryUploadRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl UploadRepository for InMemoryUploadRepository {
+    async fn create(&self, upload: NewUpload) -> Result<Upload, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        // Enforce the users foreign key
+        if !tables.users.contains_key(&upload.user_id) {
+            return Err(Box::new(DieselError::NotFound));
+        }
+
+        let now = now();
+        let upload = Upload {
+            id: Uuid::new_v4(),
+            user_id: upload.user_id,
+            purpose: upload.purpose,
+            length: upload.length,
+            offset: 0,
+            metadata: upload.metadata,
+            locked_at: None,
+            job_id: None,
+            attachment_id: None,
+            expires_at: upload.expires_at,
+            completed_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+        tables.uploads.insert(upload.id, upload.clone());
+
+        Ok(upload)
+    }
+
+    async fn find_by_id(&self, upload_id: Uuid) -> Result<Option<Upload>, Box<dyn std::error::Error>> {
+        Ok(self.store.tables().uploads.get(&upload_id).cloned())
+    }
+
+    async fn lock(&self, upload_id: Uuid, offset: i64, stale_before: NaiveDateTime) -> Result<bool, Box<dyn std::error::Error>> {
+        // The store lock makes the check and the update atomic
+        let mut tables = self.store.tables();
+
+        let Some(upload) = tables.uploads.get_mut(&upload_id) else {
+            return Ok(false);
+        };
+        let free = upload.locked_at.is_none_or(|locked_at| locked_at < stale_before);
+        if upload.offset != offset || upload.completed_at.is_some() || !free {
+            return Ok(false);
+        }
+
+        let now = now();
+        upload.locked_at = Some(now);
+        upload.updated_at = now;
+        Ok(true)
+    }
+
+    async fn advance(&self, upload_id: Uuid, offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let upload = tables.uploads.get_mut(&upload_id).ok_or(DieselError::NotFound)?;
+
+        let now = now();
+        upload.offset = offset;
+        upload.locked_at = Some(now);
+        upload.updated_at = now;
+        Ok(())
+    }
+
+    async fn unlock(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(upload) = self.store.tables().uploads.get_mut(&upload_id) {
+            upload.locked_at = None;
+        }
+        Ok(())
+    }
+
+    async fn complete(&self, upload_id: Uuid, outcome: UploadOutcome) -> Result<Upload, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let upload = tables.uploads.get_mut(&upload_id).ok_or(DieselError::NotFound)?;
+
+        let now = now();
+        match outcome {
+            UploadOutcome::Avatar { job_id } => upload.job_id = Some(job_id),
+            UploadOutcome::Attachment { attachment_id } => upload.attachment_id = Some(attachment_id),
+        }
+        upload.locked_at = None;
+        upload.completed_at = Some(now);
+        upload.updated_at = now;
+        Ok(upload.clone())
+    }
+
+    async fn delete(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        self.store.tables().uploads.remove(&upload_id);
+        Ok(())
+    }
+
+    async fn find_expired(&self, now: NaiveDateTime) -> Result<Vec<Upload>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        let mut expired: Vec<Upload> = tables.uploads.values()
+            .filter(|upload| upload.is_expired(now))
+            .cloned()
+            .collect();
+        expired.sort_by_key(|upload| (upload.expires_at, upload.created_at));
+        Ok(expired)
+    }
+
+    async fn find_all(&self) -> Result<Vec<Upload>, Box<dyn std::error::Error>> {
+        let mut uploads: Vec<Upload> = self.store.tables().uploads.values().cloned().collect();
+        uploads.sort_by_key(|upload| upload.created_at);
+        Ok(uploads)
+    }
+}
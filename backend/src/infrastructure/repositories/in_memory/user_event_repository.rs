@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+
+use crate::domain::entities::message::WebSocketMessage;
+use crate::domain::entities::user_event::UserEvent;
+use crate::domain::repositories::user_event_repository::{UserEventRepository, ACKNOWLEDGED_EVENT_RETENTION};
+use super::{now, InMemoryStore};
+
+#[derive(Clone)]
+pub struct InMemoryUserEventRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryUserEventRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl UserEventRepository for InMemoryUserEventRepository {
+    async fn append(&self, user_id: i32, event: WebSocketMessage) -> Result<UserEvent, String> {
+        let mut tables = self.store.tables();
+        // Enforce the users foreign key
+        if !tables.users.contains_key(&user_id) {
+            return Err("Database error: insert or update on table \"user_events\" violates foreign key constraint".to_string());
+        }
+
+        let event = UserEvent { id: tables.next_id() as i64, user_id, event, created_at: now() };
+        tables.user_events.insert(event.id, event.clone());
+
+        Ok(event)
+    }
+
+    async fn pending(&self, user_id: i32, after: Option<i64>, limit: i64) -> Result<Vec<UserEvent>, String> {
+        let tables = self.store.tables();
+        let after = after.or_else(|| tables.delivery_cursors.get(&user_id).copied()).unwrap_or(0);
+
+        Ok(tables.user_events.range(after + 1..)
+            .map(|(_, event)| event)
+            .filter(|event| event.user_id == user_id)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn acknowledge(&self, user_id: i32, event_id: i64) -> Result<(), String> {
+        let mut tables = self.store.tables();
+        let cursor = tables.delivery_cursors.entry(user_id).or_insert(event_id);
+        *cursor = (*cursor).max(event_id);
+        let expired = now() - ACKNOWLEDGED_EVENT_RETENTION;
+        tables.user_events.retain(|id, event| event.user_id != user_id || *id > event_id || event.created_at >= expired);
+
+        Ok(())
+    }
+
+    async fn discard_message_events(&self, user_id: i32, message_id: i32) -> Result<(), String> {
+        self.store.tables().user_events.retain(|_, queued| {
+            let carries_content = match queued.event {
+                WebSocketMessage::NewMessage { message_id: queued_id, .. }
+                | WebSocketMessage::MessageEdited { message_id: queued_id, .. } => queued_id == message_id,
+                _ => false,
+            };
+            queued.user_id != user_id || !carries_content
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use diesel::result::Error as DieselError;
+
+use crate::domain::{
+    entities::user::{User, CreateUserDto, UpdateUserDto},
+    repositories::user_repository::UserRepository,
+};
+use super::{unique_violation, InMemoryStore};
+
+#[derive(Clone)]
+pub struct InMemoryUserRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryUserRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn find_by_id(&self, user_id: i32) -> Result<User, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+        let user = tables.users.get(&user_id).cloned().ok_or(DieselError::NotFound)?;
+
+        Ok(user)
+    }
+
+    async fn create(&self, user_dto: CreateUserDto) -> Result<User, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+        let user = tables.insert_user(&user_dto.username, &user_dto.email, &user_dto.password)?;
+
+        Ok(user)
+    }
+
+    async fn find_all(&self) -> Result<Vec<User>, Box<dyn std::error::Error>> {
+        let tables = self.store.tables();
+
+        Ok(tables.users.values().cloned().collect())
+    }
+
+    async fn update(&self, user_id: i32, user_dto: UpdateUserDto) -> Result<User, Box<dyn std::error::Error>> {
+        let mut tables = self.store.tables();
+
+        let others = || tables.users.values().filter(|user| user.id != user_id);
+        if others().any(|user| user.username == user_dto.username) {
+            return Err(Box::new(unique_violation("users_username_key")));
+        }
+        if others().any(|user| user.email == user_dto.email) {
+            return Err(Box::new(unique_violation("users_email_key")));
+        }
+
+        let user = tables.users.get_mut(&user_id).ok_or(DieselError::NotFound)?;
+        user.username = user_dto.username;
+        user.email = user_dto.email;
+        user.password = user_dto.password;
+
+        Ok(user.clone())
+    }
+
+    async fn delete(&self, user_id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        self.store.tables().delete_user(user_id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{Array, Text, Timestamp};
+use crate::domain::entities::job::{Job, JobStatus, NewJob};
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::schema::jobs;
+
+#[derive(Queryable, QueryableByName, Selectable, Debug)]
+#[diesel(table_name = jobs)]
+struct JobRecord {
+    id: i64,
+    kind: String,
+    payload: serde_json::Value,
+    status: String,
+    user_id: Option<i32>,
+    attempts: i32,
+    max_attempts: i32,
+    run_at: NaiveDateTime,
+    locked_at: Option<NaiveDateTime>,
+    last_error: Option<String>,
+    result: Option<serde_json::Value>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl TryFrom<JobRecord> for Job {
+    type Error = String;
+
+    fn try_from(record: JobRecord) -> Result<Self, Self::Error> {
+        Ok(Job {
+            id: record.id,
+            kind: record.kind,
+            payload: record.payload,
+            status: record.status.parse()?,
+            user_id: record.user_id,
+            attempts: record.attempts,
+            max_attempts: record.max_attempts,
+            run_at: record.run_at,
+            locked_at: record.locked_at,
+            last_error: record.last_error,
+            result: record.result,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        })
+    }
+}
+
+/// Picks the due job and marks it running in one statement. `SKIP LOCKED` makes concurrent
+/// workers pass over rows another transaction is claiming instead of waiting for it.
+const CLAIM_SQL: &str = "
+    UPDATE jobs
+    SET status = 'running', attempts = attempts + 1, locked_at = $2, updated_at = $2
+    WHERE id = (
+        SELECT id FROM jobs
+        WHERE kind = ANY($1)
+          AND ((status = 'queued' AND run_at <= $2) OR (status = 'running' AND locked_at < $3))
+        ORDER BY run_at, id
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING *";
+
+#[derive(Clone)]
+pub struct JobRepositoryImpl {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl JobRepositoryImpl {
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl JobRepository for JobRepositoryImpl {
+    async fn enqueue(&self, job: NewJob) -> Result<Job, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+
+        let record = diesel::insert_into(jobs::table)
+            .values((
+                jobs::kind.eq(&job.kind),
+                jobs::payload.eq(&job.payload),
+                jobs::status.eq(JobStatus::Queued.as_str()),
+                jobs::user_id.eq(job.user_id),
+                jobs::max_attempts.eq(job.max_attempts),
+                jobs::run_at.eq(job.run_at.unwrap_or(now)),
+                jobs::created_at.eq(now),
+                jobs::updated_at.eq(now),
+            ))
+            .returning(JobRecord::as_returning())
+            .get_result(conn)?;
+
+        Ok(Job::try_from(record)?)
+    }
+
+    async fn claim(&self, kinds: &[&str], lease: Duration) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+        let abandoned_before = now - chrono::Duration::from_std(lease)?;
+
+        let record = diesel::sql_query(CLAIM_SQL)
+            .bind::<Array<Text>, _>(kinds)
+            .bind::<Timestamp, _>(now)
+            .bind::<Timestamp, _>(abandoned_before)
+            .get_result::<JobRecord>(conn)
+            .optional()?;
+
+        Ok(record.map(Job::try_from).transpose()?)
+    }
+
+    async fn complete(&self, job_id: i64, result: Option<serde_json::Value>) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let updated = diesel::update(jobs::table.find(job_id))
+            .set((
+                jobs::status.eq(JobStatus::Succeeded.as_str()),
+                jobs::result.eq(result),
+                jobs::locked_at.eq(None::<NaiveDateTime>),
+                jobs::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(Box::new(diesel::result::Error::NotFound));
+        }
+
+        Ok(())
+    }
+
+    async fn fail(&self, job_id: i64, error: &str, retry_at: Option<NaiveDateTime>) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+        let status = if retry_at.is_some() { JobStatus::Queued } else { JobStatus::Failed };
+
+        let updated = diesel::update(jobs::table.find(job_id))
+            .set((
+                jobs::status.eq(status.as_str()),
+                jobs::last_error.eq(error),
+                jobs::run_at.eq(retry_at.unwrap_or(now)),
+                jobs::locked_at.eq(None::<NaiveDateTime>),
+                jobs::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(Box::new(diesel::result::Error::NotFound));
+        }
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, job_id: i64) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let record = jobs::table
+            .find(job_id)
+            .select(JobRecord::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(record.map(Job::try_from).transpose()?)
+    }
+
+    async fn find_unfinished(&self, kind: &str) -> Result<Vec<Job>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let records = jobs::table
+            .filter(jobs::kind.eq(kind))
+            .filter(jobs::status.eq_any([JobStatus::Queued.as_str(), JobStatus::Running.as_str()]))
+            .order_by((jobs::created_at, jobs::id))
+            .select(JobRecord::as_select())
+            .load(conn)?;
+
+        Ok(records.into_iter().map(Job::try_from).collect::<Result<_, _>>()?)
+    }
+}
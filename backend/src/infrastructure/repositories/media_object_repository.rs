@@ -0,0 +1,70 @@
+use std::collections::BTreeSet;
+use async_trait::async_trait;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use crate::domain::entities::media::MediaObject;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use crate::schema::media_objects;
+
+#[derive(Clone)]
+pub struct MediaObjectRepositoryImpl {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl MediaObjectRepositoryImpl {
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl MediaObjectRepository for MediaObjectRepositoryImpl {
+    async fn find_by_keys(&self, keys: &[String]) -> Result<Vec<MediaObject>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let objects = media_objects::table
+            .filter(media_objects::storage_key.eq_any(keys))
+            .order_by(media_objects::storage_key)
+            .select((media_objects::storage_key, media_objects::reference_count))
+            .load::<(String, i32)>(conn)?;
+
+        Ok(objects.into_iter()
+            .map(|(storage_key, reference_count)| MediaObject { storage_key, reference_count })
+            .collect())
+    }
+
+    async fn claim_unreferenced(&self, keys: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let keys: Vec<&String> = keys.iter().collect::<BTreeSet<_>>().into_iter().collect();
+
+        let mut claimed = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Untracked keys count as unreferenced too: nothing written since counting began
+            // uses them. Tracking them at zero gives them a row to claim like any other
+            diesel::insert_into(media_objects::table)
+                .values(keys.iter().map(|key| media_objects::storage_key.eq(*key)).collect::<Vec<_>>())
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            diesel::update(media_objects::table)
+                .filter(media_objects::storage_key.eq_any(&keys))
+                .filter(media_objects::reference_count.eq(0))
+                .set(media_objects::claimed_at.eq(diesel::dsl::now))
+                .returning(media_objects::storage_key)
+                .get_results::<String>(conn)
+        })?;
+
+        claimed.sort();
+        Ok(claimed)
+    }
+
+    async fn release_claimed(&self, keys: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        diesel::delete(media_objects::table)
+            .filter(media_objects::storage_key.eq_any(keys))
+            .filter(media_objects::reference_count.eq(0))
+            .filter(media_objects::claimed_at.is_not_null())
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -1,10 +1,29 @@
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::{PgConnection, RunQueryDsl};
 use diesel::prelude::*;
+use diesel::dsl::sql;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sql_types::{Bool, Integer, Nullable, Timestamp};
+use chrono::NaiveDateTime;
 use async_trait::async_trait;
-use crate::domain::entities::message::DatabaseMessage;
+use crate::domain::entities::message::{DatabaseMessage, MessageCursor, MessageEdit, MessageRange, SendMessageError};
 use crate::domain::repositories::message_repository::MessageRepository;
-use crate::schema::messages;
+use crate::schema::{message_deletions, message_edits, messages};
+
+define_sql_function!(fn least(a: Integer, b: Integer) -> Integer);
+define_sql_function!(fn greatest(a: Integer, b: Integer) -> Integer);
+
+/// `(created_at, id) <op> cursor`, as a row comparison so Postgres can turn it into a single
+/// bound on the conversation index.
+fn compared_to(op: &'static str, cursor: MessageCursor) -> diesel::expression::SqlLiteral<Bool, impl diesel::query_builder::QueryFragment<diesel::pg::Pg> + Send> {
+    sql::<Bool>("(messages.created_at, messages.id) ")
+        .sql(op)
+        .sql(" (")
+        .bind::<Timestamp, _>(cursor.created_at)
+        .sql(", ")
+        .bind::<Integer, _>(cursor.id)
+        .sql(")")
+}
 
 #[derive(Clone)]
 pub struct MessageRepositoryImpl {
@@ -19,9 +38,10 @@ impl MessageRepositoryImpl {
 
 #[async_trait]
 impl MessageRepository for MessageRepositoryImpl {
-    async fn save_message(&self, message: DatabaseMessage) -> Result<DatabaseMessage, String> {
+    async fn save_message(&self, message: DatabaseMessage) -> Result<DatabaseMessage, SendMessageError> {
         let mut conn = self.pool.get()
-            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+            .map_err(|e| SendMessageError::Database(format!("Failed to get DB connection: {}", e)))?;
+        let (receiver_id, attachment_id) = (message.receiver_id, message.attachment_id);
 
         // Using tokio::task::spawn_blocking for diesel sync operations
         let result = tokio::task::spawn_blocking(move || {
@@ -32,8 +52,55 @@ impl MessageRepository for MessageRepositoryImpl {
                     messages::content.eq(message.content),
                     messages::is_read.eq(message.is_read),
                     messages::created_at.eq(message.created_at),
+                    messages::attachment_id.eq(message.attachment_id),
                 ))
                 .get_result::<DatabaseMessage>(&mut conn)
+        }).await
+            .map_err(|e| SendMessageError::Database(format!("Task failed: {}", e)))?
+            .map_err(|e| match &e {
+                // Deleted since the sender looked them up
+                DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => match info.constraint_name() {
+                    Some("fk_receiver") => SendMessageError::RecipientNotFound(receiver_id),
+                    Some("messages_attachment_id_fkey") => SendMessageError::AttachmentNotFound(attachment_id.unwrap_or_default()),
+                    _ => SendMessageError::Database(format!("Database error: {}", e)),
+                },
+                _ => SendMessageError::Database(format!("Database error: {}", e)),
+            })?;
+
+        Ok(result)
+    }
+
+    async fn get_messages(&self, user1_id: i32, user2_id: i32, range: MessageRange) -> Result<Vec<DatabaseMessage>, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        let result = tokio::task::spawn_blocking(move || {
+            // Matches the conversation index on the unordered pair of participants, which
+            // covers both directions with one index scan in (created_at, id) order.
+            let mut query = messages::table
+                .filter(least(messages::sender_id, messages::receiver_id).eq(user1_id.min(user2_id)))
+                .filter(greatest(messages::sender_id, messages::receiver_id).eq(user1_id.max(user2_id)))
+                .into_boxed();
+            if let Some(before) = range.before {
+                query = query.filter(compared_to("<", before));
+            }
+            if let Some(after) = range.after {
+                query = query.filter(compared_to(">", after));
+            }
+            if let Some(user_id) = range.hidden_for {
+                query = query.filter(diesel::dsl::not(diesel::dsl::exists(
+                    message_deletions::table
+                        .filter(message_deletions::message_id.eq(messages::id))
+                        .filter(message_deletions::user_id.eq(user_id)),
+                )));
+            }
+            query = if range.oldest_first {
+                query.order((messages::created_at.asc(), messages::id.asc()))
+            } else {
+                query.order((messages::created_at.desc(), messages::id.desc()))
+            };
+
+            query.limit(range.limit).load::<DatabaseMessage>(&mut conn)
         }).await
             .map_err(|e| format!("Task failed: {}", e))?
             .map_err(|e| format!("Database error: {}", e))?;
@@ -41,43 +108,165 @@ impl MessageRepository for MessageRepositoryImpl {
         Ok(result)
     }
 
-    async fn get_messages(&self, user1_id: i32, user2_id: i32) -> Result<Vec<DatabaseMessage>, String> {
+    async fn find_message(&self, message_id: i32) -> Result<Option<DatabaseMessage>, String> {
         let mut conn = self.pool.get()
             .map_err(|e| format!("Failed to get DB connection: {}", e))?;
 
         let result = tokio::task::spawn_blocking(move || {
-            messages::table
-                .filter(
-                    messages::sender_id.eq(user1_id)
-                        .and(messages::receiver_id.eq(user2_id))
-                        .or(messages::sender_id.eq(user2_id)
-                            .and(messages::receiver_id.eq(user1_id)))
-                )
-                .order(messages::created_at.asc())
-                .load::<DatabaseMessage>(&mut conn)
+            messages::table.find(message_id)
+                .first::<DatabaseMessage>(&mut conn)
+                .optional()
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(result)
+    }
+
+    async fn mark_delivered(&self, message_ids: &[i32], at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+        let message_ids = message_ids.to_vec();
+
+        let mut result = tokio::task::spawn_blocking(move || {
+            diesel::update(messages::table)
+                .filter(messages::id.eq_any(message_ids))
+                .filter(messages::delivered_at.is_null())
+                .set(messages::delivered_at.eq(at))
+                .get_results::<DatabaseMessage>(&mut conn)
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
+        result.sort_by_key(DatabaseMessage::cursor);
+
+        Ok(result)
+    }
+
+    async fn mark_read(&self, reader_id: i32, sender_id: i32, up_to: MessageCursor, at: NaiveDateTime) -> Result<Vec<DatabaseMessage>, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        // The conversations trigger takes each newly read message off the unread count
+        let mut result = tokio::task::spawn_blocking(move || {
+            diesel::update(messages::table)
+                .filter(messages::receiver_id.eq(reader_id))
+                .filter(messages::sender_id.eq(sender_id))
+                .filter(messages::is_read.eq(false))
+                .filter(compared_to("<=", up_to))
+                .set((
+                    messages::is_read.eq(true),
+                    messages::read_at.eq(at),
+                    messages::delivered_at.eq(sql::<Nullable<Timestamp>>("COALESCE(messages.delivered_at, ")
+                        .bind::<Timestamp, _>(at)
+                        .sql(")")),
+                ))
+                .get_results::<DatabaseMessage>(&mut conn)
         }).await
             .map_err(|e| format!("Task failed: {}", e))?
             .map_err(|e| format!("Database error: {}", e))?;
+        result.sort_by_key(DatabaseMessage::cursor);
 
         Ok(result)
     }
 
-    async fn mark_as_read(&self, message_id: i32) -> Result<(), String> {
+    async fn edit_message(&self, message_id: i32, content: String, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String> {
         let mut conn = self.pool.get()
             .map_err(|e| format!("Failed to get DB connection: {}", e))?;
 
         let result = tokio::task::spawn_blocking(move || {
-            diesel::update(messages::table.find(message_id))
-                .set(messages::is_read.eq(true))
-                .execute(&mut conn)
+            conn.transaction(|conn| {
+                // Locked so concurrent edits each record the content they replaced
+                let Some(previous) = messages::table.find(message_id)
+                    .filter(messages::deleted_at.is_null())
+                    .for_update()
+                    .first::<DatabaseMessage>(conn)
+                    .optional()?
+                else {
+                    return Ok(None);
+                };
+
+                diesel::insert_into(message_edits::table)
+                    .values((
+                        message_edits::message_id.eq(message_id),
+                        message_edits::content.eq(previous.content),
+                        message_edits::edited_at.eq(at),
+                    ))
+                    .execute(conn)?;
+                diesel::update(messages::table.find(message_id))
+                    .set((messages::content.eq(content), messages::edited_at.eq(at)))
+                    .get_result::<DatabaseMessage>(conn)
+                    .map(Some)
+            })
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e: diesel::result::Error| format!("Database error: {}", e))?;
+
+        Ok(result)
+    }
+
+    async fn find_edits(&self, message_id: i32) -> Result<Vec<MessageEdit>, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        let result = tokio::task::spawn_blocking(move || {
+            message_edits::table
+                .filter(message_edits::message_id.eq(message_id))
+                .order(message_edits::id.asc())
+                .select((message_edits::content, message_edits::edited_at))
+                .load::<(String, NaiveDateTime)>(&mut conn)
         }).await
             .map_err(|e| format!("Task failed: {}", e))?
             .map_err(|e| format!("Database error: {}", e))?;
 
-        if result == 0 {
-            return Err("Message not found".to_string());
-        }
+        Ok(result.into_iter().map(|(content, edited_at)| MessageEdit { content, edited_at }).collect())
+    }
+
+    async fn delete_for_everyone(&self, message_id: i32, at: NaiveDateTime) -> Result<Option<DatabaseMessage>, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        // The conversations trigger stops counting the message as unread
+        let result = tokio::task::spawn_blocking(move || {
+            conn.transaction(|conn| {
+                let tombstone = diesel::update(messages::table.find(message_id))
+                    .filter(messages::deleted_at.is_null())
+                    .set((
+                        messages::deleted_at.eq(at),
+                        messages::content.eq(""),
+                        messages::attachment_id.eq(None::<i32>),
+                    ))
+                    .get_result::<DatabaseMessage>(conn)
+                    .optional()?;
+                if tombstone.is_some() {
+                    diesel::delete(message_edits::table.filter(message_edits::message_id.eq(message_id)))
+                        .execute(conn)?;
+                }
+                Ok(tombstone)
+            })
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e: diesel::result::Error| format!("Database error: {}", e))?;
+
+        Ok(result)
+    }
+
+    async fn delete_for_user(&self, message_id: i32, user_id: i32, at: NaiveDateTime) -> Result<(), String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        tokio::task::spawn_blocking(move || {
+            diesel::insert_into(message_deletions::table)
+                .values((
+                    message_deletions::user_id.eq(user_id),
+                    message_deletions::message_id.eq(message_id),
+                    message_deletions::deleted_at.eq(at),
+                ))
+                .on_conflict_do_nothing()
+                .execute(&mut conn)
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
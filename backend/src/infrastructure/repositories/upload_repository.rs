@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use uuid::Uuid;
+use crate::domain::entities::upload::{NewUpload, Upload, UploadOutcome};
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::schema::uploads;
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = uploads)]
+struct UploadRecord {
+    id: Uuid,
+    user_id: i32,
+    purpose: String,
+    upload_length: i64,
+    upload_offset: i64,
+    metadata: serde_json::Value,
+    locked_at: Option<NaiveDateTime>,
+    job_id: Option<i64>,
+    attachment_id: Option<i32>,
+    expires_at: NaiveDateTime,
+    completed_at: Option<NaiveDateTime>,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl TryFrom<UploadRecord> for Upload {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(record: UploadRecord) -> Result<Self, Self::Error> {
+        Ok(Upload {
+            id: record.id,
+            user_id: record.user_id,
+            purpose: record.purpose.parse()?,
+            length: record.upload_length,
+            offset: record.upload_offset,
+            metadata: serde_json::from_value::<BTreeMap<String, String>>(record.metadata)?,
+            locked_at: record.locked_at,
+            job_id: record.job_id,
+            attachment_id: record.attachment_id,
+            expires_at: record.expires_at,
+            completed_at: record.completed_at,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct UploadRepositoryImpl {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl UploadRepositoryImpl {
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UploadRepository for UploadRepositoryImpl {
+    async fn create(&self, upload: NewUpload) -> Result<Upload, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+
+        let record = diesel::insert_into(uploads::table)
+            .values((
+                uploads::id.eq(Uuid::new_v4()),
+                uploads::user_id.eq(upload.user_id),
+                uploads::purpose.eq(upload.purpose.as_str()),
+                uploads::upload_length.eq(upload.length),
+                uploads::metadata.eq(serde_json::to_value(&upload.metadata)?),
+                uploads::expires_at.eq(upload.expires_at),
+                uploads::created_at.eq(now),
+                uploads::updated_at.eq(now),
+            ))
+            .returning(UploadRecord::as_returning())
+            .get_result(conn)?;
+
+        Upload::try_from(record)
+    }
+
+    async fn find_by_id(&self, upload_id: Uuid) -> Result<Option<Upload>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let record = uploads::table
+            .find(upload_id)
+            .select(UploadRecord::as_select())
+            .first(conn)
+            .optional()?;
+
+        record.map(Upload::try_from).transpose()
+    }
+
+    async fn lock(&self, upload_id: Uuid, offset: i64, stale_before: NaiveDateTime) -> Result<bool, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+
+        // One conditional update, so two requests can never both see the upload as free
+        let locked = diesel::update(uploads::table.find(upload_id))
+            .filter(uploads::upload_offset.eq(offset))
+            .filter(uploads::completed_at.is_null())
+            .filter(uploads::locked_at.is_null().or(uploads::locked_at.lt(stale_before)))
+            .set((uploads::locked_at.eq(now), uploads::updated_at.eq(now)))
+            .execute(conn)?;
+
+        Ok(locked == 1)
+    }
+
+    async fn advance(&self, upload_id: Uuid, offset: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+
+        let updated = diesel::update(uploads::table.find(upload_id))
+            .set((
+                uploads::upload_offset.eq(offset),
+                uploads::locked_at.eq(now),
+                uploads::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(Box::new(diesel::result::Error::NotFound));
+        }
+
+        Ok(())
+    }
+
+    async fn unlock(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        diesel::update(uploads::table.find(upload_id))
+            .set(uploads::locked_at.eq(None::<NaiveDateTime>))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    async fn complete(&self, upload_id: Uuid, outcome: UploadOutcome) -> Result<Upload, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+        let now = Utc::now().naive_utc();
+        let (job_id, attachment_id) = match outcome {
+            UploadOutcome::Avatar { job_id } => (Some(job_id), None),
+            UploadOutcome::Attachment { attachment_id } => (None, Some(attachment_id)),
+        };
+
+        let record = diesel::update(uploads::table.find(upload_id))
+            .set((
+                uploads::job_id.eq(job_id),
+                uploads::attachment_id.eq(attachment_id),
+                uploads::locked_at.eq(None::<NaiveDateTime>),
+                uploads::completed_at.eq(now),
+                uploads::updated_at.eq(now),
+            ))
+            .returning(UploadRecord::as_returning())
+            .get_result(conn)?;
+
+        Upload::try_from(record)
+    }
+
+    async fn delete(&self, upload_id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        diesel::delete(uploads::table.find(upload_id)).execute(conn)?;
+
+        Ok(())
+    }
+
+    async fn find_expired(&self, now: NaiveDateTime) -> Result<Vec<Upload>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let records = uploads::table
+            .filter(uploads::expires_at.le(now))
+            .order_by((uploads::expires_at, uploads::created_at))
+            .select(UploadRecord::as_select())
+            .load(conn)?;
+
+        records.into_iter().map(Upload::try_from).collect()
+    }
+
+    async fn find_all(&self) -> Result<Vec<Upload>, Box<dyn std::error::Error>> {
+        let conn = &mut self.pool.get()?;
+
+        let records = uploads::table
+            .order_by(uploads::created_at)
+            .select(UploadRecord::as_select())
+            .load(conn)?;
+
+        records.into_iter().map(Upload::try_from).collect()
+    }
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::{BigInt, Integer};
+use diesel::upsert::excluded;
+use crate::domain::entities::message::WebSocketMessage;
+use crate::domain::entities::user_event::UserEvent;
+use crate::domain::repositories::user_event_repository::{UserEventRepository, ACKNOWLEDGED_EVENT_RETENTION};
+use crate::schema::{delivery_cursors, user_events};
+
+define_sql_function!(fn greatest(a: BigInt, b: BigInt) -> BigInt);
+
+/// Holds a per-user lock until the transaction ends. Ids come from a shared sequence, so
+/// without it a later event of the user could commit first, be sent, and move the cursor past
+/// the earlier one before it is visible.
+const LOCK_USER_QUEUE_SQL: &str = "SELECT pg_advisory_xact_lock('user_events'::regclass::oid::integer, $1)";
+
+#[derive(Queryable, Debug)]
+struct UserEventRecord {
+    id: i64,
+    user_id: i32,
+    payload: serde_json::Value,
+    created_at: NaiveDateTime,
+}
+
+impl TryFrom<UserEventRecord> for UserEvent {
+    type Error = String;
+
+    fn try_from(record: UserEventRecord) -> Result<Self, Self::Error> {
+        Ok(UserEvent {
+            id: record.id,
+            user_id: record.user_id,
+            event: serde_json::from_value(record.payload)
+                .map_err(|e| format!("Invalid event {}: {}", record.id, e))?,
+            created_at: record.created_at,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct UserEventRepositoryImpl {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl UserEventRepositoryImpl {
+    pub fn new(pool: Pool<ConnectionManager<PgConnection>>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserEventRepository for UserEventRepositoryImpl {
+    async fn append(&self, user_id: i32, event: WebSocketMessage) -> Result<UserEvent, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+        let payload = serde_json::to_value(&event)
+            .map_err(|e| format!("Failed to serialize event: {}", e))?;
+
+        let record = tokio::task::spawn_blocking(move || {
+            conn.transaction(|conn| {
+                diesel::sql_query(LOCK_USER_QUEUE_SQL)
+                    .bind::<Integer, _>(user_id)
+                    .execute(conn)?;
+                diesel::insert_into(user_events::table)
+                    .values((user_events::user_id.eq(user_id), user_events::payload.eq(payload)))
+                    .get_result::<UserEventRecord>(conn)
+            })
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(UserEvent { id: record.id, user_id: record.user_id, event, created_at: record.created_at })
+    }
+
+    async fn pending(&self, user_id: i32, after: Option<i64>, limit: i64) -> Result<Vec<UserEvent>, String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        let records = tokio::task::spawn_blocking(move || {
+            let after = match after {
+                Some(after) => Some(after),
+                None => delivery_cursors::table
+                    .find(user_id)
+                    .select(delivery_cursors::event_id)
+                    .first::<i64>(&mut conn)
+                    .optional()?,
+            };
+            user_events::table
+                .filter(user_events::user_id.eq(user_id))
+                .filter(user_events::id.gt(after.unwrap_or(0)))
+                .order(user_events::id.asc())
+                .limit(limit)
+                .load::<UserEventRecord>(&mut conn)
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        records.into_iter().map(UserEvent::try_from).collect()
+    }
+
+    async fn acknowledge(&self, user_id: i32, event_id: i64) -> Result<(), String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+
+        tokio::task::spawn_blocking(move || {
+            conn.transaction(|conn| {
+                diesel::insert_into(delivery_cursors::table)
+                    .values((delivery_cursors::user_id.eq(user_id), delivery_cursors::event_id.eq(event_id)))
+                    .on_conflict(delivery_cursors::user_id)
+                    .do_update()
+                    .set(delivery_cursors::event_id.eq(greatest(delivery_cursors::event_id, excluded(delivery_cursors::event_id))))
+                    .execute(conn)?;
+                diesel::delete(user_events::table)
+                    .filter(user_events::user_id.eq(user_id))
+                    .filter(user_events::id.le(event_id))
+                    .filter(user_events::created_at.lt(now - (ACKNOWLEDGED_EVENT_RETENTION.as_secs() as i32).seconds()))
+                    .execute(conn)
+            })
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn discard_message_events(&self, user_id: i32, message_id: i32) -> Result<(), String> {
+        let mut conn = self.pool.get()
+            .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+        let new_message = serde_json::json!({ "NewMessage": { "message_id": message_id } });
+        let edited = serde_json::json!({ "MessageEdited": { "message_id": message_id } });
+
+        tokio::task::spawn_blocking(move || {
+            diesel::delete(user_events::table)
+                .filter(user_events::user_id.eq(user_id))
+                .filter(user_events::payload.contains(new_message).or(user_events::payload.contains(edited)))
+                .execute(&mut conn)
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use async_trait::async_trait;
+use crate::domain::repositories::media_storage::{MediaObjectInfo, MediaStorage, MediaStorageError, StoredObject};
+use super::{join_url, validate_key};
+
+/// Stores objects as files under `root`, one file per key. Suitable for a single instance
+/// or for replicas sharing a network volume.
+#[derive(Clone)]
+pub struct LocalMediaStorage {
+    root: PathBuf,
+    public_base_url: String,
+}
+
+impl LocalMediaStorage {
+    pub fn new(root: PathBuf, public_base_url: String) -> Self {
+        Self { root, public_base_url }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, MediaStorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+fn io_error(key: String) -> impl FnOnce(std::io::Error) -> MediaStorageError {
+    move |e| match e.kind() {
+        ErrorKind::NotFound => MediaStorageError::NotFound(key),
+        _ => MediaStorageError::Backend(e.to_string()),
+    }
+}
+
+#[async_trait]
+impl MediaStorage for LocalMediaStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<(), MediaStorageError> {
+        let path = self.path_for(key)?;
+
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Write next to the target and rename, so readers never see a partial file
+            let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+            std::fs::write(&partial, bytes)?;
+            std::fs::rename(&partial, &path).inspect_err(|_| {
+                std::fs::remove_file(&partial).ok();
+            })
+        }).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(|e| MediaStorageError::Backend(e.to_string()))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaStorageError> {
+        let path = self.path_for(key)?;
+        let key = key.to_string();
+
+        tokio::task::spawn_blocking(move || std::fs::read(&path)).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(io_error(key))
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, MediaStorageError> {
+        let path = self.path_for(key)?;
+        let key = key.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(&path)?;
+            file.seek(SeekFrom::Start(start))?;
+            let mut bytes = Vec::new();
+            file.take(end.saturating_sub(start) + 1).read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(io_error(key))
+    }
+
+    async fn stat(&self, key: &str) -> Result<MediaObjectInfo, MediaStorageError> {
+        let path = self.path_for(key)?;
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream().to_string();
+        let key = key.to_string();
+
+        let metadata = tokio::task::spawn_blocking(move || std::fs::metadata(&path)).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(io_error(key))?;
+
+        let last_modified = metadata.modified().ok();
+        // Objects are written once under unique keys, so size and mtime identify the content
+        let modified_nanos = last_modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |elapsed| elapsed.as_nanos());
+
+        Ok(MediaObjectInfo {
+            size: metadata.len(),
+            content_type,
+            last_modified,
+            etag: format!("\"{:x}-{:x}\"", metadata.len(), modified_nanos),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaStorageError> {
+        let path = self.path_for(key)?;
+
+        tokio::task::spawn_blocking(move || match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(|e| MediaStorageError::Backend(e.to_string()))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, MediaStorageError> {
+        let path = self.path_for(key)?;
+
+        tokio::task::spawn_blocking(move || path.try_exists()).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(|e| MediaStorageError::Backend(e.to_string()))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, MediaStorageError> {
+        let root = self.root.clone();
+        let prefix = prefix.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            // Only the directory the prefix names needs walking
+            let start = root.join(prefix.rsplit_once('/').map_or("", |(dir, _)| dir));
+            let mut objects = Vec::new();
+            collect_files(&root, &start, &mut objects)?;
+            objects.retain(|object| object.key.starts_with(&prefix));
+            Ok(objects)
+        }).await
+            .map_err(|e| MediaStorageError::Backend(format!("Task failed: {}", e)))?
+            .map_err(|e: std::io::Error| MediaStorageError::Backend(e.to_string()))
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        join_url(&self.public_base_url, key)
+    }
+}
+
+/// Adds the files below `dir` to `objects`, keyed by their `/`-separated path under `root`.
+fn collect_files(root: &Path, dir: &Path, objects: &mut Vec<StoredObject>) -> std::io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        entries => entries?,
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_files(root, &entry.path(), objects)?;
+        } else if let Ok(relative) = entry.path().strip_prefix(root) {
+            let key = relative.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            objects.push(StoredObject { key, size: metadata.len(), last_modified: metadata.modified().ok() });
+        }
+    }
+
+    Ok(())
+}
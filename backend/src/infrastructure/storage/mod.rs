@@ -0,0 +1,67 @@
+pub mod local;
+pub mod s3;
+pub mod signing;
+
+use std::sync::Arc;
+use crate::domain::repositories::media_storage::{MediaStorage, MediaStorageError};
+use crate::infrastructure::config::settings::{MediaBackend, Settings};
+use self::local::LocalMediaStorage;
+use self::s3::S3MediaStorage;
+use self::signing::{MediaUrlSigner, SignedMediaStorage, MEDIA_PATH};
+
+pub fn signer_from_settings(settings: &Settings) -> Arc<MediaUrlSigner> {
+    let secret = settings.media.signing_key.as_ref().unwrap_or(&settings.secret_key);
+    Arc::new(MediaUrlSigner::new(secret.as_bytes(), settings.media.url_ttl))
+}
+
+/// Builds the media storage selected by `MEDIA_STORAGE`. Unless `MEDIA_PUBLIC_BASE_URL`
+/// points at a CDN or public bucket, URLs are signed with `signer` and served by `/media`.
+pub fn from_settings(
+    settings: &Settings,
+    signer: Arc<MediaUrlSigner>,
+) -> Result<Arc<dyn MediaStorage + Send + Sync>, MediaStorageError> {
+    let public_base_url = settings.media.public_base_url.clone();
+    let signed = public_base_url.is_none();
+
+    Ok(match &settings.media.backend {
+        MediaBackend::Local => {
+            let local = LocalMediaStorage::new(
+                settings.upload_dir.clone(),
+                public_base_url.unwrap_or_else(|| MEDIA_PATH.to_string()),
+            );
+            if signed {
+                Arc::new(SignedMediaStorage::new(local, signer))
+            } else {
+                Arc::new(local)
+            }
+        }
+        MediaBackend::S3(s3_settings) => {
+            let s3 = S3MediaStorage::new(s3_settings, public_base_url)?;
+            if signed {
+                Arc::new(SignedMediaStorage::new(s3, signer))
+            } else {
+                Arc::new(s3)
+            }
+        }
+    })
+}
+
+/// Keys are relative, slash-separated paths. Anything that could escape the storage root
+/// or be read as an absolute path or URL is rejected before it reaches a backend.
+pub(crate) fn validate_key(key: &str) -> Result<(), MediaStorageError> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && !key.contains('\\')
+        && !key.contains("://")
+        && key.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+
+    if valid {
+        Ok(())
+    } else {
+        Err(MediaStorageError::InvalidKey(key.to_string()))
+    }
+}
+
+pub(crate) fn join_url(base: &str, key: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), key)
+}
@@ -0,0 +1,151 @@
+use async_trait::async_trait;
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+use crate::domain::repositories::media_storage::{MediaObjectInfo, MediaStorage, MediaStorageError, StoredObject};
+use crate::infrastructure::config::settings::S3Settings;
+use super::{join_url, validate_key};
+
+/// Stores objects in an S3-compatible bucket (AWS S3, MinIO, R2, ...). Every replica sees
+/// the same objects, so this is the backend to use when running more than one instance.
+#[derive(Clone)]
+pub struct S3MediaStorage {
+    bucket: Box<Bucket>,
+    public_base_url: String,
+}
+
+impl S3MediaStorage {
+    /// Without an explicit `public_base_url`, objects are linked path-style on the endpoint,
+    /// which requires the bucket to allow anonymous reads.
+    pub fn new(settings: &S3Settings, public_base_url: Option<String>) -> Result<Self, MediaStorageError> {
+        let region = Region::Custom {
+            region: settings.region.clone(),
+            endpoint: settings.endpoint.clone(),
+        };
+        let credentials = Credentials::new(
+            Some(&settings.access_key_id),
+            Some(&settings.secret_access_key),
+            None,
+            None,
+            None,
+        ).map_err(|e| MediaStorageError::Backend(e.to_string()))?;
+
+        let mut bucket = Bucket::new(&settings.bucket, region, credentials)
+            .map_err(backend_error)?;
+        if settings.path_style {
+            // MinIO and most self-hosted servers do not resolve bucket subdomains
+            bucket = bucket.with_path_style();
+        }
+
+        let public_base_url = public_base_url
+            .unwrap_or_else(|| join_url(&settings.endpoint, &settings.bucket));
+
+        Ok(Self { bucket, public_base_url })
+    }
+}
+
+fn backend_error(e: S3Error) -> MediaStorageError {
+    MediaStorageError::Backend(e.to_string())
+}
+
+fn map_error(key: &str, e: S3Error) -> MediaStorageError {
+    match e {
+        S3Error::HttpFailWithBody(404, _) => MediaStorageError::NotFound(key.to_string()),
+        e => backend_error(e),
+    }
+}
+
+#[async_trait]
+impl MediaStorage for S3MediaStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), MediaStorageError> {
+        validate_key(key)?;
+
+        self.bucket.put_object_with_content_type(key, &bytes, content_type).await
+            .map_err(|e| map_error(key, e))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, MediaStorageError> {
+        validate_key(key)?;
+
+        let response = self.bucket.get_object(key).await
+            .map_err(|e| map_error(key, e))?;
+        Ok(response.to_vec())
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<Vec<u8>, MediaStorageError> {
+        validate_key(key)?;
+
+        let response = self.bucket.get_object_range(key, start, Some(end)).await
+            .map_err(|e| map_error(key, e))?;
+        Ok(response.to_vec())
+    }
+
+    async fn stat(&self, key: &str) -> Result<MediaObjectInfo, MediaStorageError> {
+        validate_key(key)?;
+
+        let (head, _) = self.bucket.head_object(key).await
+            .map_err(|e| map_error(key, e))?;
+
+        let size = head.content_length
+            .and_then(|length| u64::try_from(length).ok())
+            .ok_or_else(|| MediaStorageError::Backend(format!("No content length for {}", key)))?;
+        let last_modified = head.last_modified
+            .and_then(|modified| chrono::DateTime::parse_from_rfc2822(&modified).ok())
+            .map(|modified| modified.into());
+        let content_type = head.content_type
+            .unwrap_or_else(|| mime_guess::from_path(key).first_or_octet_stream().to_string());
+
+        Ok(MediaObjectInfo {
+            size,
+            content_type,
+            last_modified,
+            etag: head.e_tag.unwrap_or_else(|| format!("\"{}\"", size)),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaStorageError> {
+        validate_key(key)?;
+
+        match self.bucket.delete_object(key).await {
+            Ok(_) => Ok(()),
+            Err(e) => match map_error(key, e) {
+                MediaStorageError::NotFound(_) => Ok(()),
+                e => Err(e),
+            },
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, MediaStorageError> {
+        validate_key(key)?;
+
+        match self.bucket.head_object(key).await {
+            Ok(_) => Ok(true),
+            Err(e) => match map_error(key, e) {
+                MediaStorageError::NotFound(_) => Ok(false),
+                e => Err(e),
+            },
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, MediaStorageError> {
+        // Pages through the whole listing
+        let pages = self.bucket.list(prefix.to_string(), None).await
+            .map_err(backend_error)?;
+
+        Ok(pages.into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| StoredObject {
+                last_modified: chrono::DateTime::parse_from_rfc3339(&object.last_modified)
+                    .ok()
+                    .map(|modified| modified.into()),
+                key: object.key,
+                size: object.size,
+            })
+            .collect())
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        join_url(&self.public_base_url, key)
+    }
+}
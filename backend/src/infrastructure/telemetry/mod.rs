@@ -0,0 +1,104 @@
+pub mod propagation;
+pub mod redact;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+use self::redact::{JsonFormat, RedactingFields};
+
+const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetrySettings {
+    /// `EnvFilter` directives, e.g. `info,rust_clean_arch=debug`.
+    pub filter: String,
+    pub format: LogFormat,
+    /// OTLP/HTTP collector endpoint; span export is disabled when unset.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl TelemetrySettings {
+    /// Reads `RUST_LOG`, `LOG_FORMAT` (`text` or `json`) and `OTEL_EXPORTER_OTLP_ENDPOINT`.
+    pub fn from_env() -> Self {
+        let format = match std::env::var("LOG_FORMAT").map(|v| v.to_ascii_lowercase()) {
+            Ok(v) if v == "json" => LogFormat::Json,
+            _ => LogFormat::Text,
+        };
+
+        Self {
+            filter: std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            format,
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|v| !v.is_empty()),
+        }
+    }
+}
+
+/// Flushes buffered spans to the collector when dropped at the end of `main`.
+pub struct TelemetryGuard {
+    provider: TracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        for result in self.provider.force_flush() {
+            if let Err(e) = result {
+                eprintln!("Failed to flush spans: {}", e);
+            }
+        }
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to shut down tracer provider: {}", e);
+        }
+    }
+}
+
+/// Installs the global subscriber and the W3C trace-context propagator.
+///
+/// The OpenTelemetry layer is always installed so incoming `traceparent` headers are
+/// honoured and trace ids show up in logs; spans only leave the process when an OTLP
+/// endpoint is configured.
+pub fn init(settings: &TelemetrySettings) -> Result<TelemetryGuard, Box<dyn std::error::Error>> {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let resource = Resource::new(vec![KeyValue::new("service.name", SERVICE_NAME)]);
+    let mut builder = TracerProvider::builder().with_resource(resource);
+    if let Some(endpoint) = &settings.otlp_endpoint {
+        use opentelemetry_otlp::WithExportConfig;
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        builder = builder.with_batch_exporter(exporter, runtime::Tokio);
+    }
+    let provider = builder.build();
+    let tracer = provider.tracer(SERVICE_NAME);
+
+    let fmt_layer = match settings.format {
+        LogFormat::Text => fmt::layer()
+            .fmt_fields(RedactingFields::text())
+            .boxed(),
+        LogFormat::Json => fmt::layer()
+            .fmt_fields(RedactingFields::json())
+            .event_format(JsonFormat)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_new(&settings.filter)?)
+        .with(fmt_layer)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()?;
+
+    Ok(TelemetryGuard { provider })
+}
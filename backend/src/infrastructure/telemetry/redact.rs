@@ -0,0 +1,202 @@
+use std::fmt;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Field names whose values never reach the log output, matched case-insensitively against
+/// the whole name. Names are listed one by one rather than by suffix, so `content_hash` or
+/// `storage_key` stay readable.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "password",
+    "password_hash",
+    "current_password",
+    "new_password",
+    "token",
+    "access_token",
+    "refresh_token",
+    "authorization",
+    "cookie",
+    "set_cookie",
+    "secret",
+    "secret_key",
+    "secret_access_key",
+    "signing_key",
+    "salt",
+    "email",
+    "phone",
+    "first_name",
+    "middle_name",
+    "last_name",
+];
+
+pub fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_FIELDS.iter().any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+}
+
+/// Collects event or span fields into a JSON map, replacing sensitive values on the way in.
+#[derive(Default)]
+struct RedactingVisitor {
+    fields: Map<String, Value>,
+}
+
+impl RedactingVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let value = if is_sensitive(field.name()) {
+            Value::String(REDACTED.to_string())
+        } else {
+            value
+        };
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for RedactingVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, serde_json::json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::String(format!("{:?}", value)));
+    }
+}
+
+/// Field formatter shared by the text and JSON outputs. In JSON mode span fields are kept
+/// as a serialized object so `JsonFormat` can merge them into each event.
+pub struct RedactingFields {
+    json: bool,
+}
+
+impl RedactingFields {
+    pub fn text() -> Self {
+        Self { json: false }
+    }
+
+    pub fn json() -> Self {
+        Self { json: true }
+    }
+
+    fn write_text(writer: &mut Writer<'_>, fields: Map<String, Value>) -> fmt::Result {
+        let mut first = true;
+        if let Some(message) = fields.get("message") {
+            write!(writer, "{}", message.as_str().map(str::to_string).unwrap_or_else(|| message.to_string()))?;
+            first = false;
+        }
+        for (name, value) in fields.iter().filter(|(name, _)| name.as_str() != "message") {
+            if !first {
+                writer.write_char(' ')?;
+            }
+            match value {
+                Value::String(s) => write!(writer, "{}={}", name, s)?,
+                other => write!(writer, "{}={}", name, other)?,
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: tracing_subscriber::field::RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = RedactingVisitor::default();
+        fields.record(&mut visitor);
+
+        if self.json {
+            write!(writer, "{}", Value::Object(visitor.fields))
+        } else {
+            Self::write_text(&mut writer, visitor.fields)
+        }
+    }
+
+    fn add_fields(&self, current: &'writer mut FormattedFields<Self>, fields: &tracing::span::Record<'_>) -> fmt::Result {
+        if !self.json {
+            if !current.fields.is_empty() {
+                current.fields.push(' ');
+            }
+            return self.format_fields(current.as_writer(), fields);
+        }
+
+        let mut visitor = RedactingVisitor {
+            fields: serde_json::from_str::<Map<String, Value>>(&current.fields).unwrap_or_default(),
+        };
+        fields.record(&mut visitor);
+        current.fields = Value::Object(visitor.fields).to_string();
+        Ok(())
+    }
+}
+
+/// One JSON object per line with the event fields plus the merged fields of every
+/// enclosing span, so `request_id` and `trace_id` appear on each log line of a request.
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+
+        let mut visitor = RedactingVisitor::default();
+        event.record(&mut visitor);
+        let mut fields = visitor.fields;
+
+        let mut output = Map::new();
+        output.insert("timestamp".to_string(), Value::from(chrono::Utc::now().to_rfc3339()));
+        output.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        output.insert("target".to_string(), Value::from(metadata.target()));
+        if let Some(message) = fields.remove("message") {
+            output.insert("message".to_string(), message);
+        }
+
+        if let Some(scope) = ctx.event_scope() {
+            let mut span_fields = Map::new();
+            let mut span_names = Vec::new();
+            for span in scope.from_root() {
+                span_names.push(Value::from(span.name()));
+                let extensions = span.extensions();
+                if let Some(formatted) = extensions.get::<FormattedFields<N>>() {
+                    if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(formatted) {
+                        span_fields.extend(map);
+                    }
+                }
+            }
+            for key in ["request_id", "trace_id"] {
+                if let Some(value) = span_fields.remove(key) {
+                    output.insert(key.to_string(), value);
+                }
+            }
+            if !span_fields.is_empty() {
+                output.insert("span".to_string(), Value::Object(span_fields));
+            }
+            output.insert("spans".to_string(), Value::Array(span_names));
+        }
+
+        if !fields.is_empty() {
+            output.insert("fields".to_string(), Value::Object(fields));
+        }
+
+        writeln!(writer, "{}", Value::Object(output))
+    }
+}
+
@@ -1,36 +1,102 @@
 use std::sync::Arc;
 use crate::infrastructure::websocket::user_status_manager::UserStatusManager;
-use crate::domain::entities::message::WebSocketMessage;
+use crate::domain::entities::message::{DatabaseMessage, WebSocketMessage};
+use crate::domain::entities::user_event::UserEvent;
+use crate::domain::repositories::user_event_repository::UserEventRepository;
+use crate::infrastructure::metrics::metrics;
+use crate::presentation::handlers::ws_handlers::FlushEvents;
+
+/// Queued events a socket is sent per round trip to the database while it catches up.
+pub const REPLAY_BATCH_SIZE: i64 = 100;
 
 #[derive(Clone)]
 pub struct RealtimeMessageManager {
     user_status_manager: Arc<UserStatusManager>,
+    user_events: Arc<dyn UserEventRepository + Send + Sync>,
 }
 
 impl RealtimeMessageManager {
-    pub fn new(user_status_manager: Arc<UserStatusManager>) -> Self {
+    pub fn new(user_status_manager: Arc<UserStatusManager>, user_events: Arc<dyn UserEventRepository + Send + Sync>) -> Self {
         Self {
-            user_status_manager
+            user_status_manager,
+            user_events,
+        }
+    }
+
+    /// Queues a stored message for its receiver as a `NewMessage` event.
+    pub async fn send_message(&self, message: &DatabaseMessage, client_msg_id: Option<String>) -> Result<(), String> {
+        metrics().websocket_messages_total.with_label_values(&["sent"]).inc();
+
+        let event = WebSocketMessage::NewMessage {
+            message_id: message.id,
+            from_user_id: message.sender_id,
+            to_user_id: message.receiver_id,
+            content: message.content.clone(),
+            attachment_id: message.attachment_id,
+            created_at: message.created_at,
+            client_msg_id,
+        };
+        let result = self.publish(message.receiver_id, event).await;
+
+        // Pushed only means a socket was open; "delivered" is counted once the receiver confirms
+        let outcome = match result {
+            Ok(true) => "pushed",
+            Ok(false) => "queued",
+            Err(_) => "undeliverable",
+        };
+        metrics().websocket_messages_total.with_label_values(&[outcome]).inc();
+
+        result.map(|_| ())
+    }
+
+    /// Queues an event the user must not miss. Each of their open sockets is told to send it
+    /// now; if there are none, it is sent when they reconnect, after everything queued before
+    /// it. Returns whether they are connected.
+    pub async fn publish(&self, user_id: i32, event: WebSocketMessage) -> Result<bool, String> {
+        self.user_events.append(user_id, event).await?;
+
+        let connections = self.user_status_manager.get_connections(user_id).await;
+        for addr in &connections {
+            addr.do_send(FlushEvents);
         }
+        Ok(!connections.is_empty())
+    }
+
+    /// The next events queued for the user after `sent_up_to`, the last one a socket was sent,
+    /// oldest first. A socket that has not been sent any starts after the delivery cursor.
+    pub async fn pending_events(&self, user_id: i32, sent_up_to: Option<i64>) -> Result<Vec<UserEvent>, String> {
+        self.user_events.pending(user_id, sent_up_to, REPLAY_BATCH_SIZE).await
+    }
+
+    /// Takes the content of a message that was deleted for everyone out of the user's queue,
+    /// so a socket that has not been sent it yet never sees it. The `MessageDeleted` event
+    /// that follows still tells them a message was there.
+    pub async fn retract_message(&self, user_id: i32, message_id: i32) -> Result<(), String> {
+        self.user_events.discard_message_events(user_id, message_id).await
     }
 
-    pub async fn send_message(&self, _from_user_id: i32, to_user_id: i32, content: String) -> Result<(), String> {
-        if let Some(addr) = self.user_status_manager.get_connection(to_user_id).await {
-            let message = WebSocketMessage::Chat {
-                to_user_id,
-                content,
-            };
-            addr.try_send(message)
-                .map_err(|e| format!("Failed to send message: {}", e))
-        } else {
-            Err(format!("User {} is not connected", to_user_id))
+    /// Records that one of the user's sockets has been sent every event up to `event_id`.
+    pub async fn acknowledge_events(&self, user_id: i32, event_id: i64) -> Result<(), String> {
+        self.user_events.acknowledge(user_id, event_id).await
+    }
+
+    /// Pushes a server event to the user's sockets, if they are connected. Nothing is kept for
+    /// users who are not; use `publish` for events they must not miss.
+    pub async fn send_to_user(&self, user_id: i32, message: WebSocketMessage) -> Result<(), String> {
+        let connections = self.user_status_manager.get_connections(user_id).await;
+        if connections.is_empty() {
+            return Err(format!("User {} is not connected", user_id));
         }
+        for addr in connections {
+            addr.try_send(message.clone()).map_err(|e| format!("Failed to send message: {}", e))?;
+        }
+        Ok(())
     }
 
     pub async fn broadcast_to_all(&self, message: WebSocketMessage) -> Result<(), String> {
         let connections = self.user_status_manager.get_online_status().await;
         for (user_id, _) in connections {
-            if let Some(addr) = self.user_status_manager.get_connection(user_id).await {
+            for addr in self.user_status_manager.get_connections(user_id).await {
                 addr.try_send(message.clone())
                     .map_err(|e| format!("Failed to broadcast to user {}: {}", user_id, e))?;
             }
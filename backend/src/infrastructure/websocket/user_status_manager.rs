@@ -2,11 +2,21 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use actix::Addr;
+use uuid::Uuid;
 use crate::presentation::handlers::ws_handlers::WebSocketActor;
 
+/// A user's open sockets; they can be connected from several devices at once.
+type UserConnections = HashMap<Uuid, Addr<WebSocketActor>>;
+
 #[derive(Clone)]
 pub struct UserStatusManager {
-    connections: Arc<RwLock<HashMap<i32, Addr<WebSocketActor>>>>,
+    connections: Arc<RwLock<HashMap<i32, UserConnections>>>,
+}
+
+impl Default for UserStatusManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UserStatusManager {
@@ -16,14 +26,17 @@ impl UserStatusManager {
         }
     }
 
-    pub async fn add_connection(&self, user_id: i32, addr: Addr<WebSocketActor>) {
+    pub async fn add_connection(&self, user_id: i32, connection_id: Uuid, addr: Addr<WebSocketActor>) {
         let mut connections = self.connections.write().await;
-        // Since Addr<T> implements Clone when T: Actor, this will now work
-        connections.insert(user_id, addr.clone());
+        let user_connections = connections.entry(user_id).or_default();
+        let came_online = user_connections.is_empty();
+        user_connections.insert(connection_id, addr.clone());
         drop(connections);
 
         // Broadcast new user's online status
-        self.broadcast_status_update(user_id, true).await.ok();
+        if came_online {
+            self.broadcast_status_update(user_id, true).await.ok();
+        }
 
         // Send existing users' status to new user
         let online_users = self.get_online_status().await;
@@ -34,8 +47,14 @@ impl UserStatusManager {
         }
     }
 
-    pub async fn remove_connection(&self, user_id: i32) {
+    /// Forgets one of the user's connections. They only go offline with their last one.
+    pub async fn remove_connection(&self, user_id: i32, connection_id: Uuid) {
         let mut connections = self.connections.write().await;
+        let Some(user_connections) = connections.get_mut(&user_id) else { return };
+        user_connections.remove(&connection_id);
+        if !user_connections.is_empty() {
+            return;
+        }
         connections.remove(&user_id);
         drop(connections);
         self.broadcast_status_update(user_id, false).await.ok();
@@ -52,9 +71,11 @@ impl UserStatusManager {
 
     async fn broadcast_status_update(&self, user_id: i32, online: bool) -> Result<(), String> {
         let connections = self.connections.read().await;
-        for (conn_user_id, addr) in connections.iter() {
+        for (conn_user_id, user_connections) in connections.iter() {
             if *conn_user_id != user_id {
-                self.send_status_to_user(addr.clone(), user_id, online).await?;
+                for addr in user_connections.values() {
+                    self.send_status_to_user(addr.clone(), user_id, online).await?;
+                }
             }
         }
         Ok(())
@@ -66,8 +87,15 @@ impl UserStatusManager {
             .map_err(|e| format!("Failed to send status: {}", e))
     }
 
-    pub async fn get_connection(&self, user_id: i32) -> Option<Addr<WebSocketActor>> {
+    pub async fn connection_count(&self) -> usize {
+        self.connections.read().await.values().map(HashMap::len).sum()
+    }
+
+    /// Every open socket of the user; empty when they are offline.
+    pub async fn get_connections(&self, user_id: i32) -> Vec<Addr<WebSocketActor>> {
         let connections = self.connections.read().await;
-        connections.get(&user_id).cloned()
+        connections.get(&user_id)
+            .map(|user_connections| user_connections.values().cloned().collect())
+            .unwrap_or_default()
     }
 }
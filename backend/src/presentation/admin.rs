@@ -0,0 +1,63 @@
+//! Operator commands, run as `<binary> admin <command>` against the configured database and
+//! media storage instead of starting the server.
+
+use crate::app::Repositories;
+use crate::application::use_cases::media_use_cases::ReconcileMediaUseCase;
+use crate::infrastructure::config::{database::DbPool, settings::Settings};
+use crate::infrastructure::storage;
+
+const USAGE: &str = "Usage: admin gc-media [--delete] [--dry-run]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminCommand {
+    /// Reports stored media without rows and rows without stored media; `--delete` removes
+    /// them. `--dry-run` overrides `--delete`, so it can be added to any command line.
+    GcMedia { delete: bool },
+}
+
+impl AdminCommand {
+    /// Reads the arguments following `admin`.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = args.into_iter();
+
+        match args.next().as_deref() {
+            Some("gc-media") => {
+                let (mut delete, mut dry_run) = (false, false);
+                for arg in args {
+                    match arg.as_str() {
+                        "--delete" => delete = true,
+                        "--dry-run" => dry_run = true,
+                        _ => return Err(format!("Unknown argument: {}\n{}", arg, USAGE)),
+                    }
+                }
+                Ok(Self::GcMedia { delete: delete && !dry_run })
+            }
+            Some(other) => Err(format!("Unknown admin command '{}'\n{}", other, USAGE)),
+            None => Err(USAGE.to_string()),
+        }
+    }
+
+    /// Runs the command and prints its report to stdout as JSON.
+    pub async fn run(self, settings: &Settings, pool: DbPool) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Self::GcMedia { delete } => {
+                let repositories = Repositories::postgres(&pool, &settings.secret_key);
+                let media_storage = storage::from_settings(settings, storage::signer_from_settings(settings))?;
+
+                let report = ReconcileMediaUseCase::new(
+                    repositories.avatars,
+                    repositories.attachments,
+                    repositories.uploads,
+                    repositories.jobs,
+                    repositories.media_objects,
+                    media_storage,
+                    settings.media.gc.grace_period,
+                ).execute(delete).await?;
+
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+
+        Ok(())
+    }
+}
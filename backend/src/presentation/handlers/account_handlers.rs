@@ -1,17 +1,18 @@
 use actix_web::{web, HttpResponse, Responder};
 use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
 use crate::application::use_cases::account_use_cases::{GetAccountUseCase, UpdateAccountUseCase};
 use crate::domain::entities::account::UpdateAccountDto;
 
-pub struct AccountHandlers<T: AccountRepository> {
-    get_account_use_case: GetAccountUseCase<T>,
-    update_account_use_case: UpdateAccountUseCase<T>,
+pub struct AccountHandlers<T: AccountRepository, S: MediaStorage> {
+    get_account_use_case: GetAccountUseCase<T, S>,
+    update_account_use_case: UpdateAccountUseCase<T, S>,
 }
 
-impl<T: AccountRepository> AccountHandlers<T> {
+impl<T: AccountRepository, S: MediaStorage> AccountHandlers<T, S> {
     pub fn new(
-        get_account_use_case: GetAccountUseCase<T>,
-        update_account_use_case: UpdateAccountUseCase<T>,
+        get_account_use_case: GetAccountUseCase<T, S>,
+        update_account_use_case: UpdateAccountUseCase<T, S>,
     ) -> Self {
         Self {
             get_account_use_case,
@@ -40,17 +41,49 @@ impl<T: AccountRepository> AccountHandlers<T> {
     }
 }
 
-pub fn configure<T: AccountRepository + 'static>(
+pub fn configure<T: AccountRepository + 'static, S: MediaStorage + 'static>(
     cfg: &mut web::ServiceConfig,
-    handlers: web::Data<AccountHandlers<T>>,
+    _handlers: web::Data<AccountHandlers<T, S>>,
 ) {
     cfg.service(
         web::scope("/account")
-            .route("/{id}", web::get().to(move |handlers: web::Data<AccountHandlers<T>>, id: web::Path<i32>| async move {
+            .route("/{id}", web::get().to(move |handlers: web::Data<AccountHandlers<T, S>>, id: web::Path<i32>| async move {
                 handlers.get_account(id).await
             }))
-            .route("/{id}", web::put().to(move |handlers: web::Data<AccountHandlers<T>>, id: web::Path<i32>, account_dto: web::Json<UpdateAccountDto>| async move {
+            .route("/{id}", web::put().to(move |handlers: web::Data<AccountHandlers<T, S>>, id: web::Path<i32>, account_dto: web::Json<UpdateAccountDto>| async move {
                 handlers.update_account(id, account_dto).await
             }))
     );
-}
\ No newline at end of file
+}
+
+pub mod doc {
+    use crate::domain::entities::account::{Account, UpdateAccountDto};
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/account/{id}",
+        tag = "account",
+        params(("id" = i32, Path, description = "User id owning the account")),
+        responses(
+            (status = 200, description = "Account with its default avatar", body = Account),
+            (status = 500, description = "Account could not be loaded", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_account() {}
+
+    #[utoipa::path(
+        put,
+        path = "/api/v1/account/{id}",
+        tag = "account",
+        params(("id" = i32, Path, description = "User id owning the account")),
+        request_body = UpdateAccountDto,
+        responses(
+            (status = 200, description = "Updated account", body = Account),
+            (status = 500, description = "Account could not be updated", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn update_account() {}
+}
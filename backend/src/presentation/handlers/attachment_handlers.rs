@@ -0,0 +1,62 @@
+use actix_web::{web, HttpResponse, Responder};
+use crate::application::use_cases::attachment_use_cases::GetAttachmentUseCase;
+use crate::domain::entities::auth::Claims;
+use crate::domain::entities::attachment::AttachmentNotFound;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+
+pub struct AttachmentHandlers<A: AttachmentRepository, S: MediaStorage> {
+    get_attachment_use_case: GetAttachmentUseCase<A, S>,
+}
+
+impl<A: AttachmentRepository, S: MediaStorage> AttachmentHandlers<A, S> {
+    pub fn new(get_attachment_use_case: GetAttachmentUseCase<A, S>) -> Self {
+        Self { get_attachment_use_case }
+    }
+
+    pub async fn get_attachment(&self, claims: Claims, attachment_id: web::Path<i32>) -> impl Responder {
+        match self.get_attachment_use_case.execute(claims.sub, attachment_id.into_inner()).await {
+            Ok(attachment) => HttpResponse::Ok().json(attachment),
+            Err(e) if e.is::<AttachmentNotFound>() => {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Attachment not found",
+                    "message": e.to_string()
+                }))
+            }
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to get attachment",
+                "message": e.to_string()
+            })),
+        }
+    }
+}
+
+pub fn configure<A: AttachmentRepository + 'static, S: MediaStorage + 'static>(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<AttachmentHandlers<A, S>>,
+) {
+    cfg.service(
+        web::scope("/attachments")
+            .route("/{attachment_id}", web::get().to(move |handlers: web::Data<AttachmentHandlers<A, S>>, claims: Claims, attachment_id: web::Path<i32>| async move {
+                handlers.get_attachment(claims, attachment_id).await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::domain::entities::attachment::Attachment;
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/attachments/{attachment_id}",
+        tag = "messages",
+        params(("attachment_id" = i32, Path, description = "Attachment the caller uploaded, sent or received")),
+        responses(
+            (status = 200, description = "The attachment with a download URL", body = Attachment),
+            (status = 404, description = "No such attachment for the caller", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_attachment() {}
+}
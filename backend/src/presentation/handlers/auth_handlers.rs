@@ -3,6 +3,7 @@ use serde_json::json;
 use crate::application::use_cases::auth_use_cases::{LoginUseCase, RegisterUseCase};
 use crate::domain::repositories::auth_repository::AuthRepository;
 use crate::domain::entities::auth::{AuthUser, RegisterUserDto};
+use crate::infrastructure::metrics::metrics;
 use tracing::debug;
 
 pub struct AuthHandlers<T: AuthRepository> {
@@ -21,15 +22,17 @@ impl<T: AuthRepository> AuthHandlers<T> {
 
     pub async fn login(&self, auth: web::Json<AuthUser>) -> impl Responder {
         let username = auth.username.clone();
-        debug!("Login attempt for user: {}", username);
+        debug!(username = %username, "Login attempt");
 
         match self.login_use_case.execute(auth.into_inner()).await {
             Ok(token) => {
-                debug!("Login successful for user: {}", username);
+                debug!(username = %username, "Login successful");
+                metrics().login_attempts_total.with_label_values(&["success"]).inc();
                 HttpResponse::Ok().json(token)
             }
             Err(e) => {
-                debug!("Login failed: {}", e);
+                debug!(username = %username, error = %e, "Login failed");
+                metrics().login_attempts_total.with_label_values(&["failure"]).inc();
                 HttpResponse::Unauthorized().json(json!({
                     "error": "Authentication failed",
                     "message": e.to_string()
@@ -81,4 +84,33 @@ pub fn configure<T: AuthRepository + 'static>(
                 }
             ))
     );
-}
\ No newline at end of file
+}
+
+pub mod doc {
+    use crate::domain::entities::auth::{AuthUser, RegisterUserDto, TokenResponse};
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/auth/login",
+        tag = "auth",
+        request_body = AuthUser,
+        responses(
+            (status = 200, description = "Bearer token for the authenticated user", body = TokenResponse),
+            (status = 401, description = "Invalid credentials", body = ErrorResponse),
+        )
+    )]
+    pub fn login() {}
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/auth/register",
+        tag = "auth",
+        request_body = RegisterUserDto,
+        responses(
+            (status = 201, description = "User and account created", body = Object),
+            (status = 400, description = "Registration failed", body = Object),
+        )
+    )]
+    pub fn register() {}
+}
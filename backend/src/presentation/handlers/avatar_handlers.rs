@@ -1,77 +1,302 @@
-use actix_web::{web, HttpResponse, Responder};
+use std::collections::HashMap;
+use actix_web::{http::header, web, HttpResponse, Responder};
 use actix_multipart::Multipart;
 use futures::{StreamExt, TryStreamExt};
-use mime_guess::from_path;
-use crate::application::use_cases::avatar_use_cases::UploadAvatarUseCase;
+use crate::application::use_cases::avatar_use_cases::{
+    DeleteAvatarUseCase, ListAvatarsUseCase, SetDefaultAvatarUseCase, UploadAvatarUseCase,
+};
+use crate::domain::entities::auth::Claims;
+use crate::domain::entities::avatar::{AvatarCrop, AvatarNotFound, InvalidCrop};
+use crate::domain::entities::media::UploadRejection;
 use crate::domain::repositories::avatar_repository::AvatarRepository;
 use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::infrastructure::imaging;
 
-pub struct AvatarHandlers<T: AvatarRepository, U: AccountRepository> {
-    upload_avatar_use_case: UploadAvatarUseCase<T, U>,
+pub struct AvatarHandlers<T: AvatarRepository, U: AccountRepository, S: MediaStorage, J: JobRepository, M: MediaObjectRepository> {
+    upload_avatar_use_case: UploadAvatarUseCase<U, S, J>,
+    list_avatars_use_case: ListAvatarsUseCase<T, U, S>,
+    set_default_avatar_use_case: SetDefaultAvatarUseCase<T, U, S>,
+    delete_avatar_use_case: DeleteAvatarUseCase<T, U, M, S>,
 }
 
-impl<T: AvatarRepository, U: AccountRepository> AvatarHandlers<T, U> {
-    pub fn new(upload_avatar_use_case: UploadAvatarUseCase<T, U>) -> Self {
+impl<T: AvatarRepository, U: AccountRepository, S: MediaStorage, J: JobRepository, M: MediaObjectRepository> AvatarHandlers<T, U, S, J, M> {
+    pub fn new(
+        upload_avatar_use_case: UploadAvatarUseCase<U, S, J>,
+        list_avatars_use_case: ListAvatarsUseCase<T, U, S>,
+        set_default_avatar_use_case: SetDefaultAvatarUseCase<T, U, S>,
+        delete_avatar_use_case: DeleteAvatarUseCase<T, U, M, S>,
+    ) -> Self {
         Self {
             upload_avatar_use_case,
+            list_avatars_use_case,
+            set_default_avatar_use_case,
+            delete_avatar_use_case,
+        }
+    }
+
+    pub async fn list_avatars(&self, claims: Claims) -> impl Responder {
+        match self.list_avatars_use_case.execute(claims.sub).await {
+            Ok(gallery) => HttpResponse::Ok().json(gallery),
+            Err(e) => avatar_error(e, "Failed to list avatars"),
+        }
+    }
+
+    pub async fn set_default_avatar(&self, claims: Claims, avatar_id: web::Path<i32>) -> impl Responder {
+        match self.set_default_avatar_use_case.execute(claims.sub, avatar_id.into_inner()).await {
+            Ok(account) => HttpResponse::Ok().json(account),
+            Err(e) => avatar_error(e, "Failed to set default avatar"),
+        }
+    }
+
+    pub async fn delete_avatar(&self, claims: Claims, avatar_id: web::Path<i32>) -> impl Responder {
+        match self.delete_avatar_use_case.execute(claims.sub, avatar_id.into_inner()).await {
+            Ok(account) => HttpResponse::Ok().json(account),
+            Err(e) => avatar_error(e, "Failed to delete avatar"),
         }
     }
 
     pub async fn upload_avatar(&self, account_id: web::Path<i32>, mut payload: Multipart) -> impl Responder {
+        let mut image_data = None;
+        let mut crop_fields = HashMap::new();
+        let max_bytes = self.upload_avatar_use_case.upload_limits().max_bytes;
+
         while let Ok(Some(mut field)) = payload.try_next().await {
-            if field.name() == "avatar" {
-                // Get content type from filename
-                if let Some(filename) = field.content_disposition().get_filename() {
-                    let content_type = from_path(filename).first_or_octet_stream();
-
-                    // Validate content type
-                    if !content_type.type_().eq(&mime::IMAGE) {
-                        return HttpResponse::BadRequest().json(serde_json::json!({
-                            "error": "Invalid file type",
-                            "message": "Only image files are allowed"
-                        }));
-                    }
+            let name = field.name().to_string();
+            if name == "avatar" {
+                // The file name and declared type are ignored; only the bytes decide
+                if field.content_disposition().get_filename().is_none() {
+                    continue;
+                }
 
-                    // Process valid image file
-                    let mut image_data = Vec::new();
-                    while let Some(chunk) = field.next().await {
-                        match chunk {
-                            Ok(data) => image_data.extend_from_slice(&data),
-                            Err(e) => {
-                                return HttpResponse::BadRequest().json(serde_json::json!({
-                                    "error": "Failed to process upload",
-                                    "message": e.to_string()
-                                }));
-                            }
-                        }
+                match read_image_field(&mut field, max_bytes).await {
+                    Ok(data) => image_data = Some(data),
+                    Err(response) => return response,
+                }
+            } else if AvatarCrop::FIELDS.contains(&name.as_str()) {
+                match read_field(&mut field, MAX_CROP_FIELD_BYTES).await {
+                    Ok(data) => {
+                        crop_fields.insert(name, String::from_utf8_lossy(&data).trim().to_string());
                     }
+                    Err(response) => return response,
+                }
+            }
+        }
+
+        let Some(image_data) = image_data else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "No avatar file provided",
+                "message": "Please provide an avatar file"
+            }));
+        };
+
+        let crop = match AvatarCrop::from_fields(|name| crop_fields.get(name).map(String::as_str)) {
+            Ok(crop) => crop,
+            Err(e) => return invalid_crop(&e),
+        };
 
-                    return match self.upload_avatar_use_case.execute(account_id.into_inner(), image_data).await {
-                        Ok(response) => HttpResponse::Ok().json(response),
-                        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-                            "error": "Failed to process avatar",
-                            "message": e.to_string()
-                        })),
-                    };
+        match self.upload_avatar_use_case.execute(account_id.into_inner(), image_data, crop).await {
+            Ok(accepted) => HttpResponse::Accepted()
+                .insert_header((header::LOCATION, format!("/api/v1/jobs/{}", accepted.job_id)))
+                .json(accepted),
+            Err(e) if matches!(e.downcast_ref::<diesel::result::Error>(), Some(diesel::result::Error::NotFound)) => {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Account not found",
+                    "message": e.to_string()
+                }))
+            }
+            Err(e) => {
+                if let Some(invalid) = e.downcast_ref::<InvalidCrop>() {
+                    return invalid_crop(invalid);
+                }
+                if let Some(rejection) = e.downcast_ref::<UploadRejection>() {
+                    return rejected_upload(rejection);
                 }
+                HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Failed to store avatar",
+                    "message": e.to_string()
+                }))
             }
         }
+    }
+}
+
+/// Crop values are short numbers or unit names.
+const MAX_CROP_FIELD_BYTES: usize = 64;
+
+async fn read_field(field: &mut actix_multipart::Field, max_bytes: usize) -> Result<Vec<u8>, HttpResponse> {
+    let mut data = Vec::new();
+    while let Some(chunk) = next_chunk(field).await? {
+        push_chunk(&mut data, &chunk, max_bytes)?;
+    }
+    Ok(data)
+}
 
+/// Reads the image while it streams in, refusing it as soon as it outgrows `max_bytes` or its
+/// leading bytes turn out not to be an accepted image format.
+async fn read_image_field(field: &mut actix_multipart::Field, max_bytes: usize) -> Result<Vec<u8>, HttpResponse> {
+    let mut data = Vec::new();
+    let mut sniffed = false;
+    while let Some(chunk) = next_chunk(field).await? {
+        push_chunk(&mut data, &chunk, max_bytes)?;
+        if !sniffed && data.len() >= imaging::SNIFF_LEN {
+            check_sniffed(&data)?;
+            sniffed = true;
+        }
+    }
+    if !sniffed {
+        check_sniffed(&data)?;
+    }
+    Ok(data)
+}
+
+async fn next_chunk(field: &mut actix_multipart::Field) -> Result<Option<web::Bytes>, HttpResponse> {
+    field.next().await.transpose().map_err(|e| {
         HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No avatar file provided",
-            "message": "Please provide an avatar file"
+            "error": "Failed to process upload",
+            "message": e.to_string()
         }))
+    })
+}
+
+fn push_chunk(data: &mut Vec<u8>, chunk: &[u8], max_bytes: usize) -> Result<(), HttpResponse> {
+    if data.len() + chunk.len() > max_bytes {
+        return Err(rejected_upload(&UploadRejection::TooManyBytes { max_bytes }));
+    }
+    data.extend_from_slice(chunk);
+    Ok(())
+}
+
+fn check_sniffed(data: &[u8]) -> Result<(), HttpResponse> {
+    match imaging::sniff(data) {
+        Some(_) => Ok(()),
+        None => Err(rejected_upload(&UploadRejection::UnsupportedType)),
+    }
+}
+
+pub(crate) fn invalid_crop(e: &InvalidCrop) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "error": "Invalid crop",
+        "message": e.to_string()
+    }))
+}
+
+pub(crate) fn rejected_upload(rejection: &UploadRejection) -> HttpResponse {
+    let (mut response, error) = match rejection {
+        UploadRejection::UnsupportedType => (HttpResponse::UnsupportedMediaType(), "Invalid file type"),
+        UploadRejection::TooManyBytes { .. } => (HttpResponse::PayloadTooLarge(), "File too large"),
+        UploadRejection::TooManyPixels { .. } => (HttpResponse::UnprocessableEntity(), "Image too large"),
+        UploadRejection::Unreadable(_) => (HttpResponse::BadRequest(), "Invalid image"),
+    };
+    response.json(serde_json::json!({
+        "error": error,
+        "message": rejection.to_string()
+    }))
+}
+
+fn avatar_error(e: Box<dyn std::error::Error>, error: &str) -> HttpResponse {
+    if e.is::<AvatarNotFound>() {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Avatar not found",
+            "message": e.to_string()
+        }));
     }
+
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": error,
+        "message": e.to_string()
+    }))
 }
 
-pub fn configure<T: AvatarRepository + 'static, U: AccountRepository + 'static>(
+pub fn configure<
+    T: AvatarRepository + 'static,
+    U: AccountRepository + 'static,
+    S: MediaStorage + 'static,
+    J: JobRepository + 'static,
+    M: MediaObjectRepository + 'static,
+>(
     cfg: &mut web::ServiceConfig,
-    handlers: web::Data<AvatarHandlers<T, U>>,
+    _handlers: web::Data<AvatarHandlers<T, U, S, J, M>>,
 ) {
     cfg.service(
         web::scope("/avatars")
-            .route("/{account_id}", web::post().to(move |handlers: web::Data<AvatarHandlers<T, U>>, account_id: web::Path<i32>, payload: Multipart| async move {
+            .route("/me", web::get().to(move |handlers: web::Data<AvatarHandlers<T, U, S, J, M>>, claims: Claims| async move {
+                handlers.list_avatars(claims).await
+            }))
+            .route("/me/{avatar_id}/default", web::put().to(move |handlers: web::Data<AvatarHandlers<T, U, S, J, M>>, claims: Claims, avatar_id: web::Path<i32>| async move {
+                handlers.set_default_avatar(claims, avatar_id).await
+            }))
+            .route("/me/{avatar_id}", web::delete().to(move |handlers: web::Data<AvatarHandlers<T, U, S, J, M>>, claims: Claims, avatar_id: web::Path<i32>| async move {
+                handlers.delete_avatar(claims, avatar_id).await
+            }))
+            .route("/{account_id}", web::post().to(move |handlers: web::Data<AvatarHandlers<T, U, S, J, M>>, account_id: web::Path<i32>, payload: Multipart| async move {
                 handlers.upload_avatar(account_id, payload).await
             }))
     );
 }
+
+pub mod doc {
+    use crate::domain::entities::account::Account;
+    use crate::domain::entities::avatar::{AvatarGallery, AvatarUploadAccepted};
+    use crate::presentation::openapi::{AvatarUploadForm, ErrorResponse};
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/avatars/{account_id}",
+        tag = "avatars",
+        params(("account_id" = i32, Path, description = "Account receiving the avatar")),
+        request_body(content = AvatarUploadForm, content_type = "multipart/form-data"),
+        responses(
+            (status = 202, description = "Original stored and queued; square variants are rendered in the background and become the default avatar. `Location` points at the job.", body = AvatarUploadAccepted),
+            (status = 400, description = "Missing or corrupt image, or invalid crop", body = ErrorResponse),
+            (status = 404, description = "Account does not exist", body = ErrorResponse),
+            (status = 413, description = "File exceeds the configured byte limit", body = ErrorResponse),
+            (status = 415, description = "Content is not a JPEG, PNG, WebP or GIF image", body = ErrorResponse),
+            (status = 422, description = "Image exceeds the configured pixel dimensions", body = ErrorResponse),
+            (status = 500, description = "Original could not be stored or queued", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn upload_avatar() {}
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/avatars/me",
+        tag = "avatars",
+        responses(
+            (status = 200, description = "All avatars of the caller's account, newest first", body = AvatarGallery),
+            (status = 404, description = "Caller has no account", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn list_avatars() {}
+
+    #[utoipa::path(
+        put,
+        path = "/api/v1/avatars/me/{avatar_id}/default",
+        tag = "avatars",
+        params(("avatar_id" = i32, Path, description = "One of the caller's avatars")),
+        responses(
+            (status = 200, description = "Account with the new default avatar", body = Account),
+            (status = 404, description = "No such avatar on the caller's account", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn set_default_avatar() {}
+
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/avatars/me/{avatar_id}",
+        tag = "avatars",
+        params(("avatar_id" = i32, Path, description = "One of the caller's avatars")),
+        responses(
+            (status = 200, description = "Avatar and its files deleted; a deleted default falls back to the most recent remaining avatar", body = Account),
+            (status = 404, description = "No such avatar on the caller's account", body = ErrorResponse),
+            (status = 500, description = "Stored files could not be removed", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn delete_avatar() {}
+}
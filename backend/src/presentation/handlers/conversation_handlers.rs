@@ -0,0 +1,78 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use crate::application::use_cases::conversation_use_cases::ListConversationsUseCase;
+use crate::domain::entities::auth::Claims;
+use crate::domain::entities::message::MessageCursor;
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::conversation_repository::ConversationRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+
+#[derive(Debug, Deserialize)]
+pub struct InboxQuery {
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+pub struct ConversationHandlers<C: ConversationRepository, A: AccountRepository, S: MediaStorage> {
+    list_conversations_use_case: ListConversationsUseCase<C, A, S>,
+}
+
+impl<C: ConversationRepository, A: AccountRepository, S: MediaStorage> ConversationHandlers<C, A, S> {
+    pub fn new(list_conversations_use_case: ListConversationsUseCase<C, A, S>) -> Self {
+        Self { list_conversations_use_case }
+    }
+
+    pub async fn list_conversations(&self, claims: Claims, query: InboxQuery) -> impl Responder {
+        let before = match query.before.map(|cursor| cursor.parse::<MessageCursor>()).transpose() {
+            Ok(before) => before,
+            Err(message) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "Invalid cursor",
+                    "message": message
+                }));
+            }
+        };
+
+        match self.list_conversations_use_case.execute(claims.sub, before, query.limit).await {
+            Ok(page) => HttpResponse::Ok().json(page),
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to list conversations",
+                "message": e.to_string()
+            })),
+        }
+    }
+}
+
+pub fn configure<C: ConversationRepository + 'static, A: AccountRepository + 'static, S: MediaStorage + 'static>(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<ConversationHandlers<C, A, S>>,
+) {
+    cfg.service(
+        web::resource("/conversations")
+            .route(web::get().to(move |handlers: web::Data<ConversationHandlers<C, A, S>>, claims: Claims, query: web::Query<InboxQuery>| async move {
+                handlers.list_conversations(claims, query.into_inner()).await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::domain::entities::conversation::ConversationPage;
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/conversations",
+        tag = "messages",
+        params(
+            ("before" = Option<String>, Query, description = "Cursor from `older`: only conversations last active before it"),
+            ("limit" = Option<i64>, Query, description = "Conversations per page; 20 by default and at most 50"),
+        ),
+        responses(
+            (status = 200, description = "The caller's conversations, most recently active first", body = ConversationPage),
+            (status = 400, description = "The cursor is malformed", body = ErrorResponse),
+            (status = 500, description = "Conversations could not be loaded", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn list_conversations() {}
+}
@@ -0,0 +1,192 @@
+use actix_web::{web, HttpResponse, Responder};
+use diesel::RunQueryDsl;
+use futures::future::OptionFuture;
+use diesel_migrations::MigrationHarness;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::domain::entities::health::{ComponentHealth, HealthReport, HealthStatus};
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::infrastructure::config::database::{DbPool, MIGRATIONS};
+use crate::infrastructure::websocket::user_status_manager::UserStatusManager;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct HealthHandlers {
+    /// `None` when running with in-memory storage; the database checks are skipped.
+    pool: Option<DbPool>,
+    media_storage: Arc<dyn MediaStorage + Send + Sync>,
+    user_status_manager: Arc<UserStatusManager>,
+}
+
+impl HealthHandlers {
+    pub fn new(
+        pool: Option<DbPool>,
+        media_storage: Arc<dyn MediaStorage + Send + Sync>,
+        user_status_manager: Arc<UserStatusManager>,
+    ) -> Self {
+        Self {
+            pool,
+            media_storage,
+            user_status_manager,
+        }
+    }
+
+    pub async fn live(&self) -> impl Responder {
+        HttpResponse::Ok().json(json!({ "status": HealthStatus::Up }))
+    }
+
+    pub async fn ready(&self) -> impl Responder {
+        let (database, migrations, media_storage, websocket) = futures::join!(
+            OptionFuture::from(self.pool.clone().map(check_database)),
+            OptionFuture::from(self.pool.clone().map(check_migrations)),
+            self.check_media_storage(),
+            self.check_websocket(),
+        );
+
+        let mut components = BTreeMap::new();
+        if let (Some(database), Some(migrations)) = (database, migrations) {
+            components.insert("database".to_string(), database);
+            components.insert("migrations".to_string(), migrations);
+        }
+        components.insert("media_storage".to_string(), media_storage);
+        components.insert("websocket".to_string(), websocket);
+
+        let report = HealthReport::from_components(components);
+        match report.status {
+            HealthStatus::Up => HttpResponse::Ok().json(report),
+            HealthStatus::Down => HttpResponse::ServiceUnavailable().json(report),
+        }
+    }
+
+    async fn check_media_storage(&self) -> ComponentHealth {
+        let media_storage = Arc::clone(&self.media_storage);
+        timed(async move {
+            // Round-trip a probe object to prove the backend accepts writes
+            let key = format!(".health/{}", uuid::Uuid::new_v4());
+            media_storage.put(&key, b"ok".to_vec(), "text/plain").await
+                .map_err(|e| format!("Media storage is not writable: {}", e))?;
+            media_storage.delete(&key).await
+                .map_err(|e| format!("Failed to remove probe object: {}", e))?;
+
+            Ok(json!({ "public_url": media_storage.public_url("") }))
+        }).await
+    }
+
+    async fn check_websocket(&self) -> ComponentHealth {
+        let user_status_manager = Arc::clone(&self.user_status_manager);
+        timed(async move {
+            // A write lock held for longer than the timeout means the manager is wedged
+            let connections = tokio::time::timeout(CHECK_TIMEOUT, user_status_manager.connection_count())
+                .await
+                .map_err(|_| "Timed out waiting for connection registry".to_string())?;
+
+            Ok(json!({ "connections": connections }))
+        }).await
+    }
+}
+
+async fn check_database(pool: DbPool) -> ComponentHealth {
+    timed(async move {
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get_timeout(CHECK_TIMEOUT)
+                .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+            diesel::sql_query("SELECT 1")
+                .execute(&mut conn)
+                .map_err(|e| format!("Database error: {}", e))?;
+
+            let state = pool.state();
+            Ok(json!({
+                "connections": state.connections,
+                "idle_connections": state.idle_connections,
+                "max_size": pool.max_size(),
+            }))
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+    }).await
+}
+
+async fn check_migrations(pool: DbPool) -> ComponentHealth {
+    timed(async move {
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get_timeout(CHECK_TIMEOUT)
+                .map_err(|e| format!("Failed to get DB connection: {}", e))?;
+            let pending = conn.pending_migrations(MIGRATIONS)
+                .map_err(|e| format!("Failed to read migrations: {}", e))?;
+
+            if pending.is_empty() {
+                Ok(json!({ "pending": 0 }))
+            } else {
+                let names: Vec<String> = pending.iter().map(|m| m.name().to_string()).collect();
+                Err(format!("{} pending migration(s): {}", names.len(), names.join(", ")))
+            }
+        }).await
+            .map_err(|e| format!("Task failed: {}", e))?
+    }).await
+}
+
+async fn timed<F>(check: F) -> ComponentHealth
+where
+    F: std::future::Future<Output = Result<serde_json::Value, String>>,
+{
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err("Check timed out".to_string()),
+    };
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    match result {
+        Ok(details) => ComponentHealth {
+            status: HealthStatus::Up,
+            latency_ms,
+            details: Some(details),
+            error: None,
+        },
+        Err(error) => ComponentHealth {
+            status: HealthStatus::Down,
+            latency_ms,
+            details: None,
+            error: Some(error),
+        },
+    }
+}
+
+pub fn configure(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<HealthHandlers>,
+) {
+    cfg.service(
+        web::scope("/health")
+            .route("/live", web::get().to(|handlers: web::Data<HealthHandlers>| async move {
+                handlers.live().await
+            }))
+            .route("/ready", web::get().to(|handlers: web::Data<HealthHandlers>| async move {
+                handlers.ready().await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::domain::entities::health::HealthReport;
+
+    #[utoipa::path(
+        get,
+        path = "/health/live",
+        tag = "health",
+        responses((status = 200, description = "Process is running", body = Object))
+    )]
+    pub fn live() {}
+
+    #[utoipa::path(
+        get,
+        path = "/health/ready",
+        tag = "health",
+        responses(
+            (status = 200, description = "Every component is up", body = HealthReport),
+            (status = 503, description = "At least one component is down", body = HealthReport),
+        )
+    )]
+    pub fn ready() {}
+}
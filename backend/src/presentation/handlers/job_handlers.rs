@@ -0,0 +1,61 @@
+use actix_web::{web, HttpResponse, Responder};
+use crate::application::use_cases::job_use_cases::GetJobUseCase;
+use crate::domain::entities::auth::Claims;
+use crate::domain::entities::job::JobNotFound;
+use crate::domain::repositories::job_repository::JobRepository;
+
+pub struct JobHandlers<J: JobRepository> {
+    get_job_use_case: GetJobUseCase<J>,
+}
+
+impl<J: JobRepository> JobHandlers<J> {
+    pub fn new(get_job_use_case: GetJobUseCase<J>) -> Self {
+        Self { get_job_use_case }
+    }
+
+    pub async fn get_job(&self, claims: Claims, job_id: web::Path<i64>) -> impl Responder {
+        match self.get_job_use_case.execute(claims.sub, job_id.into_inner()).await {
+            Ok(job) => HttpResponse::Ok().json(job),
+            Err(e) if e.is::<JobNotFound>() => {
+                HttpResponse::NotFound().json(serde_json::json!({
+                    "error": "Job not found",
+                    "message": e.to_string()
+                }))
+            }
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to get job",
+                "message": e.to_string()
+            })),
+        }
+    }
+}
+
+pub fn configure<J: JobRepository + 'static>(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<JobHandlers<J>>,
+) {
+    cfg.service(
+        web::scope("/jobs")
+            .route("/{job_id}", web::get().to(move |handlers: web::Data<JobHandlers<J>>, claims: Claims, job_id: web::Path<i64>| async move {
+                handlers.get_job(claims, job_id).await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::domain::entities::job::Job;
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/jobs/{job_id}",
+        tag = "jobs",
+        params(("job_id" = i64, Path, description = "Job started by one of the caller's requests")),
+        responses(
+            (status = 200, description = "Current state of the job", body = Job),
+            (status = 404, description = "No such job for the caller", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_job() {}
+}
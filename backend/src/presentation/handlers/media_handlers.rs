@@ -0,0 +1,284 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use actix_web::http::header::{
+    self, CacheControl, CacheDirective, ContentRange, ContentRangeSpec, ContentType,
+    EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch, IfRange, Range,
+};
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+use crate::application::use_cases::media_use_cases::AuthorizeMediaUseCase;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::avatar_repository::AvatarRepository;
+use crate::domain::repositories::media_storage::{MediaObjectInfo, MediaStorage, MediaStorageError};
+use crate::presentation::middleware::auth::decode_claims;
+use crate::infrastructure::storage::signing::{MediaUrlSigner, SignatureError, MEDIA_PATH};
+
+#[derive(Debug, Deserialize)]
+pub struct MediaQuery {
+    pub expires: Option<i64>,
+    pub signature: Option<String>,
+}
+
+/// How the request proved it may read the object.
+enum Access {
+    /// Signed URL, valid until the contained unix timestamp.
+    Signed { expires: i64 },
+    /// Bearer token of a user allowed to see the object, such as a participant in a
+    /// conversation it was sent in.
+    Authenticated,
+}
+
+pub struct MediaHandlers<S: MediaStorage, A: AttachmentRepository, T: AvatarRepository> {
+    media_storage: S,
+    signer: Arc<MediaUrlSigner>,
+    secret_key: String,
+    authorize_media_use_case: AuthorizeMediaUseCase<A, T>,
+}
+
+impl<S: MediaStorage, A: AttachmentRepository, T: AvatarRepository> MediaHandlers<S, A, T> {
+    pub fn new(
+        media_storage: S,
+        signer: Arc<MediaUrlSigner>,
+        secret_key: String,
+        authorize_media_use_case: AuthorizeMediaUseCase<A, T>,
+    ) -> Self {
+        Self {
+            media_storage,
+            signer,
+            secret_key,
+            authorize_media_use_case,
+        }
+    }
+
+    pub async fn serve(&self, req: HttpRequest, key: String, query: MediaQuery, head_only: bool) -> HttpResponse {
+        let access = match self.authorize(&req, &key, &query).await {
+            Ok(access) => access,
+            Err(response) => return response,
+        };
+
+        let info = match self.media_storage.stat(&key).await {
+            Ok(info) => info,
+            // Invalid keys get the same answer as missing ones
+            Err(MediaStorageError::NotFound(_)) | Err(MediaStorageError::InvalidKey(_)) => {
+                return HttpResponse::NotFound().json(json!({
+                    "error": "Media not found",
+                    "message": format!("No media stored under {}", key)
+                }));
+            }
+            Err(e) => return storage_error(e),
+        };
+
+        let etag = entity_tag(&info);
+        let mut builder = HttpResponse::Ok();
+        builder
+            .insert_header(header::ETag(etag.clone()))
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .insert_header(cache_control(&access));
+        if let Some(last_modified) = info.last_modified {
+            builder.insert_header(header::LastModified(HttpDate::from(last_modified)));
+        }
+
+        if is_not_modified(&req, &etag, info.last_modified) {
+            return builder.status(actix_web::http::StatusCode::NOT_MODIFIED).finish();
+        }
+
+        builder.insert_header(ContentType(info.content_type.parse().unwrap_or(mime::APPLICATION_OCTET_STREAM)));
+
+        let (start, end) = match requested_range(&req, &etag, info.size) {
+            RequestedRange::Full => (0, info.size.saturating_sub(1)),
+            RequestedRange::Partial(start, end) => {
+                builder
+                    .status(actix_web::http::StatusCode::PARTIAL_CONTENT)
+                    .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                        range: Some((start, end)),
+                        instance_length: Some(info.size),
+                    }));
+                (start, end)
+            }
+            RequestedRange::Unsatisfiable => {
+                return HttpResponse::RangeNotSatisfiable()
+                    .insert_header(ContentRange(ContentRangeSpec::Bytes {
+                        range: None,
+                        instance_length: Some(info.size),
+                    }))
+                    .finish();
+            }
+        };
+
+        if head_only || info.size == 0 {
+            return builder.no_chunking(if info.size == 0 { 0 } else { end - start + 1 }).finish();
+        }
+
+        let body = if start == 0 && end + 1 == info.size {
+            self.media_storage.get(&key).await
+        } else {
+            self.media_storage.get_range(&key, start, end).await
+        };
+
+        match body {
+            Ok(bytes) => builder.body(bytes),
+            Err(e) => storage_error(e),
+        }
+    }
+
+    async fn authorize(&self, req: &HttpRequest, key: &str, query: &MediaQuery) -> Result<Access, HttpResponse> {
+        if let Some(signature) = &query.signature {
+            let expires = query.expires.ok_or_else(|| forbidden("Signed media URLs need an expiry"))?;
+            return match self.signer.verify(key, expires, signature) {
+                Ok(()) => Ok(Access::Signed { expires }),
+                Err(SignatureError::Expired) => Err(forbidden("Media URL has expired")),
+                Err(SignatureError::Invalid) => Err(forbidden("Media URL signature is invalid")),
+            };
+        }
+
+        let token = req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        let Some(Ok(claims)) = token.map(|token| decode_claims(token, &self.secret_key)) else {
+            return Err(HttpResponse::Unauthorized().json(json!({
+                "error": "Unauthorized",
+                "message": "Media requires a signed URL or a valid bearer token"
+            })));
+        };
+
+        match self.authorize_media_use_case.execute(claims.sub, key).await {
+            Ok(true) => Ok(Access::Authenticated),
+            Ok(false) => Err(forbidden("Media is only served by signed URL to users it was not shared with")),
+            Err(e) => Err(HttpResponse::InternalServerError().json(json!({
+                "error": "Failed to authorize media access",
+                "message": e.to_string()
+            }))),
+        }
+    }
+}
+
+fn forbidden(message: &str) -> HttpResponse {
+    HttpResponse::Forbidden().json(json!({
+        "error": "Forbidden",
+        "message": message
+    }))
+}
+
+fn storage_error(e: MediaStorageError) -> HttpResponse {
+    HttpResponse::InternalServerError().json(json!({
+        "error": "Failed to read media",
+        "message": e.to_string()
+    }))
+}
+
+fn entity_tag(info: &MediaObjectInfo) -> EntityTag {
+    info.etag.parse().unwrap_or_else(|_| EntityTag::new_strong(info.etag.trim_matches('"').to_string()))
+}
+
+fn cache_control(access: &Access) -> CacheControl {
+    match access {
+        // Shared caches must not outlive the signature
+        Access::Signed { expires } => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64);
+            CacheControl(vec![
+                CacheDirective::Private,
+                CacheDirective::MaxAge((*expires - now).max(0) as u32),
+            ])
+        }
+        Access::Authenticated => CacheControl(vec![CacheDirective::Private, CacheDirective::NoCache]),
+    }
+}
+
+fn is_not_modified(req: &HttpRequest, etag: &EntityTag, last_modified: Option<SystemTime>) -> bool {
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110, 13.1.3)
+    if req.headers().contains_key(header::IF_NONE_MATCH) {
+        return match IfNoneMatch::parse(req) {
+            Ok(IfNoneMatch::Any) => true,
+            Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+            Err(_) => false,
+        };
+    }
+
+    match (IfModifiedSince::parse(req), last_modified) {
+        (Ok(IfModifiedSince(since)), Some(last_modified)) => whole_seconds(last_modified) <= whole_seconds(since.into()),
+        _ => false,
+    }
+}
+
+enum RequestedRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+fn requested_range(req: &HttpRequest, etag: &EntityTag, size: u64) -> RequestedRange {
+    let Some(range) = req.headers().get(header::RANGE).and_then(|value| value.to_str().ok()) else {
+        return RequestedRange::Full;
+    };
+
+    // A stale If-Range means the client's partial copy is of other content; send it all
+    if req.headers().contains_key(header::IF_RANGE) {
+        let current = match IfRange::parse(req) {
+            Ok(IfRange::EntityTag(tag)) => tag.strong_eq(etag),
+            _ => false,
+        };
+        if !current {
+            return RequestedRange::Full;
+        }
+    }
+
+    match range.parse::<Range>() {
+        // Multipart byte ranges are not supported; answering in full is always allowed
+        Ok(Range::Bytes(specs)) if specs.len() == 1 => match specs[0].to_satisfiable_range(size) {
+            Some((start, end)) => RequestedRange::Partial(start, end),
+            None => RequestedRange::Unsatisfiable,
+        },
+        Ok(Range::Bytes(specs)) if specs.iter().all(|spec| spec.to_satisfiable_range(size).is_none()) => {
+            RequestedRange::Unsatisfiable
+        }
+        _ => RequestedRange::Full,
+    }
+}
+
+fn whole_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+pub fn configure<S: MediaStorage + 'static, A: AttachmentRepository + 'static, T: AvatarRepository + 'static>(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<MediaHandlers<S, A, T>>,
+) {
+    cfg.service(
+        web::resource(format!("{}/{{key:.+}}", MEDIA_PATH))
+            .route(web::get().to(move |handlers: web::Data<MediaHandlers<S, A, T>>, req: HttpRequest, key: web::Path<String>, query: web::Query<MediaQuery>| async move {
+                handlers.serve(req, key.into_inner(), query.into_inner(), false).await
+            }))
+            .route(web::head().to(move |handlers: web::Data<MediaHandlers<S, A, T>>, req: HttpRequest, key: web::Path<String>, query: web::Query<MediaQuery>| async move {
+                handlers.serve(req, key.into_inner(), query.into_inner(), true).await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        get,
+        path = "/media/{key}",
+        tag = "media",
+        params(
+            ("key" = String, Path, description = "Storage key, e.g. `avatars/<sha256>/original.png`, or `avatars/<sha256>/<crop>/300.webp` for one of its variants"),
+            ("expires" = Option<i64>, Query, description = "Unix time the signed URL expires at"),
+            ("signature" = Option<String>, Query, description = "Hex HMAC-SHA256 signature; without it a bearer token is required"),
+        ),
+        responses(
+            (status = 200, description = "Object content, with ETag, Last-Modified and Cache-Control", body = Vec<u8>, content_type = "application/octet-stream"),
+            (status = 206, description = "Requested byte range", body = Vec<u8>, content_type = "application/octet-stream"),
+            (status = 304, description = "Cached copy is current"),
+            (status = 401, description = "Unsigned request without a valid bearer token", body = ErrorResponse),
+            (status = 403, description = "Signature is invalid or expired, or the caller may not see the object: bearer tokens only reach attachments shared with the caller and the caller's own avatars", body = ErrorResponse),
+            (status = 404, description = "No such object", body = ErrorResponse),
+            (status = 416, description = "Range lies outside the object"),
+        ),
+        security((), ("bearer_auth" = []))
+    )]
+    pub fn get_media() {}
+}
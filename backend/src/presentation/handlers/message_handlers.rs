@@ -1,88 +1,444 @@
+use std::collections::BTreeMap;
 use actix_web::{web, HttpResponse};
-use crate::application::use_cases::message_use_cases::{SendMessageUseCase, GetMessagesUseCase};
+use serde::Deserialize;
+use crate::application::use_cases::message_use_cases::{
+    CheckConversationUseCase, DeleteMessageUseCase, EditMessageUseCase, GetMessageEditsUseCase, GetMessagesUseCase, MarkDeliveredUseCase,
+    MarkReadUseCase, SendMessageUseCase,
+};
+use crate::domain::entities::auth::Claims;
+use crate::domain::entities::message::{
+    DatabaseMessage, EditMessageDto, MarkReadDto, MarkReadError, MessageChangeError, MessageCursor, ReadReceipt,
+    SendMessageDto, SendMessageError, WebSocketMessage,
+};
+use crate::infrastructure::metrics::metrics;
 use crate::infrastructure::websocket::realtime_message_manager::RealtimeMessageManager;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
 use crate::domain::repositories::message_repository::MessageRepository;
 
-pub struct MessageHandlers<T: MessageRepository> {
-    send_message_use_case: SendMessageUseCase<T>,
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteQuery {
+    #[serde(default)]
+    pub for_everyone: bool,
+}
+
+pub struct MessageHandlers<T: MessageRepository, A: AttachmentRepository> {
+    send_message_use_case: SendMessageUseCase<T, A>,
     get_messages_use_case: GetMessagesUseCase<T>,
+    mark_delivered_use_case: MarkDeliveredUseCase<T>,
+    mark_read_use_case: MarkReadUseCase<T>,
+    edit_message_use_case: EditMessageUseCase<T>,
+    delete_message_use_case: DeleteMessageUseCase<T>,
+    get_message_edits_use_case: GetMessageEditsUseCase<T>,
+    check_conversation_use_case: CheckConversationUseCase<T>,
     realtime_message_manager: RealtimeMessageManager,
 }
 
-impl<T: MessageRepository> MessageHandlers<T> {
+impl<T: MessageRepository, A: AttachmentRepository> MessageHandlers<T, A> {
+    // One use case per operation, like the other handlers
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        send_message_use_case: SendMessageUseCase<T>,
+        send_message_use_case: SendMessageUseCase<T, A>,
         get_messages_use_case: GetMessagesUseCase<T>,
+        mark_delivered_use_case: MarkDeliveredUseCase<T>,
+        mark_read_use_case: MarkReadUseCase<T>,
+        edit_message_use_case: EditMessageUseCase<T>,
+        delete_message_use_case: DeleteMessageUseCase<T>,
+        get_message_edits_use_case: GetMessageEditsUseCase<T>,
+        check_conversation_use_case: CheckConversationUseCase<T>,
         realtime_message_manager: RealtimeMessageManager,
     ) -> Self {
         Self {
             send_message_use_case,
             get_messages_use_case,
+            mark_delivered_use_case,
+            mark_read_use_case,
+            edit_message_use_case,
+            delete_message_use_case,
+            get_message_edits_use_case,
+            check_conversation_use_case,
             realtime_message_manager,
         }
     }
 
-    // Example handler methods
-    pub async fn send_message(
+    /// Whether the two users have a conversation, so one may tell the other they are typing.
+    pub async fn in_conversation(&self, user_id: i32, counterpart_id: i32) -> Result<bool, String> {
+        self.check_conversation_use_case.execute(user_id, counterpart_id).await
+    }
+
+    /// Stores a message and queues it for the receiver. Their socket sends it now if they are
+    /// connected, or when they reconnect, and then marks it delivered. Either way the sender
+    /// finds the message in their history.
+    pub async fn deliver(
         &self,
         sender_id: i32,
         receiver_id: i32,
         content: String,
-    ) -> Result<HttpResponse, actix_web::Error> {
-        // Save to database
+        attachment_id: Option<i32>,
+        client_msg_id: Option<String>,
+    ) -> Result<DatabaseMessage, SendMessageError> {
         let message = self.send_message_use_case
-            .execute(sender_id, receiver_id, content.clone())
-            .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+            .execute(sender_id, receiver_id, content, attachment_id)
+            .await?;
+        // The message is stored either way, and the receiver can still load it from history
+        self.realtime_message_manager.send_message(&message, client_msg_id).await.ok();
 
-        // Send real-time message
-        self.realtime_message_manager
-            .send_message(sender_id, receiver_id, content)
-            .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        Ok(message)
+    }
+
+    /// Records messages sent to `receiver_id`'s socket as delivered and queues a `Delivered`
+    /// event for each sender. Returns the messages that were not delivered before.
+    pub async fn confirm_delivery(&self, receiver_id: i32, message_ids: &[i32]) -> Result<Vec<DatabaseMessage>, String> {
+        let delivered = self.mark_delivered_use_case.execute(message_ids).await?;
+        metrics().websocket_messages_total.with_label_values(&["delivered"]).inc_by(delivered.len() as u64);
+
+        let mut by_sender: BTreeMap<i32, Vec<&DatabaseMessage>> = BTreeMap::new();
+        for message in &delivered {
+            by_sender.entry(message.sender_id).or_default().push(message);
+        }
+        for (sender_id, messages) in by_sender {
+            let Some(delivered_at) = messages[0].delivered_at else { continue };
+            let event = WebSocketMessage::Delivered {
+                by_user_id: receiver_id,
+                message_ids: messages.iter().map(|message| message.id).collect(),
+                delivered_at,
+            };
+            self.realtime_message_manager.publish(sender_id, event).await.ok();
+        }
+
+        Ok(delivered)
+    }
+
+    /// Marks the reader's conversation with `counterpart_id` read up to a message, and queues
+    /// a `Read` event for the counterpart when that changed anything.
+    pub async fn read_up_to(&self, reader_id: i32, counterpart_id: i32, up_to_message_id: i32) -> Result<ReadReceipt, MarkReadError> {
+        let receipt = self.mark_read_use_case.execute(reader_id, counterpart_id, up_to_message_id).await?;
+        if !receipt.message_ids.is_empty() {
+            let event = WebSocketMessage::Read {
+                by_user_id: reader_id,
+                message_ids: receipt.message_ids.clone(),
+                read_at: receipt.read_at,
+            };
+            self.realtime_message_manager.publish(counterpart_id, event).await.ok();
+        }
+
+        Ok(receipt)
+    }
+
+    pub async fn mark_read(&self, claims: Claims, counterpart_id: i32, dto: MarkReadDto) -> HttpResponse {
+        match self.read_up_to(claims.sub, counterpart_id, dto.up_to_message_id).await {
+            Ok(receipt) => HttpResponse::Ok().json(receipt),
+            Err(e @ MarkReadError::MessageNotFound(_)) => HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Message not found",
+                "message": e.to_string()
+            })),
+            Err(e @ MarkReadError::Database(_)) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to mark conversation read",
+                "message": e.to_string()
+            })),
+        }
+    }
+
+    pub async fn send_message(&self, claims: Claims, receiver_id: i32, dto: SendMessageDto) -> HttpResponse {
+        match self.deliver(claims.sub, receiver_id, dto.content, dto.attachment_id, None).await {
+            Ok(message) => HttpResponse::Created().json(message),
+            Err(e) => {
+                let (mut builder, error) = match e {
+                    SendMessageError::Empty | SendMessageError::AttachmentNotFound(_) => (HttpResponse::BadRequest(), "Invalid message"),
+                    SendMessageError::RecipientNotFound(_) => (HttpResponse::NotFound(), "Recipient not found"),
+                    SendMessageError::Database(_) => (HttpResponse::InternalServerError(), "Failed to send message"),
+                };
+                builder.json(serde_json::json!({
+                    "error": error,
+                    "message": e.to_string()
+                }))
+            }
+        }
+    }
+
+    /// Edits a message and queues a `MessageEdited` event for both participants, so the
+    /// sender's other sessions follow along too.
+    pub async fn edit_message(&self, claims: Claims, message_id: i32, dto: EditMessageDto) -> HttpResponse {
+        let message = match self.edit_message_use_case.execute(claims.sub, message_id, dto.content).await {
+            Ok(message) => message,
+            Err(e) => return change_error_response(e, "Failed to edit message"),
+        };
+
+        if let Some(edited_at) = message.edited_at {
+            let event = WebSocketMessage::MessageEdited {
+                message_id: message.id,
+                from_user_id: message.sender_id,
+                to_user_id: message.receiver_id,
+                content: message.content.clone(),
+                edited_at,
+            };
+            for user_id in participants(&message) {
+                self.realtime_message_manager.publish(user_id, event.clone()).await.ok();
+            }
+        }
+
+        HttpResponse::Ok().json(message)
+    }
+
+    /// Deletes a message for the caller or, with `for_everyone`, for both participants, whose
+    /// queues then lose every event still carrying its content. Everyone it was deleted for
+    /// gets a `MessageDeleted` event.
+    pub async fn delete_message(&self, claims: Claims, message_id: i32, query: DeleteQuery) -> HttpResponse {
+        let deletion = match self.delete_message_use_case.execute(claims.sub, message_id, query.for_everyone).await {
+            Ok(deletion) => deletion,
+            Err(e) => return change_error_response(e, "Failed to delete message"),
+        };
+
+        let recipients = if deletion.for_everyone {
+            let participants = participants(&deletion.message);
+            for user_id in &participants {
+                self.realtime_message_manager.retract_message(*user_id, message_id).await.ok();
+            }
+            participants
+        } else {
+            vec![claims.sub]
+        };
+        let event = WebSocketMessage::MessageDeleted {
+            message_id,
+            for_everyone: deletion.for_everyone,
+            deleted_at: deletion.deleted_at,
+        };
+        for user_id in recipients {
+            self.realtime_message_manager.publish(user_id, event.clone()).await.ok();
+        }
 
-        Ok(HttpResponse::Ok().json(message))
+        HttpResponse::NoContent().finish()
+    }
+
+    pub async fn get_message_edits(&self, claims: Claims, message_id: i32) -> HttpResponse {
+        match self.get_message_edits_use_case.execute(claims.sub, message_id).await {
+            Ok(edits) => HttpResponse::Ok().json(edits),
+            Err(e) => change_error_response(e, "Failed to load message edits"),
+        }
     }
 
     pub async fn get_messages(
         &self,
-        user1_id: i32,
-        user2_id: i32,
+        claims: Claims,
+        counterpart_id: i32,
+        query: HistoryQuery,
     ) -> Result<HttpResponse, actix_web::Error> {
-        let messages = self.get_messages_use_case
-            .execute(user1_id, user2_id)
+        let (before, after) = match (parse_cursor(query.before), parse_cursor(query.after)) {
+            (Ok(before), Ok(after)) => (before, after),
+            (Err(message), _) | (_, Err(message)) => {
+                return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "Invalid cursor",
+                    "message": message
+                })));
+            }
+        };
+
+        let page = self.get_messages_use_case
+            .execute(claims.sub, counterpart_id, before, after, query.limit)
             .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+            .map_err(actix_web::error::ErrorInternalServerError)?;
 
-        Ok(HttpResponse::Ok().json(messages))
+        Ok(HttpResponse::Ok().json(page))
     }
 }
 
+/// Both participants of a message, once each for a message to oneself.
+fn participants(message: &DatabaseMessage) -> Vec<i32> {
+    let mut user_ids = vec![message.sender_id, message.receiver_id];
+    user_ids.dedup();
+    user_ids
+}
+
+fn change_error_response(e: MessageChangeError, failure: &str) -> HttpResponse {
+    let (mut builder, error) = match e {
+        MessageChangeError::NotFound(_) => (HttpResponse::NotFound(), "Message not found"),
+        MessageChangeError::NotSender(_) => (HttpResponse::Forbidden(), "Not the sender"),
+        MessageChangeError::EditWindowClosed(_) => (HttpResponse::Conflict(), "Edit window closed"),
+        MessageChangeError::Empty => (HttpResponse::BadRequest(), "Invalid message"),
+        MessageChangeError::Database(_) => (HttpResponse::InternalServerError(), failure),
+    };
+    builder.json(serde_json::json!({
+        "error": error,
+        "message": e.to_string()
+    }))
+}
+
+fn parse_cursor(cursor: Option<String>) -> Result<Option<MessageCursor>, String> {
+    cursor.map(|cursor| cursor.parse()).transpose()
+}
+
 // Add configuration function for routes
-pub fn configure<T: MessageRepository + 'static>(
+pub fn configure<T: MessageRepository + 'static, A: AttachmentRepository + 'static>(
     cfg: &mut web::ServiceConfig,
-    _handlers: web::Data<MessageHandlers<T>>,
+    _handlers: web::Data<MessageHandlers<T, A>>,
 ) {
     cfg.service(
-        web::scope("/messages")
-            .route("", web::post().to(move |
-                sender_id: web::Path<i32>,
+        web::resource("/conversations/{user_id}/messages")
+            .route(web::post().to(move |
+                handlers: web::Data<MessageHandlers<T, A>>,
+                claims: Claims,
                 receiver_id: web::Path<i32>,
-                content: web::Json<String>,
-                handlers: web::Data<MessageHandlers<T>>,
+                dto: web::Json<SendMessageDto>,
+            | async move {
+                handlers.send_message(claims, receiver_id.into_inner(), dto.into_inner()).await
+            }))
+            .route(web::get().to(move |
+                handlers: web::Data<MessageHandlers<T, A>>,
+                claims: Claims,
+                counterpart_id: web::Path<i32>,
+                query: web::Query<HistoryQuery>,
             | async move {
-                handlers.send_message(
-                    sender_id.into_inner(),
-                    receiver_id.into_inner(),
-                    content.into_inner(),
-                ).await
+                handlers.get_messages(claims, counterpart_id.into_inner(), query.into_inner()).await
             }))
-            .route("/{user1_id}/{user2_id}", web::get().to(move |
-                path: web::Path<(i32, i32)>,
-                handlers: web::Data<MessageHandlers<T>>,
+    );
+    cfg.service(
+        web::resource("/conversations/{user_id}/read")
+            .route(web::post().to(move |
+                handlers: web::Data<MessageHandlers<T, A>>,
+                claims: Claims,
+                counterpart_id: web::Path<i32>,
+                dto: web::Json<MarkReadDto>,
             | async move {
-                let (user1_id, user2_id) = path.into_inner();
-                handlers.get_messages(user1_id, user2_id).await
+                handlers.mark_read(claims, counterpart_id.into_inner(), dto.into_inner()).await
             }))
     );
-}
\ No newline at end of file
+    cfg.service(
+        web::scope("/messages")
+            .route("/{message_id}/edits", web::get().to(move |
+                handlers: web::Data<MessageHandlers<T, A>>,
+                claims: Claims,
+                message_id: web::Path<i32>,
+            | async move {
+                handlers.get_message_edits(claims, message_id.into_inner()).await
+            }))
+            .route("/{message_id}", web::patch().to(move |
+                handlers: web::Data<MessageHandlers<T, A>>,
+                claims: Claims,
+                message_id: web::Path<i32>,
+                dto: web::Json<EditMessageDto>,
+            | async move {
+                handlers.edit_message(claims, message_id.into_inner(), dto.into_inner()).await
+            }))
+            .route("/{message_id}", web::delete().to(move |
+                handlers: web::Data<MessageHandlers<T, A>>,
+                claims: Claims,
+                message_id: web::Path<i32>,
+                query: web::Query<DeleteQuery>,
+            | async move {
+                handlers.delete_message(claims, message_id.into_inner(), query.into_inner()).await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::domain::entities::message::{
+        DatabaseMessage, EditMessageDto, MarkReadDto, MessageEdit, MessagePage, ReadReceipt, SendMessageDto,
+    };
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/conversations/{user_id}/messages",
+        tag = "messages",
+        params(("user_id" = i32, Path, description = "Recipient; the sender is the caller")),
+        request_body = SendMessageDto,
+        responses(
+            (status = 201, description = "Stored message. The recipient is sent it as a `NewMessage` event, right away if connected or else when they reconnect", body = DatabaseMessage),
+            (status = 400, description = "The message is empty or the attachment is not the caller's", body = ErrorResponse),
+            (status = 404, description = "No such recipient", body = ErrorResponse),
+            (status = 500, description = "The message could not be stored", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn send_message() {}
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/conversations/{user_id}/read",
+        tag = "messages",
+        params(("user_id" = i32, Path, description = "The other participant; the reader is the caller")),
+        request_body = MarkReadDto,
+        responses(
+            (status = 200, description = "Messages newly marked read. If there are any, the other participant also gets them as a `Read` event", body = ReadReceipt),
+            (status = 404, description = "The message is not part of this conversation", body = ErrorResponse),
+            (status = 500, description = "The conversation could not be marked read", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn mark_read() {}
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/conversations/{user_id}/messages",
+        tag = "messages",
+        params(
+            ("user_id" = i32, Path, description = "The other participant; the viewer is the caller"),
+            ("before" = Option<String>, Query, description = "Cursor from `older`: only messages older than it"),
+            ("after" = Option<String>, Query, description = "Cursor from `newer`: only messages newer than it. Without `before`, the page starts right after it"),
+            ("limit" = Option<i64>, Query, description = "Messages per page; 50 by default and at most 100"),
+        ),
+        responses(
+            (status = 200, description = "A page of the conversation, newest first. Messages the caller deleted for themselves are left out; ones deleted for everyone stay as tombstones with `deleted_at` set", body = MessagePage),
+            (status = 400, description = "A cursor is malformed", body = ErrorResponse),
+            (status = 500, description = "History could not be loaded", body = String),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_messages() {}
+
+    #[utoipa::path(
+        patch,
+        path = "/api/v1/messages/{message_id}",
+        tag = "messages",
+        params(("message_id" = i32, Path, description = "A message the caller sent")),
+        request_body = EditMessageDto,
+        responses(
+            (status = 200, description = "The edited message. Both participants are sent a `MessageEdited` event", body = DatabaseMessage),
+            (status = 400, description = "Neither text nor an attachment would be left", body = ErrorResponse),
+            (status = 403, description = "The caller received the message rather than sent it", body = ErrorResponse),
+            (status = 404, description = "No such message in the caller's conversations, or it was deleted", body = ErrorResponse),
+            (status = 409, description = "The edit window has passed", body = ErrorResponse),
+            (status = 500, description = "The message could not be edited", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn edit_message() {}
+
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/messages/{message_id}",
+        tag = "messages",
+        params(
+            ("message_id" = i32, Path, description = "A message in one of the caller's conversations"),
+            ("for_everyone" = Option<bool>, Query, description = "Replace the message with a tombstone for both participants; only its sender may. By default it is only hidden from the caller"),
+        ),
+        responses(
+            (status = 204, description = "Deleted. Whoever it was deleted for is sent a `MessageDeleted` event"),
+            (status = 403, description = "Only the sender can delete a message for everyone", body = ErrorResponse),
+            (status = 404, description = "No such message in the caller's conversations", body = ErrorResponse),
+            (status = 500, description = "The message could not be deleted", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn delete_message() {}
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/messages/{message_id}/edits",
+        tag = "messages",
+        params(("message_id" = i32, Path, description = "A message in one of the caller's conversations")),
+        responses(
+            (status = 200, description = "What the message said before each edit, oldest first", body = Vec<MessageEdit>),
+            (status = 404, description = "No such message in the caller's conversations", body = ErrorResponse),
+            (status = 500, description = "The edits could not be loaded", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_message_edits() {}
+}
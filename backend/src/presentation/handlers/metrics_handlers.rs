@@ -0,0 +1,62 @@
+use actix_web::{web, HttpResponse, Responder};
+use std::sync::Arc;
+use crate::infrastructure::config::database::DbPool;
+use crate::infrastructure::metrics::metrics;
+use crate::infrastructure::websocket::user_status_manager::UserStatusManager;
+
+pub struct MetricsHandlers {
+    pool: Option<DbPool>,
+    user_status_manager: Arc<UserStatusManager>,
+}
+
+impl MetricsHandlers {
+    pub fn new(pool: Option<DbPool>, user_status_manager: Arc<UserStatusManager>) -> Self {
+        Self {
+            pool,
+            user_status_manager,
+        }
+    }
+
+    pub async fn metrics(&self) -> impl Responder {
+        // Gauges are sampled at scrape time instead of being tracked on every change
+        if let Some(pool) = &self.pool {
+            let state = pool.state();
+            let pool_metrics = &metrics().db_pool_connections;
+            pool_metrics.with_label_values(&["active"]).set(i64::from(state.connections - state.idle_connections));
+            pool_metrics.with_label_values(&["idle"]).set(i64::from(state.idle_connections));
+            pool_metrics.with_label_values(&["max"]).set(i64::from(pool.max_size()));
+        }
+
+        let connections = self.user_status_manager.connection_count().await;
+        metrics().websocket_connections.set(connections as i64);
+
+        match metrics().render() {
+            Ok(body) => HttpResponse::Ok()
+                .content_type("text/plain; version=0.0.4; charset=utf-8")
+                .body(body),
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to render metrics",
+                "message": e
+            })),
+        }
+    }
+}
+
+pub fn configure(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<MetricsHandlers>,
+) {
+    cfg.route("/metrics", web::get().to(|handlers: web::Data<MetricsHandlers>| async move {
+        handlers.metrics().await
+    }));
+}
+
+pub mod doc {
+    #[utoipa::path(
+        get,
+        path = "/metrics",
+        tag = "health",
+        responses((status = 200, description = "Prometheus text exposition format", body = String, content_type = "text/plain"))
+    )]
+    pub fn metrics() {}
+}
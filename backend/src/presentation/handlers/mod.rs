@@ -1,6 +1,14 @@
 pub mod user_handlers;
-pub(crate) mod auth_handlers;
+pub mod auth_handlers;
 pub mod account_handlers;
 pub mod ws_handlers;
 pub mod message_handlers;
-pub mod avatar_handlers;
\ No newline at end of file
+pub mod avatar_handlers;
+pub mod media_handlers;
+pub mod health_handlers;
+pub mod metrics_handlers;
+
+pub mod job_handlers;
+pub mod upload_handlers;
+pub mod attachment_handlers;
+pub mod conversation_handlers;
\ No newline at end of file
@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use actix_web::error::PayloadError;
+use actix_web::http::header::{self, CacheControl, CacheDirective, HeaderName, HeaderValue, HttpDate};
+use actix_web::http::Method;
+use actix_web::{web, HttpRequest, HttpResponse, HttpResponseBuilder};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use uuid::Uuid;
+use crate::application::use_cases::upload_use_cases::{
+    AppendUploadUseCase, CreateUploadUseCase, GetUploadUseCase, TerminateUploadUseCase,
+};
+use crate::domain::entities::auth::Claims;
+use crate::domain::entities::avatar::InvalidCrop;
+use crate::domain::entities::media::UploadRejection;
+use crate::domain::entities::upload::{Upload, UploadError};
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::presentation::handlers::avatar_handlers::{invalid_crop, rejected_upload};
+
+/// The only tus protocol version spoken.
+pub const TUS_VERSION: &str = "1.0.0";
+const TUS_EXTENSIONS: &str = "creation,expiration,termination";
+/// Content type every `PATCH` body must declare.
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+pub const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+pub const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+pub const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+pub const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+pub const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+pub const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+pub const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+pub const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+/// Resumable uploads following tus 1.0 with the creation, expiration and termination
+/// extensions. Uploads are created with their full length and a `purpose` in
+/// `Upload-Metadata`; the request that completes one hands the file on.
+pub struct UploadHandlers<R: UploadRepository, S: MediaStorage, U: AccountRepository, J: JobRepository, A: AttachmentRepository> {
+    create_upload_use_case: CreateUploadUseCase<R>,
+    get_upload_use_case: GetUploadUseCase<R>,
+    append_upload_use_case: AppendUploadUseCase<R, S, U, J, A>,
+    terminate_upload_use_case: TerminateUploadUseCase<R, S>,
+}
+
+impl<R: UploadRepository, S: MediaStorage, U: AccountRepository, J: JobRepository, A: AttachmentRepository> UploadHandlers<R, S, U, J, A> {
+    pub fn new(
+        create_upload_use_case: CreateUploadUseCase<R>,
+        get_upload_use_case: GetUploadUseCase<R>,
+        append_upload_use_case: AppendUploadUseCase<R, S, U, J, A>,
+        terminate_upload_use_case: TerminateUploadUseCase<R, S>,
+    ) -> Self {
+        Self {
+            create_upload_use_case,
+            get_upload_use_case,
+            append_upload_use_case,
+            terminate_upload_use_case,
+        }
+    }
+
+    pub async fn options(&self) -> HttpResponse {
+        HttpResponse::NoContent()
+            .insert_header((TUS_RESUMABLE, TUS_VERSION))
+            .insert_header((TUS_VERSION_HEADER, TUS_VERSION))
+            .insert_header((TUS_EXTENSION, TUS_EXTENSIONS))
+            .insert_header((TUS_MAX_SIZE, self.create_upload_use_case.max_bytes()))
+            .finish()
+    }
+
+    pub async fn create_upload(&self, req: HttpRequest, claims: Claims) -> HttpResponse {
+        if let Err(response) = check_version(&req) {
+            return response;
+        }
+
+        let length = match req.headers().get(UPLOAD_LENGTH) {
+            Some(value) => match header_number(value) {
+                Some(length) => length,
+                None => return upload_error(Box::new(UploadError::Invalid("Upload-Length must be a whole number".to_string())), "Invalid upload"),
+            },
+            None => return upload_error(Box::new(UploadError::Invalid("Upload-Length is required".to_string())), "Invalid upload"),
+        };
+        let metadata = match parse_metadata(req.headers().get(UPLOAD_METADATA)) {
+            Ok(metadata) => metadata,
+            Err(e) => return upload_error(Box::new(e), "Invalid upload"),
+        };
+
+        match self.create_upload_use_case.execute(claims.sub, length, metadata).await {
+            Ok(upload) => tus(HttpResponse::Created())
+                .insert_header((header::LOCATION, format!("/api/v1/uploads/{}", upload.id)))
+                .insert_header((UPLOAD_OFFSET, upload.offset))
+                .insert_header((UPLOAD_EXPIRES, expires(&upload)))
+                .finish(),
+            Err(e) => upload_error(e, "Failed to create upload"),
+        }
+    }
+
+    pub async fn upload_offset(&self, req: HttpRequest, claims: Claims, upload_id: web::Path<Uuid>) -> HttpResponse {
+        if let Err(response) = check_version(&req) {
+            return response;
+        }
+
+        match self.get_upload_use_case.execute(claims.sub, upload_id.into_inner()).await {
+            Ok(upload) => {
+                let mut response = tus(HttpResponse::Ok());
+                response
+                    .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+                    .insert_header((UPLOAD_OFFSET, upload.offset))
+                    .insert_header((UPLOAD_LENGTH, upload.length))
+                    .insert_header((UPLOAD_EXPIRES, expires(&upload)));
+                if !upload.metadata.is_empty() {
+                    response.insert_header((UPLOAD_METADATA, encode_metadata(&upload.metadata)));
+                }
+                response.finish()
+            }
+            Err(e) => upload_error(e, "Failed to get upload"),
+        }
+    }
+
+    pub async fn get_upload(&self, claims: Claims, upload_id: web::Path<Uuid>) -> HttpResponse {
+        match self.get_upload_use_case.execute(claims.sub, upload_id.into_inner()).await {
+            Ok(upload) => HttpResponse::Ok().json(upload),
+            Err(e) => upload_error(e, "Failed to get upload"),
+        }
+    }
+
+    pub async fn append(&self, req: HttpRequest, claims: Claims, upload_id: web::Path<Uuid>, payload: web::Payload) -> HttpResponse {
+        if let Err(response) = check_version(&req) {
+            return response;
+        }
+
+        if req.headers().get(header::CONTENT_TYPE).is_none_or(|value| value != OFFSET_OCTET_STREAM) {
+            return tus_response(HttpResponse::UnsupportedMediaType().json(serde_json::json!({
+                "error": "Invalid content type",
+                "message": format!("Upload chunks must be sent as {}", OFFSET_OCTET_STREAM)
+            })));
+        }
+        let Some(offset) = req.headers().get(UPLOAD_OFFSET).and_then(header_number) else {
+            return upload_error(Box::new(UploadError::Invalid("Upload-Offset must be a whole number".to_string())), "Invalid upload");
+        };
+
+        match self.append_upload_use_case.execute(claims.sub, upload_id.into_inner(), offset, payload).await {
+            Ok(upload) => tus(HttpResponse::NoContent())
+                .insert_header((UPLOAD_OFFSET, upload.offset))
+                .insert_header((UPLOAD_EXPIRES, expires(&upload)))
+                .finish(),
+            Err(e) => upload_error(e, "Failed to store upload"),
+        }
+    }
+
+    pub async fn terminate(&self, req: HttpRequest, claims: Claims, upload_id: web::Path<Uuid>) -> HttpResponse {
+        if let Err(response) = check_version(&req) {
+            return response;
+        }
+
+        match self.terminate_upload_use_case.execute(claims.sub, upload_id.into_inner()).await {
+            Ok(()) => tus(HttpResponse::NoContent()).finish(),
+            Err(e) => upload_error(e, "Failed to delete upload"),
+        }
+    }
+}
+
+/// Requests without the version this server speaks are refused before anything else.
+fn check_version(req: &HttpRequest) -> Result<(), HttpResponse> {
+    match req.headers().get(TUS_RESUMABLE) {
+        Some(version) if version == TUS_VERSION => Ok(()),
+        _ => Err(tus(HttpResponse::PreconditionFailed())
+            .insert_header((TUS_VERSION_HEADER, TUS_VERSION))
+            .json(serde_json::json!({
+                "error": "Unsupported tus version",
+                "message": format!("Tus-Resumable must be {}", TUS_VERSION)
+            }))),
+    }
+}
+
+/// Starts a response carrying the `Tus-Resumable` header, as every tus response must.
+fn tus(mut builder: HttpResponseBuilder) -> HttpResponseBuilder {
+    builder.insert_header((TUS_RESUMABLE, TUS_VERSION));
+    builder
+}
+
+fn tus_response(mut response: HttpResponse) -> HttpResponse {
+    response.headers_mut().insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    response
+}
+
+fn header_number(value: &HeaderValue) -> Option<i64> {
+    value.to_str().ok()?.parse().ok().filter(|number| *number >= 0)
+}
+
+fn expires(upload: &Upload) -> HttpDate {
+    HttpDate::from(SystemTime::from(upload.expires_at.and_utc()))
+}
+
+/// Decodes `Upload-Metadata`: comma-separated keys, each followed by a space and its
+/// base64-encoded value unless it has none.
+fn parse_metadata(value: Option<&HeaderValue>) -> Result<BTreeMap<String, String>, UploadError> {
+    let mut metadata = BTreeMap::new();
+    let Some(value) = value else {
+        return Ok(metadata);
+    };
+
+    let value = value.to_str().map_err(|_| UploadError::Invalid("Upload-Metadata must be ASCII".to_string()))?;
+    for pair in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (key, encoded) = pair.split_once(' ').unwrap_or((pair, ""));
+        let decoded = BASE64.decode(encoded.trim()).ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| UploadError::Invalid(format!("the value of `{}` is not base64-encoded UTF-8", key)))?;
+        if metadata.insert(key.to_string(), decoded).is_some() {
+            return Err(UploadError::Invalid(format!("`{}` is given more than once", key)));
+        }
+    }
+
+    Ok(metadata)
+}
+
+fn encode_metadata(metadata: &BTreeMap<String, String>) -> String {
+    metadata.iter()
+        .map(|(key, value)| match value.is_empty() {
+            true => key.clone(),
+            false => format!("{} {}", key, BASE64.encode(value)),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn upload_error(e: Box<dyn std::error::Error>, error: &str) -> HttpResponse {
+    if let Some(e) = e.downcast_ref::<UploadError>() {
+        let (mut builder, error) = match e {
+            UploadError::Invalid(_) => (HttpResponse::BadRequest(), "Invalid upload"),
+            UploadError::TooLarge { .. } | UploadError::ExceedsLength { .. } => (HttpResponse::PayloadTooLarge(), "Upload too large"),
+            UploadError::OffsetMismatch { .. } => (HttpResponse::Conflict(), "Offset mismatch"),
+            UploadError::Locked => (HttpResponse::Locked(), "Upload in use"),
+            UploadError::Expired => (HttpResponse::Gone(), "Upload expired"),
+            UploadError::NotFound => (HttpResponse::NotFound(), "Upload not found"),
+        };
+        if let UploadError::OffsetMismatch { offset } = e {
+            builder.insert_header((UPLOAD_OFFSET, *offset));
+        }
+        return tus(builder).json(serde_json::json!({
+            "error": error,
+            "message": e.to_string()
+        }));
+    }
+    if let Some(invalid) = e.downcast_ref::<InvalidCrop>() {
+        return tus_response(invalid_crop(invalid));
+    }
+    if let Some(rejection) = e.downcast_ref::<UploadRejection>() {
+        return tus_response(rejected_upload(rejection));
+    }
+    if e.is::<PayloadError>() {
+        return tus(HttpResponse::BadRequest()).json(serde_json::json!({
+            "error": "Failed to read upload",
+            "message": e.to_string()
+        }));
+    }
+
+    tus(HttpResponse::InternalServerError()).json(serde_json::json!({
+        "error": error,
+        "message": e.to_string()
+    }))
+}
+
+pub fn configure<R, S, U, J, A>(
+    cfg: &mut web::ServiceConfig,
+    _handlers: web::Data<UploadHandlers<R, S, U, J, A>>,
+)
+where
+    R: UploadRepository + 'static,
+    S: MediaStorage + 'static,
+    U: AccountRepository + 'static,
+    J: JobRepository + 'static,
+    A: AttachmentRepository + 'static,
+{
+    cfg.service(
+        web::scope("/uploads")
+            .route("", web::method(Method::OPTIONS).to(move |handlers: web::Data<UploadHandlers<R, S, U, J, A>>| async move {
+                handlers.options().await
+            }))
+            .route("", web::post().to(move |handlers: web::Data<UploadHandlers<R, S, U, J, A>>, req: HttpRequest, claims: Claims| async move {
+                handlers.create_upload(req, claims).await
+            }))
+            .route("/{upload_id}", web::head().to(move |handlers: web::Data<UploadHandlers<R, S, U, J, A>>, req: HttpRequest, claims: Claims, upload_id: web::Path<Uuid>| async move {
+                handlers.upload_offset(req, claims, upload_id).await
+            }))
+            .route("/{upload_id}", web::get().to(move |handlers: web::Data<UploadHandlers<R, S, U, J, A>>, claims: Claims, upload_id: web::Path<Uuid>| async move {
+                handlers.get_upload(claims, upload_id).await
+            }))
+            .route("/{upload_id}", web::patch().to(move |handlers: web::Data<UploadHandlers<R, S, U, J, A>>, req: HttpRequest, claims: Claims, upload_id: web::Path<Uuid>, payload: web::Payload| async move {
+                handlers.append(req, claims, upload_id, payload).await
+            }))
+            .route("/{upload_id}", web::delete().to(move |handlers: web::Data<UploadHandlers<R, S, U, J, A>>, req: HttpRequest, claims: Claims, upload_id: web::Path<Uuid>| async move {
+                handlers.terminate(req, claims, upload_id).await
+            }))
+    );
+}
+
+pub mod doc {
+    use crate::domain::entities::upload::Upload;
+    use crate::presentation::openapi::ErrorResponse;
+
+    #[utoipa::path(
+        options,
+        path = "/api/v1/uploads",
+        tag = "uploads",
+        responses(
+            (status = 204, description = "tus capabilities", headers(
+                ("Tus-Version" = String, description = "Supported protocol versions: `1.0.0`"),
+                ("Tus-Extension" = String, description = "`creation,expiration,termination`"),
+                ("Tus-Max-Size" = u64, description = "Largest upload accepted, in bytes"),
+            )),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn options() {}
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/uploads",
+        tag = "uploads",
+        params(
+            ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+            ("Upload-Length" = u64, Header, description = "Size of the whole file in bytes"),
+            ("Upload-Metadata" = String, Header, description = "Comma-separated `key base64(value)` pairs. `purpose` is `avatar` or `attachment`. Avatars take the crop fields of the multipart upload form; attachments take `filename`."),
+        ),
+        responses(
+            (status = 201, description = "Upload created; `Location` is where its bytes go", headers(
+                ("Location" = String, description = "URL of the new upload"),
+                ("Upload-Expires" = String, description = "When the upload is discarded if unfinished"),
+            )),
+            (status = 400, description = "Missing length, malformed metadata, unknown purpose or invalid crop", body = ErrorResponse),
+            (status = 412, description = "Unsupported `Tus-Resumable` version", body = ErrorResponse),
+            (status = 413, description = "Length exceeds the limit for the purpose", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn create_upload() {}
+
+    #[utoipa::path(
+        head,
+        path = "/api/v1/uploads/{upload_id}",
+        tag = "uploads",
+        params(
+            ("upload_id" = uuid::Uuid, Path, description = "One of the caller's uploads"),
+            ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+        ),
+        responses(
+            (status = 200, description = "How far the upload has got", headers(
+                ("Upload-Offset" = u64, description = "Bytes received so far; the next chunk starts here"),
+                ("Upload-Length" = u64, description = "Size of the whole file"),
+                ("Upload-Metadata" = String, description = "Metadata sent at creation"),
+                ("Upload-Expires" = String, description = "When the upload is discarded"),
+            )),
+            (status = 404, description = "No such upload for the caller"),
+            (status = 410, description = "Upload expired"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn upload_offset() {}
+
+    #[utoipa::path(
+        patch,
+        path = "/api/v1/uploads/{upload_id}",
+        tag = "uploads",
+        params(
+            ("upload_id" = uuid::Uuid, Path, description = "One of the caller's uploads"),
+            ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+            ("Upload-Offset" = u64, Header, description = "Where the chunk starts; must equal the upload's current offset"),
+        ),
+        request_body(content = String, content_type = "application/offset+octet-stream", description = "The next bytes of the file"),
+        responses(
+            (status = 204, description = "Chunk stored. The chunk that completes the upload also queues an avatar or creates an attachment, found with `GET /api/v1/uploads/{upload_id}`.", headers(
+                ("Upload-Offset" = u64, description = "Bytes received so far"),
+            )),
+            (status = 400, description = "Malformed offset, body broke off, or the finished file is not a valid avatar", body = ErrorResponse),
+            (status = 404, description = "No such upload for the caller", body = ErrorResponse),
+            (status = 409, description = "Offset does not match the upload's; `Upload-Offset` has the right one", body = ErrorResponse),
+            (status = 410, description = "Upload expired", body = ErrorResponse),
+            (status = 413, description = "Chunk runs past the upload length, or a finished avatar exceeds the byte limit", body = ErrorResponse),
+            (status = 415, description = "Wrong content type, or a finished avatar is not a supported image", body = ErrorResponse),
+            (status = 422, description = "Finished avatar exceeds the pixel limit", body = ErrorResponse),
+            (status = 423, description = "Another request is writing to the upload", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn append() {}
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/uploads/{upload_id}",
+        tag = "uploads",
+        params(("upload_id" = uuid::Uuid, Path, description = "One of the caller's uploads")),
+        responses(
+            (status = 200, description = "Progress of the upload and, once finished, what it became", body = Upload),
+            (status = 404, description = "No such upload for the caller", body = ErrorResponse),
+            (status = 410, description = "Upload expired", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_upload() {}
+
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/uploads/{upload_id}",
+        tag = "uploads",
+        params(
+            ("upload_id" = uuid::Uuid, Path, description = "One of the caller's uploads"),
+            ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+        ),
+        responses(
+            (status = 204, description = "Upload and its received bytes discarded"),
+            (status = 404, description = "No such upload for the caller", body = ErrorResponse),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn terminate() {}
+}
@@ -1,8 +1,6 @@
 use actix_web::{web, HttpResponse, Responder, dev::Payload, FromRequest, HttpMessage};
 use serde_json::json;
 use std::future::{ready, Ready};
-use futures::future::LocalBoxFuture;
-use serde::{Deserialize, Serialize};
 use crate::domain::entities::auth::Claims;
 use crate::application::use_cases::user_use_cases::{CreateUserUseCase, ListUsersUseCase, GetUserByIdUseCase, UpdateUserUseCase, DeleteUserUseCase};
 use crate::domain::repositories::user_repository::UserRepository;
@@ -119,4 +117,85 @@ pub fn configure<T: UserRepository + 'static>(
                 handlers.delete_user(id).await
             })),
     );
-}
\ No newline at end of file
+}
+
+pub mod doc {
+    use crate::domain::entities::user::{CreateUserDto, UpdateUserDto, User};
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/user/me",
+        tag = "user",
+        responses(
+            (status = 200, description = "Profile of the authenticated user", body = Object),
+            (status = 404, description = "User no longer exists"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_profile() {}
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/user",
+        tag = "user",
+        responses(
+            (status = 200, description = "All users", body = [User]),
+            (status = 500, description = "Users could not be loaded"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn list_users() {}
+
+    #[utoipa::path(
+        post,
+        path = "/api/v1/user",
+        tag = "user",
+        request_body = CreateUserDto,
+        responses(
+            (status = 201, description = "Created user", body = User),
+            (status = 500, description = "User could not be created"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn create_user() {}
+
+    #[utoipa::path(
+        get,
+        path = "/api/v1/user/{id}",
+        tag = "user",
+        params(("id" = i32, Path, description = "User id")),
+        responses(
+            (status = 200, description = "User", body = User),
+            (status = 404, description = "User not found"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn get_user() {}
+
+    #[utoipa::path(
+        put,
+        path = "/api/v1/user/{id}",
+        tag = "user",
+        params(("id" = i32, Path, description = "User id")),
+        request_body = UpdateUserDto,
+        responses(
+            (status = 200, description = "Updated user", body = User),
+            (status = 404, description = "User not found"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn update_user() {}
+
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/user/{id}",
+        tag = "user",
+        params(("id" = i32, Path, description = "User id")),
+        responses(
+            (status = 204, description = "User deleted"),
+            (status = 404, description = "User not found"),
+        ),
+        security(("bearer_auth" = []))
+    )]
+    pub fn delete_user() {}
+}
@@ -1,25 +1,78 @@
-use actix::{Actor, StreamHandler, ActorContext, Running, AsyncContext, Handler};
+use actix::{Actor, ActorState, ActorContext, ActorFutureExt, AsyncContext, Handler, Running, SpawnHandle, StreamHandler, WrapFuture};
+use actix_web::error::ErrorUnauthorized;
+use actix_web::http::header;
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
+use tracing::warn;
+use uuid::Uuid;
 use crate::infrastructure::websocket::{
     user_status_manager::UserStatusManager,
-    realtime_message_manager::RealtimeMessageManager
+    realtime_message_manager::{RealtimeMessageManager, REPLAY_BATCH_SIZE}
 };
 use crate::domain::entities::message::WebSocketMessage;
+use crate::infrastructure::config::settings::{ChatSettings, Settings};
+use crate::app::{SharedAttachmentRepository, SharedMessageRepository};
+use crate::presentation::middleware::auth::decode_claims;
+use super::message_handlers::MessageHandlers;
+
+/// Chat sent over a socket is stored and delivered like chat sent through the REST API.
+type ChatHandlers = web::Data<MessageHandlers<SharedMessageRepository, SharedAttachmentRepository>>;
+
+/// Conversations one connection can show as typing in at once. Someone typing is in one or
+/// two; past this, `TypingStarted` to further users is dropped.
+const MAX_TYPING_CONVERSATIONS: usize = 5;
+
+/// A conversation this connection's user is typing in.
+struct Typing {
+    /// Sends `TypingStopped` once `ChatSettings::typing_timeout` passes without renewal.
+    expiry: SpawnHandle,
+    /// When `TypingStarted` was last relayed.
+    relayed_at: Instant,
+}
+
+/// Tells a socket actor that events were queued for its user. It sends everything still
+/// pending in the user's queue, in order.
+pub struct FlushEvents;
+
+impl actix::Message for FlushEvents {
+    type Result = ();
+}
 
 pub struct WebSocketActor {
     user_id: i32,
+    /// Tells this socket apart from the user's others.
+    connection_id: Uuid,
+    /// The last queued event this socket was sent. Each of the user's sockets keeps its own
+    /// place, so they are all sent every event.
+    sent_up_to: Option<i64>,
     user_status_manager: Arc<UserStatusManager>,
     realtime_message_manager: Arc<RealtimeMessageManager>, // Changed to Arc
+    chat_handlers: ChatHandlers,
+    chat_settings: ChatSettings,
+    /// Keyed by the other user.
+    typing: HashMap<i32, Typing>,
+    /// Users found to share a conversation with this connection's user, who may be told they
+    /// are typing.
+    contacts: HashSet<i32>,
 }
 
 impl Clone for WebSocketActor {
     fn clone(&self) -> Self {
         Self {
             user_id: self.user_id,
+            connection_id: self.connection_id,
+            sent_up_to: self.sent_up_to,
             user_status_manager: Arc::clone(&self.user_status_manager),
             realtime_message_manager: Arc::clone(&self.realtime_message_manager),
+            chat_handlers: self.chat_handlers.clone(),
+            chat_settings: self.chat_settings.clone(),
+            // Typing belongs to the connection it was started on
+            typing: HashMap::new(),
+            contacts: self.contacts.clone(),
         }
     }
 }
@@ -29,13 +82,91 @@ impl WebSocketActor {
         user_id: i32,
         user_status_manager: Arc<UserStatusManager>,
         realtime_message_manager: RealtimeMessageManager,
+        chat_handlers: ChatHandlers,
+        chat_settings: ChatSettings,
     ) -> Self {
         Self {
             user_id,
+            connection_id: Uuid::new_v4(),
+            sent_up_to: None,
             user_status_manager,
             realtime_message_manager: Arc::new(realtime_message_manager),
+            chat_handlers,
+            chat_settings,
+            typing: HashMap::new(),
+            contacts: HashSet::new(),
+        }
+    }
+
+    /// Relays `TypingStarted` to `to_user_id` if the two users have a conversation. Anyone
+    /// else is not told, so typing cannot be used to pester strangers.
+    fn typing_started(&mut self, to_user_id: i32, ctx: &mut ws::WebsocketContext<Self>) {
+        if to_user_id == self.user_id {
+            return;
+        }
+        if self.contacts.contains(&to_user_id) {
+            self.keep_typing(to_user_id, ctx);
+            return;
+        }
+
+        // Waiting holds back the actor's other messages, so a `TypingStopped` sent right after
+        // is handled once typing has started
+        let chat_handlers = self.chat_handlers.clone();
+        let user_id = self.user_id;
+        let check = async move { chat_handlers.in_conversation(user_id, to_user_id).await }
+            .into_actor(self)
+            .map(move |in_conversation, actor, ctx| match in_conversation {
+                Ok(true) => {
+                    actor.contacts.insert(to_user_id);
+                    actor.keep_typing(to_user_id, ctx);
+                }
+                Ok(false) => (),
+                Err(e) => warn!(user_id = actor.user_id, error = %e, "Failed to look up the conversation"),
+            });
+        ctx.wait(check);
+    }
+
+    /// Relays `TypingStarted` to a contact, unless it was relayed within the typing interval,
+    /// and pushes back the expiry either way.
+    fn keep_typing(&mut self, to_user_id: i32, ctx: &mut ws::WebsocketContext<Self>) {
+        let previous = self.typing.remove(&to_user_id);
+        if previous.is_none() && self.typing.len() >= MAX_TYPING_CONVERSATIONS {
+            return;
+        }
+
+        let relayed_at = previous.map(|typing| {
+            ctx.cancel_future(typing.expiry);
+            typing.relayed_at
+        });
+        let relayed_at = match relayed_at {
+            Some(relayed_at) if relayed_at.elapsed() < self.chat_settings.typing_interval => relayed_at,
+            _ => {
+                self.relay(to_user_id, WebSocketMessage::TypingStarted { user_id: self.user_id });
+                Instant::now()
+            }
+        };
+
+        let expiry = ctx.run_later(self.chat_settings.typing_timeout, move |actor, ctx| {
+            actor.typing_stopped(to_user_id, ctx);
+        });
+        self.typing.insert(to_user_id, Typing { expiry, relayed_at });
+    }
+
+    /// Relays `TypingStopped` to `to_user_id` if they were told the user is typing.
+    fn typing_stopped(&mut self, to_user_id: i32, ctx: &mut ws::WebsocketContext<Self>) {
+        if let Some(typing) = self.typing.remove(&to_user_id) {
+            ctx.cancel_future(typing.expiry);
+            self.relay(to_user_id, WebSocketMessage::TypingStopped { user_id: self.user_id });
         }
     }
+
+    /// Pushes an event to the user if they are connected; it is not kept for later.
+    fn relay(&self, to_user_id: i32, event: WebSocketMessage) {
+        let realtime_manager = Arc::clone(&self.realtime_message_manager);
+        actix::spawn(async move {
+            realtime_manager.send_to_user(to_user_id, event).await.ok();
+        });
+    }
 }
 
 impl Actor for WebSocketActor {
@@ -44,19 +175,28 @@ impl Actor for WebSocketActor {
     fn started(&mut self, ctx: &mut Self::Context) {
         let user_status_manager = Arc::clone(&self.user_status_manager);
         let user_id = self.user_id;
+        let connection_id = self.connection_id;
         let addr = ctx.address();
 
-        actix::spawn(async move {
-            user_status_manager.add_connection(user_id, addr).await;
-        });
+        // Once registered, every newly queued event triggers a flush, so the first flush
+        // only has to catch up on what was queued while the user was away.
+        ctx.wait(async move {
+            user_status_manager.add_connection(user_id, connection_id, addr).await;
+        }.into_actor(self));
+        ctx.notify(FlushEvents);
     }
 
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        for (to_user_id, _) in std::mem::take(&mut self.typing) {
+            self.relay(to_user_id, WebSocketMessage::TypingStopped { user_id: self.user_id });
+        }
+
         let user_status_manager = Arc::clone(&self.user_status_manager);
         let user_id = self.user_id;
+        let connection_id = self.connection_id;
 
         actix::spawn(async move {
-            user_status_manager.remove_connection(user_id).await;
+            user_status_manager.remove_connection(user_id, connection_id).await;
         });
         Running::Stop
     }
@@ -74,6 +214,66 @@ impl Handler<WebSocketMessage> for WebSocketActor {
     }
 }
 
+impl Handler<FlushEvents> for WebSocketActor {
+    type Result = ();
+
+    fn handle(&mut self, _: FlushEvents, ctx: &mut Self::Context) -> Self::Result {
+        // Leave the events queued for the next connection rather than write them to a
+        // socket that is closing
+        if ctx.state() != ActorState::Running {
+            return;
+        }
+
+        let realtime_manager = Arc::clone(&self.realtime_message_manager);
+        let user_id = self.user_id;
+        let sent_up_to = self.sent_up_to;
+
+        // Waiting holds back the actor's other messages, so flushes never overlap and each
+        // one reads the queue after the events the last one sent.
+        let flush = async move { realtime_manager.pending_events(user_id, sent_up_to).await }
+            .into_actor(self)
+            .then(|pending, actor, ctx| {
+                let mut events = pending.unwrap_or_else(|e| {
+                    warn!(user_id = actor.user_id, error = %e, "Failed to load queued events");
+                    Vec::new()
+                });
+                if ctx.state() != ActorState::Running {
+                    events.clear();
+                }
+                let last_event_id = events.last().map(|event| event.id);
+                actor.sent_up_to = last_event_id.or(actor.sent_up_to);
+                if events.len() as i64 == REPLAY_BATCH_SIZE {
+                    ctx.notify(FlushEvents);
+                }
+
+                let mut received = Vec::new();
+                for queued in events {
+                    if let WebSocketMessage::NewMessage { message_id, .. } = queued.event {
+                        received.push(message_id);
+                    }
+                    if let Ok(text) = serde_json::to_string(&queued.event) {
+                        ctx.text(text);
+                    }
+                }
+
+                let realtime_manager = Arc::clone(&actor.realtime_message_manager);
+                let chat_handlers = actor.chat_handlers.clone();
+                let user_id = actor.user_id;
+                async move {
+                    let Some(last_event_id) = last_event_id else { return };
+                    if let Err(e) = realtime_manager.acknowledge_events(user_id, last_event_id).await {
+                        warn!(user_id, error = %e, "Failed to move the delivery cursor");
+                    }
+                    // Senders hear about the delivery from their own queues
+                    actix::spawn(async move {
+                        chat_handlers.confirm_delivery(user_id, &received).await.ok();
+                    });
+                }.into_actor(actor)
+            });
+        ctx.wait(flush);
+    }
+}
+
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketActor {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         match msg {
@@ -82,13 +282,39 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketActor {
                 match serde_json::from_str::<WebSocketMessage>(&text) {
                     Ok(websocket_msg) => {
                         match websocket_msg {
-                            WebSocketMessage::Chat { to_user_id, content } => {
-                                let realtime_manager = Arc::clone(&self.realtime_message_manager);
+                            WebSocketMessage::Chat { to_user_id, content, attachment_id, client_msg_id } => {
+                                // Sending ends typing, as the message itself replaces the indicator
+                                self.typing_stopped(to_user_id, ctx);
+                                let chat_handlers = self.chat_handlers.clone();
                                 let from_user_id = self.user_id;
+                                let addr = ctx.address();
                                 actix::spawn(async move {
-                                    realtime_manager.send_message(from_user_id, to_user_id, content).await.ok();
+                                    let answer = match chat_handlers
+                                        .deliver(from_user_id, to_user_id, content, attachment_id, client_msg_id.clone())
+                                        .await
+                                    {
+                                        Ok(message) => WebSocketMessage::ChatAck {
+                                            client_msg_id,
+                                            message_id: message.id,
+                                            created_at: message.created_at,
+                                        },
+                                        Err(e) => WebSocketMessage::ChatFailed { client_msg_id, message: e.to_string() },
+                                    };
+                                    addr.do_send(answer);
                                 });
                             },
+                            WebSocketMessage::MarkRead { user_id, up_to_message_id } => {
+                                let chat_handlers = self.chat_handlers.clone();
+                                let reader_id = self.user_id;
+                                let addr = ctx.address();
+                                actix::spawn(async move {
+                                    if let Err(e) = chat_handlers.read_up_to(reader_id, user_id, up_to_message_id).await {
+                                        addr.do_send(WebSocketMessage::Error { message: e.to_string() });
+                                    }
+                                });
+                            },
+                            WebSocketMessage::TypingStarted { user_id } => self.typing_started(user_id, ctx),
+                            WebSocketMessage::TypingStopped { user_id } => self.typing_stopped(user_id, ctx),
                             WebSocketMessage::CallOffer { to_user_id, sdp } => {
                                 let realtime_manager = Arc::clone(&self.realtime_message_manager);
                                 actix::spawn(async move {
@@ -125,7 +351,17 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketActor {
                                     let _manager = realtime_manager;
                                 });
                             },
-                            WebSocketMessage::Status { .. } | WebSocketMessage::Error { .. } => {
+                            WebSocketMessage::Status { .. }
+                            | WebSocketMessage::NewMessage { .. }
+                            | WebSocketMessage::ChatAck { .. }
+                            | WebSocketMessage::ChatFailed { .. }
+                            | WebSocketMessage::MessageEdited { .. }
+                            | WebSocketMessage::MessageDeleted { .. }
+                            | WebSocketMessage::Delivered { .. }
+                            | WebSocketMessage::Read { .. }
+                            | WebSocketMessage::Error { .. }
+                            | WebSocketMessage::AvatarReady { .. }
+                            | WebSocketMessage::AvatarFailed { .. } => {
                                 ctx.text(serde_json::json!({
                                     "type": "error",
                                     "message": "Invalid message type for client"
@@ -153,18 +389,40 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketActor {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SocketQuery {
+    pub token: Option<String>,
+}
+
+/// Browsers cannot set headers on a WebSocket handshake, so the token may come in the query
+/// string instead of an `Authorization: Bearer` header.
+fn socket_token<'a>(req: &'a HttpRequest, query: &'a SocketQuery) -> Option<&'a str> {
+    query.token.as_deref().or_else(|| {
+        req.headers().get(header::AUTHORIZATION)?
+            .to_str().ok()?
+            .strip_prefix("Bearer ")
+    })
+}
+
 pub async fn ws_route(
     req: HttpRequest,
     stream: web::Payload,
-    path: web::Path<i32>,
+    query: web::Query<SocketQuery>,
     user_status_manager: web::Data<Arc<UserStatusManager>>,
     realtime_message_manager: web::Data<RealtimeMessageManager>,
+    chat_handlers: ChatHandlers,
+    settings: web::Data<Settings>,
 ) -> Result<HttpResponse, Error> {
-    let user_id = path.into_inner();
+    let claims = socket_token(&req, &query)
+        .and_then(|token| decode_claims(token, &settings.secret_key).ok())
+        .ok_or_else(|| ErrorUnauthorized("Invalid token"))?;
+    let user_id = claims.sub;
     let actor = WebSocketActor::new(
         user_id,
         user_status_manager.get_ref().clone(),
         realtime_message_manager.get_ref().clone(),
+        chat_handlers,
+        settings.chat.clone(),
     );
     let resp = ws::start(actor, &req, stream)?;
     Ok(resp)
@@ -173,7 +431,7 @@ pub async fn ws_route(
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
-        web::resource("/ws/{user_id}")
+        web::resource("/ws")
             .route(web::get().to(ws_route))
     );
 }
\ No newline at end of file
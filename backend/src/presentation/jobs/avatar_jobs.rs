@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use crate::application::use_cases::avatar_use_cases::ProcessAvatarUseCase;
+use crate::domain::entities::avatar::{AvatarProcessingJob, InvalidCrop};
+use crate::domain::entities::job::Job;
+use crate::domain::entities::media::UploadRejection;
+use crate::domain::entities::message::WebSocketMessage;
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::avatar_repository::AvatarRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::infrastructure::jobs::{JobError, JobHandler};
+use crate::infrastructure::websocket::realtime_message_manager::RealtimeMessageManager;
+
+/// Renders uploaded avatars and tells the owner over their socket when it is done. Users
+/// who are offline find the result through the job or their account instead.
+pub struct AvatarJobHandler<T: AvatarRepository, U: AccountRepository, S: MediaStorage> {
+    process_avatar_use_case: ProcessAvatarUseCase<T, U, S>,
+    realtime_message_manager: RealtimeMessageManager,
+}
+
+impl<T: AvatarRepository, U: AccountRepository, S: MediaStorage> AvatarJobHandler<T, U, S> {
+    pub fn new(process_avatar_use_case: ProcessAvatarUseCase<T, U, S>, realtime_message_manager: RealtimeMessageManager) -> Self {
+        Self {
+            process_avatar_use_case,
+            realtime_message_manager,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: AvatarRepository, U: AccountRepository, S: MediaStorage> JobHandler for AvatarJobHandler<T, U, S> {
+    fn kind(&self) -> &'static str {
+        AvatarProcessingJob::KIND
+    }
+
+    async fn run(&self, job: &Job) -> Result<Option<serde_json::Value>, JobError> {
+        let request: AvatarProcessingJob = serde_json::from_value(job.payload.clone()).map_err(JobError::permanent)?;
+
+        let avatar = self.process_avatar_use_case.execute(&request).await.map_err(|e| {
+            // The original is what it is, and a vanished account will not come back
+            let hopeless = e.is::<UploadRejection>()
+                || e.is::<InvalidCrop>()
+                || matches!(e.downcast_ref::<diesel::result::Error>(), Some(diesel::result::Error::NotFound));
+            if hopeless { JobError::permanent(e) } else { JobError::from(e) }
+        })?;
+
+        let ready = WebSocketMessage::AvatarReady { job_id: job.id, avatar_id: avatar.id };
+        self.realtime_message_manager.send_to_user(request.user_id, ready).await.ok();
+
+        Ok(Some(serde_json::json!({ "avatar_id": avatar.id })))
+    }
+
+    async fn failed(&self, job: &Job, error: &str) {
+        let Some(user_id) = job.user_id else {
+            return;
+        };
+        let failed = WebSocketMessage::AvatarFailed { job_id: job.id, message: error.to_string() };
+        self.realtime_message_manager.send_to_user(user_id, failed).await.ok();
+    }
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use tracing::{info, warn};
+use crate::application::use_cases::media_use_cases::ReconcileMediaUseCase;
+use crate::domain::entities::job::Job;
+use crate::domain::entities::media::MediaReconciliation;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::avatar_repository::AvatarRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::infrastructure::config::settings::MediaGcSettings;
+use crate::infrastructure::jobs::{JobError, JobHandler};
+use super::schedule_recurring;
+
+/// Reconciles stored media with the database on the interval in `MediaGcSettings`. Each run
+/// queues the next one, so a single chain of jobs keeps going across restarts.
+pub struct MediaReconciliationJobHandler<T: AvatarRepository, A: AttachmentRepository, U: UploadRepository, J: JobRepository, M: MediaObjectRepository, S: MediaStorage> {
+    reconcile_media_use_case: ReconcileMediaUseCase<T, A, U, J, M, S>,
+    job_repository: J,
+    settings: MediaGcSettings,
+}
+
+impl<T: AvatarRepository, A: AttachmentRepository, U: UploadRepository, J: JobRepository, M: MediaObjectRepository, S: MediaStorage> MediaReconciliationJobHandler<T, A, U, J, M, S> {
+    pub fn new(reconcile_media_use_case: ReconcileMediaUseCase<T, A, U, J, M, S>, job_repository: J, settings: MediaGcSettings) -> Self {
+        Self {
+            reconcile_media_use_case,
+            job_repository,
+            settings,
+        }
+    }
+
+    /// Queues the next run unless one other than `current` is already waiting. Does nothing
+    /// when no interval is configured.
+    pub async fn schedule(&self, current: Option<i64>) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(interval) = self.settings.interval else {
+            return Ok(());
+        };
+
+        schedule_recurring(&self.job_repository, MediaReconciliation::JOB_KIND, interval, current).await
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: AvatarRepository, A: AttachmentRepository, U: UploadRepository, J: JobRepository, M: MediaObjectRepository, S: MediaStorage> JobHandler for MediaReconciliationJobHandler<T, A, U, J, M, S> {
+    fn kind(&self) -> &'static str {
+        MediaReconciliation::JOB_KIND
+    }
+
+    async fn run(&self, job: &Job) -> Result<Option<serde_json::Value>, JobError> {
+        // Scheduled first, so a failing run does not end the chain
+        self.schedule(Some(job.id)).await?;
+
+        let report = self.reconcile_media_use_case.execute(self.settings.delete).await?;
+        if report.orphaned_objects.is_empty() && report.missing_objects.is_empty() {
+            info!("Media reconciliation found nothing out of step");
+        } else {
+            warn!(
+                orphaned = report.orphaned_objects.len(),
+                missing = report.missing_objects.len(),
+                deleted = report.deleted,
+                "Media reconciliation found stored objects and rows out of step"
+            );
+        }
+
+        Ok(Some(serde_json::to_value(&report).map_err(JobError::permanent)?))
+    }
+}
@@ -0,0 +1,29 @@
+pub mod avatar_jobs;
+pub mod media_jobs;
+pub mod upload_jobs;
+
+use std::time::Duration;
+use chrono::Utc;
+use crate::domain::entities::job::NewJob;
+use crate::domain::repositories::job_repository::JobRepository;
+
+/// Queues the next run of a recurring system job `interval` from now, unless a run other
+/// than `current` is already waiting. Each run calls this before doing its work, so one
+/// chain of jobs keeps going across restarts however many instances schedule it.
+pub(crate) async fn schedule_recurring<J: JobRepository>(
+    job_repository: &J,
+    kind: &str,
+    interval: Duration,
+    current: Option<i64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let unfinished = job_repository.find_unfinished(kind).await?;
+    if unfinished.iter().any(|job| Some(job.id) != current) {
+        return Ok(());
+    }
+
+    job_repository.enqueue(NewJob {
+        run_at: Some(Utc::now().naive_utc() + chrono::Duration::from_std(interval)?),
+        ..NewJob::new(kind, serde_json::json!({}))
+    }).await?;
+    Ok(())
+}
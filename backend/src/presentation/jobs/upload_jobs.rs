@@ -0,0 +1,57 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use tracing::info;
+use crate::application::use_cases::upload_use_cases::ExpireUploadsUseCase;
+use crate::domain::entities::job::Job;
+use crate::domain::entities::upload::Upload;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::infrastructure::jobs::{JobError, JobHandler};
+use super::schedule_recurring;
+
+/// Discards expired resumable uploads, and the parts stored for them, on an interval.
+pub struct UploadExpiryJobHandler<R: UploadRepository, S: MediaStorage, J: JobRepository> {
+    expire_uploads_use_case: ExpireUploadsUseCase<R, S>,
+    job_repository: J,
+    interval: Option<Duration>,
+}
+
+impl<R: UploadRepository, S: MediaStorage, J: JobRepository> UploadExpiryJobHandler<R, S, J> {
+    pub fn new(expire_uploads_use_case: ExpireUploadsUseCase<R, S>, job_repository: J, interval: Option<Duration>) -> Self {
+        Self {
+            expire_uploads_use_case,
+            job_repository,
+            interval,
+        }
+    }
+
+    /// Queues the next run unless one other than `current` is already waiting. Does nothing
+    /// when no interval is configured.
+    pub async fn schedule(&self, current: Option<i64>) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(interval) = self.interval else {
+            return Ok(());
+        };
+
+        schedule_recurring(&self.job_repository, Upload::EXPIRY_JOB_KIND, interval, current).await
+    }
+}
+
+#[async_trait(?Send)]
+impl<R: UploadRepository, S: MediaStorage, J: JobRepository> JobHandler for UploadExpiryJobHandler<R, S, J> {
+    fn kind(&self) -> &'static str {
+        Upload::EXPIRY_JOB_KIND
+    }
+
+    async fn run(&self, job: &Job) -> Result<Option<serde_json::Value>, JobError> {
+        // Scheduled first, so a failing run does not end the chain
+        self.schedule(Some(job.id)).await?;
+
+        let expired = self.expire_uploads_use_case.execute().await?;
+        if expired > 0 {
+            info!(expired, "Discarded expired uploads");
+        }
+
+        Ok(Some(serde_json::json!({ "expired": expired })))
+    }
+}
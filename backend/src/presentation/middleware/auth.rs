@@ -1,25 +1,28 @@
-use actix_web::error::ErrorUnauthorized;
-use actix_web::{Error, dev::ServiceRequest, HttpMessage};
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{web, Error, dev::ServiceRequest, HttpMessage};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use jsonwebtoken::{decode, errors::Error as JwtError, DecodingKey, Validation};
 use crate::domain::entities::auth::Claims;
+use crate::infrastructure::config::settings::Settings;
 
-#[allow(dead_code)]  // Added because the compiler can't detect usage through middleware configuration
 pub async fn validator(req: ServiceRequest, credentials: BearerAuth)
                        -> Result<ServiceRequest, (Error, ServiceRequest)> {
-    let secret_key = std::env::var("SECRET_KEY").expect("SECRET_KEY must be set");
-    let token = credentials.token();
+    let Some(settings) = req.app_data::<web::Data<Settings>>().cloned() else {
+        return Err((ErrorInternalServerError("Settings are not configured"), req));
+    };
 
-    match decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret_key.as_bytes()),
-        &Validation::default(),
-    ) {
-        Ok(token_data) => {
+    match decode_claims(credentials.token(), &settings.secret_key) {
+        Ok(claims) => {
             // Add claims to request extensions for use in handlers
-            req.extensions_mut().insert(token_data.claims);
+            req.extensions_mut().insert(claims);
             Ok(req)
         },
         Err(_) => Err((ErrorUnauthorized("Invalid token"), req)),
     }
+}
+
+/// Checks a token's signature and expiry and returns the claims it carries.
+pub fn decode_claims(token: &str, secret_key: &str) -> Result<Claims, JwtError> {
+    decode::<Claims>(token, &DecodingKey::from_secret(secret_key.as_bytes()), &Validation::default())
+        .map(|token_data| token_data.claims)
 }
\ No newline at end of file
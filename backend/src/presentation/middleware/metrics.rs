@@ -0,0 +1,32 @@
+use std::time::Instant;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use crate::infrastructure::metrics::metrics;
+
+/// Records request counts and latencies labelled by route pattern rather than raw path,
+/// so `/user/1` and `/user/2` share a series.
+pub async fn track_http_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+    let result = next.call(req).await;
+
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+
+    metrics().http_requests_total.with_label_values(&labels).inc();
+    metrics().http_request_duration_seconds
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+
+    result
+}
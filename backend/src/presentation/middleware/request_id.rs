@@ -0,0 +1,70 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage};
+use opentelemetry::trace::TraceContextExt;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use crate::infrastructure::telemetry::propagation::{extract_context, inject_context};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Request id for the current request, available to handlers through request extensions.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Accepts a well-formed `X-Request-Id` from the caller or generates one, opens the
+/// `http_request` span every log line of the request is nested under, continues any
+/// W3C trace context from `traceparent`, and echoes both ids back on the response.
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let request_id = req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "http_request",
+        request_id = %request_id,
+        trace_id = tracing::field::Empty,
+        method = %req.method(),
+        route = %req.match_pattern().unwrap_or_else(|| "unmatched".to_string()),
+        status = tracing::field::Empty,
+    );
+    span.set_parent(extract_context(req.headers()));
+
+    let context = span.context();
+    let trace_id = context.span().span_context().trace_id();
+    span.record("trace_id", tracing::field::display(trace_id));
+
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    match next.call(req).instrument(span.clone()).await {
+        Ok(mut res) => {
+            span.record("status", res.status().as_u16());
+            let headers = res.headers_mut();
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                headers.insert(REQUEST_ID_HEADER, value);
+            }
+            inject_context(&context, headers);
+            Ok(res)
+        }
+        Err(e) => {
+            span.record("status", e.as_response_error().status_code().as_u16());
+            Err(e)
+        }
+    }
+}
+
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
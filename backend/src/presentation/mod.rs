@@ -1,2 +1,5 @@
+pub mod admin;
 pub mod handlers;
-pub(crate) mod middleware;
\ No newline at end of file
+pub mod jobs;
+pub mod middleware;
+pub mod openapi;
\ No newline at end of file
@@ -0,0 +1,208 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use serde_json::json;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi, PartialSchema, ToSchema};
+use utoipa_redoc::{Redoc, Servable};
+use crate::domain::entities::{account, attachment, auth, avatar, conversation, health, job, media, message, upload, user};
+use crate::presentation::handlers::{
+    account_handlers, attachment_handlers, auth_handlers, avatar_handlers, health_handlers, job_handlers,
+    conversation_handlers, media_handlers, message_handlers, metrics_handlers, upload_handlers, user_handlers,
+};
+
+/// Error body returned by most handlers.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub message: String,
+}
+
+/// Multipart form accepted by the avatar upload endpoint. Without a crop rectangle the
+/// largest centered square of the image is used.
+#[derive(ToSchema)]
+pub struct AvatarUploadForm {
+    #[schema(value_type = String, format = Binary)]
+    pub avatar: Vec<u8>,
+    /// Left edge of the crop rectangle; the four crop fields are given together.
+    pub crop_x: Option<f64>,
+    pub crop_y: Option<f64>,
+    pub crop_width: Option<f64>,
+    pub crop_height: Option<f64>,
+    /// `pixels` (default) or `normalized`, for fractions of the image size.
+    #[schema(pattern = "^(pixels|normalized)$")]
+    pub crop_units: Option<String>,
+    /// Horizontal position, from 0 to 1, to center the square on when the rectangle is not square.
+    pub focal_x: Option<f64>,
+    /// Vertical position, from 0 to 1, to center the square on when the rectangle is not square.
+    pub focal_y: Option<f64>,
+}
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(Http::builder().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Tri-Pak API", description = "REST API shared by the web and Android clients"),
+    paths(
+        auth_handlers::doc::login,
+        auth_handlers::doc::register,
+        user_handlers::doc::get_profile,
+        user_handlers::doc::list_users,
+        user_handlers::doc::create_user,
+        user_handlers::doc::get_user,
+        user_handlers::doc::update_user,
+        user_handlers::doc::delete_user,
+        account_handlers::doc::get_account,
+        account_handlers::doc::update_account,
+        avatar_handlers::doc::upload_avatar,
+        avatar_handlers::doc::list_avatars,
+        avatar_handlers::doc::set_default_avatar,
+        avatar_handlers::doc::delete_avatar,
+        job_handlers::doc::get_job,
+        upload_handlers::doc::options,
+        upload_handlers::doc::create_upload,
+        upload_handlers::doc::upload_offset,
+        upload_handlers::doc::append,
+        upload_handlers::doc::get_upload,
+        upload_handlers::doc::terminate,
+        media_handlers::doc::get_media,
+        message_handlers::doc::send_message,
+        message_handlers::doc::mark_read,
+        message_handlers::doc::get_messages,
+        message_handlers::doc::edit_message,
+        message_handlers::doc::delete_message,
+        message_handlers::doc::get_message_edits,
+        conversation_handlers::doc::list_conversations,
+        attachment_handlers::doc::get_attachment,
+        health_handlers::doc::live,
+        health_handlers::doc::ready,
+        metrics_handlers::doc::metrics,
+    ),
+    components(schemas(
+        ErrorResponse,
+        AvatarUploadForm,
+        auth::AuthUser,
+        auth::TokenResponse,
+        auth::RegisterUserDto,
+        user::User,
+        user::CreateUserDto,
+        user::UpdateUserDto,
+        account::Account,
+        account::UpdateAccountDto,
+        avatar::Avatar,
+        avatar::AvatarUploadAccepted,
+        avatar::AvatarGallery,
+        media::ImageSources,
+        media::ImageFormat,
+        job::Job,
+        job::JobStatus,
+        upload::Upload,
+        upload::UploadPurpose,
+        attachment::Attachment,
+        message::DatabaseMessage,
+        message::MessagePage,
+        message::SendMessageDto,
+        message::MarkReadDto,
+        message::ReadReceipt,
+        message::EditMessageDto,
+        message::MessageEdit,
+        message::WebSocketMessage,
+        conversation::ConversationPage,
+        conversation::ConversationSummary,
+        conversation::Counterpart,
+        conversation::MessagePreview,
+        health::HealthReport,
+        health::ComponentHealth,
+        health::HealthStatus,
+    )),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "auth", description = "Login and registration"),
+        (name = "user", description = "User management"),
+        (name = "account", description = "Account profiles"),
+        (name = "avatars", description = "Avatar uploads and gallery"),
+        (name = "jobs", description = "Background work started by other requests"),
+        (name = "uploads", description = "Resumable uploads (tus 1.0) for avatars and attachments"),
+        (name = "media", description = "Signed access to stored media"),
+        (name = "messages", description = "Direct messages"),
+        (name = "health", description = "Probes and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// AsyncAPI 3.0 description of the `/ws` channel. Frames are `WebSocketMessage`
+/// values serialized as JSON in both directions.
+pub fn asyncapi_document() -> serde_json::Value {
+    let websocket_message = serde_json::to_value(<message::WebSocketMessage as PartialSchema>::schema())
+        .expect("WebSocketMessage schema serializes");
+
+    json!({
+        "asyncapi": "3.0.0",
+        "info": {
+            "title": "Tri-Pak realtime API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Chat, presence and call signalling over a single WebSocket per user."
+        },
+        "channels": {
+            "userSocket": {
+                "address": "/ws",
+                "description": "The socket belongs to the user named by the bearer token, sent as the `token` query parameter or an `Authorization: Bearer` header. The upgrade is refused with 401 without a valid one.",
+                "bindings": {
+                    "ws": {
+                        "query": {
+                            "type": "object",
+                            "properties": {
+                                "token": { "type": "string", "description": "Bearer token of the connecting user" }
+                            }
+                        }
+                    }
+                },
+                "messages": {
+                    "webSocketMessage": { "$ref": "#/components/messages/WebSocketMessage" }
+                }
+            }
+        },
+        "operations": {
+            "sendFrame": {
+                "action": "send",
+                "channel": { "$ref": "#/channels/userSocket" },
+                "messages": [{ "$ref": "#/channels/userSocket/messages/webSocketMessage" }]
+            },
+            "receiveFrame": {
+                "action": "receive",
+                "channel": { "$ref": "#/channels/userSocket" },
+                "messages": [{ "$ref": "#/channels/userSocket/messages/webSocketMessage" }]
+            }
+        },
+        "components": {
+            "messages": {
+                "WebSocketMessage": {
+                    "contentType": "application/json",
+                    "payload": { "$ref": "#/components/schemas/WebSocketMessage" }
+                }
+            },
+            "schemas": {
+                "WebSocketMessage": websocket_message
+            }
+        }
+    })
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/openapi.json", web::get().to(|| async {
+        HttpResponse::Ok().json(ApiDoc::openapi())
+    }))
+        .route("/asyncapi.json", web::get().to(|| async {
+            HttpResponse::Ok().json(asyncapi_document())
+        }))
+        .service(Redoc::with_url("/docs", ApiDoc::openapi()));
+}
@@ -13,14 +13,110 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    attachments (id) {
+        id -> Int4,
+        user_id -> Int4,
+        storage_key -> Varchar,
+        #[max_length = 255]
+        filename -> Nullable<Varchar>,
+        #[max_length = 255]
+        content_type -> Varchar,
+        byte_size -> Int8,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     avatars (id) {
         id -> Int4,
         account_id -> Int4,
-        avatar_300x300_url -> Nullable<Varchar>,
-        avatar_40x40_url -> Nullable<Varchar>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        original_key -> Nullable<Varchar>,
+        blurhash -> Nullable<Varchar>,
+        #[max_length = 7]
+        dominant_color -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    delivery_cursors (user_id) {
+        user_id -> Int4,
+        event_id -> Int8,
+    }
+}
+
+diesel::table! {
+    conversations (user_id, counterpart_id) {
+        user_id -> Int4,
+        counterpart_id -> Int4,
+        last_message_id -> Int4,
+        last_message_at -> Timestamp,
+        unread_count -> Int4,
+    }
+}
+
+diesel::table! {
+    jobs (id) {
+        id -> Int8,
+        #[max_length = 64]
+        kind -> Varchar,
+        payload -> Jsonb,
+        #[max_length = 16]
+        status -> Varchar,
+        user_id -> Nullable<Int4>,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        run_at -> Timestamp,
+        locked_at -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        result -> Nullable<Jsonb>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    media_objects (storage_key) {
+        storage_key -> Varchar,
+        reference_count -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        claimed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    media_variants (id) {
+        id -> Int4,
+        avatar_id -> Int4,
+        size -> Int4,
+        density -> Int4,
+        #[max_length = 8]
+        format -> Varchar,
+        width -> Int4,
+        height -> Int4,
+        storage_key -> Varchar,
+        byte_size -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    message_deletions (user_id, message_id) {
+        user_id -> Int4,
+        message_id -> Int4,
+        deleted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    message_edits (id) {
+        id -> Int4,
+        message_id -> Int4,
+        content -> Text,
+        edited_at -> Timestamp,
     }
 }
 
@@ -32,6 +128,11 @@ diesel::table! {
         content -> Text,
         is_read -> Bool,
         created_at -> Timestamp,
+        attachment_id -> Nullable<Int4>,
+        delivered_at -> Nullable<Timestamp>,
+        read_at -> Nullable<Timestamp>,
+        edited_at -> Nullable<Timestamp>,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -47,6 +148,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    uploads (id) {
+        id -> Uuid,
+        user_id -> Int4,
+        #[max_length = 16]
+        purpose -> Varchar,
+        upload_length -> Int8,
+        upload_offset -> Int8,
+        metadata -> Jsonb,
+        locked_at -> Nullable<Timestamp>,
+        job_id -> Nullable<Int8>,
+        attachment_id -> Nullable<Int4>,
+        expires_at -> Timestamp,
+        completed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_events (id) {
+        id -> Int8,
+        user_id -> Int4,
+        payload -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     user_roles (id) {
         id -> Int4,
@@ -66,14 +195,36 @@ diesel::table! {
 }
 
 diesel::joinable!(accounts -> users (user_id));
+diesel::joinable!(attachments -> users (user_id));
+diesel::joinable!(delivery_cursors -> users (user_id));
+diesel::joinable!(jobs -> users (user_id));
+diesel::joinable!(media_variants -> avatars (avatar_id));
+diesel::joinable!(message_deletions -> messages (message_id));
+diesel::joinable!(message_deletions -> users (user_id));
+diesel::joinable!(message_edits -> messages (message_id));
+diesel::joinable!(messages -> attachments (attachment_id));
+diesel::joinable!(uploads -> attachments (attachment_id));
+diesel::joinable!(uploads -> jobs (job_id));
+diesel::joinable!(uploads -> users (user_id));
+diesel::joinable!(user_events -> users (user_id));
 diesel::joinable!(user_roles -> roles (role_id));
 diesel::joinable!(user_roles -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     accounts,
+    attachments,
     avatars,
+    conversations,
+    delivery_cursors,
+    jobs,
+    media_objects,
+    media_variants,
+    message_deletions,
+    message_edits,
     messages,
     roles,
+    uploads,
+    user_events,
     user_roles,
     users,
 );
@@ -0,0 +1,35 @@
+// File: src/tests/account_test.rs
+
+use actix_web::test;
+use serde_json::{json, Value};
+use crate::app::build_app;
+use crate::tests::support::test_context;
+use crate::tests::support::seeds::UserSeed;
+use crate::tests::support::tokens::{bearer, token_for};
+
+#[actix_web::test]
+async fn test_get_and_update_account() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let user = UserSeed::new("named").names("Ada", "Lovelace").create(&ctx.pool()).await;
+    // A second account makes sure lookups are scoped to the requested user
+    UserSeed::new("other").names("Grace", "Hopper").create(&ctx.pool()).await;
+    let token = token_for(user.id);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/account/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(account["id"], user.account_id);
+    assert_eq!(account["first_name"], "Ada");
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/v1/account/{}", user.id))
+        .insert_header(bearer(&token))
+        .set_json(json!({ "first_name": "Augusta", "middle_name": "Ada", "last_name": "King" }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["first_name"], "Augusta");
+    assert_eq!(updated["last_name"], "King");
+}
@@ -0,0 +1,99 @@
+// File: src/tests/auth_test.rs
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use crate::app::build_app;
+use crate::tests::support::test_context;
+use crate::tests::support::seeds::UserSeed;
+use crate::tests::support::tokens::{bearer, expired_token_for};
+
+#[actix_web::test]
+async fn test_register_then_login() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/register")
+        .set_json(json!({
+            "username": "new_user",
+            "email": "new_user@example.com",
+            "password": "s3cret-password",
+            "first_name": "New",
+            "last_name": "User"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["username"], "new_user");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/login")
+        .set_json(json!({ "username": "new_user", "password": "s3cret-password" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let token: Value = test::read_body_json(resp).await;
+    assert_eq!(token["token_type"], "Bearer");
+
+    // The issued token is accepted by protected routes
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user/me")
+        .insert_header(bearer(token["access_token"].as_str().unwrap()))
+        .to_request();
+    let profile: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(profile["username"], "new_user");
+}
+
+#[actix_web::test]
+async fn test_register_duplicate_username() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let existing = UserSeed::new("taken").create(&ctx.pool()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/register")
+        .set_json(json!({
+            "username": existing.username,
+            "email": "other@example.com",
+            "password": "whatever"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_login_wrong_password() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let user = UserSeed::new("careful").password("right-password").create(&ctx.pool()).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/login")
+        .set_json(json!({ "username": user.username, "password": "wrong-password" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_protected_route_rejects_missing_and_expired_tokens() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let user = UserSeed::new("expired").create(&ctx.pool()).await;
+
+    let req = test::TestRequest::get().uri("/api/v1/user/me").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user/me")
+        .insert_header(bearer(&expired_token_for(user.id)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,138 @@
+// File: src/tests/conversation_test.rs
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use diesel::prelude::*;
+use serde_json::Value;
+use crate::app::build_app;
+use crate::schema::{messages, users};
+use crate::tests::support::test_context;
+use crate::tests::support::seeds::{seed_message, UserSeed};
+use crate::tests::support::tokens::{bearer, token_for};
+
+fn inbox(user_id: i32, query: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(&format!("/api/v1/conversations?{}", query))
+        .insert_header(bearer(&token_for(user_id)))
+}
+
+fn counterparts(page: &Value) -> Vec<&str> {
+    page["conversations"].as_array().unwrap().iter()
+        .map(|conversation| conversation["counterpart"]["username"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn test_inbox_follows_messages_as_they_are_sent_read_and_deleted() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").names("Alice", "Liddell").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let carol = UserSeed::new("carol").create(&ctx.pool()).await;
+
+    let first = seed_message(&ctx.pool(), alice.id, bob.id, "hi bob").await;
+    seed_message(&ctx.pool(), bob.id, alice.id, "hi alice").await;
+    seed_message(&ctx.pool(), carol.id, bob.id, "hello from carol").await;
+    let latest = seed_message(&ctx.pool(), alice.id, bob.id, "still there?").await;
+
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert_eq!(counterparts(&page), vec!["alice", "carol"]);
+    let with_alice = &page["conversations"][0];
+    assert_eq!(with_alice["counterpart"]["first_name"], "Alice");
+    assert_eq!(with_alice["last_message"]["content"], "still there?");
+    assert_eq!(with_alice["unread_count"], 2);
+    assert!(page["older"].is_null());
+
+    let page: Value = test::call_and_read_body_json(&app, inbox(alice.id, "").to_request()).await;
+    assert_eq!(counterparts(&page), vec!["bob"]);
+    assert_eq!(page["conversations"][0]["unread_count"], 1);
+
+    let read = test::TestRequest::post()
+        .uri(&format!("/api/v1/conversations/{}/read", alice.id))
+        .insert_header(bearer(&token_for(bob.id)))
+        .set_json(serde_json::json!({ "up_to_message_id": first.id }))
+        .to_request();
+    let receipt: Value = test::call_and_read_body_json(&app, read).await;
+    assert_eq!(receipt["message_ids"], serde_json::json!([first.id]));
+    let conn = &mut ctx.pool().get().unwrap();
+    diesel::delete(messages::table.find(latest.id)).execute(conn).unwrap();
+
+    // The previous message takes over, and deleting an unread one no longer counts it
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert_eq!(counterparts(&page), vec!["carol", "alice"]);
+    assert_eq!(page["conversations"][1]["last_message"]["content"], "hi alice");
+    assert_eq!(page["conversations"][1]["unread_count"], 0);
+
+    diesel::delete(users::table.find(carol.id)).execute(conn).unwrap();
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert_eq!(counterparts(&page), vec!["alice"]);
+}
+
+#[actix_web::test]
+async fn test_inbox_is_paged() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    for name in ["bob", "carol", "dave"] {
+        let other = UserSeed::new(name).create(&ctx.pool()).await;
+        seed_message(&ctx.pool(), other.id, alice.id, "hi").await;
+    }
+
+    let page: Value = test::call_and_read_body_json(&app, inbox(alice.id, "limit=2").to_request()).await;
+    assert_eq!(counterparts(&page), vec!["dave", "carol"]);
+
+    let query = format!("limit=2&before={}", page["older"].as_str().expect("another page"));
+    let page: Value = test::call_and_read_body_json(&app, inbox(alice.id, &query).to_request()).await;
+    assert_eq!(counterparts(&page), vec!["bob"]);
+    assert!(page["older"].is_null());
+
+    let resp = test::call_service(&app, inbox(alice.id, "before=nope").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_inbox_leaves_out_messages_deleted_for_oneself() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let first = seed_message(&ctx.pool(), alice.id, bob.id, "hi bob").await;
+    let second = seed_message(&ctx.pool(), alice.id, bob.id, "about yesterday").await;
+    let latest = seed_message(&ctx.pool(), alice.id, bob.id, "never mind").await;
+    let delete_for_bob = |message_id: i32| test::TestRequest::delete()
+        .uri(&format!("/api/v1/messages/{}", message_id))
+        .insert_header(bearer(&token_for(bob.id)))
+        .to_request();
+    assert_eq!(test::call_service(&app, delete_for_bob(latest.id)).await.status(), StatusCode::NO_CONTENT);
+
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert_eq!(page["conversations"][0]["last_message"]["content"], "about yesterday");
+    assert_eq!(page["conversations"][0]["unread_count"], 2);
+
+    // Alice's side is untouched
+    let page: Value = test::call_and_read_body_json(&app, inbox(alice.id, "").to_request()).await;
+    assert_eq!(page["conversations"][0]["last_message"]["content"], "never mind");
+
+    // Reading past a message deleted for oneself doesn't count it off twice
+    let read = test::TestRequest::post()
+        .uri(&format!("/api/v1/conversations/{}/read", alice.id))
+        .insert_header(bearer(&token_for(bob.id)))
+        .set_json(serde_json::json!({ "up_to_message_id": latest.id }))
+        .to_request();
+    assert_eq!(test::call_service(&app, read).await.status(), StatusCode::OK);
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert_eq!(page["conversations"][0]["unread_count"], 0);
+
+    let conn = &mut ctx.pool().get().unwrap();
+    diesel::delete(messages::table.find(latest.id)).execute(conn).unwrap();
+    assert_eq!(test::call_service(&app, delete_for_bob(second.id)).await.status(), StatusCode::NO_CONTENT);
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert_eq!(page["conversations"][0]["last_message"]["content"], "hi bob");
+    assert_eq!(page["conversations"][0]["unread_count"], 0);
+
+    // Nothing left to show drops the conversation from the inbox
+    assert_eq!(test::call_service(&app, delete_for_bob(first.id)).await.status(), StatusCode::NO_CONTENT);
+    let page: Value = test::call_and_read_body_json(&app, inbox(bob.id, "").to_request()).await;
+    assert!(counterparts(&page).is_empty());
+}
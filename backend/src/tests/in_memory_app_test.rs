@@ -0,0 +1,57 @@
+// File: src/tests/in_memory_app_test.rs
+//
+// The `--storage memory` server: the full app with no database behind it.
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use crate::app::{build_app_with_state, AppState};
+use crate::infrastructure::config::settings::{ChatSettings, JobSettings, Settings};
+use crate::tests::support::{test_media_settings, TEST_SECRET_KEY};
+use crate::tests::support::tokens::bearer;
+
+#[actix_web::test]
+async fn test_in_memory_app_registers_logs_in_and_reports_ready() {
+    let upload_dir = std::env::temp_dir().join(format!("uploads_memory_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&upload_dir).unwrap();
+    let settings = Settings {
+        secret_key: TEST_SECRET_KEY.to_string(),
+        upload_dir: upload_dir.clone(),
+        bind_address: "127.0.0.1:0".to_string(),
+        allowed_origins: vec![],
+        media: test_media_settings(),
+        jobs: JobSettings::default(),
+        chat: ChatSettings::default(),
+    };
+    let app = test::init_service(build_app_with_state(&AppState::in_memory(settings))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/register")
+        .set_json(json!({ "username": "demo", "email": "demo@example.com", "password": "demo-password" }))
+        .to_request();
+    let registered: Value = test::call_and_read_body_json(&app, req).await;
+    let user_id = registered["data"]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/login")
+        .set_json(json!({ "username": "demo", "password": "demo-password" }))
+        .to_request();
+    let token: Value = test::call_and_read_body_json(&app, req).await;
+    let token = token["access_token"].as_str().unwrap();
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/account/{}", user_id))
+        .insert_header(bearer(token))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(account["user_id"], user_id);
+
+    // Without Postgres the database checks are left out rather than reported down
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: Value = test::read_body_json(resp).await;
+    assert!(report["components"].get("database").is_none());
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
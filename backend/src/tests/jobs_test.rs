@@ -0,0 +1,234 @@
+// File: src/tests/jobs_test.rs
+//
+// The background job queue: worker retry and failure handling and the scheduled media
+// reconciliation against the in-memory repository, and claiming from concurrent workers
+// against Postgres.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use crate::application::use_cases::media_use_cases::ReconcileMediaUseCase;
+use crate::domain::entities::job::{Job, JobStatus, NewJob};
+use crate::domain::entities::media::MediaReconciliation;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::infrastructure::config::settings::{JobSettings, MediaGcSettings};
+use crate::infrastructure::jobs::{JobError, JobHandler, JobWorker};
+use crate::infrastructure::repositories::in_memory::{
+    InMemoryAttachmentRepository, InMemoryAvatarRepository, InMemoryJobRepository, InMemoryMediaObjectRepository, InMemoryStore,
+    InMemoryUploadRepository,
+};
+use crate::infrastructure::repositories::job_repository::JobRepositoryImpl;
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use crate::presentation::jobs::media_jobs::MediaReconciliationJobHandler;
+use crate::tests::support::test_context;
+
+const LEASE: Duration = Duration::from_secs(600);
+
+/// Fails every attempt the way it is told to and records what the worker reported back.
+#[derive(Default)]
+struct FailingHandler {
+    retryable: bool,
+    attempts: Mutex<Vec<i32>>,
+    failures: Mutex<Vec<(i64, String)>>,
+}
+
+#[async_trait(?Send)]
+impl JobHandler for FailingHandler {
+    fn kind(&self) -> &'static str {
+        "test.failing"
+    }
+
+    async fn run(&self, job: &Job) -> Result<Option<Value>, JobError> {
+        self.attempts.lock().unwrap().push(job.attempts);
+        Err(JobError { message: format!("attempt {} failed", job.attempts), retryable: self.retryable })
+    }
+
+    async fn failed(&self, job: &Job, error: &str) {
+        self.failures.lock().unwrap().push((job.id, error.to_string()));
+    }
+}
+
+struct EchoHandler;
+
+#[async_trait(?Send)]
+impl JobHandler for EchoHandler {
+    fn kind(&self) -> &'static str {
+        "test.echo"
+    }
+
+    async fn run(&self, job: &Job) -> Result<Option<Value>, JobError> {
+        Ok(Some(job.payload.clone()))
+    }
+}
+
+fn worker(store: &InMemoryStore, handler: Arc<dyn JobHandler + Send + Sync>) -> JobWorker<InMemoryJobRepository> {
+    JobWorker::new(InMemoryJobRepository::new(store.clone()), vec![handler], JobSettings::default())
+}
+
+#[actix_web::test]
+async fn test_worker_records_result_and_ignores_other_kinds() {
+    let store = InMemoryStore::new();
+    let jobs = InMemoryJobRepository::new(store.clone());
+    let worker = worker(&store, Arc::new(EchoHandler));
+
+    let echo = jobs.enqueue(NewJob::new("test.echo", json!({ "hello": "world" }))).await.unwrap();
+    let other = jobs.enqueue(NewJob::new("test.unhandled", json!({}))).await.unwrap();
+
+    assert_eq!(worker.run_pending().await.unwrap(), 1);
+
+    let echo = jobs.find_by_id(echo.id).await.unwrap().unwrap();
+    assert_eq!(echo.status, JobStatus::Succeeded);
+    assert_eq!(echo.attempts, 1);
+    assert_eq!(echo.result, Some(json!({ "hello": "world" })));
+    assert!(echo.locked_at.is_none());
+
+    let other = jobs.find_by_id(other.id).await.unwrap().unwrap();
+    assert_eq!(other.status, JobStatus::Queued);
+    assert_eq!(other.attempts, 0);
+}
+
+#[actix_web::test]
+async fn test_worker_retries_with_backoff_until_attempts_run_out() {
+    let store = InMemoryStore::new();
+    let jobs = InMemoryJobRepository::new(store.clone());
+    let handler = Arc::new(FailingHandler { retryable: true, ..Default::default() });
+    let worker = worker(&store, handler.clone());
+
+    let job = jobs.enqueue(NewJob { max_attempts: 2, ..NewJob::new("test.failing", json!({})) }).await.unwrap();
+
+    // The first failure puts the job back in the queue, but not straight away
+    assert_eq!(worker.run_pending().await.unwrap(), 1);
+    let queued = jobs.find_by_id(job.id).await.unwrap().unwrap();
+    assert_eq!(queued.status, JobStatus::Queued);
+    assert_eq!(queued.last_error.as_deref(), Some("attempt 1 failed"));
+    assert!(queued.run_at > queued.updated_at, "retry is scheduled after a backoff");
+    assert!(handler.failures.lock().unwrap().is_empty());
+
+    store.tables().jobs.get_mut(&job.id).unwrap().run_at = queued.updated_at;
+    assert_eq!(worker.run_pending().await.unwrap(), 1);
+
+    let failed = jobs.find_by_id(job.id).await.unwrap().unwrap();
+    assert_eq!(failed.status, JobStatus::Failed);
+    assert_eq!(failed.attempts, 2);
+    assert_eq!(*handler.attempts.lock().unwrap(), vec![1, 2]);
+    assert_eq!(*handler.failures.lock().unwrap(), vec![(job.id, "attempt 2 failed".to_string())]);
+    assert_eq!(worker.run_pending().await.unwrap(), 0);
+}
+
+#[actix_web::test]
+async fn test_worker_does_not_retry_permanent_failures() {
+    let store = InMemoryStore::new();
+    let jobs = InMemoryJobRepository::new(store.clone());
+    let handler = Arc::new(FailingHandler::default());
+    let worker = worker(&store, handler.clone());
+
+    let job = jobs.enqueue(NewJob::new("test.failing", json!({}))).await.unwrap();
+    assert_eq!(worker.run_pending().await.unwrap(), 1);
+
+    let failed = jobs.find_by_id(job.id).await.unwrap().unwrap();
+    assert_eq!(failed.status, JobStatus::Failed);
+    assert_eq!(failed.attempts, 1);
+    assert_eq!(handler.failures.lock().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_media_reconciliation_keeps_one_run_scheduled() {
+    let store = InMemoryStore::new();
+    let jobs = InMemoryJobRepository::new(store.clone());
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let settings = MediaGcSettings { interval: Some(Duration::from_secs(3600)), ..MediaGcSettings::default() };
+    let handler = Arc::new(MediaReconciliationJobHandler::new(
+        ReconcileMediaUseCase::new(
+            InMemoryAvatarRepository::new(store.clone()),
+            InMemoryAttachmentRepository::new(store.clone()),
+            InMemoryUploadRepository::new(store.clone()),
+            jobs.clone(),
+            InMemoryMediaObjectRepository::new(store.clone()),
+            media_storage,
+            settings.grace_period,
+        ),
+        jobs.clone(),
+        settings,
+    ));
+    let worker = worker(&store, handler.clone());
+
+    // Every instance schedules at startup; only the first one queues a run
+    handler.schedule(None).await.unwrap();
+    handler.schedule(None).await.unwrap();
+    let scheduled = jobs.find_unfinished(MediaReconciliation::JOB_KIND).await.unwrap();
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(worker.run_pending().await.unwrap(), 0, "the first run waits for the interval");
+
+    store.tables().jobs.get_mut(&scheduled[0].id).unwrap().run_at = scheduled[0].created_at;
+    assert_eq!(worker.run_pending().await.unwrap(), 1);
+
+    let done = jobs.find_by_id(scheduled[0].id).await.unwrap().unwrap();
+    assert_eq!(done.status, JobStatus::Succeeded);
+    let report: MediaReconciliation = serde_json::from_value(done.result.unwrap()).unwrap();
+    assert_eq!(report, MediaReconciliation::default());
+
+    let next = jobs.find_unfinished(MediaReconciliation::JOB_KIND).await.unwrap();
+    assert_eq!(next.len(), 1);
+    assert!(next[0].run_at > done.updated_at);
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_claim_reclaims_jobs_whose_lease_ran_out() {
+    let ctx = test_context!();
+    let jobs = JobRepositoryImpl::new(ctx.pool());
+
+    let job = jobs.enqueue(NewJob::new("test.echo", json!({}))).await.unwrap();
+    let claimed = jobs.claim(&["test.echo"], LEASE).await.unwrap().expect("job is due");
+    assert_eq!((claimed.id, claimed.status, claimed.attempts), (job.id, JobStatus::Running, 1));
+
+    // Still leased to the first worker
+    assert!(jobs.claim(&["test.echo"], LEASE).await.unwrap().is_none());
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let reclaimed = jobs.claim(&["test.echo"], Duration::ZERO).await.unwrap().expect("lease ran out");
+    assert_eq!((reclaimed.id, reclaimed.attempts), (job.id, 2));
+
+    assert_eq!(jobs.find_unfinished("test.echo").await.unwrap().len(), 1);
+    jobs.complete(job.id, Some(json!({ "done": true }))).await.unwrap();
+    let done = jobs.find_by_id(job.id).await.unwrap().unwrap();
+    assert_eq!(done.status, JobStatus::Succeeded);
+    assert!(jobs.claim(&["test.echo"], Duration::ZERO).await.unwrap().is_none());
+    assert!(jobs.find_unfinished("test.echo").await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_concurrent_workers_never_claim_the_same_job() {
+    let ctx = test_context!();
+    let jobs = JobRepositoryImpl::new(ctx.pool());
+
+    let mut enqueued = HashSet::new();
+    for n in 0..20 {
+        enqueued.insert(jobs.enqueue(NewJob::new("test.echo", json!({ "n": n }))).await.unwrap().id);
+    }
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let jobs = jobs.clone();
+            std::thread::spawn(move || {
+                let mut claimed = Vec::new();
+                while let Some(job) = futures::executor::block_on(jobs.claim(&["test.echo"], LEASE)).unwrap() {
+                    claimed.push(job.id);
+                }
+                claimed
+            })
+        })
+        .collect();
+
+    let mut claimed = HashSet::new();
+    for worker in workers {
+        for id in worker.join().unwrap() {
+            assert!(claimed.insert(id), "job {} was claimed twice", id);
+        }
+    }
+    assert_eq!(claimed, enqueued);
+}
@@ -0,0 +1,84 @@
+// File: src/tests/media_storage_test.rs
+//
+// Contract tests run against every `MediaStorage` backend. The S3 backend is exercised
+// against MinIO (see `docker-compose.yml`) when `TEST_S3_ENDPOINT` is set, and skipped
+// otherwise.
+
+use crate::domain::repositories::media_storage::{MediaStorage, MediaStorageError};
+use crate::infrastructure::config::settings::S3Settings;
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use crate::infrastructure::storage::s3::S3MediaStorage;
+
+async fn assert_storage_contract<S: MediaStorage>(storage: &S) {
+    let key = format!("tests/{}/object.txt", uuid::Uuid::new_v4());
+
+    assert!(!storage.exists(&key).await.unwrap());
+    assert!(matches!(storage.get(&key).await, Err(MediaStorageError::NotFound(_))));
+
+    storage.put(&key, b"first".to_vec(), "text/plain").await.unwrap();
+    storage.put(&key, b"second".to_vec(), "text/plain").await.unwrap();
+    assert!(storage.exists(&key).await.unwrap());
+    assert_eq!(storage.get(&key).await.unwrap(), b"second");
+
+    storage.delete(&key).await.unwrap();
+    assert!(!storage.exists(&key).await.unwrap());
+    // Deleting twice is fine
+    storage.delete(&key).await.unwrap();
+
+    let prefix = format!("tests/{}/", uuid::Uuid::new_v4());
+    for name in ["a.txt", "nested/b.txt"] {
+        storage.put(&format!("{}{}", prefix, name), b"listed".to_vec(), "text/plain").await.unwrap();
+    }
+    let mut listed = storage.list(&prefix).await.unwrap();
+    listed.sort_by(|a, b| a.key.cmp(&b.key));
+    let keys: Vec<&str> = listed.iter().map(|object| object.key.as_str()).collect();
+    assert_eq!(keys, [format!("{}a.txt", prefix), format!("{}nested/b.txt", prefix)]);
+    assert!(listed.iter().all(|object| object.size == 6 && object.last_modified.is_some()));
+    assert!(storage.list(&format!("{}missing/", prefix)).await.unwrap().is_empty());
+    for object in listed {
+        storage.delete(&object.key).await.unwrap();
+    }
+
+    for invalid in ["", "/etc/passwd", "../secrets", "avatars/../../x", "a//b", "http://evil/x"] {
+        assert!(
+            matches!(storage.put(invalid, vec![], "text/plain").await, Err(MediaStorageError::InvalidKey(_))),
+            "{:?} should be rejected",
+            invalid
+        );
+    }
+}
+
+#[actix_web::test]
+async fn test_local_media_storage() {
+    let root = std::env::temp_dir().join(format!("media_{}", uuid::Uuid::new_v4()));
+    let storage = LocalMediaStorage::new(root.clone(), "https://cdn.example.com/media/".to_string());
+
+    assert_storage_contract(&storage).await;
+    assert_eq!(storage.public_url("avatars/1/a.webp"), "https://cdn.example.com/media/avatars/1/a.webp");
+
+    std::fs::remove_dir_all(root).ok();
+}
+
+#[actix_web::test]
+async fn test_s3_media_storage() {
+    let Ok(endpoint) = std::env::var("TEST_S3_ENDPOINT") else {
+        eprintln!("skipping: TEST_S3_ENDPOINT is not set");
+        return;
+    };
+    let env_or = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+    let settings = S3Settings {
+        endpoint: endpoint.clone(),
+        region: env_or("TEST_S3_REGION", "us-east-1"),
+        bucket: env_or("TEST_S3_BUCKET", "media"),
+        access_key_id: env_or("TEST_S3_ACCESS_KEY_ID", "minioadmin"),
+        secret_access_key: env_or("TEST_S3_SECRET_ACCESS_KEY", "minioadmin"),
+        path_style: true,
+    };
+    let storage = S3MediaStorage::new(&settings, None).unwrap();
+
+    assert_storage_contract(&storage).await;
+    assert_eq!(
+        storage.public_url("avatars/1/a.webp"),
+        format!("{}/{}/avatars/1/a.webp", endpoint.trim_end_matches('/'), settings.bucket)
+    );
+}
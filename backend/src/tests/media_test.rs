@@ -0,0 +1,253 @@
+// File: src/tests/media_test.rs
+//
+// The `/media` endpoint: signed URLs, bearer fallback, conditional requests and ranges.
+// Runs on in-memory repositories with local media storage, so no database is needed.
+
+use std::time::Duration;
+use actix_web::http::{header, StatusCode};
+use actix_web::test;
+use crate::app::{build_app_with_state, AppState, Repositories};
+use crate::application::use_cases::message_use_cases::SendMessageUseCase;
+use crate::domain::entities::attachment::NewAttachment;
+use crate::domain::entities::auth::RegisterUserDto;
+use crate::domain::entities::user::User;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::auth_repository::AuthRepository;
+use crate::infrastructure::config::settings::{ChatSettings, JobSettings, MediaSettings, Settings};
+use crate::infrastructure::storage::{self, signing::{MediaUrlSigner, SignatureError}};
+use crate::tests::support::TEST_SECRET_KEY;
+use crate::tests::support::tokens::{bearer, token_for};
+
+const KEY: &str = "avatars/7/300_test.webp";
+const CONTENT: &[u8] = b"0123456789abcdef";
+
+struct MediaFixture {
+    settings: Settings,
+}
+
+impl MediaFixture {
+    fn new() -> Self {
+        let upload_dir = std::env::temp_dir().join(format!("media_endpoint_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(upload_dir.join("avatars/7")).unwrap();
+        std::fs::write(upload_dir.join(KEY), CONTENT).unwrap();
+
+        Self {
+            settings: Settings {
+                secret_key: TEST_SECRET_KEY.to_string(),
+                upload_dir,
+                bind_address: "127.0.0.1:0".to_string(),
+                allowed_origins: vec![],
+                media: MediaSettings::local(),
+                jobs: JobSettings::default(),
+                chat: ChatSettings::default(),
+            },
+        }
+    }
+
+    fn signed_url(&self, key: &str) -> String {
+        storage::signer_from_settings(&self.settings).sign(key)
+    }
+}
+
+impl Drop for MediaFixture {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.settings.upload_dir).ok();
+    }
+}
+
+#[test]
+async fn test_signer_round_trip_and_expiry() {
+    let signer = MediaUrlSigner::new(b"signing-secret", Duration::from_secs(60));
+    let url = signer.sign_at("a/b.webp", 1_000);
+    let query = url.split_once('?').unwrap().1;
+    let params: Vec<(&str, &str)> = query.split('&').map(|pair| pair.split_once('=').unwrap()).collect();
+    let expires: i64 = params[0].1.parse().unwrap();
+    let signature = params[1].1;
+
+    assert!(url.starts_with("/media/a/b.webp?"));
+    assert!((1_060..=1_120).contains(&expires), "valid for one to two TTLs");
+    // Stable within a window so caches can reuse it
+    assert_eq!(signer.sign_at("a/b.webp", 1_001), url);
+
+    assert_eq!(signer.verify_at("a/b.webp", expires, signature, 1_000), Ok(()));
+    assert_eq!(signer.verify_at("a/b.webp", expires, signature, expires + 1), Err(SignatureError::Expired));
+    assert_eq!(signer.verify_at("a/c.webp", expires, signature, 1_000), Err(SignatureError::Invalid));
+    assert_eq!(signer.verify_at("a/b.webp", expires + 60, signature, 1_000), Err(SignatureError::Invalid));
+    assert_eq!(signer.verify_at("a/b.webp", expires, "not-hex", 1_000), Err(SignatureError::Invalid));
+}
+
+#[actix_web::test]
+async fn test_signed_url_serves_object_with_cache_headers() {
+    let fixture = MediaFixture::new();
+    let app = test::init_service(build_app_with_state(&AppState::in_memory(fixture.settings.clone()))).await;
+
+    let req = test::TestRequest::get().uri(&fixture.signed_url(KEY)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let headers = resp.headers().clone();
+    assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "image/webp");
+    assert_eq!(headers.get(header::ACCEPT_RANGES).unwrap(), "bytes");
+    assert!(headers.get(header::CACHE_CONTROL).unwrap().to_str().unwrap().starts_with("private, max-age="));
+    let etag = headers.get(header::ETAG).expect("ETag").clone();
+    let last_modified = headers.get(header::LAST_MODIFIED).expect("Last-Modified").clone();
+    assert_eq!(test::read_body(resp).await, CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri(&fixture.signed_url(KEY))
+        .insert_header((header::IF_NONE_MATCH, etag))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_MODIFIED);
+
+    let req = test::TestRequest::get()
+        .uri(&fixture.signed_url(KEY))
+        .insert_header((header::IF_MODIFIED_SINCE, last_modified))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_MODIFIED);
+
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::HEAD)
+        .uri(&fixture.signed_url(KEY))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(header::CONTENT_LENGTH).unwrap(), &CONTENT.len().to_string());
+}
+
+#[actix_web::test]
+async fn test_range_requests() {
+    let fixture = MediaFixture::new();
+    let app = test::init_service(build_app_with_state(&AppState::in_memory(fixture.settings.clone()))).await;
+
+    let req = test::TestRequest::get()
+        .uri(&fixture.signed_url(KEY))
+        .insert_header((header::RANGE, "bytes=2-5"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.headers().get(header::CONTENT_RANGE).unwrap(), "bytes 2-5/16");
+    assert_eq!(test::read_body(resp).await, &CONTENT[2..=5]);
+
+    let req = test::TestRequest::get()
+        .uri(&fixture.signed_url(KEY))
+        .insert_header((header::RANGE, "bytes=-4"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(test::read_body(resp).await, &CONTENT[12..]);
+
+    let req = test::TestRequest::get()
+        .uri(&fixture.signed_url(KEY))
+        .insert_header((header::RANGE, "bytes=100-200"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(resp.headers().get(header::CONTENT_RANGE).unwrap(), "bytes */16");
+
+    // A stale If-Range gets the whole object
+    let req = test::TestRequest::get()
+        .uri(&fixture.signed_url(KEY))
+        .insert_header((header::RANGE, "bytes=2-5"))
+        .insert_header((header::IF_RANGE, "\"stale\""))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_media_access_control() {
+    let fixture = MediaFixture::new();
+    let app = test::init_service(build_app_with_state(&AppState::in_memory(fixture.settings.clone()))).await;
+
+    let tampered = fixture.signed_url(KEY).replace("300_test", "40_test");
+    let req = test::TestRequest::get().uri(&tampered).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let signer = storage::signer_from_settings(&fixture.settings);
+    let expired = signer.sign_at(KEY, chrono::Utc::now().timestamp() - 3 * 3600);
+    let req = test::TestRequest::get().uri(&expired).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get().uri(&format!("/media/{}", KEY)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // A bearer token alone does not open someone else's avatar
+    let req = test::TestRequest::get()
+        .uri(&format!("/media/{}", KEY))
+        .insert_header(bearer(&token_for(1)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // Keys escaping the storage root look like any other missing object
+    let req = test::TestRequest::get().uri(&fixture.signed_url("avatars/../../etc/passwd")).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    // The old public directory listing is gone; prefixes nobody owns are refused before any lookup
+    for uri in ["/uploads/", "/uploads/avatars/7/", "/media/", "/media/avatars/7/"] {
+        let req = test::TestRequest::get().uri(uri).insert_header(bearer(&token_for(1))).to_request();
+        let status = test::call_service(&app, req).await.status();
+        assert!(status == StatusCode::NOT_FOUND || status == StatusCode::FORBIDDEN, "{} answered {}", uri, status);
+    }
+}
+
+async fn register(repositories: &Repositories, username: &str) -> User {
+    repositories.auth
+        .register(RegisterUserDto {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+            first_name: None,
+            middle_name: None,
+            last_name: None,
+        })
+        .await
+        .expect("registration succeeds")
+}
+
+#[actix_web::test]
+async fn test_bearer_access_is_limited_to_participants() {
+    let fixture = MediaFixture::new();
+    let repositories = Repositories::in_memory(TEST_SECRET_KEY);
+    let alice = register(&repositories, "alice").await;
+    let bob = register(&repositories, "bob").await;
+    let mallory = register(&repositories, "mallory").await;
+
+    let attachment_key = "attachments/0123abcd";
+    std::fs::create_dir_all(fixture.settings.upload_dir.join("attachments")).unwrap();
+    std::fs::write(fixture.settings.upload_dir.join(attachment_key), CONTENT).unwrap();
+    let attachment = repositories.attachments
+        .create(NewAttachment {
+            user_id: alice.id,
+            storage_key: attachment_key.to_string(),
+            filename: Some("notes.txt".to_string()),
+            content_type: "text/plain".to_string(),
+            byte_size: CONTENT.len() as i64,
+        })
+        .await
+        .unwrap();
+    SendMessageUseCase::new(repositories.messages.clone(), repositories.attachments.clone())
+        .execute(alice.id, bob.id, String::new(), Some(attachment.id))
+        .await
+        .unwrap();
+
+    let app = test::init_service(build_app_with_state(&AppState::with_repositories(fixture.settings.clone(), repositories, None))).await;
+
+    for user in [&alice, &bob] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/media/{}", attachment_key))
+            .insert_header(bearer(&token_for(user.id)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{} is a participant", user.username);
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "private, no-cache");
+        assert_eq!(test::read_body(resp).await, CONTENT);
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/media/{}", attachment_key))
+        .insert_header(bearer(&token_for(mallory.id)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // A signed URL still works for anyone holding it
+    let req = test::TestRequest::get().uri(&fixture.signed_url(attachment_key)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
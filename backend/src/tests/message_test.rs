@@ -0,0 +1,180 @@
+// File: src/tests/message_test.rs
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::Value;
+use crate::app::build_app;
+use crate::tests::support::test_context;
+use crate::tests::support::seeds::{seed_message, UserSeed};
+use crate::tests::support::tokens::{bearer, token_for};
+
+#[actix_web::test]
+async fn test_get_conversation_history() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let carol = UserSeed::new("carol").create(&ctx.pool()).await;
+
+    seed_message(&ctx.pool(), alice.id, bob.id, "hi bob").await;
+    seed_message(&ctx.pool(), bob.id, alice.id, "hi alice").await;
+    seed_message(&ctx.pool(), carol.id, alice.id, "unrelated").await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/conversations/{}/messages", bob.id))
+        .insert_header(bearer(&token_for(alice.id)))
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(contents(&page), vec!["hi alice", "hi bob"]);
+    assert_eq!(page["has_more"], false);
+    assert!(page["older"].is_null());
+
+    // History is always the caller's own; there is no way to name two other users
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/messages/{}/{}", alice.id, bob.id))
+        .insert_header(bearer(&token_for(carol.id)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/conversations/{}/messages", bob.id))
+        .insert_header(bearer(&token_for(carol.id)))
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(contents(&page).is_empty());
+}
+
+fn contents(page: &Value) -> Vec<&str> {
+    page["messages"].as_array().unwrap().iter().map(|m| m["content"].as_str().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn test_conversation_history_is_paged() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    for n in 1..=5 {
+        seed_message(&ctx.pool(), alice.id, bob.id, &n.to_string()).await;
+    }
+    let history = |query: String| test::TestRequest::get()
+        .uri(&format!("/api/v1/conversations/{}/messages?{}", bob.id, query))
+        .insert_header(bearer(&token_for(alice.id)))
+        .to_request();
+
+    let latest: Value = test::call_and_read_body_json(&app, history("limit=3".to_string())).await;
+    assert_eq!(contents(&latest), vec!["5", "4", "3"]);
+    assert_eq!(latest["has_more"], true);
+
+    let older = latest["older"].as_str().unwrap();
+    let rest: Value = test::call_and_read_body_json(&app, history(format!("limit=3&before={}", older))).await;
+    assert_eq!(contents(&rest), vec!["2", "1"]);
+    assert_eq!(rest["has_more"], false);
+
+    let newer = rest["newer"].as_str().unwrap();
+    let caught_up: Value = test::call_and_read_body_json(&app, history(format!("limit=1&after={}", newer))).await;
+    assert_eq!(contents(&caught_up), vec!["4"]);
+    assert_eq!(caught_up["has_more"], true);
+
+    let resp = test::call_service(&app, history("before=garbage".to_string())).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_send_message_to_offline_recipient() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let send = |to: i32, body: Value| test::TestRequest::post()
+        .uri(&format!("/api/v1/conversations/{}/messages", to))
+        .insert_header(bearer(&token_for(alice.id)))
+        .set_json(body)
+        .to_request();
+
+    let resp = test::call_service(&app, send(bob.id, serde_json::json!({ "content": "are you there?" }))).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let message: Value = test::read_body_json(resp).await;
+    assert_eq!(message["sender_id"], alice.id);
+    assert_eq!(message["receiver_id"], bob.id);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/conversations/{}/messages", alice.id))
+        .insert_header(bearer(&token_for(bob.id)))
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["messages"][0]["id"], message["id"]);
+
+    let resp = test::call_service(&app, send(bob.id, serde_json::json!({ "content": "  " }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, send(bob.id, serde_json::json!({ "content": "hi", "attachment_id": 9999 }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, send(9999, serde_json::json!({ "content": "anyone?" }))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/v1/conversations/{}/messages", bob.id))
+        .set_json(serde_json::json!({ "content": "who am I?" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_messages_are_edited_and_deleted() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let typo = seed_message(&ctx.pool(), alice.id, bob.id, "helo").await;
+    let reply = seed_message(&ctx.pool(), bob.id, alice.id, "hi").await;
+    let unread = seed_message(&ctx.pool(), alice.id, bob.id, "oops, wrong chat").await;
+    let edit = |user_id: i32, message_id: i32, content: &str| test::TestRequest::patch()
+        .uri(&format!("/api/v1/messages/{}", message_id))
+        .insert_header(bearer(&token_for(user_id)))
+        .set_json(serde_json::json!({ "content": content }))
+        .to_request();
+    let delete = |user_id: i32, message_id: i32, query: &str| test::TestRequest::delete()
+        .uri(&format!("/api/v1/messages/{}?{}", message_id, query))
+        .insert_header(bearer(&token_for(user_id)))
+        .to_request();
+    let history = |user_id: i32, counterpart_id: i32| test::TestRequest::get()
+        .uri(&format!("/api/v1/conversations/{}/messages", counterpart_id))
+        .insert_header(bearer(&token_for(user_id)))
+        .to_request();
+
+    let edited: Value = test::call_and_read_body_json(&app, edit(alice.id, typo.id, "hello")).await;
+    assert_eq!(edited["content"], "hello");
+    assert!(edited["edited_at"].is_string());
+    assert_eq!(test::call_service(&app, edit(bob.id, typo.id, "hijacked")).await.status(), StatusCode::FORBIDDEN);
+    assert_eq!(test::call_service(&app, edit(alice.id, 9999, "anything")).await.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/messages/{}/edits", typo.id))
+        .insert_header(bearer(&token_for(bob.id)))
+        .to_request();
+    let edits: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(edits[0]["content"], "helo");
+
+    // Deleting for oneself only hides the message from the one deleting it
+    assert_eq!(test::call_service(&app, delete(alice.id, reply.id, "")).await.status(), StatusCode::NO_CONTENT);
+    let page: Value = test::call_and_read_body_json(&app, history(alice.id, bob.id)).await;
+    assert_eq!(contents(&page), vec!["oops, wrong chat", "hello"]);
+    let page: Value = test::call_and_read_body_json(&app, history(bob.id, alice.id)).await;
+    assert_eq!(contents(&page), vec!["oops, wrong chat", "hi", "hello"]);
+
+    assert_eq!(test::call_service(&app, delete(bob.id, unread.id, "for_everyone=true")).await.status(), StatusCode::FORBIDDEN);
+    assert_eq!(test::call_service(&app, delete(alice.id, unread.id, "for_everyone=true")).await.status(), StatusCode::NO_CONTENT);
+    let page: Value = test::call_and_read_body_json(&app, history(bob.id, alice.id)).await;
+    assert_eq!(page["messages"][0]["id"], unread.id);
+    assert_eq!(page["messages"][0]["content"], "");
+    assert!(page["messages"][0]["deleted_at"].is_string());
+    assert_eq!(test::call_service(&app, edit(alice.id, unread.id, "back")).await.status(), StatusCode::NOT_FOUND);
+
+    // A message deleted for everyone no longer counts as unread
+    let req = test::TestRequest::get()
+        .uri("/api/v1/conversations")
+        .insert_header(bearer(&token_for(bob.id)))
+        .to_request();
+    let inbox: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(inbox["conversations"][0]["unread_count"], 1);
+}
@@ -0,0 +1,73 @@
+// File: src/tests/metrics_test.rs
+//
+// The Prometheus scrape endpoint. Metrics live in one process-wide registry, so other tests
+// add to the same series; assertions look for series and lower bounds, not exact counts.
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::json;
+use crate::app::{build_app_with_state, AppState, Repositories};
+use crate::domain::entities::auth::RegisterUserDto;
+use crate::domain::entities::user::User;
+use crate::domain::repositories::auth_repository::AuthRepository;
+use crate::infrastructure::config::settings::{ChatSettings, JobSettings, Settings};
+use crate::tests::support::{test_media_settings, TEST_SECRET_KEY};
+use crate::tests::support::tokens::{bearer, token_for};
+
+async fn register(repositories: &Repositories, username: &str) -> User {
+    repositories.auth
+        .register(RegisterUserDto {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+            first_name: None,
+            middle_name: None,
+            last_name: None,
+        })
+        .await
+        .expect("registration succeeds")
+}
+
+/// The value of the first sample whose line starts with `series`.
+fn sample(scrape: &str, series: &str) -> Option<f64> {
+    scrape.lines()
+        .find(|line| line.starts_with(series))
+        .and_then(|line| line.rsplit(' ').next()?.parse().ok())
+}
+
+#[actix_web::test]
+async fn test_metrics_count_messages_and_requests() {
+    let settings = Settings {
+        secret_key: TEST_SECRET_KEY.to_string(),
+        upload_dir: std::env::temp_dir().join(format!("uploads_metrics_{}", uuid::Uuid::new_v4())),
+        bind_address: "127.0.0.1:0".to_string(),
+        allowed_origins: vec![],
+        media: test_media_settings(),
+        jobs: JobSettings::default(),
+        chat: ChatSettings::default(),
+    };
+    let repositories = Repositories::in_memory(TEST_SECRET_KEY);
+    let alice = register(&repositories, "alice").await;
+    let bob = register(&repositories, "bob").await;
+    let app = test::init_service(build_app_with_state(&AppState::with_repositories(settings, repositories, None))).await;
+
+    // Bob has no socket open, so the message is queued for him
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/v1/conversations/{}/messages", bob.id))
+        .insert_header(bearer(&token_for(alice.id)))
+        .set_json(json!({ "content": "counted" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let scrape = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    assert!(sample(&scrape, r#"websocket_messages_total{outcome="sent"}"#) >= Some(1.0), "{}", scrape);
+    assert!(sample(&scrape, r#"websocket_messages_total{outcome="queued"}"#) >= Some(1.0), "{}", scrape);
+    let send = r#"{method="POST",route="/api/v1/conversations/{user_id}/messages",status="201"}"#;
+    assert!(sample(&scrape, &format!("http_requests_total{}", send)) >= Some(1.0), "{}", scrape);
+    assert!(sample(&scrape, &format!("http_request_duration_seconds_count{}", send)) >= Some(1.0), "{}", scrape);
+    assert!(scrape.contains("http_request_duration_seconds_bucket{method=\"POST\",route=\"/api/v1/conversations/{user_id}/messages\",status=\"201\",le="));
+    assert!(scrape.contains("# TYPE websocket_connections gauge"));
+}
@@ -1 +1,17 @@
-pub mod upload_avatar_test;
\ No newline at end of file
+pub mod support;
+pub mod account_test;
+pub mod auth_test;
+pub mod conversation_test;
+pub mod in_memory_app_test;
+pub mod jobs_test;
+pub mod media_storage_test;
+pub mod media_test;
+pub mod message_test;
+pub mod metrics_test;
+pub mod openapi_test;
+pub mod resumable_upload_test;
+pub mod telemetry_test;
+pub mod upload_avatar_test;
+pub mod use_cases;
+pub mod user_test;
+pub mod ws_test;
\ No newline at end of file
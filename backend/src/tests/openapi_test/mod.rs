@@ -0,0 +1,2 @@
+#[allow(clippy::module_inception)]
+pub mod openapi_test;
\ No newline at end of file
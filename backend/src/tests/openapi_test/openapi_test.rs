@@ -0,0 +1,98 @@
+// File: src/tests/openapi_test/openapi_test.rs
+//
+// The committed documents in `openapi/` are what the web and Android clients generate
+// their API code from. These tests fail when a handler or DTO change alters the generated
+// spec without the snapshot being refreshed; regenerate with
+// `UPDATE_API_SNAPSHOTS=1 cargo test openapi_test`.
+//
+// The spec is written apart from the routes, so it is also checked against the app itself.
+
+use std::path::PathBuf;
+use actix_web::http::{Method, StatusCode};
+use utoipa::OpenApi;
+use crate::app::{build_app_with_state, AppState};
+use crate::infrastructure::config::settings::{ChatSettings, JobSettings, Settings};
+use crate::presentation::openapi::{asyncapi_document, ApiDoc};
+use crate::tests::support::{test_media_settings, TEST_SECRET_KEY};
+use crate::tests::support::tokens::{bearer, token_for};
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openapi").join(name)
+}
+
+fn assert_matches_snapshot(name: &str, generated: serde_json::Value) {
+    let path = snapshot_path(name);
+    let rendered = serde_json::to_string_pretty(&generated).expect("document serializes") + "\n";
+
+    if std::env::var("UPDATE_API_SNAPSHOTS").is_ok() {
+        std::fs::write(&path, &rendered).expect("snapshot is writable");
+        return;
+    }
+
+    let committed = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Missing snapshot {:?}: {}", path, e));
+    let committed: serde_json::Value = serde_json::from_str(&committed).expect("snapshot is valid JSON");
+
+    assert_eq!(committed, generated,
+               "{} drifted from the handlers; rerun with UPDATE_API_SNAPSHOTS=1 and commit the result", name);
+}
+
+#[test]
+fn test_openapi_spec_matches_snapshot() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).expect("spec serializes");
+    assert_eq!(spec["openapi"], "3.1.0");
+    assert_matches_snapshot("openapi.json", spec);
+}
+
+#[test]
+fn test_asyncapi_spec_matches_snapshot() {
+    assert_matches_snapshot("asyncapi.json", asyncapi_document());
+}
+
+/// `/media/{key:.*}` as documented: `/media/{key}`.
+fn without_regex(pattern: &str) -> String {
+    pattern.split('/')
+        .map(|segment| match segment.split_once(':') {
+            Some((name, _)) if segment.starts_with('{') => format!("{}}}", name),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[actix_web::test]
+async fn test_documented_operations_are_routed() {
+    let settings = Settings {
+        secret_key: TEST_SECRET_KEY.to_string(),
+        upload_dir: std::env::temp_dir().join(format!("uploads_openapi_{}", uuid::Uuid::new_v4())),
+        bind_address: "127.0.0.1:0".to_string(),
+        allowed_origins: vec![],
+        media: test_media_settings(),
+        jobs: JobSettings::default(),
+        chat: ChatSettings::default(),
+    };
+    let app = actix_web::test::init_service(build_app_with_state(&AppState::in_memory(settings))).await;
+
+    let spec = serde_json::to_value(ApiDoc::openapi()).expect("spec serializes");
+    let paths = spec["paths"].as_object().expect("spec has paths");
+    assert!(!paths.is_empty());
+    for (path, operations) in paths {
+        let uri = path.split('/')
+            .map(|segment| if segment.starts_with('{') { "1" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+        for method in operations.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes()).unwrap();
+            let req = actix_web::test::TestRequest::default()
+                .method(method.clone())
+                .uri(&uri)
+                .insert_header(bearer(&token_for(1)))
+                .to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+
+            let matched = resp.request().match_pattern().map(|pattern| without_regex(&pattern));
+            assert_eq!(matched.as_deref(), Some(path.as_str()), "{} {} is documented but not routed there", method, path);
+            assert_ne!(resp.status(), StatusCode::METHOD_NOT_ALLOWED, "{} {} is documented but not routed", method, path);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+// File: src/tests/resumable_upload_test.rs
+
+use actix_web::http::{Method, StatusCode};
+use actix_web::test;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::Value;
+use crate::app::{build_app_with_state, AppState};
+use crate::domain::entities::message::DatabaseMessage;
+use crate::domain::repositories::message_repository::MessageRepository;
+use crate::infrastructure::repositories::message_repository::MessageRepositoryImpl;
+use crate::tests::support::test_context;
+use crate::tests::support::seeds::UserSeed;
+use crate::tests::support::tokens::{bearer, token_for};
+
+const TEST_AVATAR: &[u8] = include_bytes!("upload_avatar_test/test_avatar.jpg");
+
+fn metadata(pairs: &[(&str, &str)]) -> String {
+    pairs.iter()
+        .map(|(key, value)| format!("{} {}", key, BASE64.encode(value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn create(token: &str, length: usize, metadata: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/v1/uploads")
+        .insert_header(bearer(token))
+        .insert_header(("Tus-Resumable", "1.0.0"))
+        .insert_header(("Upload-Length", length.to_string()))
+        .insert_header(("Upload-Metadata", metadata.to_string()))
+}
+
+fn patch(location: &str, token: &str, offset: usize, bytes: &[u8]) -> test::TestRequest {
+    test::TestRequest::patch()
+        .uri(location)
+        .insert_header(bearer(token))
+        .insert_header(("Tus-Resumable", "1.0.0"))
+        .insert_header(("Content-Type", "application/offset+octet-stream"))
+        .insert_header(("Upload-Offset", offset.to_string()))
+        .set_payload(bytes.to_vec())
+}
+
+fn header<B>(resp: &actix_web::dev::ServiceResponse<B>, name: &str) -> String {
+    resp.headers().get(name).unwrap_or_else(|| panic!("{} header", name)).to_str().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn test_avatar_uploaded_in_chunks_is_processed() {
+    let ctx = test_context!();
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let app = test::init_service(build_app_with_state(&state)).await;
+    let user = UserSeed::new("tus_owner").create(&ctx.pool()).await;
+    let token = token_for(user.id);
+
+    let req = test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .uri("/api/v1/uploads")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(header(&resp, "Tus-Version"), "1.0.0");
+    assert!(header(&resp, "Tus-Extension").contains("creation"));
+    assert!(header(&resp, "Tus-Max-Size").parse::<u64>().is_ok());
+
+    let resp = test::call_service(&app, create(&token, TEST_AVATAR.len(), &metadata(&[("purpose", "avatar")])).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(header(&resp, "Tus-Resumable"), "1.0.0");
+    assert_eq!(header(&resp, "Upload-Offset"), "0");
+    let location = header(&resp, "Location");
+    assert!(location.starts_with("/api/v1/uploads/"));
+
+    let half = TEST_AVATAR.len() / 2;
+    let resp = test::call_service(&app, patch(&location, &token, 0, &TEST_AVATAR[..half]).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(header(&resp, "Upload-Offset"), half.to_string());
+
+    // A client that lost track of the offset asks for it
+    let req = test::TestRequest::default()
+        .method(Method::HEAD)
+        .uri(&location)
+        .insert_header(bearer(&token))
+        .insert_header(("Tus-Resumable", "1.0.0"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(header(&resp, "Upload-Offset"), half.to_string());
+    assert_eq!(header(&resp, "Upload-Length"), TEST_AVATAR.len().to_string());
+    assert_eq!(header(&resp, "Cache-Control"), "no-store");
+
+    let resp = test::call_service(&app, patch(&location, &token, 0, TEST_AVATAR).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert_eq!(header(&resp, "Upload-Offset"), half.to_string());
+
+    let req = patch(&location, &token, half, &TEST_AVATAR[half..]).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(header(&resp, "Upload-Offset"), TEST_AVATAR.len().to_string());
+
+    let req = test::TestRequest::get().uri(&location).insert_header(bearer(&token)).to_request();
+    let upload: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(upload["purpose"], "avatar");
+    assert!(upload["completed_at"].is_string());
+    let job_id = upload["job_id"].as_i64().expect("processing queued");
+
+    state.job_worker.run_pending().await.expect("job queue is reachable");
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/jobs/{}", job_id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let job: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(job["status"], "succeeded");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/account/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(account["default_avatar_id"], job["result"]["avatar_id"]);
+
+    // Uploads belong to whoever created them
+    let other = token_for(UserSeed::new("tus_snoop").create(&ctx.pool()).await.id);
+    let req = test::TestRequest::get().uri(&location).insert_header(bearer(&other)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_upload_requests_are_validated() {
+    let ctx = test_context!();
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let app = test::init_service(build_app_with_state(&state)).await;
+    let user = UserSeed::new("tus_validated").create(&ctx.pool()).await;
+    let token = token_for(user.id);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/uploads")
+        .insert_header(bearer(&token))
+        .insert_header(("Upload-Length", "10"))
+        .insert_header(("Upload-Metadata", metadata(&[("purpose", "attachment")])))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(header(&resp, "Tus-Version"), "1.0.0");
+
+    let resp = test::call_service(&app, create(&token, 10, "").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, create(&token, 10, "purpose %%%").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let too_large = ctx.settings.media.upload_limits.max_bytes as usize + 1;
+    let resp = test::call_service(&app, create(&token, too_large, &metadata(&[("purpose", "avatar")])).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let resp = test::call_service(&app, create(&token, 4, &metadata(&[("purpose", "attachment")])).to_request()).await;
+    let location = header(&resp, "Location");
+
+    let req = patch(&location, &token, 0, b"abc").insert_header(("Content-Type", "application/octet-stream")).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let req = patch(&location, &token, 0, b"abcde").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let req = test::TestRequest::delete()
+        .uri(&location)
+        .insert_header(bearer(&token))
+        .insert_header(("Tus-Resumable", "1.0.0"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    let req = patch(&location, &token, 0, b"abcd").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_attachment_is_visible_to_the_conversation() {
+    let ctx = test_context!();
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let app = test::init_service(build_app_with_state(&state)).await;
+    let sender = UserSeed::new("tus_sender").create(&ctx.pool()).await;
+    let receiver = UserSeed::new("tus_receiver").create(&ctx.pool()).await;
+    let stranger = UserSeed::new("tus_stranger").create(&ctx.pool()).await;
+    let token = token_for(sender.id);
+    let bytes = b"minutes of the meeting";
+
+    let req = create(&token, bytes.len(), &metadata(&[("purpose", "attachment"), ("filename", "minutes.txt")])).to_request();
+    let location = header(&test::call_service(&app, req).await, "Location");
+    let resp = test::call_service(&app, patch(&location, &token, 0, bytes).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get().uri(&location).insert_header(bearer(&token)).to_request();
+    let upload: Value = test::call_and_read_body_json(&app, req).await;
+    let attachment_id = upload["attachment_id"].as_i64().expect("attachment created") as i32;
+
+    let attachment = |user_id: i32| test::TestRequest::get()
+        .uri(&format!("/api/v1/attachments/{}", attachment_id))
+        .insert_header(bearer(&token_for(user_id)))
+        .to_request();
+    let owned: Value = test::call_and_read_body_json(&app, attachment(sender.id)).await;
+    assert_eq!(owned["filename"], "minutes.txt");
+    assert_eq!(owned["byte_size"], bytes.len());
+    assert_eq!(test::call_service(&app, attachment(receiver.id)).await.status(), StatusCode::NOT_FOUND);
+
+    MessageRepositoryImpl::new(ctx.pool())
+        .save_message(DatabaseMessage {
+            id: 0,
+            sender_id: sender.id,
+            receiver_id: receiver.id,
+            content: "see attached".to_string(),
+            is_read: false,
+            created_at: chrono::Utc::now().naive_utc(),
+            attachment_id: Some(attachment_id),
+            delivered_at: None,
+            read_at: None,
+            edited_at: None,
+            deleted_at: None,
+        })
+        .await
+        .unwrap();
+
+    let shared: Value = test::call_and_read_body_json(&app, attachment(receiver.id)).await;
+    assert_eq!(test::call_service(&app, attachment(stranger.id)).await.status(), StatusCode::NOT_FOUND);
+
+    let url = shared["url"].as_str().expect("signed url");
+    let resp = test::call_service(&app, test::TestRequest::get().uri(url).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(test::read_body(resp).await, &bytes[..]);
+}
@@ -0,0 +1,125 @@
+// File: src/tests/support/mod.rs
+//
+// Shared fixtures for the in-process integration tests: an isolated Postgres schema per
+// test, JWT factories and seed builders. Database tests fail when `TEST_DATABASE_URL` or
+// `DATABASE_URL` is missing or unreachable, unless `SKIP_DB_TESTS=1` skips them on purpose.
+
+pub mod seeds;
+pub mod test_db;
+pub mod tokens;
+
+use std::path::PathBuf;
+use crate::domain::entities::media::{ImageFormat, VariantPlan};
+use crate::infrastructure::config::{database::DbPool, settings::{ChatSettings, JobSettings, MediaSettings, Settings}};
+use self::test_db::TestDb;
+
+pub const TEST_SECRET_KEY: &str = "integration-test-secret-key-0123456789";
+
+/// Local media storage with a variant plan trimmed to what the tests look at. AVIF
+/// encoding in particular is far too slow unoptimized to run on every test upload.
+pub fn test_media_settings() -> MediaSettings {
+    MediaSettings {
+        avatar_variants: test_variant_plan(),
+        ..MediaSettings::local()
+    }
+}
+
+pub fn test_variant_plan() -> VariantPlan {
+    VariantPlan {
+        sizes: vec![40, 300],
+        densities: vec![1, 2],
+        formats: vec![ImageFormat::Webp, ImageFormat::Jpeg],
+    }
+}
+
+pub struct TestContext {
+    pub db: TestDb,
+    pub settings: Settings,
+}
+
+impl TestContext {
+    /// `None` when database tests are skipped with `SKIP_DB_TESTS=1`.
+    pub fn new() -> Option<Self> {
+        if std::env::var("SKIP_DB_TESTS").is_ok_and(|value| value == "1") {
+            return None;
+        }
+        let db = TestDb::new()
+            .unwrap_or_else(|e| panic!("{}; set SKIP_DB_TESTS=1 to skip the database tests", e));
+
+        let upload_dir = std::env::temp_dir().join(format!("uploads_{}", db.schema()));
+        std::fs::create_dir_all(&upload_dir).expect("temporary upload dir is writable");
+
+        let settings = Settings {
+            secret_key: TEST_SECRET_KEY.to_string(),
+            upload_dir,
+            bind_address: "127.0.0.1:0".to_string(),
+            allowed_origins: vec!["http://localhost:3000".to_string()],
+            media: test_media_settings(),
+            jobs: JobSettings::default(),
+            chat: ChatSettings::default(),
+        };
+
+        Some(Self { db, settings })
+    }
+
+    pub fn pool(&self) -> DbPool {
+        self.db.pool.clone()
+    }
+
+    pub fn upload_dir(&self) -> &PathBuf {
+        &self.settings.upload_dir
+    }
+}
+
+impl Drop for TestContext {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.settings.upload_dir).ok();
+    }
+}
+
+/// Creates a `TestContext`, or returns early from the test when database tests are skipped.
+macro_rules! test_context {
+    () => {
+        match $crate::tests::support::TestContext::new() {
+            Some(ctx) => ctx,
+            None => {
+                eprintln!("skipping: SKIP_DB_TESTS=1");
+                return;
+            }
+        }
+    };
+}
+pub(crate) use test_context;
+
+/// Builds a `multipart/form-data` body with a single file field, returning the
+/// `Content-Type` header value and the encoded body.
+pub fn multipart_file(field: &str, filename: &str, content_type: &str, bytes: &[u8]) -> (String, Vec<u8>) {
+    multipart_form(&[], field, filename, content_type, bytes)
+}
+
+/// Like [`multipart_file`], with text fields sent ahead of the file.
+pub fn multipart_form(
+    text_fields: &[(&str, &str)],
+    field: &str,
+    filename: &str,
+    content_type: &str,
+    bytes: &[u8],
+) -> (String, Vec<u8>) {
+    let boundary = format!("----test-boundary-{}", uuid::Uuid::new_v4().simple());
+    let mut body = Vec::new();
+    for (name, value) in text_fields {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes());
+        body.extend_from_slice(value.as_bytes());
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(format!(
+        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n", field, filename
+    ).as_bytes());
+    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
@@ -0,0 +1,98 @@
+// File: src/tests/support/seeds.rs
+
+use diesel::prelude::*;
+use crate::domain::entities::auth::RegisterUserDto;
+use crate::domain::entities::message::DatabaseMessage;
+use crate::domain::repositories::auth_repository::AuthRepository;
+use crate::domain::repositories::message_repository::MessageRepository;
+use crate::infrastructure::config::database::DbPool;
+use crate::infrastructure::repositories::auth_repository::AuthRepositoryImpl;
+use crate::infrastructure::repositories::message_repository::MessageRepositoryImpl;
+use crate::schema::accounts;
+use super::TEST_SECRET_KEY;
+
+pub struct SeededUser {
+    pub id: i32,
+    pub account_id: i32,
+    pub username: String,
+    pub email: String,
+    pub password: String,
+}
+
+/// Registers a user (and its account) through the real auth repository so the stored
+/// password hash matches what login expects.
+pub struct UserSeed {
+    username: String,
+    email: String,
+    password: String,
+    first_name: Option<String>,
+    last_name: Option<String>,
+}
+
+impl UserSeed {
+    pub fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "correct horse battery staple".to_string(),
+            first_name: None,
+            last_name: None,
+        }
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_string();
+        self
+    }
+
+    pub fn names(mut self, first_name: &str, last_name: &str) -> Self {
+        self.first_name = Some(first_name.to_string());
+        self.last_name = Some(last_name.to_string());
+        self
+    }
+
+    pub async fn create(self, pool: &DbPool) -> SeededUser {
+        let repository = AuthRepositoryImpl::new(pool.clone(), TEST_SECRET_KEY.to_string());
+        let user = repository.register(RegisterUserDto {
+            username: self.username.clone(),
+            email: self.email.clone(),
+            password: self.password.clone(),
+            first_name: self.first_name,
+            middle_name: None,
+            last_name: self.last_name,
+        }).await.expect("Failed to seed user");
+
+        let account_id = accounts::table
+            .filter(accounts::user_id.eq(user.id))
+            .select(accounts::id)
+            .first::<i32>(&mut pool.get().expect("Failed to get connection"))
+            .expect("Seeded user has no account");
+
+        SeededUser {
+            id: user.id,
+            account_id,
+            username: self.username,
+            email: self.email,
+            password: self.password,
+        }
+    }
+}
+
+pub async fn seed_message(pool: &DbPool, sender_id: i32, receiver_id: i32, content: &str) -> DatabaseMessage {
+    MessageRepositoryImpl::new(pool.clone())
+        .save_message(DatabaseMessage {
+            id: 0,
+            sender_id,
+            receiver_id,
+            content: content.to_string(),
+            is_read: false,
+            created_at: chrono::Utc::now().naive_utc(),
+            attachment_id: None,
+            delivered_at: None,
+            read_at: None,
+            edited_at: None,
+            deleted_at: None,
+        })
+        .await
+        .expect("Failed to seed message")
+}
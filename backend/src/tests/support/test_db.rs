@@ -0,0 +1,74 @@
+// File: src/tests/support/test_db.rs
+
+use diesel::connection::SimpleConnection;
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+use diesel::Connection;
+use diesel_migrations::MigrationHarness;
+use crate::infrastructure::config::database::{DbPool, MIGRATIONS};
+
+/// Points every pooled connection at the test's private schema.
+#[derive(Debug)]
+struct SearchPath(String);
+
+impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for SearchPath {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.batch_execute(&format!("SET search_path TO {}", self.0))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// A freshly migrated schema that is dropped again when the fixture goes out of scope,
+/// so tests can run in parallel against one database without seeing each other's rows.
+pub struct TestDb {
+    pub pool: DbPool,
+    schema: String,
+    database_url: String,
+}
+
+impl TestDb {
+    /// Fails, rather than skipping, when no database is configured or reachable: a
+    /// misconfigured run should not pass by testing nothing.
+    pub fn new() -> Result<Self, String> {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("TEST_DATABASE_URL")
+            .or_else(|_| std::env::var("DATABASE_URL"))
+            .map_err(|_| "neither TEST_DATABASE_URL nor DATABASE_URL is set".to_string())?;
+
+        let mut admin = PgConnection::establish(&database_url)
+            .map_err(|e| format!("test database unavailable: {}", e))?;
+
+        let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+        admin.batch_execute(&format!("CREATE SCHEMA {}", schema))
+            .expect("Failed to create test schema");
+
+        let pool = Pool::builder()
+            .max_size(4)
+            .connection_customizer(Box::new(SearchPath(schema.clone())))
+            .build(ConnectionManager::<PgConnection>::new(&database_url))
+            .expect("Failed to create test pool");
+
+        pool.get()
+            .expect("Failed to get test connection")
+            .run_pending_migrations(MIGRATIONS)
+            .expect("Failed to run migrations");
+
+        Ok(Self {
+            pool,
+            schema,
+            database_url,
+        })
+    }
+
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        if let Ok(mut admin) = PgConnection::establish(&self.database_url) {
+            admin.batch_execute(&format!("DROP SCHEMA IF EXISTS {} CASCADE", self.schema)).ok();
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// File: src/tests/support/tokens.rs
+
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use crate::domain::entities::auth::Claims;
+use super::TEST_SECRET_KEY;
+
+fn token_with_expiry(user_id: i32, expires_in: Duration) -> String {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user_id,
+        exp: (now + expires_in).timestamp(),
+        iat: now.timestamp(),
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(TEST_SECRET_KEY.as_bytes()))
+        .expect("Failed to encode test token")
+}
+
+/// A valid bearer token for `user_id`, signed with the test secret.
+pub fn token_for(user_id: i32) -> String {
+    token_with_expiry(user_id, Duration::hours(1))
+}
+
+/// A correctly signed token that expired an hour ago.
+pub fn expired_token_for(user_id: i32) -> String {
+    token_with_expiry(user_id, Duration::hours(-1))
+}
+
+pub fn bearer(token: &str) -> (&'static str, String) {
+    ("Authorization", format!("Bearer {}", token))
+}
@@ -0,0 +1,109 @@
+// File: src/tests/telemetry_test.rs
+//
+// Request ids, W3C trace-context propagation and log redaction.
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::test;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing_subscriber::layer::SubscriberExt;
+use crate::app::{build_app_with_state, AppState};
+use crate::infrastructure::config::settings::{ChatSettings, JobSettings, Settings};
+use crate::infrastructure::telemetry::propagation::{extract_context, inject_context};
+use crate::infrastructure::telemetry::redact::is_sensitive;
+use crate::presentation::middleware::request_id::REQUEST_ID_HEADER;
+use crate::tests::support::{test_media_settings, TEST_SECRET_KEY};
+
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+fn settings() -> Settings {
+    Settings {
+        secret_key: TEST_SECRET_KEY.to_string(),
+        upload_dir: std::env::temp_dir().join(format!("uploads_telemetry_{}", uuid::Uuid::new_v4())),
+        bind_address: "127.0.0.1:0".to_string(),
+        allowed_origins: vec![],
+        media: test_media_settings(),
+        jobs: JobSettings::default(),
+        chat: ChatSettings::default(),
+    }
+}
+
+#[actix_web::test]
+async fn test_request_id_is_echoed_or_generated() {
+    let app = test::init_service(build_app_with_state(&AppState::in_memory(settings()))).await;
+    let request_id = |req: test::TestRequest| {
+        let app = &app;
+        async move {
+            let resp = test::call_service(app, req.uri("/health/live").to_request()).await;
+            resp.headers().get(&REQUEST_ID_HEADER).expect("X-Request-Id").to_str().unwrap().to_string()
+        }
+    };
+
+    let echoed = request_id(test::TestRequest::get().insert_header((REQUEST_ID_HEADER, "client-req.42"))).await;
+    assert_eq!(echoed, "client-req.42");
+
+    let generated = request_id(test::TestRequest::get()).await;
+    assert!(uuid::Uuid::parse_str(&generated).is_ok(), "{} is not a UUID", generated);
+    assert_ne!(generated, request_id(test::TestRequest::get()).await);
+
+    // Anything that could smuggle text into the logs is replaced rather than echoed
+    let replaced = request_id(test::TestRequest::get().insert_header((REQUEST_ID_HEADER, "a b\"c"))).await;
+    assert!(uuid::Uuid::parse_str(&replaced).is_ok(), "{} was echoed", replaced);
+}
+
+#[test]
+async fn test_traceparent_round_trips() {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let mut incoming = HeaderMap::new();
+    incoming.insert(HeaderName::from_static("traceparent"), HeaderValue::from_static(TRACEPARENT));
+    incoming.insert(HeaderName::from_static("tracestate"), HeaderValue::from_static("vendor=value"));
+
+    let mut outgoing = HeaderMap::new();
+    inject_context(&extract_context(&incoming), &mut outgoing);
+    assert_eq!(outgoing.get("traceparent").unwrap(), TRACEPARENT);
+    assert_eq!(outgoing.get("tracestate").unwrap(), "vendor=value");
+
+    // No incoming context, nothing to pass on
+    let mut outgoing = HeaderMap::new();
+    inject_context(&extract_context(&HeaderMap::new()), &mut outgoing);
+    assert!(outgoing.get("traceparent").is_none());
+}
+
+#[actix_web::test]
+async fn test_requests_continue_the_callers_trace() {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("telemetry_test")));
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let app = test::init_service(build_app_with_state(&AppState::in_memory(settings()))).await;
+    let req = test::TestRequest::get()
+        .uri("/health/live")
+        .insert_header(("traceparent", TRACEPARENT))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    let traceparent = resp.headers().get("traceparent").expect("traceparent").to_str().unwrap();
+    let parts: Vec<&str> = traceparent.split('-').collect();
+    assert_eq!(parts[1], TRACE_ID);
+    assert_ne!(parts[2], "00f067aa0ba902b7", "the response names the server's own span");
+}
+
+#[test]
+async fn test_sensitive_fields_are_matched_by_exact_name() {
+    for name in [
+        "password", "password_hash", "new_password", "token", "access_token", "Authorization",
+        "secret_key", "secret_access_key", "signing_key", "email", "first_name", "last_name",
+    ] {
+        assert!(is_sensitive(name), "{} should be redacted", name);
+    }
+    for name in [
+        "content_hash", "storage_key", "original_key", "username", "user_id", "token_type",
+        "request_id", "trace_id", "blurhash", "filename", "counterpart_username",
+    ] {
+        assert!(!is_sensitive(name), "{} should be logged", name);
+    }
+}
@@ -1 +1,2 @@
+#[allow(clippy::module_inception)]
 pub mod upload_avatar_test;
\ No newline at end of file
@@ -0,0 +1,52 @@
+// File: src/tests/use_cases/account_use_cases_test.rs
+
+use crate::application::use_cases::account_use_cases::{GetAccountUseCase, UpdateAccountUseCase};
+use crate::domain::entities::account::UpdateAccountDto;
+use crate::infrastructure::repositories::in_memory::{InMemoryAccountRepository, InMemoryStore};
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use super::register;
+
+/// Account use cases only resolve URLs; nothing is written to storage.
+fn media_storage() -> LocalMediaStorage {
+    LocalMediaStorage::new(std::env::temp_dir(), "/media".to_string())
+}
+
+#[actix_web::test]
+async fn test_get_account_is_scoped_to_user() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    register(&store, "grace").await;
+
+    let account = GetAccountUseCase::new(InMemoryAccountRepository::new(store), media_storage()).execute(ada.id).await.unwrap();
+    assert_eq!(account.user_id, ada.id);
+    assert_eq!(account.first_name.as_deref(), Some("ada"));
+    assert!(account.default_avatar.is_none());
+}
+
+#[actix_web::test]
+async fn test_update_account_names() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let repository = InMemoryAccountRepository::new(store);
+
+    let updated = UpdateAccountUseCase::new(repository.clone(), media_storage())
+        .execute(ada.id, UpdateAccountDto {
+            first_name: Some("Augusta".to_string()),
+            middle_name: Some("Ada".to_string()),
+            last_name: Some("King".to_string()),
+        })
+        .await
+        .unwrap();
+    assert_eq!(updated.last_name.as_deref(), Some("King"));
+
+    let reloaded = GetAccountUseCase::new(repository, media_storage()).execute(ada.id).await.unwrap();
+    assert_eq!(reloaded.first_name.as_deref(), Some("Augusta"));
+}
+
+#[actix_web::test]
+async fn test_account_use_cases_report_missing_account() {
+    let repository = InMemoryAccountRepository::new(InMemoryStore::new());
+
+    let error = GetAccountUseCase::new(repository, media_storage()).execute(42).await.unwrap_err();
+    assert!(matches!(error.downcast_ref::<diesel::result::Error>(), Some(diesel::result::Error::NotFound)));
+}
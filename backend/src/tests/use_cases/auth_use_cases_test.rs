@@ -0,0 +1,64 @@
+// File: src/tests/use_cases/auth_use_cases_test.rs
+
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use crate::application::use_cases::auth_use_cases::{LoginUseCase, RegisterUseCase};
+use crate::domain::entities::auth::{AuthUser, Claims, RegisterUserDto};
+use crate::infrastructure::repositories::in_memory::{InMemoryAuthRepository, InMemoryStore};
+use crate::tests::support::TEST_SECRET_KEY;
+
+fn repository() -> InMemoryAuthRepository {
+    InMemoryAuthRepository::new(InMemoryStore::new(), TEST_SECRET_KEY.to_string())
+}
+
+fn register_dto(username: &str) -> RegisterUserDto {
+    RegisterUserDto {
+        username: username.to_string(),
+        email: format!("{}@example.com", username),
+        password: "password123".to_string(),
+        first_name: None,
+        middle_name: None,
+        last_name: None,
+    }
+}
+
+#[actix_web::test]
+async fn test_register_then_login_issues_token_for_user() {
+    let repository = repository();
+    let user = RegisterUseCase::new(repository.clone()).execute(register_dto("ada")).await.unwrap();
+    assert_ne!(user.password, "password123", "password is stored hashed");
+
+    let token = LoginUseCase::new(repository, TEST_SECRET_KEY.to_string())
+        .execute(AuthUser { username: "ada".to_string(), password: "password123".to_string() })
+        .await
+        .unwrap();
+    assert_eq!(token.token_type, "Bearer");
+
+    let claims = decode::<Claims>(
+        &token.access_token,
+        &DecodingKey::from_secret(TEST_SECRET_KEY.as_bytes()),
+        &Validation::default(),
+    ).unwrap().claims;
+    assert_eq!(claims.sub, user.id);
+}
+
+#[actix_web::test]
+async fn test_login_rejects_wrong_password_and_unknown_user() {
+    let repository = repository();
+    RegisterUseCase::new(repository.clone()).execute(register_dto("ada")).await.unwrap();
+    let login = LoginUseCase::new(repository, TEST_SECRET_KEY.to_string());
+
+    let wrong_password = AuthUser { username: "ada".to_string(), password: "nope".to_string() };
+    assert!(login.execute(wrong_password).await.is_err());
+
+    let unknown = AuthUser { username: "nobody".to_string(), password: "password123".to_string() };
+    assert!(login.execute(unknown).await.is_err());
+}
+
+#[actix_web::test]
+async fn test_register_rejects_taken_username() {
+    let use_case = RegisterUseCase::new(repository());
+    use_case.execute(register_dto("ada")).await.unwrap();
+
+    let error = use_case.execute(register_dto("ada")).await.unwrap_err();
+    assert_eq!(error.to_string(), "Username already exists");
+}
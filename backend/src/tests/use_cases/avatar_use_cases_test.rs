@@ -0,0 +1,479 @@
+// File: src/tests/use_cases/avatar_use_cases_test.rs
+
+use crate::application::use_cases::avatar_use_cases::{
+    delete_unreferenced_objects, DeleteAvatarUseCase, ListAvatarsUseCase, ProcessAvatarUseCase, SetDefaultAvatarUseCase,
+    UploadAvatarUseCase,
+};
+use crate::domain::entities::avatar::{
+    Avatar, AvatarCrop, AvatarProcessingJob, CropRect, CropUnits, FocalPoint, InvalidCrop, SquareRegion,
+};
+use crate::domain::entities::job::JobStatus;
+use crate::domain::entities::media::{content_digest, ImageFormat, MediaObject, UploadLimits, UploadRejection, VariantPlan};
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_object_repository::MediaObjectRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::infrastructure::repositories::in_memory::{
+    InMemoryAccountRepository, InMemoryAvatarRepository, InMemoryJobRepository, InMemoryMediaObjectRepository, InMemoryStore,
+};
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use crate::tests::support::test_variant_plan;
+use super::register;
+
+const TEST_AVATAR: &[u8] = include_bytes!("../upload_avatar_test/test_avatar.jpg");
+
+fn variant_key(avatar: &Avatar, size: u32, density: u32, format: ImageFormat) -> String {
+    avatar.variants.iter()
+        .find(|variant| (variant.size, variant.density, variant.format) == (size, density, format))
+        .map(|variant| variant.storage_key.clone())
+        .unwrap_or_else(|| panic!("no {} @ {}x {:?} variant", size, density, format))
+}
+
+/// Upload and processing sharing one store, with the queued job run as soon as it is enqueued.
+struct AvatarPipeline {
+    upload: UploadAvatarUseCase<InMemoryAccountRepository, LocalMediaStorage, InMemoryJobRepository>,
+    process: ProcessAvatarUseCase<InMemoryAvatarRepository, InMemoryAccountRepository, LocalMediaStorage>,
+    jobs: InMemoryJobRepository,
+}
+
+impl AvatarPipeline {
+    fn new(store: &InMemoryStore, media_storage: &LocalMediaStorage, variant_plan: VariantPlan, limits: UploadLimits) -> Self {
+        let accounts = InMemoryAccountRepository::new(store.clone());
+        let jobs = InMemoryJobRepository::new(store.clone());
+        Self {
+            upload: UploadAvatarUseCase::new(accounts.clone(), media_storage.clone(), jobs.clone(), limits),
+            process: ProcessAvatarUseCase::new(
+                InMemoryAvatarRepository::new(store.clone()),
+                accounts,
+                media_storage.clone(),
+                variant_plan,
+                limits,
+            ),
+            jobs,
+        }
+    }
+
+    async fn queue(&self, user_id: i32, image: Vec<u8>, crop: AvatarCrop) -> Result<AvatarProcessingJob, Box<dyn std::error::Error>> {
+        let accepted = self.upload.execute(user_id, image, crop).await?;
+        let job = self.jobs.find_by_id(accepted.job_id).await?.expect("upload queues a job");
+        assert_eq!((job.kind.as_str(), job.status, job.user_id), (AvatarProcessingJob::KIND, JobStatus::Queued, Some(user_id)));
+        Ok(serde_json::from_value(job.payload)?)
+    }
+
+    /// Queues and processes an upload, finishing the job as the worker would.
+    async fn upload(&self, user_id: i32, image: Vec<u8>, crop: AvatarCrop) -> Result<Avatar, Box<dyn std::error::Error>> {
+        let job = self.queue(user_id, image, crop).await?;
+        let avatar = self.process.execute(&job).await?;
+        for queued in self.jobs.find_unfinished(AvatarProcessingJob::KIND).await? {
+            self.jobs.complete(queued.id, None).await?;
+        }
+        Ok(avatar)
+    }
+}
+
+#[actix_web::test]
+async fn test_upload_avatar_writes_variants_and_sets_default() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let accounts = InMemoryAccountRepository::new(store.clone());
+
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+
+    let pipeline = AvatarPipeline::new(
+        &store,
+        &media_storage,
+        // Every format at both densities, at sizes small enough to encode quickly unoptimized
+        VariantPlan { sizes: vec![40, 80], ..VariantPlan::default() },
+        UploadLimits::default(),
+    );
+
+    // The upload only stores the original; nothing is rendered until the job runs
+    let job = pipeline.queue(ada.id, TEST_AVATAR.to_vec(), AvatarCrop::default()).await.unwrap();
+    assert!(job.original_key.ends_with("/original.jpg"), "{}", job.original_key);
+    assert_eq!(media_storage.get(&job.original_key).await.unwrap(), TEST_AVATAR);
+    assert!(accounts.find_by_user_id(ada.id).await.unwrap().default_avatar.is_none());
+
+    let response = pipeline.process.execute(&job).await.unwrap();
+    let account = accounts.find_by_user_id(ada.id).await.unwrap();
+    let avatar = account.default_avatar.expect("processed avatar becomes the default");
+    assert_eq!(avatar.id, response.id);
+    assert_eq!(avatar.original_key.as_deref(), Some(job.original_key.as_str()));
+
+    // 2 sizes x 2 densities x 3 formats, with equal pixel sizes sharing a file
+    assert_eq!(avatar.variants.len(), 12);
+    let keys: std::collections::BTreeSet<&str> = avatar.variants.iter().map(|variant| variant.storage_key.as_str()).collect();
+    assert_eq!(keys.len(), 3 * 3);
+    for key in keys {
+        assert!(media_storage.exists(key).await.unwrap(), "{} was not stored", key);
+    }
+    assert_eq!(variant_key(&avatar, 40, 2, ImageFormat::Avif), variant_key(&avatar, 80, 1, ImageFormat::Avif));
+
+    let large = media_storage.get(&variant_key(&avatar, 80, 2, ImageFormat::Avif)).await.unwrap();
+    assert_eq!(&large[4..12], b"ftypavif");
+    let jpeg = media_storage.get(&variant_key(&avatar, 40, 2, ImageFormat::Jpeg)).await.unwrap();
+    assert_eq!(image::load_from_memory(&jpeg).unwrap().width(), 80);
+
+    // srcset-ready map: every size, every format, both densities
+    assert_eq!(response.images.keys().copied().collect::<Vec<_>>(), vec![40, 80]);
+    let sources = &response.images[&40];
+    assert_eq!(sources.src, format!("/media/{}", variant_key(&avatar, 40, 1, ImageFormat::Jpeg)));
+    assert_eq!(sources.srcset.keys().copied().collect::<Vec<_>>(), vec![ImageFormat::Avif, ImageFormat::Webp, ImageFormat::Jpeg]);
+    assert_eq!(sources.srcset[&ImageFormat::Webp], format!(
+        "/media/{} 1x, /media/{} 2x",
+        variant_key(&avatar, 40, 1, ImageFormat::Webp),
+        variant_key(&avatar, 40, 2, ImageFormat::Webp),
+    ));
+    assert_eq!(response.avatar_40x40_url.as_deref(), Some(sources.src.as_str()));
+    assert_eq!(response.avatar_300x300_url, None);
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_upload_avatar_rejects_invalid_image() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let pipeline = AvatarPipeline::new(&store, &media_storage, test_variant_plan(), UploadLimits::default());
+
+    // Refused before anything is stored or queued
+    assert!(pipeline.upload.execute(ada.id, b"not an image".to_vec(), AvatarCrop::default()).await.is_err());
+    let outside = AvatarCrop { rect: Some(CropRect { x: 5000.0, y: 0.0, width: 10.0, height: 10.0, units: CropUnits::Pixels }), focal_point: None };
+    let error = pipeline.upload.execute(ada.id, TEST_AVATAR.to_vec(), outside).await.unwrap_err();
+    assert!(error.is::<InvalidCrop>(), "{}", error);
+    assert!(store.tables().jobs.is_empty());
+    assert!(!upload_dir.exists() || std::fs::read_dir(&upload_dir).unwrap().next().is_none());
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_identical_uploads_share_content_addressed_files() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let media_objects = InMemoryMediaObjectRepository::new(store.clone());
+    let pipeline = AvatarPipeline::new(&store, &media_storage, test_variant_plan(), UploadLimits::default());
+
+    let first = pipeline.upload(ada.id, TEST_AVATAR.to_vec(), AvatarCrop::default()).await.unwrap();
+    let original_key = first.original_key.clone().unwrap();
+    assert_eq!(original_key, format!("avatars/{}/original.jpg", content_digest(TEST_AVATAR)));
+
+    // Marked, so a second rendering would show
+    let marked = variant_key(&first, 40, 1, ImageFormat::Webp);
+    media_storage.put(&marked, b"rendered once".to_vec(), "image/webp").await.unwrap();
+
+    let second = pipeline.upload(grace.id, TEST_AVATAR.to_vec(), AvatarCrop::default()).await.unwrap();
+    assert_ne!(second.id, first.id);
+    assert_eq!(second.original_key.as_deref(), Some(original_key.as_str()));
+    assert_eq!(second.variants, first.variants);
+    assert_eq!((&second.blurhash, &second.dominant_color), (&first.blurhash, &first.dominant_color));
+    assert_eq!(media_storage.get(&marked).await.unwrap(), b"rendered once", "variants are reused, not rendered again");
+
+    // Another crop of the same picture shares only the original
+    let crop = AvatarCrop { rect: None, focal_point: Some(FocalPoint { x: 0.9, y: 0.5 }) };
+    let cropped = pipeline.upload(grace.id, TEST_AVATAR.to_vec(), crop).await.unwrap();
+    assert_eq!(cropped.original_key.as_deref(), Some(original_key.as_str()));
+    assert_ne!(variant_key(&cropped, 40, 1, ImageFormat::Webp), marked);
+
+    let counts = media_objects.find_by_keys(&[original_key.clone(), marked.clone()]).await.unwrap();
+    // Ordered by key
+    assert_eq!(counts, [
+        MediaObject { storage_key: marked.clone(), reference_count: 2 },
+        MediaObject { storage_key: original_key.clone(), reference_count: 3 },
+    ]);
+
+    let avatars = InMemoryAvatarRepository::new(store.clone());
+    let accounts = InMemoryAccountRepository::new(store.clone());
+    let delete = DeleteAvatarUseCase::new(avatars, accounts, media_objects.clone(), media_storage.clone());
+    delete.execute(ada.id, first.id).await.unwrap();
+    assert!(media_storage.exists(&marked).await.unwrap(), "grace still uses it");
+
+    delete.execute(grace.id, second.id).await.unwrap();
+    assert!(!media_storage.exists(&marked).await.unwrap());
+    assert!(media_storage.exists(&original_key).await.unwrap(), "the cropped avatar still uses it");
+    assert_eq!(delete_unreferenced_objects(&media_objects, &media_storage, &[original_key.clone(), marked]).await.unwrap().len(), 1);
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_avatar_gallery_select_and_delete_with_fallback() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let avatars = InMemoryAvatarRepository::new(store.clone());
+    let accounts = InMemoryAccountRepository::new(store.clone());
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+
+    let pipeline = AvatarPipeline::new(&store, &media_storage, test_variant_plan(), UploadLimits::default());
+    let list = ListAvatarsUseCase::new(avatars.clone(), accounts.clone(), media_storage.clone());
+    let set_default = SetDefaultAvatarUseCase::new(avatars.clone(), accounts.clone(), media_storage.clone());
+    let delete = DeleteAvatarUseCase::new(avatars, accounts, InMemoryMediaObjectRepository::new(store.clone()), media_storage.clone());
+
+    for _ in 0..3 {
+        pipeline.upload(ada.id, TEST_AVATAR.to_vec(), AvatarCrop::default()).await.unwrap();
+    }
+    let gallery = list.execute(ada.id).await.unwrap();
+    let ids: Vec<i32> = gallery.avatars.iter().map(|avatar| avatar.id).collect();
+    let (newest, middle, oldest) = (ids[0], ids[1], ids[2]);
+    assert_eq!(ids.len(), 3);
+    assert_eq!(gallery.default_avatar_id, Some(newest));
+    assert!(gallery.avatars.iter().all(|avatar| avatar.avatar_40x40_url.is_some()));
+
+    let account = set_default.execute(ada.id, oldest).await.unwrap();
+    assert_eq!(account.default_avatar_id, Some(oldest));
+    assert!(account.default_avatar.unwrap().avatar_300x300_url.is_some());
+
+    // Someone else's avatar looks like a missing one
+    assert!(set_default.execute(grace.id, oldest).await.is_err());
+    assert!(delete.execute(grace.id, oldest).await.is_err());
+
+    // Deleting a non-default avatar keeps the default. The three uploads were the same
+    // picture, so the files stay for the other two
+    let middle_avatar = list.execute(ada.id).await.unwrap().avatars.remove(1);
+    let account = delete.execute(ada.id, middle).await.unwrap();
+    assert_eq!(account.default_avatar_id, Some(oldest));
+    let middle_keys: Vec<&String> = middle_avatar.variants.iter().map(|variant| &variant.storage_key).chain(&middle_avatar.original_key).collect();
+    for key in &middle_keys {
+        assert!(media_storage.exists(key).await.unwrap(), "{} is still in use", key);
+    }
+
+    // Deleting the default falls back to the most recent remaining avatar
+    let account = delete.execute(ada.id, oldest).await.unwrap();
+    assert_eq!(account.default_avatar_id, Some(newest));
+
+    // The last one takes the files with it, original included
+    let account = delete.execute(ada.id, newest).await.unwrap();
+    assert_eq!(account.default_avatar_id, None);
+    assert!(list.execute(ada.id).await.unwrap().avatars.is_empty());
+    for key in &middle_keys {
+        assert!(!media_storage.exists(key).await.unwrap(), "{} was left behind", key);
+    }
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+/// 600x200 image with red, green and blue vertical thirds.
+fn striped_png() -> Vec<u8> {
+    let image = image::RgbImage::from_fn(600, 200, |x, _| match x / 200 {
+        0 => image::Rgb([255, 0, 0]),
+        1 => image::Rgb([0, 255, 0]),
+        _ => image::Rgb([0, 0, 255]),
+    });
+    let mut png = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+    png.into_inner()
+}
+
+#[test]
+fn test_avatar_crop_square_region() {
+    let region = |crop: AvatarCrop| crop.square_region(600, 200);
+    let pixels = |x, y, width, height| Some(CropRect { x, y, width, height, units: CropUnits::Pixels });
+
+    // Default: largest centered square
+    assert_eq!(region(AvatarCrop::default()), Ok(SquareRegion { x: 200, y: 0, side: 200 }));
+
+    // Focal point shifts the square, but never past the image edge
+    let focused = |x| AvatarCrop { rect: None, focal_point: Some(FocalPoint { x, y: 0.5 }) };
+    assert_eq!(region(focused(0.25)), Ok(SquareRegion { x: 50, y: 0, side: 200 }));
+    assert_eq!(region(focused(1.0)), Ok(SquareRegion { x: 400, y: 0, side: 200 }));
+
+    // Pixel and normalized rectangles select the same area
+    let rect = AvatarCrop { rect: pixels(300.0, 50.0, 150.0, 100.0), focal_point: None };
+    let normalized = AvatarCrop {
+        rect: Some(CropRect { x: 0.5, y: 0.25, width: 0.25, height: 0.5, units: CropUnits::Normalized }),
+        focal_point: None,
+    };
+    assert_eq!(region(rect), Ok(SquareRegion { x: 325, y: 50, side: 100 }));
+    assert_eq!(region(normalized), region(rect));
+
+    // Rectangles overhanging the image are clipped; ones outside it are rejected
+    assert_eq!(region(AvatarCrop { rect: pixels(500.0, 0.0, 300.0, 300.0), focal_point: None }),
+        Ok(SquareRegion { x: 500, y: 50, side: 100 }));
+    assert!(region(AvatarCrop { rect: pixels(700.0, 0.0, 10.0, 10.0), focal_point: None }).is_err());
+    assert!(region(AvatarCrop { rect: pixels(-1.0, 0.0, 10.0, 10.0), focal_point: None }).is_err());
+    assert!(region(AvatarCrop { rect: pixels(0.0, 0.0, 0.0, 10.0), focal_point: None }).is_err());
+    assert!(region(focused(1.5)).is_err());
+}
+
+#[actix_web::test]
+async fn test_upload_avatar_variants_are_exact_squares() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let accounts = InMemoryAccountRepository::new(store.clone());
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let pipeline = AvatarPipeline::new(&store, &media_storage, test_variant_plan(), UploadLimits::default());
+
+    let stored_variants = || async {
+        let avatar = accounts.find_by_user_id(ada.id).await.unwrap().default_avatar.unwrap();
+        let large = media_storage.get(&variant_key(&avatar, 300, 1, ImageFormat::Webp)).await.unwrap();
+        let small = media_storage.get(&variant_key(&avatar, 40, 1, ImageFormat::Webp)).await.unwrap();
+        (image::load_from_memory(&large).unwrap().to_rgb8(), image::load_from_memory(&small).unwrap().to_rgb8())
+    };
+    let dominant_channel = |image: &image::RgbImage| {
+        let pixel = image.get_pixel(image.width() / 2, image.height() / 2).0;
+        (0..3).max_by_key(|channel| pixel[*channel]).unwrap()
+    };
+
+    // Default center crop picks the green middle third
+    pipeline.upload(ada.id, striped_png(), AvatarCrop::default()).await.unwrap();
+    let (large, small) = stored_variants().await;
+    assert_eq!(large.dimensions(), (300, 300));
+    assert_eq!(small.dimensions(), (40, 40));
+    assert_eq!(dominant_channel(&large), 1);
+
+    // A focal point on the right moves the square onto the blue third
+    let crop = AvatarCrop { rect: None, focal_point: Some(FocalPoint { x: 0.95, y: 0.5 }) };
+    pipeline.upload(ada.id, striped_png(), crop).await.unwrap();
+    let (large, small) = stored_variants().await;
+    assert_eq!(dominant_channel(&large), 2);
+    assert_eq!(dominant_channel(&small), 2);
+
+    // A non-square rectangle over the red third still yields exact squares
+    let crop = AvatarCrop {
+        rect: Some(CropRect { x: 0.0, y: 0.0, width: 190.0, height: 120.0, units: CropUnits::Pixels }),
+        focal_point: None,
+    };
+    pipeline.upload(ada.id, striped_png(), crop).await.unwrap();
+    let (large, small) = stored_variants().await;
+    assert_eq!((large.dimensions(), small.dimensions()), ((300, 300), (40, 40)));
+    assert_eq!(dominant_channel(&large), 0);
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_processed_avatar_has_placeholder() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let pipeline = AvatarPipeline::new(&store, &media_storage, test_variant_plan(), UploadLimits::default());
+
+    // Mostly red, with a strip of green on the right
+    let crop = AvatarCrop {
+        rect: Some(CropRect { x: 0.0, y: 0.0, width: 260.0, height: 200.0, units: CropUnits::Pixels }),
+        focal_point: None,
+    };
+    let reddish = pipeline.upload(ada.id, striped_png(), crop).await.unwrap();
+    assert_eq!(reddish.dominant_color.as_deref(), Some("#ff0000"));
+    let blurhash = reddish.blurhash.clone().expect("blurhash");
+    assert_eq!(blurhash.len(), 36);
+
+    let green = pipeline.upload(ada.id, striped_png(), AvatarCrop::default()).await.unwrap();
+    assert_eq!(green.dominant_color.as_deref(), Some("#00ff00"));
+    assert_ne!(green.blurhash, Some(blurhash));
+
+    // Served with the avatar wherever it appears
+    let json = serde_json::to_value(&green).unwrap();
+    assert_eq!(json["dominant_color"], "#00ff00");
+    assert!(json["blurhash"].is_string());
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+/// 200x100 JPEG, red on the left and blue on the right, whose EXIF says to display it rotated
+/// 90° clockwise. The EXIF also carries an Artist tag to look for in the outputs.
+fn rotated_jpeg_with_exif() -> Vec<u8> {
+    let image = image::RgbImage::from_fn(200, 100, |x, _| {
+        if x < 100 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) }
+    });
+    let mut jpeg = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(95)).unwrap();
+    let jpeg = jpeg.into_inner();
+
+    let artist = b"SECRET-LOCATION\0";
+    // Little-endian TIFF header, then IFD0 with Orientation = 6 and Artist, data after the IFD
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+    tiff.extend_from_slice(&2u16.to_le_bytes());
+    tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+    tiff.extend_from_slice(&[0x3b, 0x01, 2, 0]);
+    tiff.extend_from_slice(&(artist.len() as u32).to_le_bytes());
+    tiff.extend_from_slice(&(8 + 2 + 2 * 12 + 4u32).to_le_bytes());
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff.extend_from_slice(artist);
+
+    let mut app1 = vec![0xff, 0xe1];
+    app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(&tiff);
+
+    // Right after the SOI marker
+    [&jpeg[..2], &app1, &jpeg[2..]].concat()
+}
+
+#[actix_web::test]
+async fn test_upload_avatar_applies_exif_orientation_and_strips_metadata() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let accounts = InMemoryAccountRepository::new(store.clone());
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let pipeline = AvatarPipeline::new(&store, &media_storage, test_variant_plan(), UploadLimits::default());
+
+    pipeline.upload(ada.id, rotated_jpeg_with_exif(), AvatarCrop::default()).await.unwrap();
+    let avatar = accounts.find_by_user_id(ada.id).await.unwrap().default_avatar.unwrap();
+
+    // Upright, the red half is on top
+    let large = media_storage.get(&variant_key(&avatar, 300, 1, ImageFormat::Jpeg)).await.unwrap();
+    let large = image::load_from_memory(&large).unwrap().to_rgb8();
+    let red = |pixel: &image::Rgb<u8>| pixel.0[0] > 200 && pixel.0[2] < 60;
+    assert!(red(large.get_pixel(270, 60)), "top right is {:?}", large.get_pixel(270, 60));
+    assert!(!red(large.get_pixel(30, 240)), "bottom left is {:?}", large.get_pixel(30, 240));
+
+    for variant in &avatar.variants {
+        let bytes = media_storage.get(&variant.storage_key).await.unwrap();
+        let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|window| window == needle);
+        assert!(!contains(b"Exif") && !contains(b"SECRET-LOCATION"), "{} carries metadata", variant.storage_key);
+    }
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_upload_avatar_enforces_upload_limits() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+    let upload_with = |limits: UploadLimits| AvatarPipeline::new(&store, &media_storage, test_variant_plan(), limits);
+    let rejection = |result: Result<Avatar, Box<dyn std::error::Error>>| {
+        result.err().and_then(|e| e.downcast::<UploadRejection>().ok()).map(|rejection| *rejection)
+    };
+
+    let narrow = upload_with(UploadLimits { max_width: 599, ..UploadLimits::default() });
+    assert_eq!(
+        rejection(narrow.upload(ada.id, striped_png(), AvatarCrop::default()).await),
+        Some(UploadRejection::TooManyPixels { width: 600, height: 200, max_width: 599, max_height: 8192 })
+    );
+
+    let small = upload_with(UploadLimits { max_bytes: 100, ..UploadLimits::default() });
+    assert_eq!(
+        rejection(small.upload(ada.id, striped_png(), AvatarCrop::default()).await),
+        Some(UploadRejection::TooManyBytes { max_bytes: 100 })
+    );
+
+    let default = upload_with(UploadLimits::default());
+    assert_eq!(
+        rejection(default.upload(ada.id, b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>".to_vec(), AvatarCrop::default()).await),
+        Some(UploadRejection::UnsupportedType)
+    );
+    // The header is intact, so this one is only found out while processing
+    let truncated = striped_png()[..64].to_vec();
+    assert!(matches!(
+        rejection(default.upload(ada.id, truncated, AvatarCrop::default()).await),
+        Some(UploadRejection::Unreadable(_))
+    ));
+    assert!(InMemoryAccountRepository::new(store.clone()).find_by_user_id(ada.id).await.unwrap().default_avatar.is_none());
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
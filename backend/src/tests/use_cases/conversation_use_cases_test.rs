@@ -0,0 +1,75 @@
+// File: src/tests/use_cases/conversation_use_cases_test.rs
+
+use crate::application::use_cases::conversation_use_cases::ListConversationsUseCase;
+use crate::application::use_cases::message_use_cases::MarkReadUseCase;
+use crate::domain::entities::conversation::PREVIEW_LENGTH;
+use crate::infrastructure::repositories::in_memory::{
+    InMemoryAccountRepository, InMemoryConversationRepository, InMemoryMessageRepository, InMemoryStore,
+};
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use super::{register, send_message};
+
+fn inbox(store: &InMemoryStore) -> ListConversationsUseCase<InMemoryConversationRepository, InMemoryAccountRepository, LocalMediaStorage> {
+    ListConversationsUseCase::new(
+        InMemoryConversationRepository::new(store.clone()),
+        InMemoryAccountRepository::new(store.clone()),
+        LocalMediaStorage::new(std::env::temp_dir(), "/media".to_string()),
+    )
+}
+
+#[actix_web::test]
+async fn test_inbox_lists_latest_message_and_unread_count_per_counterpart() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let alan = register(&store, "alan").await;
+    let messages = InMemoryMessageRepository::new(store.clone());
+    let send = send_message(&store);
+
+    let first = send.execute(grace.id, ada.id, "are you there?".to_string(), None).await.unwrap();
+    send.execute(alan.id, ada.id, "lunch?".to_string(), None).await.unwrap();
+    send.execute(grace.id, ada.id, "x".repeat(PREVIEW_LENGTH + 1), None).await.unwrap();
+    send.execute(ada.id, alan.id, "sure".to_string(), None).await.unwrap();
+    MarkReadUseCase::new(messages).execute(ada.id, grace.id, first.id).await.unwrap();
+
+    let page = inbox(&store).execute(ada.id, None, None).await.unwrap();
+    assert!(page.older.is_none());
+    let [with_alan, with_grace] = page.conversations.as_slice() else { panic!("two conversations") };
+
+    assert_eq!(with_alan.counterpart.username, "alan");
+    assert_eq!(with_alan.counterpart.first_name.as_deref(), Some("alan"));
+    assert_eq!(with_alan.last_message.content, "sure");
+    assert_eq!(with_alan.last_message.sender_id, ada.id);
+    assert_eq!(with_alan.unread_count, 1);
+
+    assert_eq!(with_grace.counterpart.user_id, grace.id);
+    assert_eq!(with_grace.last_message.content, format!("{}…", "x".repeat(PREVIEW_LENGTH)));
+    assert_eq!(with_grace.unread_count, 1);
+
+    // The other side sees its own unread count
+    let page = inbox(&store).execute(alan.id, None, None).await.unwrap();
+    assert_eq!(page.conversations[0].counterpart.username, "ada");
+    assert_eq!(page.conversations[0].unread_count, 1);
+}
+
+#[actix_web::test]
+async fn test_inbox_pages_by_recency() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let send = send_message(&store);
+    for name in ["grace", "alan", "edsger"] {
+        let other = register(&store, name).await;
+        send.execute(other.id, ada.id, format!("hi from {}", name), None).await.unwrap();
+    }
+    let inbox = inbox(&store);
+
+    let page = inbox.execute(ada.id, None, Some(2)).await.unwrap();
+    let names: Vec<&str> = page.conversations.iter().map(|conversation| conversation.counterpart.username.as_str()).collect();
+    assert_eq!(names, ["edsger", "alan"]);
+
+    let before = page.older.expect("another page").parse().unwrap();
+    let page = inbox.execute(ada.id, Some(before), Some(2)).await.unwrap();
+    let names: Vec<&str> = page.conversations.iter().map(|conversation| conversation.counterpart.username.as_str()).collect();
+    assert_eq!(names, ["grace"]);
+    assert!(page.older.is_none());
+}
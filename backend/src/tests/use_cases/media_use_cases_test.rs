@@ -0,0 +1,196 @@
+// File: src/tests/use_cases/media_use_cases_test.rs
+
+use std::time::Duration;
+use crate::application::use_cases::media_use_cases::ReconcileMediaUseCase;
+use crate::domain::entities::attachment::NewAttachment;
+use crate::domain::entities::avatar::{AvatarCrop, AvatarProcessingJob, NewAvatar};
+use crate::domain::entities::job::NewJob;
+use crate::domain::entities::media::{ImageFormat, MediaOwner, MediaReference, MediaVariant};
+use crate::domain::entities::upload::{NewUpload, UploadPurpose};
+use crate::domain::repositories::account_repository::AccountRepository;
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::avatar_repository::AvatarRepository;
+use crate::domain::repositories::job_repository::JobRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::infrastructure::repositories::in_memory::{
+    InMemoryAccountRepository, InMemoryAttachmentRepository, InMemoryAvatarRepository, InMemoryJobRepository,
+    InMemoryMediaObjectRepository, InMemoryStore, InMemoryUploadRepository,
+};
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use super::register;
+
+fn variant(storage_key: &str) -> MediaVariant {
+    MediaVariant {
+        size: 40,
+        density: 1,
+        format: ImageFormat::Jpeg,
+        width: 40,
+        height: 40,
+        storage_key: storage_key.to_string(),
+        byte_size: None,
+    }
+}
+
+#[actix_web::test]
+async fn test_reconcile_media_reports_and_deletes_what_is_out_of_step() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let account = InMemoryAccountRepository::new(store.clone()).find_by_user_id(ada.id).await.unwrap();
+    let avatars = InMemoryAvatarRepository::new(store.clone());
+    let jobs = InMemoryJobRepository::new(store.clone());
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+
+    for key in [
+        "avatars/1/intact/40.jpg",
+        "avatars/1/intact/original.png",
+        // Left behind by an upload whose avatar row was never written
+        "avatars/1/crashed/40.jpg",
+        // Queued, not processed yet
+        "avatars/1/pending/original.png",
+        // Kept by an avatar that lost its other variant
+        "avatars/1/broken/40.jpg",
+        // Outside the reconciled prefixes
+        ".health/probe",
+    ] {
+        media_storage.put(key, b"bytes".to_vec(), "image/jpeg").await.unwrap();
+    }
+    let intact = avatars.create(NewAvatar {
+        account_id: account.id,
+        original_key: Some("avatars/1/intact/original.png".to_string()),
+        variants: vec![variant("avatars/1/intact/40.jpg")],
+        placeholder: None,
+    }).await.unwrap();
+    let broken = avatars.create(NewAvatar {
+        account_id: account.id,
+        original_key: None,
+        variants: vec![variant("avatars/1/broken/40.jpg"), variant("avatars/1/broken/80.jpg")],
+        placeholder: None,
+    }).await.unwrap();
+    let pending = AvatarProcessingJob {
+        user_id: ada.id,
+        account_id: account.id,
+        original_key: "avatars/1/pending/original.png".to_string(),
+        crop: AvatarCrop::default(),
+    };
+    jobs.enqueue(NewJob::new(AvatarProcessingJob::KIND, serde_json::to_value(&pending).unwrap())).await.unwrap();
+
+    // Everything is still within the grace period
+    let media_objects = InMemoryMediaObjectRepository::new(store.clone());
+    let cautious = ReconcileMediaUseCase::new(
+        avatars.clone(),
+        InMemoryAttachmentRepository::new(store.clone()),
+        InMemoryUploadRepository::new(store.clone()),
+        jobs.clone(),
+        media_objects.clone(),
+        media_storage.clone(),
+        Duration::from_secs(3600),
+    );
+    let report = cautious.execute(false).await.unwrap();
+    assert!(report.orphaned_objects.is_empty());
+    assert_eq!(report.missing_objects.len(), 1, "rows with missing files are not subject to the grace period");
+
+    let reconcile = ReconcileMediaUseCase::new(
+        avatars.clone(),
+        InMemoryAttachmentRepository::new(store.clone()),
+        InMemoryUploadRepository::new(store.clone()),
+        jobs.clone(),
+        media_objects,
+        media_storage.clone(),
+        Duration::ZERO,
+    );
+
+    let report = reconcile.execute(false).await.unwrap();
+    assert_eq!(report.orphaned_objects, ["avatars/1/crashed/40.jpg"]);
+    assert_eq!(report.missing_objects, [MediaReference { owner: MediaOwner::Avatar(broken.id), key: "avatars/1/broken/80.jpg".to_string() }]);
+    assert!(!report.deleted);
+    assert!(media_storage.exists("avatars/1/crashed/40.jpg").await.unwrap(), "a report deletes nothing");
+    assert!(avatars.find_by_id(broken.id).await.unwrap().is_some());
+
+    let report = reconcile.execute(true).await.unwrap();
+    assert!(report.deleted);
+    assert!(!media_storage.exists("avatars/1/crashed/40.jpg").await.unwrap());
+    assert!(avatars.find_by_id(broken.id).await.unwrap().is_none());
+    assert!(!media_storage.exists("avatars/1/broken/40.jpg").await.unwrap());
+    for kept in ["avatars/1/intact/40.jpg", "avatars/1/intact/original.png", "avatars/1/pending/original.png", ".health/probe"] {
+        assert!(media_storage.exists(kept).await.unwrap(), "{} was deleted", kept);
+    }
+    assert!(avatars.find_by_id(intact.id).await.unwrap().is_some());
+
+    let report = reconcile.execute(true).await.unwrap();
+    assert!(report.orphaned_objects.is_empty() && report.missing_objects.is_empty());
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
+
+#[actix_web::test]
+async fn test_reconcile_media_covers_attachments_and_uploads() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let attachments = InMemoryAttachmentRepository::new(store.clone());
+    let uploads = InMemoryUploadRepository::new(store.clone());
+    let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+    let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+
+    let attachment = |storage_key: &str| NewAttachment {
+        user_id: ada.id,
+        storage_key: storage_key.to_string(),
+        filename: None,
+        content_type: "application/pdf".to_string(),
+        byte_size: 5,
+    };
+    attachments.create(attachment("attachments/kept")).await.unwrap();
+    let lost = attachments.create(attachment("attachments/lost")).await.unwrap();
+    let new_upload = || NewUpload {
+        user_id: ada.id,
+        purpose: UploadPurpose::Attachment,
+        length: 10,
+        metadata: Default::default(),
+        expires_at: chrono::Utc::now().naive_utc() + chrono::Duration::hours(1),
+    };
+    let under_way = uploads.create(new_upload()).await.unwrap();
+    uploads.advance(under_way.id, 5).await.unwrap();
+    let emptied = uploads.create(new_upload()).await.unwrap();
+    uploads.advance(emptied.id, 5).await.unwrap();
+
+    for key in [
+        "attachments/kept".to_string(),
+        // Its message was never sent
+        "attachments/orphaned".to_string(),
+        under_way.part_key(0),
+        // Left behind by an upload that was discarded
+        format!("uploads/{}/{:020}", uuid::Uuid::new_v4(), 0),
+    ] {
+        media_storage.put(&key, b"bytes".to_vec(), "application/octet-stream").await.unwrap();
+    }
+
+    let reconcile = ReconcileMediaUseCase::new(
+        InMemoryAvatarRepository::new(store.clone()),
+        attachments.clone(),
+        uploads.clone(),
+        InMemoryJobRepository::new(store.clone()),
+        InMemoryMediaObjectRepository::new(store.clone()),
+        media_storage.clone(),
+        Duration::ZERO,
+    );
+    let report = reconcile.execute(true).await.unwrap();
+
+    let mut orphaned = report.orphaned_objects.clone();
+    let stray_part = orphaned.pop().unwrap();
+    assert!(stray_part.starts_with("uploads/"));
+    assert_eq!(orphaned, ["attachments/orphaned"]);
+    assert_eq!(report.missing_objects, [
+        MediaReference { owner: MediaOwner::Attachment(lost.id), key: "attachments/lost".to_string() },
+        MediaReference { owner: MediaOwner::Upload(emptied.id), key: emptied.part_key(0) },
+    ]);
+    assert!(!media_storage.exists("attachments/orphaned").await.unwrap());
+    assert!(!media_storage.exists(&stray_part).await.unwrap());
+    assert!(media_storage.exists("attachments/kept").await.unwrap());
+    assert!(media_storage.exists(&under_way.part_key(0)).await.unwrap());
+    assert!(attachments.find_by_id(lost.id).await.unwrap().is_some(), "attachments are only reported");
+    assert!(uploads.find_by_id(emptied.id).await.unwrap().is_none(), "an upload that lost its parts cannot finish");
+    assert!(uploads.find_by_id(under_way.id).await.unwrap().is_some());
+
+    std::fs::remove_dir_all(upload_dir).ok();
+}
@@ -0,0 +1,268 @@
+// File: src/tests/use_cases/message_use_cases_test.rs
+
+use std::time::Duration;
+use crate::application::use_cases::message_use_cases::{
+    DeleteMessageUseCase, EditMessageUseCase, GetMessageEditsUseCase, GetMessagesUseCase, MarkDeliveredUseCase,
+    MarkReadUseCase, MAX_PAGE_SIZE,
+};
+use crate::domain::entities::attachment::NewAttachment;
+use crate::domain::entities::message::{MarkReadError, MessageChangeError, MessageCursor, MessagePage, SendMessageError};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::message_repository::MessageRepository;
+use crate::infrastructure::repositories::in_memory::{InMemoryAttachmentRepository, InMemoryMessageRepository, InMemoryStore};
+use super::{register, send_message};
+
+#[actix_web::test]
+async fn test_send_and_get_conversation_in_order() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let alan = register(&store, "alan").await;
+    let repository = InMemoryMessageRepository::new(store.clone());
+    let send = send_message(&store);
+
+    let first = send.execute(ada.id, grace.id, "hello".to_string(), None).await.unwrap();
+    send.execute(grace.id, ada.id, "hi ada".to_string(), None).await.unwrap();
+    send.execute(ada.id, alan.id, "unrelated".to_string(), None).await.unwrap();
+    assert!(first.id > 0);
+    assert!(!first.is_read);
+
+    let conversation = GetMessagesUseCase::new(repository).execute(grace.id, ada.id, None, None, None).await.unwrap();
+    assert_eq!(contents(&conversation), ["hi ada", "hello"]);
+    assert!(!conversation.has_more);
+    assert!(conversation.older.is_none());
+}
+
+fn contents(page: &MessagePage) -> Vec<&str> {
+    page.messages.iter().map(|message| message.content.as_str()).collect()
+}
+
+fn cursor(value: &Option<String>) -> Option<MessageCursor> {
+    Some(value.as_deref().expect("cursor").parse().expect("valid cursor"))
+}
+
+#[actix_web::test]
+async fn test_conversation_pages_through_cursors() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let repository = InMemoryMessageRepository::new(store.clone());
+    let send = send_message(&store);
+    for n in 1..=5 {
+        let (from, to) = if n % 2 == 0 { (grace.id, ada.id) } else { (ada.id, grace.id) };
+        send.execute(from, to, n.to_string(), None).await.unwrap();
+    }
+    let history = GetMessagesUseCase::new(repository);
+
+    let latest = history.execute(ada.id, grace.id, None, None, Some(2)).await.unwrap();
+    assert_eq!(contents(&latest), ["5", "4"]);
+    assert!(latest.has_more);
+
+    let middle = history.execute(ada.id, grace.id, cursor(&latest.older), None, Some(2)).await.unwrap();
+    assert_eq!(contents(&middle), ["3", "2"]);
+    let first = history.execute(ada.id, grace.id, cursor(&middle.older), None, Some(2)).await.unwrap();
+    assert_eq!(contents(&first), ["1"]);
+    assert!(!first.has_more);
+    assert!(first.older.is_none());
+
+    // Paging forwards from an old page returns the messages right after the ones already held
+    let next = history.execute(ada.id, grace.id, None, cursor(&first.newer), Some(2)).await.unwrap();
+    assert_eq!(contents(&next), ["4", "3"]);
+    assert!(next.has_more);
+    let rest = history.execute(ada.id, grace.id, None, cursor(&next.newer), Some(2)).await.unwrap();
+    assert_eq!(contents(&rest), ["5"]);
+    assert!(!rest.has_more);
+    let nothing_new = history.execute(ada.id, grace.id, None, cursor(&rest.newer), Some(2)).await.unwrap();
+    assert!(nothing_new.messages.is_empty());
+    assert_eq!(nothing_new.newer, rest.newer);
+
+    // Both cursors fill the gap between them
+    let gap = history.execute(ada.id, grace.id, cursor(&latest.older), cursor(&first.newer), None).await.unwrap();
+    assert_eq!(contents(&gap), ["3"]);
+    assert!(!gap.has_more);
+    assert_eq!(gap.older, first.newer);
+    assert_eq!(gap.newer, latest.older);
+}
+
+#[actix_web::test]
+async fn test_page_size_is_capped() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let repository = InMemoryMessageRepository::new(store.clone());
+    let send = send_message(&store);
+    for n in 0..MAX_PAGE_SIZE + 1 {
+        send.execute(ada.id, grace.id, n.to_string(), None).await.unwrap();
+    }
+    let history = GetMessagesUseCase::new(repository);
+
+    let page = history.execute(ada.id, grace.id, None, None, Some(10_000)).await.unwrap();
+    assert_eq!(page.messages.len() as i64, MAX_PAGE_SIZE);
+    assert!(page.has_more);
+    let page = history.execute(ada.id, grace.id, None, None, Some(0)).await.unwrap();
+    assert_eq!(contents(&page), [MAX_PAGE_SIZE.to_string()]);
+}
+
+#[actix_web::test]
+async fn test_cursor_round_trips_and_rejects_garbage() {
+    let cursor = MessageCursor {
+        created_at: chrono::DateTime::from_timestamp_micros(1_760_000_000_123_456).unwrap().naive_utc(),
+        id: 42,
+    };
+    assert_eq!(cursor.to_string().parse::<MessageCursor>(), Ok(cursor));
+    assert!("not a cursor".parse::<MessageCursor>().is_err());
+    assert!("MTIzNDU".parse::<MessageCursor>().is_err());
+}
+
+#[actix_web::test]
+async fn test_send_message_to_unknown_user_fails() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+
+    let send = send_message(&store);
+    assert_eq!(send.execute(ada.id, 9999, "hello?".to_string(), None).await.unwrap_err(), SendMessageError::RecipientNotFound(9999));
+}
+
+#[actix_web::test]
+async fn test_empty_messages_are_refused() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+
+    let send = send_message(&store);
+    assert_eq!(send.execute(ada.id, grace.id, " \n".to_string(), None).await.unwrap_err(), SendMessageError::Empty);
+}
+
+#[actix_web::test]
+async fn test_messages_only_carry_the_senders_own_attachments() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let alan = register(&store, "alan").await;
+    let attachments = InMemoryAttachmentRepository::new(store.clone());
+    let send = send_message(&store);
+
+    let attachment = attachments.create(NewAttachment {
+        user_id: ada.id,
+        storage_key: format!("attachments/{}/report", ada.id),
+        filename: Some("report.pdf".to_string()),
+        content_type: "application/octet-stream".to_string(),
+        byte_size: 5,
+    }).await.unwrap();
+    assert!(attachments.find_visible_to(attachment.id, grace.id).await.unwrap().is_none());
+
+    assert!(send.execute(grace.id, ada.id, "mine now".to_string(), Some(attachment.id)).await.is_err());
+    assert!(send.execute(ada.id, grace.id, "missing".to_string(), Some(9999)).await.is_err());
+
+    let message = send.execute(ada.id, grace.id, "the report".to_string(), Some(attachment.id)).await.unwrap();
+    assert_eq!(message.attachment_id, Some(attachment.id));
+    assert!(attachments.find_visible_to(attachment.id, grace.id).await.unwrap().is_some());
+    assert!(attachments.find_visible_to(attachment.id, alan.id).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn test_conversation_is_read_up_to_a_message() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let alan = register(&store, "alan").await;
+    let repository = InMemoryMessageRepository::new(store.clone());
+    let send = send_message(&store);
+
+    let first = send.execute(grace.id, ada.id, "one".to_string(), None).await.unwrap();
+    let reply = send.execute(ada.id, grace.id, "two".to_string(), None).await.unwrap();
+    let later = send.execute(grace.id, ada.id, "three".to_string(), None).await.unwrap();
+    let elsewhere = send.execute(alan.id, ada.id, "hi".to_string(), None).await.unwrap();
+    let delivered = MarkDeliveredUseCase::new(repository.clone()).execute(&[later.id]).await.unwrap();
+    assert_eq!(delivered.len(), 1);
+
+    // Up to her own reply, Ada has only read what Grace sent before it
+    let mark_read = MarkReadUseCase::new(repository.clone());
+    let receipt = mark_read.execute(ada.id, grace.id, reply.id).await.unwrap();
+    assert_eq!(receipt.message_ids, [first.id]);
+    let receipt = mark_read.execute(ada.id, grace.id, later.id).await.unwrap();
+    assert_eq!(receipt.message_ids, [later.id]);
+    assert!(mark_read.execute(ada.id, grace.id, later.id).await.unwrap().message_ids.is_empty());
+
+    let first = repository.find_message(first.id).await.unwrap().unwrap();
+    assert!(first.is_read);
+    assert_eq!(first.delivered_at, first.read_at);
+    let later = repository.find_message(later.id).await.unwrap().unwrap();
+    assert_eq!(later.delivered_at, delivered[0].delivered_at);
+    assert!(later.read_at.is_some());
+    assert!(!repository.find_message(reply.id).await.unwrap().unwrap().is_read);
+
+    assert_eq!(mark_read.execute(ada.id, grace.id, elsewhere.id).await.unwrap_err(), MarkReadError::MessageNotFound(elsewhere.id));
+    assert!(MarkDeliveredUseCase::new(repository).execute(&[later.id]).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_only_the_sender_edits_within_the_window() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let alan = register(&store, "alan").await;
+    let repository = InMemoryMessageRepository::new(store.clone());
+    let message = send_message(&store).execute(ada.id, grace.id, "helo".to_string(), None).await.unwrap();
+    let edit = EditMessageUseCase::new(repository.clone(), Duration::from_secs(60));
+
+    assert_eq!(edit.execute(grace.id, message.id, "hijacked".to_string()).await.unwrap_err(), MessageChangeError::NotSender(message.id));
+    assert_eq!(edit.execute(alan.id, message.id, "hijacked".to_string()).await.unwrap_err(), MessageChangeError::NotFound(message.id));
+    assert_eq!(edit.execute(ada.id, message.id, " ".to_string()).await.unwrap_err(), MessageChangeError::Empty);
+
+    let edited = edit.execute(ada.id, message.id, "hello".to_string()).await.unwrap();
+    assert_eq!(edited.content, "hello");
+    assert!(edited.edited_at.is_some());
+    edit.execute(ada.id, message.id, "hello!".to_string()).await.unwrap();
+    // An edit that changes nothing is not recorded
+    edit.execute(ada.id, message.id, "hello!".to_string()).await.unwrap();
+
+    let edits = GetMessageEditsUseCase::new(repository.clone());
+    let history: Vec<_> = edits.execute(grace.id, message.id).await.unwrap().into_iter().map(|edit| edit.content).collect();
+    assert_eq!(history, ["helo", "hello"]);
+    assert_eq!(edits.execute(alan.id, message.id).await.unwrap_err(), MessageChangeError::NotFound(message.id));
+
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let closed = EditMessageUseCase::new(repository, Duration::ZERO);
+    assert_eq!(closed.execute(ada.id, message.id, "too late".to_string()).await.unwrap_err(), MessageChangeError::EditWindowClosed(message.id));
+}
+
+#[actix_web::test]
+async fn test_messages_are_deleted_for_oneself_or_everyone() {
+    let store = InMemoryStore::new();
+    let ada = register(&store, "ada").await;
+    let grace = register(&store, "grace").await;
+    let repository = InMemoryMessageRepository::new(store.clone());
+    let send = send_message(&store);
+    let mine = send.execute(ada.id, grace.id, "first draft".to_string(), None).await.unwrap();
+    let theirs = send.execute(grace.id, ada.id, "noted".to_string(), None).await.unwrap();
+    EditMessageUseCase::new(repository.clone(), Duration::from_secs(60))
+        .execute(ada.id, mine.id, "second draft".to_string()).await.unwrap();
+    let delete = DeleteMessageUseCase::new(repository.clone());
+    let history = GetMessagesUseCase::new(repository.clone());
+
+    // Deleting for oneself hides the message from that participant only
+    let deletion = delete.execute(ada.id, theirs.id, false).await.unwrap();
+    assert!(!deletion.for_everyone);
+    assert_eq!(contents(&history.execute(ada.id, grace.id, None, None, None).await.unwrap()), ["second draft"]);
+    assert_eq!(contents(&history.execute(grace.id, ada.id, None, None, None).await.unwrap()), ["noted", "second draft"]);
+
+    assert_eq!(delete.execute(ada.id, theirs.id, true).await.unwrap_err(), MessageChangeError::NotSender(theirs.id));
+
+    // Deleting for everyone leaves a tombstone without the content or its edits
+    let deletion = delete.execute(ada.id, mine.id, true).await.unwrap();
+    assert!(deletion.for_everyone);
+    assert_eq!(deletion.message.deleted_at, Some(deletion.deleted_at));
+    let page = history.execute(grace.id, ada.id, None, None, None).await.unwrap();
+    let tombstone = page.messages.iter().find(|message| message.id == mine.id).unwrap();
+    assert!(tombstone.content.is_empty());
+    assert!(tombstone.deleted_at.is_some());
+    assert!(GetMessageEditsUseCase::new(repository.clone()).execute(grace.id, mine.id).await.unwrap().is_empty());
+    assert_eq!(
+        EditMessageUseCase::new(repository, Duration::from_secs(60)).execute(ada.id, mine.id, "back".to_string()).await.unwrap_err(),
+        MessageChangeError::NotFound(mine.id)
+    );
+
+    let again = delete.execute(ada.id, mine.id, true).await.unwrap();
+    assert_eq!(again.deleted_at, deletion.deleted_at);
+}
@@ -0,0 +1,46 @@
+// File: src/tests/use_cases/mod.rs
+//
+// Unit tests for the application use cases, run against the in-memory repositories so they
+// need neither Postgres nor an HTTP server.
+
+pub mod account_use_cases_test;
+pub mod auth_use_cases_test;
+pub mod avatar_use_cases_test;
+pub mod conversation_use_cases_test;
+pub mod media_use_cases_test;
+pub mod message_use_cases_test;
+pub mod upload_use_cases_test;
+pub mod user_use_cases_test;
+
+use crate::application::use_cases::message_use_cases::SendMessageUseCase;
+use crate::domain::entities::auth::RegisterUserDto;
+use crate::domain::entities::user::User;
+use crate::infrastructure::repositories::in_memory::{
+    InMemoryAttachmentRepository, InMemoryAuthRepository, InMemoryMessageRepository, InMemoryStore,
+};
+use crate::tests::support::TEST_SECRET_KEY;
+
+/// Registers a user (and their account) through the auth repository, like the API does.
+pub async fn register(store: &InMemoryStore, username: &str) -> User {
+    use crate::domain::repositories::auth_repository::AuthRepository;
+
+    InMemoryAuthRepository::new(store.clone(), TEST_SECRET_KEY.to_string())
+        .register(RegisterUserDto {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+            first_name: Some(username.to_string()),
+            middle_name: None,
+            last_name: None,
+        })
+        .await
+        .expect("registration succeeds")
+}
+
+/// Sends messages into `store`, like the API does.
+pub fn send_message(store: &InMemoryStore) -> SendMessageUseCase<InMemoryMessageRepository, InMemoryAttachmentRepository> {
+    SendMessageUseCase::new(
+        InMemoryMessageRepository::new(store.clone()),
+        InMemoryAttachmentRepository::new(store.clone()),
+    )
+}
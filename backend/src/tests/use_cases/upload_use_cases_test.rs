@@ -0,0 +1,250 @@
+// File: src/tests/use_cases/upload_use_cases_test.rs
+
+use std::collections::BTreeMap;
+use std::io;
+use futures::stream;
+use crate::application::use_cases::avatar_use_cases::UploadAvatarUseCase;
+use crate::application::use_cases::upload_use_cases::{
+    AppendUploadUseCase, CreateUploadUseCase, ExpireUploadsUseCase, TerminateUploadUseCase,
+};
+use crate::domain::entities::avatar::InvalidCrop;
+use crate::domain::entities::media::{UploadLimits, UploadRejection};
+use crate::domain::entities::upload::{Upload, UploadError, UploadPurpose};
+use crate::domain::repositories::attachment_repository::AttachmentRepository;
+use crate::domain::repositories::media_storage::MediaStorage;
+use crate::domain::repositories::upload_repository::UploadRepository;
+use crate::infrastructure::config::settings::ResumableUploadSettings;
+use crate::infrastructure::repositories::in_memory::{
+    InMemoryAccountRepository, InMemoryAttachmentRepository, InMemoryJobRepository, InMemoryStore, InMemoryUploadRepository,
+};
+use crate::infrastructure::storage::local::LocalMediaStorage;
+use super::register;
+
+type Append = AppendUploadUseCase<
+    InMemoryUploadRepository,
+    LocalMediaStorage,
+    InMemoryAccountRepository,
+    InMemoryJobRepository,
+    InMemoryAttachmentRepository,
+>;
+
+struct Fixture {
+    store: InMemoryStore,
+    uploads: InMemoryUploadRepository,
+    attachments: InMemoryAttachmentRepository,
+    media_storage: LocalMediaStorage,
+    create: CreateUploadUseCase<InMemoryUploadRepository>,
+    append: Append,
+    upload_dir: std::path::PathBuf,
+}
+
+impl Fixture {
+    /// Parts of four bytes, so short bodies already span several.
+    fn new() -> Self {
+        let store = InMemoryStore::new();
+        let uploads = InMemoryUploadRepository::new(store.clone());
+        let attachments = InMemoryAttachmentRepository::new(store.clone());
+        let upload_dir = std::env::temp_dir().join(format!("uploads_unit_{}", uuid::Uuid::new_v4()));
+        let media_storage = LocalMediaStorage::new(upload_dir.clone(), "/media".to_string());
+        let limits = UploadLimits { max_bytes: 256, ..UploadLimits::default() };
+        let settings = ResumableUploadSettings { max_bytes: 1024, part_bytes: 4, ..ResumableUploadSettings::default() };
+
+        Self {
+            create: CreateUploadUseCase::new(uploads.clone(), settings, limits),
+            append: AppendUploadUseCase::new(
+                uploads.clone(),
+                media_storage.clone(),
+                UploadAvatarUseCase::new(
+                    InMemoryAccountRepository::new(store.clone()),
+                    media_storage.clone(),
+                    InMemoryJobRepository::new(store.clone()),
+                    limits,
+                ),
+                attachments.clone(),
+                4,
+            ),
+            store,
+            uploads,
+            attachments,
+            media_storage,
+            upload_dir,
+        }
+    }
+
+    async fn parts(&self, upload: &Upload) -> Vec<String> {
+        let mut keys: Vec<String> = self.media_storage.list(&upload.parts_prefix()).await.unwrap()
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.upload_dir).ok();
+    }
+}
+
+fn metadata(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+}
+
+fn body(chunks: &[&[u8]]) -> impl futures::Stream<Item = Result<Vec<u8>, io::Error>> + Unpin {
+    stream::iter(chunks.iter().map(|chunk| Ok(chunk.to_vec())).collect::<Vec<_>>())
+}
+
+fn upload_error(e: Box<dyn std::error::Error>) -> UploadError {
+    match e.downcast::<UploadError>() {
+        Ok(e) => *e,
+        Err(e) => panic!("expected an upload error, got {}", e),
+    }
+}
+
+#[actix_web::test]
+async fn test_create_upload_checks_purpose_length_and_crop() {
+    let fixture = Fixture::new();
+    let ada = register(&fixture.store, "ada").await;
+    let create = |length: i64, pairs: &[(&str, &str)]| fixture.create.execute(ada.id, length, metadata(pairs));
+
+    assert!(matches!(upload_error(create(10, &[]).await.unwrap_err()), UploadError::Invalid(_)));
+    assert!(matches!(upload_error(create(10, &[("purpose", "video")]).await.unwrap_err()), UploadError::Invalid(_)));
+    assert!(matches!(upload_error(create(0, &[("purpose", "attachment")]).await.unwrap_err()), UploadError::Invalid(_)));
+    assert_eq!(upload_error(create(2048, &[("purpose", "attachment")]).await.unwrap_err()), UploadError::TooLarge { max_bytes: 1024 });
+    // Avatars are held to the avatar byte limit as well
+    assert_eq!(upload_error(create(257, &[("purpose", "avatar")]).await.unwrap_err()), UploadError::TooLarge { max_bytes: 256 });
+    let e = create(10, &[("purpose", "avatar"), ("crop_x", "1")]).await.unwrap_err();
+    assert!(e.is::<InvalidCrop>());
+
+    let upload = create(1024, &[("purpose", "attachment"), ("filename", "notes.txt")]).await.unwrap();
+    assert_eq!((upload.purpose, upload.offset, upload.length), (UploadPurpose::Attachment, 0, 1024));
+    assert_eq!(upload.metadata["filename"], "notes.txt");
+    assert!(upload.expires_at > upload.created_at);
+}
+
+#[actix_web::test]
+async fn test_append_keeps_what_arrived_before_the_body_broke_off() {
+    let fixture = Fixture::new();
+    let ada = register(&fixture.store, "ada").await;
+    let upload = fixture.create.execute(ada.id, 10, metadata(&[("purpose", "attachment"), ("filename", "../../etc/notes.txt")])).await.unwrap();
+
+    let interrupted = stream::iter(vec![
+        Ok(b"abc".to_vec()),
+        Ok(b"de".to_vec()),
+        Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset")),
+    ]);
+    assert!(fixture.append.execute(ada.id, upload.id, 0, interrupted).await.unwrap_err().is::<io::Error>());
+
+    let stored = fixture.uploads.find_by_id(upload.id).await.unwrap().unwrap();
+    assert_eq!(stored.offset, 5);
+    assert!(stored.locked_at.is_none(), "the lock is released for the retry");
+    assert_eq!(fixture.parts(&upload).await, [upload.part_key(0), upload.part_key(4)]);
+
+    // The client resumes from the offset it is told about
+    let e = fixture.append.execute(ada.id, upload.id, 0, body(&[b"abcdefghij"])).await.unwrap_err();
+    assert_eq!(upload_error(e), UploadError::OffsetMismatch { offset: 5 });
+
+    let completed = fixture.append.execute(ada.id, upload.id, 5, body(&[b"fghij"])).await.unwrap();
+    assert_eq!(completed.offset, 10);
+    assert!(completed.completed_at.is_some());
+    assert!(fixture.parts(&upload).await.is_empty(), "parts are removed once assembled");
+
+    let attachment = fixture.attachments.find_by_id(completed.attachment_id.expect("attachment created")).await.unwrap().unwrap();
+    assert_eq!(attachment.user_id, ada.id);
+    assert_eq!(attachment.filename.as_deref(), Some("notes.txt"));
+    assert_eq!(attachment.content_type, "application/octet-stream");
+    assert_eq!(attachment.byte_size, 10);
+    assert_eq!(fixture.media_storage.get(&attachment.storage_key).await.unwrap(), b"abcdefghij");
+
+    // Repeating the last request changes nothing
+    let again = fixture.append.execute(ada.id, upload.id, 10, body(&[])).await.unwrap();
+    assert_eq!(again.attachment_id, completed.attachment_id);
+}
+
+#[actix_web::test]
+async fn test_append_refuses_conflicting_requests() {
+    let fixture = Fixture::new();
+    let ada = register(&fixture.store, "ada").await;
+    let grace = register(&fixture.store, "grace").await;
+    let upload = fixture.create.execute(ada.id, 6, metadata(&[("purpose", "attachment")])).await.unwrap();
+
+    let e = fixture.append.execute(grace.id, upload.id, 0, body(&[b"abc"])).await.unwrap_err();
+    assert_eq!(upload_error(e), UploadError::NotFound);
+
+    let e = fixture.append.execute(ada.id, upload.id, 0, body(&[b"abcdefg"])).await.unwrap_err();
+    assert_eq!(upload_error(e), UploadError::ExceedsLength { length: 6 });
+    assert_eq!(fixture.uploads.find_by_id(upload.id).await.unwrap().unwrap().offset, 0);
+
+    // Another request holds the upload
+    assert!(fixture.uploads.lock(upload.id, 0, chrono::NaiveDateTime::MIN).await.unwrap());
+    let e = fixture.append.execute(ada.id, upload.id, 0, body(&[b"abc"])).await.unwrap_err();
+    assert_eq!(upload_error(e), UploadError::Locked);
+
+    // ...until it has been silent long enough to be presumed dead
+    let long_ago = upload.created_at - chrono::Duration::hours(1);
+    fixture.store.tables().uploads.get_mut(&upload.id).unwrap().locked_at = Some(long_ago);
+    let progress = fixture.append.execute(ada.id, upload.id, 0, body(&[b"abc"])).await.unwrap();
+    assert_eq!(progress.offset, 3);
+
+    fixture.store.tables().uploads.get_mut(&upload.id).unwrap().expires_at = long_ago;
+    let e = fixture.append.execute(ada.id, upload.id, 3, body(&[b"def"])).await.unwrap_err();
+    assert_eq!(upload_error(e), UploadError::Expired);
+}
+
+#[actix_web::test]
+async fn test_finished_avatar_upload_is_queued_or_discarded() {
+    let fixture = Fixture::new();
+    let ada = register(&fixture.store, "ada").await;
+    let jobs = InMemoryJobRepository::new(fixture.store.clone());
+
+    // Not an image; only noticed once the last byte is in
+    let upload = fixture.create.execute(ada.id, 9, metadata(&[("purpose", "avatar")])).await.unwrap();
+    let e = fixture.append.execute(ada.id, upload.id, 0, body(&[b"not a", b" png"])).await.unwrap_err();
+    assert!(e.is::<UploadRejection>());
+    assert!(fixture.uploads.find_by_id(upload.id).await.unwrap().is_none(), "a refused file is discarded");
+    assert!(fixture.parts(&upload).await.is_empty());
+
+    let png = {
+        let mut bytes = Vec::new();
+        image::DynamicImage::new_rgb8(2, 2)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    };
+    let upload = fixture.create.execute(ada.id, png.len() as i64, metadata(&[("purpose", "avatar")])).await.unwrap();
+    let completed = fixture.append.execute(ada.id, upload.id, 0, body(&[&png])).await.unwrap();
+
+    use crate::domain::repositories::job_repository::JobRepository;
+    let job = jobs.find_by_id(completed.job_id.expect("processing queued")).await.unwrap().unwrap();
+    assert_eq!(job.user_id, Some(ada.id));
+}
+
+#[actix_web::test]
+async fn test_terminated_and_expired_uploads_are_discarded() {
+    let fixture = Fixture::new();
+    let ada = register(&fixture.store, "ada").await;
+    let terminate = TerminateUploadUseCase::new(fixture.uploads.clone(), fixture.media_storage.clone());
+    let expire = ExpireUploadsUseCase::new(fixture.uploads.clone(), fixture.media_storage.clone());
+
+    let mut partial = Vec::new();
+    for _ in 0..3 {
+        let upload = fixture.create.execute(ada.id, 10, metadata(&[("purpose", "attachment")])).await.unwrap();
+        fixture.append.execute(ada.id, upload.id, 0, body(&[b"abcdef"])).await.unwrap();
+        partial.push(upload);
+    }
+
+    terminate.execute(ada.id, partial[0].id).await.unwrap();
+    assert!(fixture.uploads.find_by_id(partial[0].id).await.unwrap().is_none());
+    assert!(fixture.parts(&partial[0]).await.is_empty());
+
+    let long_ago = partial[1].created_at - chrono::Duration::hours(1);
+    fixture.store.tables().uploads.get_mut(&partial[1].id).unwrap().expires_at = long_ago;
+    assert_eq!(expire.execute().await.unwrap(), 1);
+    assert!(fixture.uploads.find_by_id(partial[1].id).await.unwrap().is_none());
+    assert!(fixture.parts(&partial[1]).await.is_empty());
+
+    assert_eq!(fixture.uploads.find_by_id(partial[2].id).await.unwrap().unwrap().offset, 6);
+    assert_eq!(fixture.parts(&partial[2]).await.len(), 2);
+    assert_eq!(expire.execute().await.unwrap(), 0);
+}
@@ -0,0 +1,64 @@
+// File: src/tests/use_cases/user_use_cases_test.rs
+
+use crate::application::use_cases::user_use_cases::{
+    CreateUserUseCase, DeleteUserUseCase, GetUserByIdUseCase, ListUsersUseCase, UpdateUserUseCase,
+};
+use crate::domain::entities::user::{CreateUserDto, UpdateUserDto};
+use crate::infrastructure::repositories::in_memory::{InMemoryStore, InMemoryUserRepository};
+
+fn create_dto(username: &str) -> CreateUserDto {
+    CreateUserDto {
+        username: username.to_string(),
+        email: format!("{}@example.com", username),
+        password: "secret".to_string(),
+    }
+}
+
+#[actix_web::test]
+async fn test_create_get_and_list_users() {
+    let repository = InMemoryUserRepository::new(InMemoryStore::new());
+
+    let ada = CreateUserUseCase::new(repository.clone()).execute(create_dto("ada")).await.unwrap();
+    let grace = CreateUserUseCase::new(repository.clone()).execute(create_dto("grace")).await.unwrap();
+    assert_ne!(ada.id, grace.id);
+
+    let found = GetUserByIdUseCase::new(repository.clone()).execute(grace.id).await.unwrap();
+    assert_eq!(found.username, "grace");
+
+    let users = ListUsersUseCase::new(repository).execute().await.unwrap();
+    let names: Vec<&str> = users.iter().map(|user| user.username.as_str()).collect();
+    assert_eq!(names, ["ada", "grace"]);
+}
+
+#[actix_web::test]
+async fn test_create_user_rejects_duplicate_username() {
+    let use_case = CreateUserUseCase::new(InMemoryUserRepository::new(InMemoryStore::new()));
+    use_case.execute(create_dto("ada")).await.unwrap();
+
+    let error = use_case.execute(create_dto("ada")).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<diesel::result::Error>(),
+        Some(diesel::result::Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, _))
+    ));
+}
+
+#[actix_web::test]
+async fn test_update_and_delete_user() {
+    let repository = InMemoryUserRepository::new(InMemoryStore::new());
+    let user = CreateUserUseCase::new(repository.clone()).execute(create_dto("ada")).await.unwrap();
+
+    let updated = UpdateUserUseCase::new(repository.clone())
+        .execute(user.id, UpdateUserDto {
+            username: "countess".to_string(),
+            email: "countess@example.com".to_string(),
+            password: "changed".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(updated.id, user.id);
+    assert_eq!(updated.username, "countess");
+
+    DeleteUserUseCase::new(repository.clone()).execute(user.id).await.unwrap();
+    let error = GetUserByIdUseCase::new(repository).execute(user.id).await.unwrap_err();
+    assert!(matches!(error.downcast_ref::<diesel::result::Error>(), Some(diesel::result::Error::NotFound)));
+}
@@ -0,0 +1,73 @@
+// File: src/tests/user_test.rs
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use crate::app::build_app;
+use crate::tests::support::test_context;
+use crate::tests::support::seeds::UserSeed;
+use crate::tests::support::tokens::{bearer, token_for};
+
+#[actix_web::test]
+async fn test_list_and_get_users() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    UserSeed::new("bob").create(&ctx.pool()).await;
+    let token = token_for(alice.id);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user")
+        .insert_header(bearer(&token))
+        .to_request();
+    let users: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(users.len(), 2);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/user/{}", alice.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let user: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(user["email"], alice.email);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user/999999")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_update_and_delete_user() {
+    let ctx = test_context!();
+    let app = test::init_service(build_app(ctx.settings.clone(), ctx.pool())).await;
+    let user = UserSeed::new("changeling").create(&ctx.pool()).await;
+    let token = token_for(user.id);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/v1/user/{}", user.id))
+        .insert_header(bearer(&token))
+        .set_json(json!({
+            "username": "changed",
+            "email": "changed@example.com",
+            "password": "irrelevant"
+        }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["username"], "changed");
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/v1/user/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user/me")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,568 @@
+// File: src/tests/ws_test.rs
+
+use std::time::Duration;
+use actix_web::http::StatusCode;
+use awc::ws;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use crate::app::{build_app_with_state, AppState};
+use crate::infrastructure::config::settings::ChatSettings;
+use crate::tests::support::{multipart_file, test_context};
+use crate::tests::support::seeds::UserSeed;
+use crate::tests::support::tokens::{bearer, expired_token_for, token_for};
+
+const TEST_AVATAR: &[u8] = include_bytes!("upload_avatar_test/test_avatar.jpg");
+
+/// Where `user_id` opens their socket, authenticated with a token for them.
+fn socket_url(srv: &actix_test::TestServer, user_id: i32) -> String {
+    srv.url(&format!("/ws?token={}", token_for(user_id)))
+}
+
+/// Reads text frames until one satisfies `predicate`, failing the test after two seconds.
+async fn next_matching<S, E>(conn: &mut S, predicate: impl Fn(&Value) -> bool) -> Value
+where
+    S: futures::Stream<Item = Result<ws::Frame, E>> + Unpin,
+{
+    let read = async {
+        while let Some(frame) = conn.next().await {
+            if let Ok(ws::Frame::Text(bytes)) = frame {
+                let value: Value = serde_json::from_slice(&bytes).expect("frame is JSON");
+                if predicate(&value) {
+                    return value;
+                }
+            }
+        }
+        panic!("WebSocket closed before the expected frame arrived");
+    };
+
+    tokio::time::timeout(Duration::from_secs(2), read)
+        .await
+        .expect("Timed out waiting for WebSocket frame")
+}
+
+#[actix_web::test]
+async fn test_socket_requires_a_valid_token() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let refused = |result: Result<_, awc::error::WsClientError>| matches!(
+        result,
+        Err(awc::error::WsClientError::InvalidResponseStatus(StatusCode::UNAUTHORIZED))
+    );
+
+    assert!(refused(awc::Client::new().ws(srv.url("/ws")).connect().await.map(|_| ())));
+    let forged = srv.url("/ws?token=not-a-token");
+    assert!(refused(awc::Client::new().ws(forged).connect().await.map(|_| ())));
+    let expired = srv.url(&format!("/ws?token={}", expired_token_for(alice.id)));
+    assert!(refused(awc::Client::new().ws(expired).connect().await.map(|_| ())));
+
+    // The header works as well as the query parameter
+    let (_, mut alice_ws) = awc::Client::new()
+        .ws(srv.url("/ws"))
+        .bearer_auth(token_for(alice.id))
+        .connect()
+        .await
+        .expect("alice connects");
+    let chat = json!({ "Chat": { "to_user_id": alice.id, "content": "note to self" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let received = next_matching(&mut alice_ws, |v| v.get("NewMessage").is_some()).await;
+    assert_eq!(received["NewMessage"]["from_user_id"], alice.id);
+}
+
+#[actix_web::test]
+async fn test_presence_and_chat_relay() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+
+    let (_, mut alice_ws) = awc::Client::new()
+        .ws(socket_url(&srv, alice.id))
+        .connect()
+        .await
+        .expect("alice connects");
+    // Registration happens on a spawned task; give it a moment before the second connect
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (_, mut bob_ws) = awc::Client::new()
+        .ws(socket_url(&srv, bob.id))
+        .connect()
+        .await
+        .expect("bob connects");
+
+    let status = next_matching(&mut alice_ws, |v| v.get("Status").is_some()).await;
+    assert_eq!(status["Status"], json!({ "user_id": bob.id, "online": true }));
+
+    let status = next_matching(&mut bob_ws, |v| v.get("Status").is_some()).await;
+    assert_eq!(status["Status"], json!({ "user_id": alice.id, "online": true }));
+
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "hello bob", "client_msg_id": "c-1" } });
+    alice_ws
+        .send(ws::Message::Text(chat.to_string().into()))
+        .await
+        .expect("alice sends chat");
+
+    let received = next_matching(&mut bob_ws, |v| v.get("NewMessage").is_some()).await;
+    let received = &received["NewMessage"];
+    assert_eq!(received["content"], "hello bob");
+    assert_eq!(received["from_user_id"], alice.id);
+    assert_eq!(received["client_msg_id"], "c-1");
+    assert!(received["created_at"].is_string());
+
+    let ack = next_matching(&mut alice_ws, |v| v.get("ChatAck").is_some()).await;
+    assert_eq!(ack["ChatAck"]["client_msg_id"], "c-1");
+    assert_eq!(ack["ChatAck"]["message_id"], received["message_id"]);
+}
+
+#[actix_web::test]
+async fn test_chat_is_stored_and_acknowledged() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+
+    let (_, mut alice_ws) = awc::Client::new()
+        .ws(socket_url(&srv, alice.id))
+        .connect()
+        .await
+        .expect("alice connects");
+
+    // Bob is offline; the message is kept for him all the same
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "call me", "client_msg_id": "c-1" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let ack = next_matching(&mut alice_ws, |v| v.get("ChatAck").is_some()).await;
+    assert_eq!(ack["ChatAck"]["client_msg_id"], "c-1");
+
+    let mut resp = awc::Client::new()
+        .get(srv.url(&format!("/api/v1/conversations/{}/messages", alice.id)))
+        .insert_header(bearer(&token_for(bob.id)))
+        .send()
+        .await
+        .expect("history is requested");
+    let history: Value = resp.json().await.expect("history is JSON");
+    assert_eq!(history["messages"][0]["id"], ack["ChatAck"]["message_id"]);
+    assert_eq!(history["messages"][0]["sender_id"], alice.id);
+    assert_eq!(history["messages"][0]["content"], "call me");
+    assert!(history["messages"][0]["delivered_at"].is_null());
+
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "see attached", "attachment_id": 9999, "client_msg_id": "c-2" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let failed = next_matching(&mut alice_ws, |v| v.get("ChatFailed").is_some()).await;
+    assert_eq!(failed["ChatFailed"]["client_msg_id"], "c-2");
+}
+
+#[actix_web::test]
+async fn test_receipts_reach_the_sender() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+
+    let (_, mut alice_ws) = awc::Client::new()
+        .ws(socket_url(&srv, alice.id))
+        .connect()
+        .await
+        .expect("alice connects");
+    let (_, mut bob_ws) = awc::Client::new()
+        .ws(socket_url(&srv, bob.id))
+        .connect()
+        .await
+        .expect("bob connects");
+    next_matching(&mut alice_ws, |v| v["Status"]["user_id"] == bob.id).await;
+
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "are you there?" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let received = next_matching(&mut bob_ws, |v| v.get("NewMessage").is_some()).await;
+    let message_id = received["NewMessage"]["message_id"].clone();
+
+    let delivered = next_matching(&mut alice_ws, |v| v.get("Delivered").is_some()).await;
+    assert_eq!(delivered["Delivered"]["by_user_id"], bob.id);
+    assert_eq!(delivered["Delivered"]["message_ids"], json!([message_id]));
+    assert!(delivered["Delivered"]["delivered_at"].is_string());
+
+    // Bob was online, so the message was pushed to him, and counted delivered once he confirmed it
+    let mut resp = awc::Client::new().get(srv.url("/metrics")).send().await.expect("metrics are scraped");
+    let scrape = String::from_utf8(resp.body().await.expect("metrics body").to_vec()).unwrap();
+    for outcome in ["pushed", "delivered"] {
+        let series = format!("websocket_messages_total{{outcome=\"{}\"}} ", outcome);
+        let count: f64 = scrape.lines()
+            .find_map(|line| line.strip_prefix(&series))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("no {} series in {}", outcome, scrape));
+        assert!(count >= 1.0, "{} = {}", outcome, count);
+    }
+
+    let mark_read = json!({ "MarkRead": { "user_id": alice.id, "up_to_message_id": message_id } });
+    bob_ws.send(ws::Message::Text(mark_read.to_string().into())).await.expect("bob marks read");
+    let read = next_matching(&mut alice_ws, |v| v.get("Read").is_some()).await;
+    assert_eq!(read["Read"]["by_user_id"], bob.id);
+    assert_eq!(read["Read"]["message_ids"], json!([message_id]));
+
+    let mut resp = awc::Client::new()
+        .get(srv.url("/api/v1/conversations"))
+        .insert_header(bearer(&token_for(bob.id)))
+        .send()
+        .await
+        .expect("inbox is requested");
+    let inbox: Value = resp.json().await.expect("inbox is JSON");
+    assert_eq!(inbox["conversations"][0]["unread_count"], 0);
+
+    // Marking read up to a message from another conversation is refused
+    let mark_read = json!({ "MarkRead": { "user_id": alice.id, "up_to_message_id": 9999 } });
+    bob_ws.send(ws::Message::Text(mark_read.to_string().into())).await.expect("bob marks read");
+    next_matching(&mut bob_ws, |v| v.get("Error").is_some()).await;
+}
+
+#[actix_web::test]
+async fn test_missed_events_are_replayed_on_reconnect() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let connect = |user_id: i32| awc::Client::new().ws(socket_url(&srv, user_id)).connect();
+
+    // Bob is offline while Alice writes to him
+    let (_, mut alice_ws) = connect(alice.id).await.expect("alice connects");
+    let mut sent = Vec::new();
+    for (n, content) in ["first", "second"].into_iter().enumerate() {
+        let chat = json!({ "Chat": { "to_user_id": bob.id, "content": content, "client_msg_id": n.to_string() } });
+        alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+        let ack = next_matching(&mut alice_ws, |v| v.get("ChatAck").is_some()).await;
+        sent.push(ack["ChatAck"]["message_id"].clone());
+    }
+
+    let (_, mut bob_ws) = connect(bob.id).await.expect("bob connects");
+    for (message_id, content) in sent.iter().zip(["first", "second"]) {
+        let received = next_matching(&mut bob_ws, |v| v.get("NewMessage").is_some()).await;
+        assert_eq!(&received["NewMessage"]["message_id"], message_id);
+        assert_eq!(received["NewMessage"]["content"], content);
+    }
+    let delivered = next_matching(&mut alice_ws, |v| v.get("Delivered").is_some()).await;
+    assert_eq!(delivered["Delivered"]["message_ids"], json!(sent));
+
+    // Now Alice is away while Bob reads
+    alice_ws.send(ws::Message::Close(None)).await.expect("alice disconnects");
+    drop(alice_ws);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut resp = awc::Client::new()
+        .post(srv.url(&format!("/api/v1/conversations/{}/read", alice.id)))
+        .insert_header(bearer(&token_for(bob.id)))
+        .send_json(&json!({ "up_to_message_id": sent[1] }))
+        .await
+        .expect("bob marks read");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let receipt: Value = resp.json().await.expect("receipt is JSON");
+    assert_eq!(receipt["message_ids"], json!(sent));
+
+    let (_, mut alice_ws) = connect(alice.id).await.expect("alice reconnects");
+    let read = next_matching(&mut alice_ws, |v| v.get("Read").is_some()).await;
+    assert_eq!(read["Read"]["by_user_id"], bob.id);
+    assert_eq!(read["Read"]["message_ids"], json!(sent));
+
+    // Events already sent are not replayed again
+    alice_ws.send(ws::Message::Close(None)).await.expect("alice disconnects");
+    drop(alice_ws);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let chat = json!({ "Chat": { "to_user_id": alice.id, "content": "welcome back" } });
+    bob_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("bob sends chat");
+    next_matching(&mut bob_ws, |v| v.get("ChatAck").is_some()).await;
+
+    let (_, mut alice_ws) = connect(alice.id).await.expect("alice reconnects");
+    let next = next_matching(&mut alice_ws, |v| v.get("Read").is_some() || v.get("NewMessage").is_some()).await;
+    assert_eq!(next["NewMessage"]["content"], "welcome back");
+}
+
+#[actix_web::test]
+async fn test_replay_only_reaches_the_authenticated_user() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let carol = UserSeed::new("carol").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let connect = |url: String| awc::Client::new().ws(url).connect();
+
+    let (_, mut alice_ws) = connect(socket_url(&srv, alice.id)).await.expect("alice connects");
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "for bob only" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let ack = next_matching(&mut alice_ws, |v| v.get("ChatAck").is_some()).await;
+
+    // Naming Bob next to Carol's token still opens Carol's socket
+    let url = srv.url(&format!("/ws?token={}&user_id={}", token_for(carol.id), bob.id));
+    let (_, mut carol_ws) = connect(url).await.expect("carol connects");
+    let replayed = tokio::time::timeout(
+        Duration::from_millis(500),
+        next_matching(&mut carol_ws, |v| v.get("NewMessage").is_some()),
+    ).await;
+    assert!(replayed.is_err(), "Carol was sent Bob's queue");
+
+    let mut resp = awc::Client::new()
+        .get(srv.url(&format!("/api/v1/conversations/{}/messages", bob.id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send()
+        .await
+        .expect("history is requested");
+    let history: Value = resp.json().await.expect("history is JSON");
+    assert!(history["messages"][0]["delivered_at"].is_null());
+
+    let (_, mut bob_ws) = connect(socket_url(&srv, bob.id)).await.expect("bob connects");
+    let received = next_matching(&mut bob_ws, |v| v.get("NewMessage").is_some()).await;
+    assert_eq!(received["NewMessage"]["message_id"], ack["ChatAck"]["message_id"]);
+}
+
+#[actix_web::test]
+async fn test_closing_one_device_keeps_the_other_connected() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let connect = |user_id: i32| awc::Client::new().ws(socket_url(&srv, user_id)).connect();
+
+    let (_, mut alice_ws) = connect(alice.id).await.expect("alice connects");
+    let (_, mut phone_ws) = connect(bob.id).await.expect("bob connects his phone");
+    next_matching(&mut alice_ws, |v| v["Status"]["user_id"] == bob.id).await;
+    let (_, mut laptop_ws) = connect(bob.id).await.expect("bob connects his laptop");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "to both" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    for conn in [&mut phone_ws, &mut laptop_ws] {
+        let received = next_matching(conn, |v| v.get("NewMessage").is_some()).await;
+        assert_eq!(received["NewMessage"]["content"], "to both");
+    }
+
+    phone_ws.send(ws::Message::Close(None)).await.expect("bob closes his phone");
+    drop(phone_ws);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Bob is still online, and his laptop still gets new messages
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "still there?" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let received = next_matching(&mut laptop_ws, |v| v.get("NewMessage").is_some()).await;
+    assert_eq!(received["NewMessage"]["content"], "still there?");
+    let message_ids = json!([received["NewMessage"]["message_id"]]);
+    let next = next_matching(&mut alice_ws, |v| {
+        v.get("Status").is_some() || v["Delivered"]["message_ids"] == message_ids
+    }).await;
+    assert!(next.get("Delivered").is_some(), "Bob was reported offline");
+
+    laptop_ws.send(ws::Message::Close(None)).await.expect("bob closes his laptop");
+    let status = next_matching(&mut alice_ws, |v| v.get("Status").is_some()).await;
+    assert_eq!(status["Status"], json!({ "user_id": bob.id, "online": false }));
+}
+
+#[actix_web::test]
+async fn test_typing_is_relayed_to_the_counterpart_only() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let carol = UserSeed::new("carol").create(&ctx.pool()).await;
+
+    let mut settings = ctx.settings.clone();
+    settings.chat = ChatSettings {
+        typing_timeout: Duration::from_millis(300),
+        typing_interval: Duration::from_secs(60),
+        ..ChatSettings::default()
+    };
+    let state = AppState::new(settings, ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let connect = |user_id: i32| awc::Client::new().ws(socket_url(&srv, user_id)).connect();
+
+    let (_, mut alice_ws) = connect(alice.id).await.expect("alice connects");
+    let (_, mut bob_ws) = connect(bob.id).await.expect("bob connects");
+    let (_, mut carol_ws) = connect(carol.id).await.expect("carol connects");
+    next_matching(&mut alice_ws, |v| v["Status"]["user_id"] == carol.id).await;
+    let typing = |v: &Value| v.get("TypingStarted").is_some() || v.get("TypingStopped").is_some();
+
+    let chat = json!({ "Chat": { "to_user_id": alice.id, "content": "hi alice" } });
+    bob_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("bob sends chat");
+    next_matching(&mut alice_ws, |v| v.get("NewMessage").is_some()).await;
+
+    let started = json!({ "TypingStarted": { "user_id": bob.id } });
+    let stopped = json!({ "TypingStopped": { "user_id": bob.id } });
+    alice_ws.send(ws::Message::Text(started.to_string().into())).await.expect("alice types");
+    let event = next_matching(&mut bob_ws, typing).await;
+    assert_eq!(event, json!({ "TypingStarted": { "user_id": alice.id } }));
+
+    // Repeats within the interval only keep the indicator alive
+    alice_ws.send(ws::Message::Text(started.to_string().into())).await.expect("alice types");
+    alice_ws.send(ws::Message::Text(stopped.to_string().into())).await.expect("alice stops");
+    let event = next_matching(&mut bob_ws, typing).await;
+    assert_eq!(event, json!({ "TypingStopped": { "user_id": alice.id } }));
+
+    // Without a stop, the server sends one when the indicator expires
+    alice_ws.send(ws::Message::Text(started.to_string().into())).await.expect("alice types");
+    next_matching(&mut bob_ws, typing).await;
+    let event = next_matching(&mut bob_ws, typing).await;
+    assert_eq!(event, json!({ "TypingStopped": { "user_id": alice.id } }));
+
+    // Carol has no conversation with Alice until Alice writes to her
+    let started = json!({ "TypingStarted": { "user_id": carol.id } });
+    alice_ws.send(ws::Message::Text(started.to_string().into())).await.expect("alice types");
+    let chat = json!({ "Chat": { "to_user_id": carol.id, "content": "hi carol" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let event = next_matching(&mut carol_ws, |v| typing(v) || v.get("NewMessage").is_some()).await;
+    assert_eq!(event["NewMessage"]["content"], "hi carol");
+
+    alice_ws.send(ws::Message::Text(started.to_string().into())).await.expect("alice types");
+    let event = next_matching(&mut carol_ws, typing).await;
+    assert_eq!(event, json!({ "TypingStarted": { "user_id": alice.id } }));
+}
+
+#[actix_web::test]
+async fn test_edits_and_deletions_are_sent_live() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+    let carol = UserSeed::new("carol").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let connect = |user_id: i32| awc::Client::new().ws(socket_url(&srv, user_id)).connect();
+    let client = awc::Client::new();
+
+    let (_, mut alice_ws) = connect(alice.id).await.expect("alice connects");
+    let (_, mut bob_ws) = connect(bob.id).await.expect("bob connects");
+    let chat = json!({ "Chat": { "to_user_id": bob.id, "content": "see you at 5" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let received = next_matching(&mut bob_ws, |v| v.get("NewMessage").is_some()).await;
+    let message_id = received["NewMessage"]["message_id"].as_i64().unwrap();
+
+    let resp = client
+        .patch(srv.url(&format!("/api/v1/messages/{}", message_id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send_json(&json!({ "content": "see you at 6" }))
+        .await
+        .expect("alice edits");
+    assert_eq!(resp.status(), StatusCode::OK);
+    for conn in [&mut bob_ws, &mut alice_ws] {
+        let edited = next_matching(conn, |v| v.get("MessageEdited").is_some()).await;
+        assert_eq!(edited["MessageEdited"]["message_id"], message_id);
+        assert_eq!(edited["MessageEdited"]["content"], "see you at 6");
+    }
+
+    let resp = client
+        .delete(srv.url(&format!("/api/v1/messages/{}?for_everyone=true", message_id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send()
+        .await
+        .expect("alice deletes");
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let deleted = next_matching(&mut bob_ws, |v| v.get("MessageDeleted").is_some()).await;
+    assert_eq!(deleted["MessageDeleted"]["message_id"], message_id);
+    assert_eq!(deleted["MessageDeleted"]["for_everyone"], true);
+
+    // Carol was never sent a message deleted before she came online
+    let chat = json!({ "Chat": { "to_user_id": carol.id, "content": "wrong chat" } });
+    alice_ws.send(ws::Message::Text(chat.to_string().into())).await.expect("alice sends chat");
+    let ack = next_matching(&mut alice_ws, |v| v.get("ChatAck").is_some()).await;
+    let message_id = &ack["ChatAck"]["message_id"];
+    client
+        .delete(srv.url(&format!("/api/v1/messages/{}?for_everyone=true", message_id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send()
+        .await
+        .expect("alice deletes");
+
+    let (_, mut carol_ws) = connect(carol.id).await.expect("carol connects");
+    let event = next_matching(&mut carol_ws, |v| v.get("NewMessage").is_some() || v.get("MessageDeleted").is_some()).await;
+    assert_eq!(&event["MessageDeleted"]["message_id"], message_id);
+}
+
+#[actix_web::test]
+async fn test_deleted_message_content_is_not_replayed() {
+    let ctx = test_context!();
+    let alice = UserSeed::new("alice").create(&ctx.pool()).await;
+    let bob = UserSeed::new("bob").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let srv = actix_test::start(move || build_app_with_state(&state));
+    let client = awc::Client::new();
+
+    // Bob is offline throughout: the message, its edit and its deletion all wait for him
+    let mut resp = client
+        .post(srv.url(&format!("/api/v1/conversations/{}/messages", bob.id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send_json(&json!({ "content": "first draft" }))
+        .await
+        .expect("alice sends");
+    let message: Value = resp.json().await.expect("message is JSON");
+    let message_id = message["id"].as_i64().unwrap();
+    let resp = client
+        .patch(srv.url(&format!("/api/v1/messages/{}", message_id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send_json(&json!({ "content": "second draft" }))
+        .await
+        .expect("alice edits");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = client
+        .delete(srv.url(&format!("/api/v1/messages/{}?for_everyone=true", message_id)))
+        .insert_header(bearer(&token_for(alice.id)))
+        .send()
+        .await
+        .expect("alice deletes");
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let (_, mut bob_ws) = awc::Client::new().ws(socket_url(&srv, bob.id)).connect().await.expect("bob connects");
+    let mut replayed = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_millis(500), async {
+        while let Some(Ok(frame)) = bob_ws.next().await {
+            if let ws::Frame::Text(bytes) = frame {
+                replayed.push(serde_json::from_slice::<Value>(&bytes).expect("frame is JSON"));
+            }
+        }
+    }).await;
+
+    assert!(replayed.iter().any(|event| event["MessageDeleted"]["message_id"] == message_id), "{:?}", replayed);
+    for event in &replayed {
+        assert!(event.get("NewMessage").is_none() && event.get("MessageEdited").is_none(), "replayed {}", event);
+        assert!(!event.to_string().contains("draft"), "replayed content: {}", event);
+    }
+}
+
+#[actix_web::test]
+async fn test_avatar_ready_event_after_processing() {
+    let ctx = test_context!();
+    let user = UserSeed::new("avatar_waiter").create(&ctx.pool()).await;
+
+    let state = AppState::new(ctx.settings.clone(), ctx.pool());
+    let worker = state.job_worker.clone();
+    let srv = actix_test::start(move || build_app_with_state(&state));
+
+    let (_, mut conn) = awc::Client::new()
+        .ws(socket_url(&srv, user.id))
+        .connect()
+        .await
+        .expect("user connects");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (content_type, body) = multipart_file("avatar", "test_avatar.jpg", "image/jpeg", TEST_AVATAR);
+    let mut resp = awc::Client::new()
+        .post(srv.url(&format!("/api/v1/avatars/{}", user.id)))
+        .insert_header(bearer(&token_for(user.id)))
+        .insert_header(("Content-Type", content_type))
+        .send_body(body)
+        .await
+        .expect("upload is sent");
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let accepted: Value = resp.json().await.expect("upload is accepted");
+
+    assert_eq!(worker.run_pending().await.expect("job queue is reachable"), 1);
+
+    let ready = next_matching(&mut conn, |v| v.get("AvatarReady").is_some()).await;
+    assert_eq!(ready["AvatarReady"]["job_id"], accepted["job_id"]);
+    assert!(ready["AvatarReady"]["avatar_id"].is_number());
+}